@@ -0,0 +1,60 @@
+//! Compares `get_needed_libs`, which reads only the parts of ELF files naming their
+//! dependencies, with parsing whole files the way a scan does: wall time and peak resident set
+//! size of each over every file of a directory, `/usr/bin` unless given as the first argument.
+//!
+//! `cargo bench --bench needed_libs -- /usr/lib/x86_64-linux-gnu`
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use linux_hs_elf::{get_needed_libs, Scanner};
+
+/// Field of `/proc/self/status` in kB
+fn status_kb(field: &str) -> Option<u64> {
+    fs::read_to_string("/proc/self/status").ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Time taken by the fastest of `runs` passes of `pass` over `files` and the peak RSS growth
+/// over the resident set at the start, in kB
+fn measure(files: &[PathBuf], runs: usize, pass: impl Fn(&PathBuf) -> usize) -> (Duration, usize, Option<u64>) {
+    // writing 5 resets the peak to the current resident set
+    let reset = fs::write("/proc/self/clear_refs", "5").is_ok();
+    let start = status_kb("VmRSS");
+    let mut fastest = Duration::MAX;
+    let mut libs = 0;
+    for _ in 0..runs {
+        let started = Instant::now();
+        libs = files.iter().map(&pass).sum();
+        fastest = fastest.min(started.elapsed());
+    }
+    let peak = status_kb("VmHWM").zip(start).filter(|_| reset).map(|(peak, start)| peak.saturating_sub(start));
+    (fastest, libs, peak)
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`
+    let dir = env::args().skip(1).find(|a| !a.starts_with("--")).unwrap_or_else(|| "/usr/bin".to_string());
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir).expect("Couldn't list the directory")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    let bytes: u64 = files.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+    println!("{}: {} files, {} MB", dir, files.len(), bytes / 1_000_000);
+    let scanner = Scanner::default();
+    report("get_needed_libs", measure(&files, 3, |path| get_needed_libs(path).map_or(0, |libs| libs.len())));
+    report("whole file", measure(&files, 3, |path| scanner.parse(path).map_or(0, |info| info.needed.len())));
+}
+
+fn report(name: &str, (time, libs, peak): (Duration, usize, Option<u64>)) {
+    let peak = peak.map_or_else(|| "unknown".to_string(), |kb| format!("{} kB", kb));
+    println!("\t{}: {:.3}s, {} needed libraries, peak RSS growth {}", name, time.as_secs_f64(), libs, peak);
+}
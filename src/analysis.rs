@@ -0,0 +1,26 @@
+//! Analyzers that run over the parsed files once the scan is done.
+
+pub mod centrality;
+pub mod checksec;
+pub mod clusters;
+pub mod compat32;
+pub mod directories;
+pub mod dlopen;
+pub mod duplicates;
+pub mod embedded;
+pub mod fragmentation;
+pub mod ifuncs;
+pub mod isa;
+pub mod musl;
+pub mod orphans;
+pub mod packages;
+pub mod providers;
+pub mod rpaths;
+pub mod runtimes;
+pub mod services;
+pub mod sizes;
+pub mod symbol_usage;
+pub mod symbol_versions;
+pub mod symbols;
+pub mod tls;
+pub mod unsafe_symbols;
@@ -0,0 +1,105 @@
+//! Importance of libraries in the dependency graph, to rank them by blast radius.
+
+use std::collections::VecDeque;
+use crate::graph::{DepGraph, NodeKind};
+
+#[derive(Debug, Clone)]
+pub struct Centrality {
+    pub node: usize,
+    /// Number of files needing the library directly
+    pub in_degree: usize,
+    /// Number of files needing the library directly or transitively
+    pub dependents: usize,
+    /// Share of shortest dependency paths going through the library
+    pub betweenness: f64,
+}
+
+fn transitive_dependents(reverse: &[Vec<usize>], node: usize) -> usize {
+    let mut seen = vec![false; reverse.len()];
+    let mut queue = VecDeque::from([node]);
+    seen[node] = true;
+    let mut count = 0;
+    while let Some(n) = queue.pop_front() {
+        for &m in &reverse[n] {
+            if !seen[m] {
+                seen[m] = true;
+                count += 1;
+                queue.push_back(m);
+            }
+        }
+    }
+    count
+}
+
+/// Brandes' algorithm for unweighted directed graphs
+fn betweenness(graph: &DepGraph) -> Vec<f64> {
+    let n = graph.nodes.len();
+    let mut result = vec![0.0; n];
+    for source in 0..n {
+        let mut stack = vec![];
+        let mut preds: Vec<Vec<usize>> = vec![vec![]; n];
+        let mut paths = vec![0.0f64; n];
+        let mut dist = vec![usize::MAX; n];
+        paths[source] = 1.0;
+        dist[source] = 0;
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            for &w in &graph.edges[v] {
+                if dist[w] == usize::MAX {
+                    dist[w] = dist[v] + 1;
+                    queue.push_back(w);
+                }
+                if dist[w] == dist[v] + 1 {
+                    paths[w] += paths[v];
+                    preds[w].push(v);
+                }
+            }
+        }
+        let mut delta = vec![0.0f64; n];
+        while let Some(w) = stack.pop() {
+            for &v in &preds[w] {
+                delta[v] += paths[v] / paths[w] * (1.0 + delta[w]);
+            }
+            if w != source {
+                result[w] += delta[w];
+            }
+        }
+    }
+    result
+}
+
+/// Centrality of every library node, most critical first
+pub fn rank(graph: &DepGraph) -> Vec<Centrality> {
+    let reverse = graph.reverse_edges();
+    let betweenness = betweenness(graph);
+    let mut ranking: Vec<Centrality> = (0..graph.nodes.len())
+        .filter(|&n| graph.nodes[n].kind == NodeKind::Library)
+        .map(|n| Centrality {
+            node: n,
+            in_degree: reverse[n].len(),
+            dependents: transitive_dependents(&reverse, n),
+            betweenness: betweenness[n],
+        })
+        .collect();
+    ranking.sort_by(|a, b| {
+        b.dependents.cmp(&a.dependents)
+            .then(b.in_degree.cmp(&a.in_degree))
+            .then(b.betweenness.total_cmp(&a.betweenness))
+            .then(graph.nodes[a.node].name.cmp(&graph.nodes[b.node].name))
+    });
+    ranking
+}
+
+pub fn print_report(graph: &DepGraph, top: usize) {
+    let ranking = rank(graph);
+    println!("most critical libraries");
+    println!("\t{:>4} {:>10} {:>6} {:>11}  library", "rank", "dependents", "direct", "betweenness");
+    for (i, c) in ranking.iter().take(top).enumerate() {
+        println!(
+            "\t{:>4} {:>10} {:>6} {:>11.1}  {}",
+            i + 1, c.dependents, c.in_degree, c.betweenness, graph.nodes[c.node].name
+        );
+    }
+    println!()
+}
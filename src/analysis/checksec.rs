@@ -0,0 +1,78 @@
+//! Exploit mitigations of every ELF binary, like `checksec`: PIE, RELRO, a non-executable stack,
+//! stack protector use, load-time binding and text relocations.
+
+use std::fmt;
+use serde::Serialize;
+use crate::elf::{BinaryFormat, ElfInfo};
+use crate::scan::ScannedFile;
+
+/// Functions only binaries built with `-fstack-protector` call or read
+const CANARY_SYMBOLS: &[&str] = &["__stack_chk_fail", "__stack_chk_guard"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relro {
+    None,
+    /// `PT_GNU_RELRO` but lazy binding, so the GOT stays writable
+    Partial,
+    Full,
+}
+
+impl fmt::Display for Relro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Relro::None => "none",
+            Relro::Partial => "partial",
+            Relro::Full => "full",
+        })
+    }
+}
+
+/// Mitigations of one binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Checks {
+    pub pie: bool,
+    pub relro: Relro,
+    pub nx: bool,
+    pub canary: bool,
+    pub bind_now: bool,
+    pub textrel: bool,
+}
+
+pub fn checks(info: &ElfInfo) -> Checks {
+    let hardening = &info.hardening;
+    let relro = match (hardening.relro, hardening.bind_now) {
+        (false, _) => Relro::None,
+        (true, false) => Relro::Partial,
+        (true, true) => Relro::Full,
+    };
+    let canary = info.imports.iter().chain(&info.exports).any(|s| CANARY_SYMBOLS.contains(&s.as_str()));
+    Checks { pie: info.pie, relro, nx: hardening.nx, canary, bind_now: hardening.bind_now, textrel: hardening.textrel }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+pub fn print_report(files: &[ScannedFile]) {
+    let results: Vec<(&ScannedFile, Checks)> = files.iter()
+        .filter(|f| f.info.format == BinaryFormat::Elf)
+        .map(|f| (f, checks(&f.info)))
+        .collect();
+    let count = |check: fn(&Checks) -> bool| results.iter().filter(|(_, c)| check(c)).count();
+    println!("hardening ({} exes)", results.len());
+    println!(
+        "\tpie: {}, full relro: {}, partial relro: {}, nx: {}, canary: {}, textrel: {}",
+        count(|c| c.pie), count(|c| c.relro == Relro::Full), count(|c| c.relro == Relro::Partial),
+        count(|c| c.nx), count(|c| c.canary), count(|c| c.textrel),
+    );
+    println!();
+    println!("\tpie\trelro\tnx\tcanary\ttextrel\tname");
+    for (file, checks) in &results {
+        println!(
+            "\t{}\t{}\t{}\t{}\t{}\t{}",
+            yes_no(checks.pie), checks.relro, yes_no(checks.nx), yes_no(checks.canary), yes_no(checks.textrel), file.name,
+        );
+    }
+    println!();
+}
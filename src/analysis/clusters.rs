@@ -0,0 +1,97 @@
+//! Functional groups in the dependency graph, found with label propagation.
+//!
+//! Hubs such as libc are linked by nearly everything and would pull the whole graph into one
+//! cluster, so they keep a cluster of their own and don't take part in propagation.
+
+use std::collections::{BTreeMap, HashMap};
+use crate::graph::{DepGraph, NodeKind};
+
+const MAX_ITERATIONS: usize = 100;
+
+/// Nodes needed directly by more than this share of the scanned files are hubs
+const HUB_SHARE: f64 = 0.05;
+
+/// Cluster id of every node. Ids are node indices of some cluster member
+pub fn label_propagation(graph: &DepGraph) -> Vec<usize> {
+    let n = graph.nodes.len();
+    let reverse = graph.reverse_edges();
+    let scanned = graph.nodes.iter().filter(|n| n.kind == NodeKind::Scanned).count();
+    let hub_threshold = ((scanned as f64 * HUB_SHARE) as usize).max(2);
+    let hub: Vec<bool> = reverse.iter().map(|r| r.len() > hub_threshold).collect();
+
+    let neighbours: Vec<Vec<usize>> = (0..n)
+        .map(|v| graph.edges[v].iter()
+            .chain(reverse[v].iter())
+            .copied()
+            .filter(|&w| !hub[w])
+            .collect())
+        .collect();
+    let mut labels: Vec<usize> = (0..n).collect();
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for v in (0..n).filter(|&v| !hub[v]) {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for &w in &neighbours[v] {
+                *counts.entry(labels[w]).or_default() += 1;
+            }
+            // most frequent label, smallest one on ties, keeping the current one if it's tied
+            let best = counts.iter()
+                .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+                .map(|(&label, &count)| {
+                    if counts.get(&labels[v]) == Some(&count) { labels[v] } else { label }
+                });
+            if let Some(best) = best {
+                if best != labels[v] {
+                    labels[v] = best;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    labels
+}
+
+/// Name for a cluster: its library with the most connections
+pub fn cluster_name(graph: &DepGraph, members: &[usize]) -> String {
+    let reverse = graph.reverse_edges();
+    members.iter()
+        .max_by_key(|&&m| (
+            graph.nodes[m].kind == NodeKind::Library,
+            reverse[m].len() + graph.edges[m].len(),
+            std::cmp::Reverse(&graph.nodes[m].name),
+        ))
+        .map(|&m| graph.nodes[m].name.clone())
+        .unwrap_or_default()
+}
+
+pub fn print_report(graph: &DepGraph) {
+    let labels = label_propagation(graph);
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (node, &label) in labels.iter().enumerate() {
+        clusters.entry(label).or_default().push(node);
+    }
+    let mut clusters: Vec<Vec<usize>> = clusters.into_values()
+        .filter(|c| c.len() > 1)
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.len()));
+    println!("clusters ({} clusters)", clusters.len());
+    println!();
+    for members in clusters {
+        let (libs, exes): (Vec<usize>, Vec<usize>) = members.iter()
+            .partition(|&&m| graph.nodes[m].kind == NodeKind::Library);
+        println!(
+            "{} ({} libs, {} exes)",
+            cluster_name(graph, &members), libs.len(), exes.len()
+        );
+        for lib in libs {
+            println!("\t{}", graph.nodes[lib].name);
+        }
+        for exe in exes {
+            println!("\t<= {}", graph.nodes[exe].name);
+        }
+        println!()
+    }
+}
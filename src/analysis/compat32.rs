@@ -0,0 +1,97 @@
+//! 32-bit executables on a 64-bit root, and what has to be installed to run them.
+
+use std::collections::BTreeMap;
+use crate::elf::{BinaryFormat, Target};
+use crate::resolve::Resolver;
+use crate::root;
+use crate::scan::ScannedFile;
+
+/// 32-bit executables of one target with what they need
+#[derive(Debug, Default)]
+pub struct Requirements<'a> {
+    pub exes: Vec<&'a ScannedFile>,
+    /// Program interpreters, with whether they exist
+    pub loaders: BTreeMap<String, bool>,
+    /// Libraries no compatible copy of could be found, with their users
+    pub missing: BTreeMap<String, Vec<&'a str>>,
+}
+
+/// Requirements of the 32-bit files, `None` if there's no 64-bit file to suggest a 64-bit root
+pub fn requirements<'a>(files: &'a [ScannedFile], resolver: &mut Resolver) -> Option<BTreeMap<Target, Requirements<'a>>> {
+    if !files.iter().any(|f| f.info.bits == 64) {
+        return None;
+    }
+    let mut result: BTreeMap<Target, Requirements> = BTreeMap::new();
+    // 32-bit PE files need Wine rather than multilib libraries
+    for file in files.iter().filter(|f| f.info.bits == 32 && f.info.format == BinaryFormat::Elf) {
+        let target = file.info.target();
+        let closure = resolver.closure_of(&file.info);
+        let req = result.entry(target.clone()).or_default();
+        req.exes.push(file);
+        if let Some(interp) = &file.info.interp {
+            let exists = root::canonicalize(&resolver.root, &root::join(&resolver.root, interp)).is_some();
+            req.loaders.insert(interp.clone(), exists);
+        }
+        for soname in closure {
+            if resolver.resolve(&soname, &target).is_none() {
+                req.missing.entry(soname).or_default().push(&file.name);
+            }
+        }
+    }
+    Some(result)
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver) {
+    println!("32-bit compatibility");
+    let requirements = match requirements(files, resolver) {
+        Some(r) if !r.is_empty() => r,
+        Some(_) => {
+            println!("\tno 32-bit executables\n");
+            return;
+        },
+        None => {
+            println!("\tno 64-bit files found, not a 64-bit root\n");
+            return;
+        },
+    };
+    for (target, req) in &requirements {
+        let missing_loaders = req.loaders.values().filter(|exists| !**exists).count();
+        println!(
+            "\t{}: {} exes, {} of {} loaders missing, {} libraries missing",
+            target, req.exes.len(), missing_loaders, req.loaders.len(), req.missing.len()
+        );
+    }
+    println!();
+    for (target, req) in requirements {
+        println!("{} executables ({} exes)", target, req.exes.len());
+        for exe in &req.exes {
+            let missing = req.missing.values().filter(|users| users.contains(&exe.name.as_str())).count();
+            let loader_ok = exe.info.interp.as_ref().is_none_or(|i| req.loaders[i]);
+            let status = match (loader_ok, missing) {
+                (true, 0) => "runnable".to_string(),
+                (true, n) => format!("{} libraries missing", n),
+                (false, 0) => "loader missing".to_string(),
+                (false, n) => format!("loader and {} libraries missing", n),
+            };
+            println!("\t<= {} ({})", exe.name, status);
+        }
+        println!();
+        for (loader, exists) in &req.loaders {
+            println!("loader {} ({})", loader, if *exists { "installed" } else { "missing" });
+        }
+        if !req.loaders.is_empty() {
+            println!();
+        }
+        if req.missing.is_empty() {
+            continue;
+        }
+        println!("{} libraries to install ({} libs)", target, req.missing.len());
+        for (lib, users) in req.missing {
+            println!("\t{}", lib);
+            for user in users {
+                println!("\t\t<= {}", user);
+            }
+        }
+        println!()
+    }
+}
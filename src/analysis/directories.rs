@@ -0,0 +1,55 @@
+//! Results rolled up by directory, for reviewing large trees top-down. The members of an archive
+//! count as a directory of their own.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use crate::archive;
+use crate::escape;
+use crate::resolve::Resolver;
+use crate::scan::Scan;
+
+/// Totals of one directory
+#[derive(Debug, Default)]
+struct Rollup<'a> {
+    binaries: usize,
+    failed: usize,
+    dependencies: BTreeSet<&'a str>,
+    missing: BTreeSet<&'a str>,
+}
+
+fn directory(path: &Path) -> &Path {
+    archive::archive_of(path)
+        .or_else(|| path.parent())
+        .unwrap_or(path)
+}
+
+pub fn print_report(scan: &Scan, resolver: &mut Resolver) {
+    let mut dirs: BTreeMap<&Path, Rollup> = BTreeMap::new();
+    for file in &scan.files {
+        let rollup = dirs.entry(directory(&file.path)).or_default();
+        rollup.binaries += 1;
+        let target = file.info.target();
+        for lib in &file.info.needed {
+            rollup.dependencies.insert(lib);
+            if resolver.resolve(lib, &target).is_none() {
+                rollup.missing.insert(lib);
+            }
+        }
+    }
+    for failure in scan.failures.iter().filter(|f| !f.is_expected()) {
+        dirs.entry(directory(&failure.path)).or_default().failed += 1;
+    }
+    println!("directories ({})", dirs.len());
+    println!();
+    for (dir, rollup) in dirs {
+        println!("{} ({} binaries)", escape::path(dir), rollup.binaries);
+        println!(
+            "\tfailed: {}, dependencies: {}, missing: {}",
+            rollup.failed, rollup.dependencies.len(), rollup.missing.len()
+        );
+        for lib in rollup.missing {
+            println!("\tmissing {}", lib);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,55 @@
+//! Libraries loaded at run time with `dlopen`, which never show up in `DT_NEEDED`: shared object
+//! names and paths found in the read-only data of binaries importing `dlopen`.
+//!
+//! This is a heuristic. A name in the binary may never be loaded, and names put together at run
+//! time, like `libfoo-%s.so`, aren't found.
+
+use std::collections::{BTreeMap, HashSet};
+use crate::elf::Target;
+use crate::escape;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Probable `dlopen` dependencies for each target, with the files naming them
+pub fn probable<'a>(files: &'a [ScannedFile], filter: &LibFilter) -> BTreeMap<(&'a str, Target), Vec<&'a str>> {
+    let mut probable: BTreeMap<(&str, Target), Vec<&str>> = BTreeMap::new();
+    for file in files {
+        let names = file.info.dlopen_strings.iter()
+            .filter(|s| !file.info.needed.contains(s) && !filter.is_hidden(s));
+        for name in names {
+            probable.entry((name, file.info.target())).or_default().push(&file.name);
+        }
+    }
+    probable
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter) {
+    let probable = probable(files, filter);
+    let targets: HashSet<&Target> = probable.keys().map(|(_, target)| target).collect();
+    println!("probable dlopen dependencies, guessed from strings ({} libs)", probable.len());
+    println!();
+    for ((name, target), exes) in &probable {
+        if targets.len() > 1 {
+            println!("{} [{}] ({} exes)", name, target, exes.len());
+        }
+        else {
+            println!("{} ({} exes)", name, exes.len());
+        }
+        if name.contains('/') {
+            if !resolver.has_file(name) {
+                println!("\t! doesn't exist");
+            }
+        }
+        else {
+            match resolver.resolve(name, target) {
+                Some(lib) => println!("\tfound at {}", escape::path(&lib.path)),
+                None => println!("\t! not found"),
+            }
+        }
+        for exe in exes {
+            println!("\t<= {}", exe);
+        }
+        println!();
+    }
+}
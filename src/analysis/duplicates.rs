@@ -0,0 +1,67 @@
+//! Identical binaries reached through different paths: copies and hardlinks sharing a GNU
+//! build-id. Only files linked with `--build-id` carry one, so those without it never count as
+//! duplicates.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use crate::elf::BinaryFormat;
+use crate::escape;
+use crate::scan::{self, FileId, ScannedFile};
+
+/// Files of every build-id shared by more than one path, in scan order
+pub fn groups(files: &[ScannedFile]) -> BTreeMap<&str, Vec<&ScannedFile>> {
+    let mut groups: BTreeMap<&str, Vec<&ScannedFile>> = BTreeMap::new();
+    for file in files.iter().filter(|f| f.info.format == BinaryFormat::Elf) {
+        if let Some(build_id) = &file.info.build_id {
+            groups.entry(build_id).or_default().push(file);
+        }
+    }
+    groups.retain(|_, files| files.len() > 1);
+    groups
+}
+
+/// Keeps only the first of the files sharing a build-id
+pub fn dedupe(files: &mut Vec<ScannedFile>) {
+    let mut seen = HashSet::new();
+    files.retain(|f| f.info.format != BinaryFormat::Elf || f.info.build_id.as_ref().is_none_or(|id| seen.insert(id.clone())));
+}
+
+/// Identity of a file on the host, the same for hardlinks on Unix
+fn inode(file: &ScannedFile) -> Option<FileId> {
+    fs::metadata(&file.path).ok().and_then(|m| scan::file_id(&file.path, &m))
+}
+
+pub fn print_report(files: &[ScannedFile]) {
+    let groups = groups(files);
+    let mut redundant = 0;
+    let mut wasted = 0;
+    let mut lines = vec![];
+    for (build_id, files) in &groups {
+        let mut inodes = HashSet::new();
+        let mut paths = vec![];
+        for file in files {
+            // archive members and image files have no inode of their own
+            let copy = inode(file).is_none_or(|i| inodes.insert(i));
+            if copy && !paths.is_empty() {
+                redundant += 1;
+                wasted += file.info.size;
+            }
+            paths.push((file, copy));
+        }
+        lines.push((build_id, paths));
+    }
+    println!("duplicate binaries ({} build-ids, {} redundant copies, {} bytes)", groups.len(), redundant, wasted);
+    println!();
+    for (build_id, paths) in lines {
+        println!("{} ({} paths)", build_id, paths.len());
+        for (file, copy) in paths {
+            if copy {
+                println!("\t= {}", escape::path(&file.path));
+            }
+            else {
+                println!("\t= {} (hardlink)", escape::path(&file.path));
+            }
+        }
+        println!();
+    }
+}
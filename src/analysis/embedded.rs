@@ -0,0 +1,24 @@
+//! Third-party libraries linked statically into the scanned binaries.
+//!
+//! These copies don't show up in `DT_NEEDED`, so updating the shared library doesn't fix
+//! vulnerabilities in them; every binary listed has to be rebuilt.
+
+use std::collections::BTreeMap;
+use crate::scan::ScannedFile;
+use crate::signatures::Embedded;
+
+pub fn print_report(files: &[ScannedFile]) {
+    let mut copies: BTreeMap<&Embedded, Vec<&str>> = BTreeMap::new();
+    for file in files {
+        for embedded in &file.info.embedded {
+            copies.entry(embedded).or_default().push(&file.name);
+        }
+    }
+    for (embedded, names) in copies {
+        println!("{} [static] ({} exes)", embedded, names.len());
+        for name in names {
+            println!("\t<= {}", name);
+        }
+        println!();
+    }
+}
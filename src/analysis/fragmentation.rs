@@ -0,0 +1,52 @@
+//! Library families needed in several major versions at once, e.g. `libicuuc.so.70` and
+//! `libicuuc.so.72`, with the executables still on the older ones.
+
+use std::collections::BTreeMap;
+use crate::elf::BinaryFormat;
+use crate::output::text::soname_family;
+use crate::pattern::LibFilter;
+use crate::scan::ScannedFile;
+
+/// Numeric components of the version after `.so`, `libfoo.so.1.2` → `[1, 2]`. Non-numeric
+/// components sort first.
+fn version(soname: &str) -> Vec<u64> {
+    let family = soname_family(soname);
+    soname[family.len()..].trim_start_matches(".so")
+        .split('.')
+        .filter(|c| !c.is_empty())
+        .map(|c| c.parse().unwrap_or_default())
+        .collect()
+}
+
+pub fn print_report(files: &[ScannedFile], filter: &LibFilter) {
+    // family -> soname -> executables
+    let mut families: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for file in files.iter().filter(|f| f.info.format == BinaryFormat::Elf) {
+        for lib in file.info.needed.iter().filter(|l| !filter.is_hidden(l)) {
+            families.entry(soname_family(lib)).or_default()
+                .entry(lib).or_default()
+                .push(&file.name);
+        }
+    }
+    families.retain(|_, versions| versions.len() > 1);
+    println!("soname version fragmentation ({} families)", families.len());
+    println!();
+    for (family, versions) in families {
+        let mut versions: Vec<(&str, Vec<&str>)> = versions.into_iter().collect();
+        versions.sort_by(|a, b| version(b.0).cmp(&version(a.0)).then(a.0.cmp(b.0)));
+        let users: usize = versions.iter().map(|(_, exes)| exes.len()).sum();
+        println!("{} ({} versions, {} exes)", family, versions.len(), users);
+        for (i, (lib, exes)) in versions.iter().enumerate() {
+            if i == 0 {
+                println!("\t{} ({} exes)", lib, exes.len());
+            }
+            else {
+                println!("\t{} ({} exes, outdated)", lib, exes.len());
+            }
+            for exe in exes {
+                println!("\t\t<= {}", exe);
+            }
+        }
+        println!();
+    }
+}
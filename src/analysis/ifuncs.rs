@@ -0,0 +1,21 @@
+//! Binaries using GNU indirect functions.
+//!
+//! IFUNC resolvers run while the dynamic loader (or, in static binaries, the startup code)
+//! processes relocations, before constructors and often before libc is usable, which matters
+//! for early-startup code, static linking and hardening that restricts relocation processing.
+
+use crate::scan::ScannedFile;
+
+pub fn print_report(files: &[ScannedFile]) {
+    let users: Vec<&ScannedFile> = files.iter()
+        .filter(|f| f.info.ifuncs.used())
+        .collect();
+    println!("indirect functions ({} exes)", users.len());
+    for file in users {
+        println!(
+            "\t<= {} ({} ifunc symbols, {} IRELATIVE relocations)",
+            file.name, file.info.ifuncs.symbols, file.info.ifuncs.irelative
+        );
+    }
+    println!();
+}
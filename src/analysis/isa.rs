@@ -0,0 +1,43 @@
+//! x86-64 microarchitecture levels the scanned binaries need.
+//!
+//! The level comes from the ISA-needed property the toolchain records in `.note.gnu.property`
+//! (binutils 2.36 and later with `-mneeded` or `-march=x86-64-v*`). Binaries built without it
+//! are counted as unknown; they usually run on the baseline.
+
+use std::collections::BTreeMap;
+use crate::scan::ScannedFile;
+
+fn level_name(level: u8) -> String {
+    match level {
+        1 => "x86-64-baseline".to_string(),
+        _ => format!("x86-64-v{}", level),
+    }
+}
+
+pub fn print_report(files: &[ScannedFile]) {
+    let x86_64: Vec<&ScannedFile> = files.iter()
+        .filter(|f| f.info.arch == "x86_64")
+        .collect();
+    let mut levels: BTreeMap<u8, Vec<&str>> = BTreeMap::new();
+    let mut unknown = 0;
+    for file in &x86_64 {
+        match file.info.isa_level {
+            Some(level) => levels.entry(level).or_default().push(&file.name),
+            None => unknown += 1,
+        }
+    }
+    println!("x86-64 ISA levels ({} exes)", x86_64.len());
+    let counts: Vec<String> = levels.iter()
+        .map(|(level, exes)| format!("{}: {}", level_name(*level), exes.len()))
+        .chain(std::iter::once(format!("unknown: {}", unknown)))
+        .collect();
+    println!("\t{}", counts.join(", "));
+    println!();
+    for (level, exes) in levels.iter().rev().filter(|(level, _)| **level > 1) {
+        println!("need {} ({} exes)", level_name(*level), exes.len());
+        for exe in exes {
+            println!("\t<= {}", exe);
+        }
+        println!();
+    }
+}
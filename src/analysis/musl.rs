@@ -0,0 +1,147 @@
+//! glibc to musl migration feasibility.
+//!
+//! Every dynamically linked glibc binary has to be rebuilt to run on musl, so the interesting
+//! question is which of them would also need source changes. Findings are split into those
+//! that go away with a rebuild (glibc sonames, symbol versions, `__isoc99_*` redirects) and
+//! those that don't (NSS, libnsl, glibc-only functions).
+
+use crate::elf::{BinaryFormat, ElfInfo};
+use crate::scan::ScannedFile;
+
+/// Libraries that are part of glibc. musl has all of them merged into `libc.so`
+const GLIBC_SONAMES: &[&str] = &[
+    "libc.so.6", "libm.so.6", "libpthread.so.0", "libdl.so.2", "librt.so.1",
+    "libutil.so.1", "libresolv.so.2", "libcrypt.so.1", "libanl.so.1",
+    "libBrokenLocale.so.1", "libmvec.so.1", "libthread_db.so.1",
+];
+
+/// Functions provided by glibc but not by musl
+const GLIBC_ONLY_SYMBOLS: &[&str] = &[
+    "backtrace", "backtrace_symbols", "backtrace_symbols_fd",
+    "error", "error_at_line",
+    "argp_parse", "argp_help", "argp_usage", "argp_error", "argp_failure", "argp_state_help",
+    "_obstack_begin", "_obstack_newchunk", "_obstack_free", "obstack_free",
+    "fts_open", "fts_read", "fts_close", "fts_children", "fts_set",
+    "mallinfo", "mallinfo2", "malloc_info", "malloc_stats", "mtrace", "muntrace", "mcheck",
+    "gnu_get_libc_version", "gnu_get_libc_release",
+    "__nss_database_lookup", "__nss_configure_lookup",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    /// No glibc-specific interfaces
+    Portable,
+    /// Uses glibc ABI details that disappear when rebuilt against musl
+    Rebuild,
+    /// Uses interfaces musl doesn't have
+    Blocked,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub blocking: bool,
+    pub message: String,
+}
+
+fn is_glibc_soname(lib: &str) -> bool {
+    GLIBC_SONAMES.contains(&lib) || lib.starts_with("ld-linux")
+}
+
+pub fn analyze(info: &ElfInfo) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut blocking = |message: String| findings.push(Finding { blocking: true, message });
+    for lib in &info.needed {
+        if lib.starts_with("libnsl.so") {
+            blocking(format!("needs {} (Sun RPC/NIS, not available on musl)", lib));
+        }
+        else if lib.starts_with("libnss_") {
+            blocking(format!("needs NSS module {}", lib));
+        }
+    }
+    for s in &info.dlopen_strings {
+        if s.rsplit('/').next().unwrap_or(s).starts_with("libnss_") {
+            blocking(format!("dlopens NSS module {}", s));
+        }
+    }
+    for sym in &info.imports {
+        if GLIBC_ONLY_SYMBOLS.contains(&sym.as_str()) {
+            blocking(format!("imports glibc-only function {}", sym));
+        }
+    }
+    for need in &info.version_needs {
+        if need.versions.iter().any(|v| v == "GLIBC_PRIVATE") {
+            blocking(format!("needs GLIBC_PRIVATE symbols from {}", need.file));
+        }
+    }
+
+    let mut rebuild = |message: String| findings.push(Finding { blocking: false, message });
+    for lib in info.needed.iter().filter(|l| is_glibc_soname(l)) {
+        rebuild(format!("links glibc library {}", lib));
+    }
+    let isoc: Vec<&str> = info.imports.iter()
+        .filter(|s| s.starts_with("__isoc99_") || s.starts_with("__isoc23_"))
+        .map(String::as_str)
+        .collect();
+    if !isoc.is_empty() {
+        rebuild(format!("imports {}", isoc.join(", ")));
+    }
+    for need in &info.version_needs {
+        let versions: Vec<&str> = need.versions.iter()
+            .filter(|v| v.starts_with("GLIBC_2"))
+            .map(String::as_str)
+            .collect();
+        if !versions.is_empty() {
+            rebuild(format!("needs {} from {}", versions.join(", "), need.file));
+        }
+    }
+    findings
+}
+
+pub fn verdict(findings: &[Finding]) -> Verdict {
+    if findings.iter().any(|f| f.blocking) {
+        Verdict::Blocked
+    }
+    else if findings.is_empty() {
+        Verdict::Portable
+    }
+    else {
+        Verdict::Rebuild
+    }
+}
+
+pub fn print_report(files: &[ScannedFile]) {
+    let mut results: Vec<(&str, Vec<Finding>, Verdict)> = files.iter()
+        .filter(|file| file.info.format == BinaryFormat::Elf)
+        .map(|file| {
+            let findings = analyze(&file.info);
+            let v = verdict(&findings);
+            (file.name.as_str(), findings, v)
+        })
+        .collect();
+    results.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+    let count = |v: Verdict| results.iter().filter(|r| r.2 == v).count();
+    println!("musl compatibility");
+    println!(
+        "\tportable: {}, needs rebuild: {}, blocked: {}",
+        count(Verdict::Portable), count(Verdict::Rebuild), count(Verdict::Blocked)
+    );
+    println!();
+    for (verdict, title) in [
+        (Verdict::Blocked, "would not work on musl"),
+        (Verdict::Rebuild, "would work on musl after a rebuild"),
+        (Verdict::Portable, "would work on musl as is"),
+    ] {
+        let exes: Vec<_> = results.iter().filter(|r| r.2 == verdict).collect();
+        if exes.is_empty() {
+            continue;
+        }
+        println!("{} ({} exes)", title, exes.len());
+        for (name, findings, _) in exes {
+            println!("\t<= {}", name);
+            for f in findings.iter().filter(|f| f.blocking || verdict == Verdict::Rebuild) {
+                println!("\t\t{}", f.message);
+            }
+        }
+        println!()
+    }
+}
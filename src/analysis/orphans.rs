@@ -0,0 +1,77 @@
+//! Orphan libraries: shared objects in the standard library directories that nothing scanned
+//! needs, directly or transitively. Libraries loaded only through `dlopen` show up here as well,
+//! so the result is a list of removal candidates rather than a verdict.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::escape;
+use crate::resolve::Resolver;
+use crate::root;
+use crate::scan::ScannedFile;
+
+fn is_elf(path: &PathBuf) -> bool {
+    let mut magic = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok() && magic == *b"\x7fELF"
+}
+
+/// Shared objects in the library directories, keyed by canonical path, with every name
+/// (symlinks included) they are reachable by
+fn installed_libraries(root: &Path, dirs: &[PathBuf]) -> BTreeMap<PathBuf, Vec<String>> {
+    let mut libs: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for dir in dirs {
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let name = escape::os_str(&entry.file_name());
+            if !name.contains(".so") {
+                continue;
+            }
+            let path = match root::canonicalize(root, &entry.path()) {
+                Some(p) => p,
+                None => continue,
+            };
+            if path.is_file() && is_elf(&path) {
+                libs.entry(path).or_default().push(name);
+            }
+        }
+    }
+    libs
+}
+
+/// Canonical paths of orphaned libraries with the names they are installed under
+pub fn find_orphans(files: &[ScannedFile], resolver: &mut Resolver) -> BTreeMap<PathBuf, Vec<String>> {
+    let root = resolver.root.clone();
+    let mut used: HashSet<PathBuf> = files.iter()
+        .filter_map(|f| root::canonicalize(&root, &f.path))
+        .collect();
+    for file in files {
+        let target = file.info.target();
+        for soname in resolver.closure_of(&file.info) {
+            if let Some(lib) = resolver.resolve(&soname, &target) {
+                if let Some(path) = root::canonicalize(&root, &lib.path) {
+                    used.insert(path);
+                }
+            }
+        }
+    }
+    let mut libs = installed_libraries(&root, &resolver.dirs);
+    libs.retain(|path, _| !used.contains(path));
+    libs
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver) {
+    let orphans = find_orphans(files, resolver);
+    println!("orphan libraries ({} libs)", orphans.len());
+    for (path, mut names) in orphans {
+        names.sort();
+        names.dedup();
+        println!("\t{} ({})", escape::path(&path), names.join(", "));
+    }
+    println!()
+}
@@ -0,0 +1,67 @@
+//! Distro packages the scanned executables need: the owners of every library of their dependency
+//! closures, e.g. everything a minimal container image has to install. Libraries no package owns
+//! were installed by hand and have to be copied.
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::elf::BinaryFormat;
+use crate::escape;
+use crate::packages::Database;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Libraries and dependants of a package
+#[derive(Default)]
+pub struct Requirement<'a> {
+    pub libs: BTreeSet<String>,
+    pub exes: BTreeSet<&'a str>,
+}
+
+/// Packages needed by `files`, and the paths of the libraries no package owns with their sonames
+pub fn requirements<'a>(
+    files: &'a [ScannedFile], resolver: &mut Resolver, database: &'a Database, filter: &LibFilter,
+) -> (BTreeMap<&'a str, Requirement<'a>>, BTreeMap<String, String>) {
+    let mut packages: BTreeMap<&str, Requirement> = BTreeMap::new();
+    let mut unowned = BTreeMap::new();
+    for file in files.iter().filter(|f| f.info.format == BinaryFormat::Elf) {
+        let target = file.info.target();
+        for soname in resolver.closure_of(&file.info).into_iter().filter(|l| !filter.is_hidden(l)) {
+            let path = match resolver.resolve(&soname, &target) {
+                Some(lib) => lib.path.clone(),
+                None => continue,
+            };
+            match database.owner(&path) {
+                Some(package) => {
+                    let requirement = packages.entry(package).or_default();
+                    requirement.libs.insert(soname);
+                    requirement.exes.insert(&file.name);
+                },
+                None => {
+                    unowned.insert(escape::path(&path), soname);
+                },
+            }
+        }
+    }
+    (packages, unowned)
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver, database: &Database, filter: &LibFilter) {
+    let (packages, unowned) = requirements(files, resolver, database, filter);
+    println!("packages required ({} packages, {})", packages.len(), database.manager);
+    println!();
+    for (package, requirement) in packages {
+        println!("{} ({} exes)", package, requirement.exes.len());
+        for lib in requirement.libs {
+            println!("\t{}", lib);
+        }
+        for exe in requirement.exes {
+            println!("\t<= {}", exe);
+        }
+        println!();
+    }
+    println!("libraries owned by no package ({} libs)", unowned.len());
+    for (path, soname) in unowned {
+        println!("\t{} ({})", soname, path);
+    }
+    println!();
+}
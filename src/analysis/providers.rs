@@ -0,0 +1,96 @@
+//! The scanned tree as a self-contained whole, like an AppDir or a container rootfs: which
+//! scanned file provides each soname, the libraries nothing else in the tree needs and the
+//! sonames the tree needs but doesn't ship.
+//!
+//! Libraries are all scanned along with the executables, so their own needs count as well and
+//! direct needs cover every closure. A soname is only provided to binaries of the same target.
+
+use std::collections::{BTreeMap, HashSet};
+use crate::elf::{BinaryFormat, Target};
+use crate::escape;
+use crate::pattern::LibFilter;
+use crate::scan::ScannedFile;
+
+/// Name the loader finds `file` by: its `DT_SONAME`, or the file name of shared objects
+/// without one
+pub fn provided_name(file: &ScannedFile) -> Option<&str> {
+    if file.info.format != BinaryFormat::Elf {
+        return None;
+    }
+    match &file.info.soname {
+        Some(soname) => Some(soname),
+        None => file.path.file_name()
+            .and_then(|n| n.to_str())
+            .filter(|n| n.contains(".so")),
+    }
+}
+
+/// Scanned files providing each soname
+pub fn index(files: &[ScannedFile]) -> BTreeMap<&str, Vec<&ScannedFile>> {
+    let mut providers: BTreeMap<&str, Vec<&ScannedFile>> = BTreeMap::new();
+    for file in files {
+        if let Some(name) = provided_name(file) {
+            providers.entry(name).or_default().push(file);
+        }
+    }
+    providers
+}
+
+/// Providers, orphaned libraries and needs the tree doesn't satisfy
+pub struct Report<'a> {
+    pub providers: BTreeMap<&'a str, Vec<&'a ScannedFile>>,
+    /// Libraries no other scanned file needs
+    pub orphans: Vec<&'a ScannedFile>,
+    /// Sonames no scanned file provides, with the files needing them
+    pub unprovided: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+pub fn report<'a>(files: &'a [ScannedFile], filter: &LibFilter) -> Report<'a> {
+    let providers = index(files);
+    let mut needed: HashSet<(&str, Target)> = HashSet::new();
+    let mut unprovided: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in files {
+        let target = file.info.target();
+        for soname in &file.info.needed {
+            let provided = providers.get(soname.as_str())
+                .is_some_and(|p| p.iter().any(|l| l.info.target() == target && l.path != file.path));
+            if provided {
+                needed.insert((soname, target.clone()));
+            }
+            else if !filter.is_hidden(soname) {
+                unprovided.entry(soname).or_default().push(&file.name);
+            }
+        }
+    }
+    let orphans = files.iter()
+        .filter(|f| provided_name(f).is_some_and(|name| !needed.contains(&(name, f.info.target()))))
+        .collect();
+    Report { providers, orphans, unprovided }
+}
+
+pub fn print_report(files: &[ScannedFile], filter: &LibFilter) {
+    let report = report(files, filter);
+    println!("sonames provided ({} libs)", report.providers.len());
+    for (soname, files) in &report.providers {
+        for file in files {
+            println!("\t{} => {}", soname, escape::path(&file.path));
+        }
+    }
+    println!();
+    println!("libraries nothing in the tree needs ({} libs)", report.orphans.len());
+    for file in &report.orphans {
+        println!("\t{}", escape::path(&file.path));
+    }
+    println!();
+    println!("sonames needed but not provided ({} libs)", report.unprovided.len());
+    println!();
+    for (soname, mut exes) in report.unprovided {
+        exes.sort();
+        exes.dedup();
+        println!("{} ({} exes)", soname, exes.len());
+        for exe in exes {
+            println!("\t<= {}", exe);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,87 @@
+//! Problems with the run-time search paths binaries carry: `DT_RPATH`, which `LD_LIBRARY_PATH`
+//! can't override and which is ignored next to `DT_RUNPATH`, entries relative to the working
+//! directory and absolute paths left over from the machine the binary was built on.
+
+use std::fmt;
+use std::path::Path;
+use crate::elf::BinaryFormat;
+use crate::loader;
+use crate::root;
+use crate::scan::ScannedFile;
+
+/// Directories builds happen in but that binaries shouldn't load libraries from
+const BUILD_DIRS: &[&str] = &["/home/", "/root/", "/tmp/", "/var/tmp/", "/build/", "/builddir/", "/usr/src/", "/workspace/", "/__w/"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// `DT_RPATH` without `DT_RUNPATH`
+    Rpath,
+    /// `DT_RPATH` next to `DT_RUNPATH`, so the loader doesn't look at it
+    IgnoredRpath,
+    /// Entry resolved against the working directory of the process
+    Relative(String),
+    /// Absolute entry under a directory builds happen in
+    BuildPath(String),
+    /// Absolute entry that doesn't exist on the analyzed filesystem
+    Nonexistent(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Rpath => write!(f, "uses the deprecated DT_RPATH, which LD_LIBRARY_PATH can't override; link with --enable-new-dtags for DT_RUNPATH"),
+            Problem::IgnoredRpath => write!(f, "has DT_RPATH next to DT_RUNPATH, the loader ignores it"),
+            Problem::Relative(dir) => write!(f, "search path {} is relative to the working directory, use $ORIGIN", dir),
+            Problem::BuildPath(dir) => write!(f, "search path {} looks like a path on the build machine", dir),
+            Problem::Nonexistent(dir) => write!(f, "search path {} doesn't exist", dir),
+        }
+    }
+}
+
+/// Problems with the search paths of an ELF file, `root` being the filesystem to check absolute
+/// entries against, if any
+pub fn lint(file: &ScannedFile, root: Option<&Path>) -> Vec<Problem> {
+    let info = &file.info;
+    let mut problems = vec![];
+    if info.format != BinaryFormat::Elf {
+        return problems;
+    }
+    if !info.rpaths.is_empty() {
+        problems.push(if info.runpaths.is_empty() { Problem::Rpath } else { Problem::IgnoredRpath });
+    }
+    let target = info.target();
+    for dir in info.rpaths.iter().chain(&info.runpaths) {
+        if !dir.starts_with('/') && !dir.starts_with("$ORIGIN") && !dir.starts_with("${ORIGIN}") {
+            problems.push(Problem::Relative(dir.clone()));
+            continue;
+        }
+        if !dir.starts_with('/') {
+            continue;
+        }
+        let expanded = loader::expand_tokens(root.unwrap_or(Path::new("/")), dir, Path::new(""), &target);
+        let directory = format!("{}/", expanded.trim_end_matches('/'));
+        if BUILD_DIRS.iter().any(|d| directory.starts_with(d)) {
+            problems.push(Problem::BuildPath(dir.clone()));
+        }
+        else if root.is_some_and(|root| !root::join(root, &expanded).is_dir()) {
+            problems.push(Problem::Nonexistent(dir.clone()));
+        }
+    }
+    problems
+}
+
+pub fn print_report(files: &[ScannedFile], root: Option<&Path>) {
+    let results: Vec<(&ScannedFile, Vec<Problem>)> = files.iter()
+        .map(|f| (f, lint(f, root)))
+        .filter(|(_, problems)| !problems.is_empty())
+        .collect();
+    println!("search path problems ({} exes)", results.len());
+    println!();
+    for (file, problems) in results {
+        println!("{}", file.name);
+        for problem in problems {
+            println!("\t! {}", problem);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,63 @@
+//! Runtime-stack categorization: which language runtimes, GPU and GUI stacks show up in the
+//! dependency closures of the scanned binaries.
+
+use std::collections::BTreeMap;
+use crate::scan::ScannedFile;
+use crate::resolve::Resolver;
+
+/// (category, soname prefixes revealing it)
+const CATEGORIES: &[(&str, &[&str])] = &[
+    ("Python", &["libpython"]),
+    ("Java", &["libjvm.so"]),
+    ("Ruby", &["libruby"]),
+    ("Node.js", &["libnode.so"]),
+    ("Perl", &["libperl.so"]),
+    ("Lua", &["liblua"]),
+    ("Tcl", &["libtcl"]),
+    ("GPU: CUDA", &["libcuda.so", "libcudart.so"]),
+    ("GPU: OpenCL", &["libOpenCL.so"]),
+    ("GPU: Vulkan", &["libvulkan.so"]),
+    ("GPU: OpenGL", &["libGL.so", "libEGL.so", "libGLX.so", "libOpenGL.so"]),
+    ("GUI: Qt", &["libQt"]),
+    ("GUI: GTK", &["libgtk-", "libgdk-"]),
+    ("GUI: X11", &["libX11.so", "libxcb.so"]),
+    ("GUI: Wayland", &["libwayland-client.so"]),
+];
+
+/// Categories revealed by a dependency closure, with the sonames that revealed each of them
+pub fn categorize(closure: &[String]) -> BTreeMap<&'static str, Vec<String>> {
+    let mut found: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+    for soname in closure {
+        for (category, prefixes) in CATEGORIES {
+            if prefixes.iter().any(|p| soname.starts_with(p)) {
+                found.entry(category).or_default().push(soname.clone());
+            }
+        }
+    }
+    found
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver) {
+    let mut rollup: BTreeMap<&'static str, Vec<(&str, Vec<String>)>> = BTreeMap::new();
+    for file in files {
+        let closure = resolver.closure_of(&file.info);
+        for (category, libs) in categorize(&closure) {
+            rollup.entry(category).or_default().push((&file.name, libs));
+        }
+    }
+    println!("runtime stacks");
+    if rollup.is_empty() {
+        println!("\tnone found");
+    }
+    for (category, exes) in &rollup {
+        println!("\t{}: {} exes", category, exes.len());
+    }
+    println!();
+    for (category, exes) in rollup {
+        println!("{} ({} exes)", category, exes.len());
+        for (exe, libs) in exes {
+            println!("\t<= {} ({})", exe, libs.join(", "));
+        }
+        println!()
+    }
+}
@@ -0,0 +1,131 @@
+//! systemd services mapped to the binaries they run and the libraries those need, answering
+//! "which services to restart after upgrading a library".
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use crate::elf::parse_elf;
+use crate::escape;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::root;
+
+/// Unit directories in order of precedence
+const UNIT_DIRS: &[&str] = &["etc/systemd/system", "run/systemd/system", "usr/lib/systemd/system", "lib/systemd/system"];
+
+/// Where systemd looks for executables given without a path
+const EXEC_SEARCH_PATH: &[&str] = &["usr/local/sbin", "usr/local/bin", "usr/sbin", "usr/bin", "sbin", "bin"];
+
+const EXEC_KEYS: &[&str] = &[
+    "ExecCondition", "ExecStartPre", "ExecStart", "ExecStartPost",
+    "ExecReload", "ExecStop", "ExecStopPost",
+];
+
+#[derive(Debug, Clone)]
+pub struct Unit {
+    pub name: String,
+    /// Executables from the `Exec*=` lines, as absolute paths inside the root
+    pub execs: BTreeSet<String>,
+}
+
+/// The executable of an `Exec*=` value, without systemd's special prefixes
+fn exec_path(value: &str) -> Option<&str> {
+    let value = value.trim_start_matches(['@', '-', ':', '+', '!']);
+    value.split_whitespace().next()
+}
+
+fn parse_unit(name: String, path: &Path, root: &Path) -> Option<Unit> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("Couldn't read unit {}: {}", path.display(), e);
+            return None;
+        },
+    };
+    let mut execs = BTreeSet::new();
+    for line in content.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => continue,
+        };
+        if !EXEC_KEYS.contains(&key) {
+            continue;
+        }
+        let exec = match exec_path(value) {
+            Some(e) => e,
+            None => continue,
+        };
+        if exec.starts_with('/') {
+            execs.insert(exec.to_string());
+        }
+        else if let Some(dir) = EXEC_SEARCH_PATH.iter().find(|d| root::join(root, d).join(exec).is_file()) {
+            execs.insert(format!("/{}/{}", dir, exec));
+        }
+    }
+    Some(Unit { name, execs })
+}
+
+/// Service units found under `root`, units in earlier directories overriding later ones
+pub fn find_units(root: &Path) -> Vec<Unit> {
+    let mut units: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in UNIT_DIRS {
+        let entries = match fs::read_dir(root::join(root, dir)) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let name = escape::os_str(&entry.file_name());
+            if name.ends_with(".service") && entry.path().is_file() {
+                units.entry(name).or_insert_with(|| entry.path());
+            }
+        }
+    }
+    units.into_iter()
+        .filter_map(|(name, path)| parse_unit(name, &path, root))
+        .collect()
+}
+
+/// Libraries with the services whose executables need them, directly or transitively
+pub fn services_by_library(root: &Path, resolver: &mut Resolver, filter: &LibFilter) -> BTreeMap<String, BTreeSet<(String, String)>> {
+    let mut result: BTreeMap<String, BTreeSet<(String, String)>> = BTreeMap::new();
+    for unit in find_units(root) {
+        for exec in &unit.execs {
+            let path = match root::canonicalize(root, &root::join(root, exec)) {
+                Some(p) => p,
+                None => {
+                    debug!("Skipping {} of {}: not found", exec, unit.name);
+                    continue;
+                },
+            };
+            let info = match parse_elf(&path) {
+                Ok(i) => i,
+                Err(e) => {
+                    debug!("Skipping {} of {}: {}", exec, unit.name, e);
+                    continue;
+                },
+            };
+            for lib in resolver.closure_of(&info) {
+                if !filter.is_hidden(&lib) {
+                    result.entry(lib).or_default().insert((unit.name.clone(), exec.clone()));
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn print_report(root: &Path, resolver: &mut Resolver, filter: &LibFilter) {
+    let libs = services_by_library(root, resolver, filter);
+    let mut libs: Vec<_> = libs.into_iter().collect();
+    libs.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+    println!("services by library ({} libs)", libs.len());
+    println!();
+    for (lib, services) in libs {
+        println!("{} ({} services)", lib, services.len());
+        for (service, exec) in services {
+            println!("\t<= {} ({})", service, exec);
+        }
+        println!()
+    }
+}
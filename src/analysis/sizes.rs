@@ -0,0 +1,115 @@
+//! On-disk size of what the scanned executables load: every library found for them, counted
+//! once for the whole tree, and the dependency closure of each executable. Shows which
+//! dependencies are worth trimming when shrinking an image.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use crate::dry_run::human_bytes;
+use crate::escape;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Library found for some scanned executable
+#[derive(Debug, Clone)]
+pub struct LibrarySize {
+    pub soname: String,
+    pub path: String,
+    pub size: u64,
+    /// Executables with the library in their closure
+    pub dependants: usize,
+}
+
+/// Size of an executable with the libraries of its dependency closure
+#[derive(Debug, Clone)]
+pub struct ClosureSize<'a> {
+    pub exe: &'a str,
+    pub own: u64,
+    /// Total size of the libraries found, each file counted once
+    pub libraries: u64,
+    pub count: usize,
+}
+
+impl ClosureSize<'_> {
+    pub fn total(&self) -> u64 {
+        self.own + self.libraries
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Footprint<'a> {
+    /// Total size of the scanned files
+    pub executables: u64,
+    /// Largest first
+    pub libraries: Vec<LibrarySize>,
+    /// Largest first
+    pub closures: Vec<ClosureSize<'a>>,
+    /// Sonames needed but not found, which aren't counted
+    pub missing: BTreeSet<String>,
+}
+
+impl Footprint<'_> {
+    /// Size of every library found, each file counted once
+    pub fn unique_libraries(&self) -> u64 {
+        self.libraries.iter().map(|l| l.size).sum()
+    }
+}
+
+pub fn footprint<'a>(files: &'a [ScannedFile], resolver: &mut Resolver) -> Footprint<'a> {
+    let mut libraries: BTreeMap<String, LibrarySize> = BTreeMap::new();
+    let mut closures = vec![];
+    let mut missing = BTreeSet::new();
+    for file in files {
+        let target = file.info.target();
+        // sonames resolving to the same file through different links are counted once
+        let mut paths = HashSet::new();
+        let mut closure = ClosureSize { exe: &file.name, own: file.info.size, libraries: 0, count: 0 };
+        for soname in resolver.closure_of(&file.info) {
+            let lib = match resolver.resolve(&soname, &target) {
+                Some(lib) => lib,
+                None => {
+                    missing.insert(soname);
+                    continue;
+                },
+            };
+            let path = escape::path(&lib.path);
+            if !paths.insert(path.clone()) {
+                continue;
+            }
+            closure.libraries += lib.info.size;
+            closure.count += 1;
+            libraries.entry(path.clone())
+                .or_insert_with(|| LibrarySize { soname, path, size: lib.info.size, dependants: 0 })
+                .dependants += 1;
+        }
+        closures.push(closure);
+    }
+    let mut libraries: Vec<LibrarySize> = libraries.into_values().collect();
+    libraries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    closures.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.exe.cmp(b.exe)));
+    let executables = files.iter().map(|f| f.info.size).sum();
+    Footprint { executables, libraries, closures, missing }
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver, top: usize) {
+    let footprint = footprint(files, resolver);
+    println!("library footprint ({} libs)", footprint.libraries.len());
+    println!("\texecutables: {} ({} exes)", human_bytes(footprint.executables), files.len());
+    println!("\tlibraries needed by them: {} ({} libs)", human_bytes(footprint.unique_libraries()), footprint.libraries.len());
+    println!("\ttotal: {}", human_bytes(footprint.executables + footprint.unique_libraries()));
+    for soname in &footprint.missing {
+        println!("\t! {} not found, not counted", soname);
+    }
+    println!();
+    println!("largest libraries");
+    for lib in footprint.libraries.iter().take(top) {
+        println!("\t{:>10}  {} at {} ({} exes)", human_bytes(lib.size), lib.soname, lib.path, lib.dependants);
+    }
+    println!();
+    println!("largest dependency closures");
+    for closure in footprint.closures.iter().take(top) {
+        println!(
+            "\t{:>10}  {} ({} itself, {} in {} libs)",
+            human_bytes(closure.total()), closure.exe, human_bytes(closure.own), human_bytes(closure.libraries), closure.count
+        );
+    }
+    println!();
+}
@@ -0,0 +1,77 @@
+//! How much of each needed library's interface the scanned tree actually uses.
+//!
+//! A library's exported symbol counts as used if any scanned file or library that has it in its
+//! dependency closure imports a symbol of that name. Imports aren't bound to a particular
+//! library, so a symbol exported by several libraries counts for each of them, and symbols only
+//! looked up with `dlsym` count as unused.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use crate::elf::ElfInfo;
+use crate::resolve::{Library, Resolver};
+use crate::scan::ScannedFile;
+
+/// Exported symbols of one library, split by whether anything imports them
+pub struct Usage {
+    pub name: String,
+    pub used: Vec<String>,
+    pub unused: Vec<String>,
+}
+
+impl Usage {
+    pub fn coverage(&self) -> f64 {
+        let total = self.used.len() + self.unused.len();
+        if total == 0 { 0.0 } else { self.used.len() as f64 * 100.0 / total as f64 }
+    }
+}
+
+/// Usage of every library in the closure of the scanned files with exported symbols, least
+/// used first
+pub fn usage(files: &[ScannedFile], resolver: &mut Resolver) -> Vec<Usage> {
+    // libraries by path, with the name they were first needed under
+    let mut libraries: BTreeMap<PathBuf, (String, Library)> = BTreeMap::new();
+    let mut imported: BTreeMap<PathBuf, HashSet<String>> = BTreeMap::new();
+    let mut consumers: Vec<ElfInfo> = files.iter().map(|f| f.info.clone()).collect();
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    while let Some(consumer) = consumers.pop() {
+        let target = consumer.target();
+        for soname in resolver.closure(&consumer.needed, &target) {
+            let lib = match resolver.resolve(&soname, &target) {
+                Some(lib) => lib.clone(),
+                None => continue,
+            };
+            let imports = imported.entry(lib.path.clone()).or_default();
+            imports.extend(consumer.imports.iter().cloned());
+            if seen.insert(lib.path.clone()) {
+                consumers.push(lib.info.clone());
+                libraries.insert(lib.path.clone(), (soname, lib));
+            }
+        }
+    }
+    let mut usage: Vec<Usage> = libraries.into_iter()
+        .filter(|(_, (_, lib))| !lib.info.exports.is_empty())
+        .map(|(path, (name, lib))| {
+            let imports = &imported[&path];
+            let (used, unused) = lib.info.exports.into_iter().partition(|s| imports.contains(s));
+            Usage { name, used, unused }
+        })
+        .collect();
+    usage.sort_by(|a, b| a.coverage().total_cmp(&b.coverage()).then_with(|| a.name.cmp(&b.name)));
+    usage
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver) {
+    for lib in usage(files, resolver) {
+        println!(
+            "{} ({} of {} exported symbols used, {:.1}%)",
+            lib.name, lib.used.len(), lib.used.len() + lib.unused.len(), lib.coverage()
+        );
+        for symbol in &lib.used {
+            println!("\t+ {}", symbol);
+        }
+        for symbol in &lib.unused {
+            println!("\t- {}", symbol);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,61 @@
+//! Symbol versions required from each library, from the binaries' `.gnu.version_r` sections:
+//! the newest `GLIBC_2.34` a binary asks for is the oldest glibc it can run on.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use crate::elf::BinaryFormat;
+use crate::pattern::LibFilter;
+use crate::profiles::numbers;
+use crate::scan::ScannedFile;
+
+/// Orders versions of the same node family newest first, by family name otherwise, with names
+/// that aren't numbered (`GLIBC_PRIVATE`) after the numbered ones
+fn newest_first(a: &str, b: &str) -> Ordering {
+    let split = |v: &str| match v.rsplit_once('_') {
+        Some((family, number)) => (family.to_string(), numbers(number)),
+        None => (v.to_string(), None),
+    };
+    let ((family_a, number_a), (family_b, number_b)) = (split(a), split(b));
+    family_a.cmp(&family_b)
+        .then_with(|| match (number_a, number_b) {
+            (Some(x), Some(y)) => y.cmp(&x),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+        .then_with(|| a.cmp(b))
+}
+
+/// Libraries with the versions required from them and the binaries requiring each
+pub fn requirements<'a>(files: &'a [ScannedFile], filter: &LibFilter) -> BTreeMap<&'a str, BTreeMap<&'a str, Vec<&'a str>>> {
+    let mut libraries: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for file in files.iter().filter(|f| f.info.format == BinaryFormat::Elf) {
+        for need in file.info.version_needs.iter().filter(|n| !filter.is_hidden(&n.file)) {
+            let versions = libraries.entry(&need.file).or_default();
+            for version in &need.versions {
+                versions.entry(version).or_default().push(&file.name);
+            }
+        }
+    }
+    libraries
+}
+
+pub fn print_report(files: &[ScannedFile], filter: &LibFilter) {
+    let libraries = requirements(files, filter);
+    println!("symbol version requirements ({} libraries)", libraries.len());
+    println!();
+    for (lib, versions) in libraries {
+        let mut versions: Vec<(&str, Vec<&str>)> = versions.into_iter().collect();
+        versions.sort_by(|a, b| newest_first(a.0, b.0));
+        println!("{} ({} versions)", lib, versions.len());
+        for (version, mut exes) in versions {
+            exes.sort();
+            exes.dedup();
+            println!("\t{} ({} exes)", version, exes.len());
+            for exe in exes {
+                println!("\t\t<= {}", exe);
+            }
+        }
+        println!();
+    }
+}
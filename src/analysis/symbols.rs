@@ -0,0 +1,101 @@
+//! Which library each undefined symbol of a binary binds to, the symbols nothing provides and
+//! the needed libraries providing nothing the binary uses.
+//!
+//! Symbols are looked up in the binary's dependency closure in load order, direct dependencies
+//! first, so the first library exporting a name is the one it binds to. Symbol versions aren't
+//! compared, and weak imports missing everywhere are fine since the loader leaves them null.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use crate::elf::BinaryFormat;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Symbol-level view of one binary's dependencies
+pub struct Bindings {
+    pub name: String,
+    /// Imports with the soname of the library providing them
+    pub bound: Vec<(String, String)>,
+    /// Strong imports no loaded library exports
+    pub unresolved: Vec<String>,
+    /// Direct dependencies none of the imports bind to
+    pub unused: Vec<String>,
+    /// Libraries of the closure that weren't found, whose symbols count as unresolved
+    pub missing: Vec<String>,
+}
+
+/// Exported symbols of every library looked at, by path
+#[derive(Default)]
+pub struct Exports(HashMap<PathBuf, HashSet<String>>);
+
+/// Bindings of `file`'s imports
+pub fn bindings(file: &ScannedFile, resolver: &mut Resolver, exports: &mut Exports) -> Bindings {
+    let target = file.info.target();
+    // (soname, exports) in load order
+    let mut loaded: Vec<(String, &HashSet<String>)> = vec![];
+    let mut paths = vec![];
+    let mut missing = vec![];
+    for soname in resolver.closure(&file.info.needed, &target) {
+        match resolver.resolve(&soname, &target) {
+            Some(lib) => {
+                exports.0.entry(lib.path.clone()).or_insert_with(|| lib.info.exports.iter().cloned().collect());
+                paths.push((soname, lib.path.clone()));
+            },
+            None => missing.push(soname),
+        }
+    }
+    for (soname, path) in paths {
+        loaded.push((soname, &exports.0[&path]));
+    }
+    let weak: HashSet<&String> = file.info.weak_imports.iter().collect();
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut result = Bindings { name: file.name.clone(), bound: vec![], unresolved: vec![], unused: vec![], missing };
+    let mut imports = file.info.imports.clone();
+    imports.sort();
+    imports.dedup();
+    for symbol in imports {
+        match loaded.iter().find(|(_, exports)| exports.contains(&symbol)) {
+            Some((soname, _)) => {
+                used.insert(soname);
+                result.bound.push((symbol, soname.clone()));
+            },
+            None if !weak.contains(&symbol) => result.unresolved.push(symbol),
+            None => {},
+        }
+    }
+    // the closure lists the direct dependencies first
+    result.unused = file.info.needed.iter()
+        .filter(|soname| !used.contains(soname.as_str()) && loaded.iter().any(|(s, _)| s == *soname))
+        .cloned()
+        .collect();
+    result
+}
+
+pub fn print_report(files: &[ScannedFile], resolver: &mut Resolver) {
+    let mut exports = Exports::default();
+    let results: Vec<Bindings> = files.iter()
+        .filter(|f| f.info.format == BinaryFormat::Elf)
+        .map(|f| bindings(f, resolver, &mut exports))
+        .collect();
+    let unresolved = results.iter().filter(|b| !b.unresolved.is_empty()).count();
+    let overlinked = results.iter().filter(|b| !b.unused.is_empty()).count();
+    println!("symbols ({} exes)", results.len());
+    println!("\tunresolved symbols: {} exes, unused libraries: {} exes", unresolved, overlinked);
+    println!();
+    for result in &results {
+        println!("{} ({} undefined symbols)", result.name, result.bound.len() + result.unresolved.len());
+        for (symbol, soname) in &result.bound {
+            println!("\t{} => {}", symbol, soname);
+        }
+        for soname in &result.missing {
+            println!("\t! {} not found, symbols it would provide are unresolved", soname);
+        }
+        for symbol in &result.unresolved {
+            println!("\t! {} isn't exported by any loaded library", symbol);
+        }
+        for soname in &result.unused {
+            println!("\t? {} provides nothing used", soname);
+        }
+        println!();
+    }
+}
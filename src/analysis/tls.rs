@@ -0,0 +1,59 @@
+//! Thread-local storage use and libraries relying on the initial-exec model.
+//!
+//! Initial-exec TLS lives in the static TLS block sized when the process starts. A library using
+//! it that's loaded later with `dlopen` only gets the little surplus glibc reserves, so loading it
+//! can fail with "cannot allocate memory in static TLS block", which no dependency listing shows.
+
+use crate::scan::ScannedFile;
+
+/// Model names of the file's TLS accesses
+fn models(file: &ScannedFile) -> Vec<&'static str> {
+    let tls = &file.info.tls;
+    let mut models = vec![];
+    if tls.dynamic_relocs > 0 {
+        models.push("dynamic");
+    }
+    if tls.initial_exec() {
+        models.push("initial-exec");
+    }
+    models
+}
+
+pub fn print_report(files: &[ScannedFile]) {
+    let users: Vec<&ScannedFile> = files.iter()
+        .filter(|f| f.info.tls.used())
+        .collect();
+    println!("thread-local storage ({} files)", users.len());
+    for file in &users {
+        let models = models(file);
+        if models.is_empty() {
+            println!("\t<= {} ({} bytes)", file.name, file.info.tls.size);
+        }
+        else {
+            println!("\t<= {} ({} bytes, {})", file.name, file.info.tls.size, models.join(", "));
+        }
+    }
+    println!();
+
+    let libraries: Vec<&ScannedFile> = users.into_iter()
+        .filter(|f| f.info.soname.is_some() && f.info.tls.initial_exec())
+        .collect();
+    if libraries.is_empty() {
+        return;
+    }
+    println!("libraries with initial-exec TLS, may fail to dlopen ({} libs)", libraries.len());
+    for lib in libraries {
+        let soname = lib.info.soname.as_deref().unwrap_or_default();
+        let loaders: Vec<&str> = files.iter()
+            .filter(|f| f.info.dlopen_strings.iter().any(|s| s.rsplit('/').next() == Some(soname)))
+            .map(|f| f.name.as_str())
+            .collect();
+        if loaders.is_empty() {
+            println!("\t<= {}", lib.name);
+        }
+        else {
+            println!("\t<= {} (dlopened by {})", lib.name, loaders.join(", "));
+        }
+    }
+    println!();
+}
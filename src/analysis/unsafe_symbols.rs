@@ -0,0 +1,98 @@
+//! Imports of dangerous, deprecated or removed libc interfaces.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::scan::ScannedFile;
+
+/// Symbols flagged when no denylist is given, with the reason
+const DEFAULT_DENYLIST: &[(&str, &str)] = &[
+    ("gets", "no bounds checking, removed in C11"),
+    ("tmpnam", "race between naming and creating the file"),
+    ("tempnam", "race between naming and creating the file"),
+    ("mktemp", "race between naming and creating the file"),
+    ("getpw", "no bounds checking"),
+    ("sys_errlist", "removed in glibc 2.32"),
+    ("sys_nerr", "removed in glibc 2.32"),
+    ("sys_siglist", "removed in glibc 2.32"),
+    ("getwd", "no bounds checking"),
+    ("ftime", "deprecated"),
+    ("sigvec", "removed in glibc 2.33"),
+    ("sigblock", "deprecated"),
+    ("sigsetmask", "deprecated"),
+    ("clnt_create", "Sun RPC, removed from glibc in 2.32"),
+    ("clnt_call", "Sun RPC, removed from glibc in 2.32"),
+    ("svc_register", "Sun RPC, removed from glibc in 2.32"),
+    ("svc_run", "Sun RPC, removed from glibc in 2.32"),
+    ("xdr_string", "Sun RPC, removed from glibc in 2.32"),
+    ("yp_get_default_domain", "NIS, moved out of glibc into libnsl"),
+    ("yp_match", "NIS, moved out of glibc into libnsl"),
+];
+
+/// Flagged symbols with the reason
+#[derive(Debug, Clone)]
+pub struct Denylist {
+    symbols: BTreeMap<String, String>,
+}
+
+impl Default for Denylist {
+    fn default() -> Self {
+        let symbols = DEFAULT_DENYLIST.iter()
+            .map(|(s, reason)| (s.to_string(), reason.to_string()))
+            .collect();
+        Denylist { symbols }
+    }
+}
+
+impl Denylist {
+    /// Reads a denylist with a symbol per line, optionally followed by the reason. Empty lines
+    /// and lines starting with `#` are skipped.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut symbols = BTreeMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (symbol, reason) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            symbols.insert(symbol.to_string(), reason.trim().to_string());
+        }
+        Ok(Denylist { symbols })
+    }
+}
+
+impl Denylist {
+    /// Symbols of `imports` on the list, with the reason
+    pub fn flagged<'a>(&'a self, imports: &'a [String]) -> impl Iterator<Item = (&'a str, &'a str)> {
+        imports.iter().filter_map(|s| self.symbols.get_key_value(s))
+            .map(|(s, reason)| (s.as_str(), reason.as_str()))
+    }
+}
+
+pub fn print_report(files: &[ScannedFile], denylist: &Denylist) {
+    let mut users: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in files {
+        for (symbol, _) in denylist.flagged(&file.info.imports) {
+            users.entry(symbol).or_default().push(&file.name);
+        }
+    }
+    let exes: usize = files.iter()
+        .filter(|f| f.info.imports.iter().any(|s| denylist.symbols.contains_key(s)))
+        .count();
+    println!("deprecated or unsafe imports ({} exes)", exes);
+    println!();
+    for (symbol, exes) in users {
+        let reason = &denylist.symbols[symbol];
+        if reason.is_empty() {
+            println!("{} ({} exes)", symbol, exes.len());
+        }
+        else {
+            println!("{}: {} ({} exes)", symbol, reason, exes.len());
+        }
+        for exe in exes {
+            println!("\t<= {}", exe);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,241 @@
+//! Native libraries inside zip archives: JNI libraries in jars, extension modules in Python
+//! wheels, Android packages.
+//!
+//! Members are shown as `<archive>!/<member>`, the way Java names resources in jars. Only the
+//! central directory and stored or deflated members are supported, not zip64.
+
+use std::io::Read;
+use std::path::Path;
+use flate2::read::DeflateDecoder;
+use crate::elf::HandleError;
+
+/// Extensions of the zip-based archives scanned with `--archives`
+const EXTENSIONS: &[&str] = &["zip", "jar", "whl", "aar", "apk"];
+
+/// Marks the archive's component in a member's path
+pub const SEPARATOR: &str = "!";
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+/// Whether a member's name looks like a native library worth parsing
+pub fn is_native_library(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    file_name.ends_with(".so") || file_name.contains(".so.")
+        || [".dylib", ".jnilib", ".dll", ".pyd"].iter().any(|x| file_name.ends_with(x))
+}
+
+/// Archive `path` is a member of, ending with the separator, `None` outside archives
+pub fn archive_of(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|a| a.file_name().is_some_and(|n| n.to_string_lossy().ends_with(SEPARATOR)))
+}
+
+/// Directory containing the archive `path` is a member of, `None` outside archives
+pub fn container_dir(path: &Path) -> Option<&Path> {
+    archive_of(path).and_then(Path::parent)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, HandleError> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(HandleError::Malformed("Truncated zip archive"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, HandleError> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(HandleError::Malformed("Truncated zip archive"))
+}
+
+/// File stored in an archive
+#[derive(Debug)]
+pub struct Member<'a> {
+    pub name: String,
+    method: u16,
+    size: usize,
+    data: &'a [u8],
+}
+
+impl Member<'_> {
+    /// Uncompressed contents
+    pub fn contents(&self) -> Result<Vec<u8>, HandleError> {
+        match self.method {
+            STORED => Ok(self.data.to_vec()),
+            DEFLATED => {
+                let mut contents = Vec::with_capacity(self.size);
+                DeflateDecoder::new(self.data)
+                    .take(self.size as u64)
+                    .read_to_end(&mut contents)
+                    .map_err(HandleError::IoError)?;
+                Ok(contents)
+            },
+            _ => Err(HandleError::Malformed("Unsupported zip compression method")),
+        }
+    }
+}
+
+/// Members listed in the archive's central directory
+pub fn members(data: &[u8]) -> Result<Vec<Member<'_>>, HandleError> {
+    // the end of central directory record is followed by a comment of at most 64 KiB
+    let search_from = data.len().saturating_sub(22 + 0xffff);
+    let end = (search_from..data.len().saturating_sub(21)).rev()
+        .find(|&i| u32_at(data, i).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or(HandleError::Malformed("No zip end of central directory"))?;
+    let count = u16_at(data, end + 10)? as usize;
+    let mut offset = u32_at(data, end + 16)? as usize;
+    let mut members = vec![];
+    for _ in 0..count {
+        if u32_at(data, offset)? != CENTRAL_DIRECTORY_ENTRY {
+            return Err(HandleError::Malformed("Invalid zip central directory entry"));
+        }
+        let method = u16_at(data, offset + 10)?;
+        let compressed = u32_at(data, offset + 20)?;
+        let size = u32_at(data, offset + 24)?;
+        let name_len = u16_at(data, offset + 28)? as usize;
+        let extra_len = u16_at(data, offset + 30)? as usize;
+        let comment_len = u16_at(data, offset + 32)? as usize;
+        let local = u32_at(data, offset + 42)? as usize;
+        let name = data.get(offset + 46..offset + 46 + name_len)
+            .ok_or(HandleError::Malformed("Truncated zip archive"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        offset += 46 + name_len + extra_len + comment_len;
+        if compressed == u32::MAX || size == u32::MAX || local == u32::MAX as usize {
+            return Err(HandleError::Malformed("zip64 archives aren't supported"));
+        }
+        if name.ends_with('/') {
+            continue;
+        }
+        if u32_at(data, local)? != LOCAL_HEADER {
+            return Err(HandleError::Malformed("Invalid zip local header"));
+        }
+        let start = local + 30 + u16_at(data, local + 26)? as usize + u16_at(data, local + 28)? as usize;
+        let data = data.get(start..start + compressed as usize)
+            .ok_or(HandleError::Malformed("Truncated zip archive"))?;
+        members.push(Member { name, method, size: size as usize, data });
+    }
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    /// Archive of `(name, method, contents)` members, the deflated ones compressed here, and
+    /// `comment` after the end of central directory record
+    fn zip(members: &[(&str, u16, &[u8])], comment: &[u8]) -> Vec<u8> {
+        let (mut file, mut central) = (vec![], vec![]);
+        for (name, method, contents) in members {
+            let data = match *method {
+                DEFLATED => {
+                    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+                    encoder.write_all(contents).unwrap();
+                    encoder.finish().unwrap()
+                },
+                _ => contents.to_vec(),
+            };
+            let fields = |signature: u32| {
+                let mut fields = signature.to_le_bytes().to_vec();
+                fields.extend_from_slice(&[20, 0, 0, 0]);
+                fields.extend_from_slice(&method.to_le_bytes());
+                fields.extend_from_slice(&[0; 8]);
+                fields.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                fields.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+                fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                fields.extend_from_slice(&[0; 2]);
+                fields
+            };
+            let mut entry = fields(CENTRAL_DIRECTORY_ENTRY);
+            // version made by, then no comment, disk 0 and attributes
+            entry.splice(4..4, [20, 0]);
+            entry.extend_from_slice(&[0; 10]);
+            entry.extend_from_slice(&(file.len() as u32).to_le_bytes());
+            entry.extend_from_slice(name.as_bytes());
+            central.extend(entry);
+            file.extend(fields(LOCAL_HEADER));
+            file.extend_from_slice(name.as_bytes());
+            file.extend(data);
+        }
+        let count = (members.len() as u16).to_le_bytes();
+        let mut end = END_OF_CENTRAL_DIRECTORY.to_le_bytes().to_vec();
+        end.extend_from_slice(&[0; 4]);
+        end.extend([count, count].concat());
+        end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        end.extend_from_slice(&(file.len() as u32).to_le_bytes());
+        end.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+        [file, central, end, comment.to_vec()].concat()
+    }
+
+    fn library() -> Vec<u8> {
+        (0..2000).map(|i| (i % 7) as u8).collect()
+    }
+
+    fn error<T>(result: Result<T, HandleError>) -> String {
+        result.err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn reads_members() {
+        let library = library();
+        let data = zip(&[
+            ("META-INF/MANIFEST.MF", STORED, b"Manifest-Version: 1.0\n"),
+            ("lib/", STORED, b""),
+            ("lib/arm64-v8a/libnative.so", DEFLATED, &library),
+        ], b"built by hand");
+        let read = members(&data).unwrap();
+        // directories are left out
+        let names: Vec<&str> = read.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["META-INF/MANIFEST.MF", "lib/arm64-v8a/libnative.so"]);
+        assert_eq!(read[0].contents().unwrap(), b"Manifest-Version: 1.0\n");
+        assert!(read[1].data.len() < library.len());
+        assert_eq!(read[1].contents().unwrap(), library);
+        assert!(members(&zip(&[], b"")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn damaged_archives() {
+        let data = zip(&[("libfoo.so", STORED, b"\x7fELF"), ("libbar.so", DEFLATED, &library())], b"");
+        let central = data.len() - 22 - 2 * (46 + 9);
+        assert_eq!(error(members(&data[..data.len() - 1])), "malformed ELF: No zip end of central directory");
+        // sizes and offsets of 0xffffffff mark zip64 archives
+        for field in [20, 24, 42] {
+            let mut zip64 = data.clone();
+            zip64[central + field..central + field + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert_eq!(error(members(&zip64)), "malformed ELF: zip64 archives aren't supported");
+        }
+        // a local header cut short, in the archive comment at the end of the file, or not where
+        // the entry says
+        let header = [&LOCAL_HEADER.to_le_bytes()[..], &[20, 0, 0, 0, 0, 0, 0, 0]].concat();
+        let mut cut = zip(&[("libfoo.so", STORED, b"\x7fELF"), ("libbar.so", DEFLATED, &library())], &header);
+        let at = (cut.len() - header.len()) as u32;
+        cut[central + 42..central + 46].copy_from_slice(&at.to_le_bytes());
+        assert_eq!(error(members(&cut)), "malformed ELF: Truncated zip archive");
+        let mut moved = data.clone();
+        moved[central + 42..central + 46].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(error(members(&moved)), "malformed ELF: Invalid zip local header");
+        // more entries than the central directory has, and contents past the end of the file
+        let mut count = data.clone();
+        let end = data.len() - 22;
+        count[end + 10..end + 12].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(error(members(&count)), "malformed ELF: Invalid zip central directory entry");
+        let mut long = data.clone();
+        long[central + 20..central + 24].copy_from_slice(&100_000u32.to_le_bytes());
+        assert_eq!(error(members(&long)), "malformed ELF: Truncated zip archive");
+
+        let bzip2 = zip(&[("libfoo.so", 12, b"BZh9")], b"");
+        assert_eq!(error(members(&bzip2).unwrap()[0].contents()), "malformed ELF: Unsupported zip compression method");
+    }
+}
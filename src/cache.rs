@@ -0,0 +1,160 @@
+//! Parse results kept between runs, so that rescanning a large tree only reparses the files that
+//! changed.
+//!
+//! Files are looked up by path and count as unchanged while their size, inode, modification and
+//! status change times stay the same. A cache written by another version of the tool, or with
+//! other options affecting parsing, is discarded as a whole.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::elf::ElfInfo;
+use crate::escape;
+use crate::scan::ScanOptions;
+
+/// What a cached result is only valid for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    inode: u64,
+    /// Nanoseconds since the Unix epoch
+    mtime: i128,
+    ctime: i128,
+}
+
+impl Stamp {
+    #[cfg(unix)]
+    fn new(metadata: &fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        let nanos = |secs: i64, nsecs: i64| i128::from(secs) * 1_000_000_000 + i128::from(nsecs);
+        Stamp {
+            size: metadata.len(),
+            inode: metadata.ino(),
+            mtime: nanos(metadata.mtime(), metadata.mtime_nsec()),
+            ctime: nanos(metadata.ctime(), metadata.ctime_nsec()),
+        }
+    }
+
+    /// Other hosts have no inode or status change time, so only the size and modification time
+    /// are compared
+    #[cfg(not(unix))]
+    fn new(metadata: &fs::Metadata) -> Self {
+        let mtime = metadata.modified().ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos() as i128);
+        Stamp { size: metadata.len(), inode: 0, mtime, ctime: 0 }
+    }
+}
+
+/// Result of parsing a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Parsed {
+    Info(Box<ElfInfo>),
+    /// [`HandleError::kind`](crate::elf::HandleError::kind) and message of the failure
+    Failure { kind: String, message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    stamp: Stamp,
+    parsed: Parsed,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: String,
+    /// Options the results depend on
+    options: String,
+    /// By escaped path
+    entries: HashMap<String, Entry>,
+}
+
+/// Options parse results depend on, as stored in the cache
+fn options_key(options: &ScanOptions) -> String {
+    format!("pe={},embedded={}", options.pe, options.embedded)
+}
+
+/// Cached results shared by the threads of a scan
+#[derive(Debug)]
+pub struct Cache {
+    /// File the cache is saved to, `None` for one kept in memory for the run only
+    path: Option<PathBuf>,
+    file: Mutex<CacheFile>,
+    hits: Mutex<usize>,
+}
+
+impl CacheFile {
+    fn empty(options: &ScanOptions) -> Self {
+        CacheFile { version: env!("CARGO_PKG_VERSION").to_string(), options: options_key(options), entries: HashMap::new() }
+    }
+}
+
+impl Cache {
+    /// Empty cache that isn't saved, for rescans within one run
+    pub fn in_memory(options: &ScanOptions) -> Self {
+        Cache { path: None, file: Mutex::new(CacheFile::empty(options)), hits: Mutex::new(0) }
+    }
+
+    /// Loads the cache at `path`, starting empty if it doesn't exist, is unreadable or was
+    /// written by another version or with other `options`. `fresh` ignores what's cached.
+    pub fn load(path: &Path, options: &ScanOptions, fresh: bool) -> Self {
+        let expected = CacheFile::empty(options);
+        let loaded = match fs::File::open(path) {
+            _ if fresh => None,
+            Ok(file) => match serde_json::from_reader::<_, CacheFile>(BufReader::new(file)) {
+                Ok(cache) if cache.version != expected.version => {
+                    info!("Discarding cache {} written by version {}", path.display(), cache.version);
+                    None
+                },
+                Ok(cache) if cache.options != expected.options => {
+                    info!("Discarding cache {} written with other options", path.display());
+                    None
+                },
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    info!("Discarding unreadable cache {}: {}", path.display(), e);
+                    None
+                },
+            },
+            Err(_) => None,
+        };
+        Cache { path: Some(path.to_path_buf()), file: Mutex::new(loaded.unwrap_or(expected)), hits: Mutex::new(0) }
+    }
+
+    /// Cached result for the file at `path` if it hasn't changed since
+    pub fn get(&self, path: &Path, metadata: &fs::Metadata) -> Option<Parsed> {
+        let file = self.file.lock().unwrap();
+        let entry = file.entries.get(&escape::path(path)).filter(|e| e.stamp == Stamp::new(metadata))?;
+        *self.hits.lock().unwrap() += 1;
+        Some(entry.parsed.clone())
+    }
+
+    pub fn insert(&self, path: &Path, metadata: &fs::Metadata, parsed: Parsed) {
+        let entry = Entry { stamp: Stamp::new(metadata), parsed };
+        self.file.lock().unwrap().entries.insert(escape::path(path), entry);
+    }
+
+    /// Files whose results came from the cache since the last call
+    pub fn take_hits(&self) -> usize {
+        std::mem::take(&mut *self.hits.lock().unwrap())
+    }
+
+    /// Forgets files that no longer exist and writes the cache back. The previous cache is only
+    /// replaced once the new one is completely written.
+    pub fn save(&self) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        file.entries.retain(|path, _| Path::new(&escape::unescape_os_str(path)).exists());
+        let Some(path) = &self.path else { return Ok(()) };
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = Path::new(&partial);
+        let mut writer = BufWriter::new(fs::File::create(partial)?);
+        serde_json::to_writer(&mut writer, &*file).map_err(io::Error::from)?;
+        writer.flush()?;
+        fs::rename(partial, path)
+    }
+}
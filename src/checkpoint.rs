@@ -0,0 +1,49 @@
+//! Scan progress saved to disk every now and then, so that an interrupted scan of a huge tree
+//! can be resumed instead of starting over.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::scan::{Failure, Scan, ScannedFile};
+
+/// How often progress is saved
+pub const INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Checkpoint {
+    /// Escaped paths of the directory entries handled so far
+    pub processed: BTreeSet<String>,
+    pub files: Vec<ScannedFile>,
+    pub failures: Vec<Failure>,
+}
+
+/// [`Checkpoint`] borrowing the scan in progress
+#[derive(Serialize)]
+struct Progress<'a> {
+    processed: &'a BTreeSet<String>,
+    files: &'a [ScannedFile],
+    failures: &'a [Failure],
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(fs::File::open(path)?);
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+}
+
+/// Saves the progress of `scan` to `path`, replacing the previous checkpoint only once the new
+/// one is completely written
+pub fn save(path: &Path, processed: &BTreeSet<String>, scan: &Scan) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = Path::new(&partial);
+    let mut writer = BufWriter::new(fs::File::create(partial)?);
+    let progress = Progress { processed, files: &scan.files, failures: &scan.failures };
+    serde_json::to_writer(&mut writer, &progress).map_err(io::Error::from)?;
+    writer.flush()?;
+    fs::rename(partial, path)
+}
@@ -0,0 +1,598 @@
+//! The `linux-hs-elf` command: scanning what the arguments name and printing the report, or
+//! running one of the subcommands.
+
+mod args;
+mod report;
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use clap::Parser;
+use tracing::{error, info, warn};
+use crate::{
+    analysis, cache, commands, container, daemon, diff, dry_run, elf, escape, fd_budget, fixtures, fleet, ignore, index,
+    inotify, loader, metadata, pattern, profiles, progress, resolve, scan, throttle, webhook, Scanner,
+};
+use self::args::{Args, CheckArgs, Command, DaemonArgs, DepsArgs, DiffArgs, GenFixturesArgs, ImageArgs, MergeArgs, WhoNeedsArgs};
+use self::report::lib_filter;
+
+/// Quiet time after a change before `--watch` rescans, so that files being copied in are done
+const WATCH_SETTLE: Duration = Duration::from_millis(200);
+
+fn scanner(args: &Args) -> Scanner {
+    Scanner { options: scan_options(args), image: args.image.clone(), sysroot: args.sysroot.clone(), container: container(args) }
+}
+
+fn image_args(args: &Args) -> Option<&ImageArgs> {
+    match &args.command {
+        Some(Command::Image(image)) => Some(image),
+        _ => None,
+    }
+}
+
+/// Filesystem or container image being scanned, if any
+fn image_path(args: &Args) -> Option<&Path> {
+    args.image.as_deref().or_else(|| image_args(args).map(|i| i.archive.as_path()))
+}
+
+/// Root filesystem of the `image` subcommand's container image, read once
+fn container(args: &Args) -> Option<Arc<container::Rootfs>> {
+    static ROOTFS: OnceLock<Arc<container::Rootfs>> = OnceLock::new();
+    let image = image_args(args)?;
+    let platform = image.platform.clone()
+        .or_else(|| match &args.arch[..] {
+            [arch] => container::Platform::of_arch(arch),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let rootfs = ROOTFS.get_or_init(|| match container::Rootfs::load(&image.archive, &platform) {
+        Ok(rootfs) => Arc::new(rootfs),
+        Err(e) => {
+            error!("Couldn't read container image {}: {}", image.archive.display(), e);
+            process::exit(2);
+        },
+    });
+    Some(rootfs.clone())
+}
+
+/// Host directory of the analyzed root filesystem
+fn root(args: &Args) -> PathBuf {
+    args.sysroot.clone().unwrap_or_else(|| PathBuf::from("/"))
+}
+
+/// Directories and files to scan: the positional paths and the `$PATH` directories with
+/// `--path`, `--executables-dir` if there are neither
+fn inputs(args: &Args) -> Vec<PathBuf> {
+    if let Some(image) = image_args(args) {
+        return if image.inputs.is_empty() { vec![PathBuf::from("/")] } else { image.inputs.clone() };
+    }
+    let mut inputs = args.inputs.clone();
+    if args.path {
+        let path = env::var_os("PATH").unwrap_or_default();
+        let scanner = scanner(args);
+        for dir in env::split_paths(&path).filter(|d| scanner.host_path(d).is_dir()) {
+            if !inputs.contains(&dir) {
+                inputs.push(dir);
+            }
+        }
+    }
+    if inputs.is_empty() {
+        inputs.push(args.executables_dir.clone());
+    }
+    inputs
+}
+
+/// Files listed in `--files-from`, read once
+fn listed_files(args: &Args) -> Option<&'static [PathBuf]> {
+    static LISTED: OnceLock<Vec<PathBuf>> = OnceLock::new();
+    let list = args.files_from.as_ref()?;
+    let listed = LISTED.get_or_init(|| {
+        let data = if list.as_os_str() == "-" {
+            let mut data = vec![];
+            io::stdin().read_to_end(&mut data).map(|_| data)
+        }
+        else {
+            fs::read(list)
+        };
+        match data {
+            Ok(data) => scan::file_list(&data),
+            Err(e) => {
+                error!("Couldn't read the list of files {}: {}", list.display(), e);
+                process::exit(2);
+            },
+        }
+    });
+    Some(listed)
+}
+
+/// The scanned paths as one, separated by colons like `$PATH`, or the list of files scanned
+fn scan_root(args: &Args) -> PathBuf {
+    if let Some(list) = &args.files_from {
+        return list.clone();
+    }
+    let inputs = inputs(args);
+    env::join_paths(&inputs).map(PathBuf::from).unwrap_or_else(|_| inputs[0].clone())
+}
+
+/// Host paths of the inputs, or of the listed files
+fn host_inputs(args: &Args) -> Vec<PathBuf> {
+    let scanner = scanner(args);
+    match listed_files(args) {
+        // the scan skips directories and files listed twice
+        Some(listed) => {
+            let mut seen = HashSet::new();
+            listed.iter().map(|f| scanner.host_path(f)).filter(|f| !f.is_dir() && seen.insert(f.clone())).collect()
+        },
+        None => inputs(args).iter().map(|i| scanner.host_path(i)).collect(),
+    }
+}
+
+/// Scanned files shown as `--paths` asks, relative to the input they were found in
+fn load_files(args: &Args, index: Option<&Path>) -> (scan::Scan, resolve::Resolver) {
+    load_files_with(args, index, scan_cache(args))
+}
+
+/// Cache of `--cache`, if any
+fn scan_cache(args: &Args) -> Option<Arc<cache::Cache>> {
+    args.cache.as_ref().map(|path| Arc::new(cache::Cache::load(path, &scan_options(args), args.no_cache)))
+}
+
+/// [`load_files`] reusing the results in `cache`
+fn load_files_with(args: &Args, index: Option<&Path>, cache: Option<Arc<cache::Cache>>) -> (scan::Scan, resolve::Resolver) {
+    let (mut scan, resolver) = scan_or_load(args, index, cache);
+    if let Some(unknown) = args.arch.iter().find(|a| elf::machine_number(a).is_none()) {
+        error!("Unknown architecture {}", unknown);
+        process::exit(2);
+    }
+    if !args.arch.is_empty() {
+        scan.files.retain(|f| args.arch.contains(&f.info.arch));
+    }
+    if args.dedupe {
+        analysis::duplicates::dedupe(&mut scan.files);
+    }
+    // listed files are shown as if each had been given on its own
+    let inputs = if args.files_from.is_some() { vec![] } else { host_inputs(args) };
+    for file in &mut scan.files {
+        let root = match inputs.iter().find(|i| file.path.starts_with(i)) {
+            None if inputs.is_empty() => file.path.parent().unwrap_or(&file.path),
+            Some(input) if *input == file.path => input.parent().unwrap_or(input),
+            Some(input) => input,
+            None => &inputs[0],
+        };
+        file.name = args.paths.display(&file.path, root);
+    }
+    (scan, resolver)
+}
+
+/// Metadata of a scan with `args` embedded in machine-readable output
+fn scan_metadata(args: &Args) -> metadata::Metadata {
+    metadata::Metadata::new(args.label.as_deref(), &scan_root(args), image_path(args))
+}
+
+/// Paths left out by `--ignore-file`, `--exclude` and `--include`
+fn path_filter(args: &Args) -> ignore::PathFilter {
+    let mut filter = ignore::PathFilter::default();
+    if let Some(path) = &args.ignore_file {
+        if let Err(e) = filter.load(path) {
+            error!("Couldn't read ignore file {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    }
+    for pattern in &args.exclude {
+        filter.exclude(pattern);
+    }
+    for pattern in &args.include {
+        filter.include(pattern);
+    }
+    filter
+}
+
+fn scan_options(args: &Args) -> scan::ScanOptions {
+    scan::ScanOptions {
+        pe: args.pe,
+        embedded: args.embedded || report::rules(args).iter().any(|rule| rule.reads("embedded")),
+        fail_fast: args.fail_fast,
+        archives: args.archives,
+        checkpoint: args.checkpoint.clone(),
+        resume: args.resume,
+        throttle: throttle::Limits {
+            files_per_sec: args.throttle_files,
+            bytes_per_sec: args.throttle_mb.map(|mb| mb * 1_000_000.0),
+        },
+        include_special: args.include_special,
+        walk: scan::Walk {
+            recursive: args.recursive || args.max_depth.is_some() || image_args(args).is_some_and(|i| i.inputs.is_empty()),
+            max_depth: args.max_depth,
+            follow_symlinks: args.follow_symlinks,
+            one_file_system: args.one_file_system,
+            filter: path_filter(args),
+        },
+        jobs: args.jobs.unwrap_or_default(),
+        root: args.sysroot.clone(),
+        cache: None,
+        progress: None,
+    }
+}
+
+/// Scans `--executables-dir`, or loads the results from `index`
+fn scan_or_load(args: &Args, index: Option<&Path>, cache: Option<Arc<cache::Cache>>) -> (scan::Scan, resolve::Resolver) {
+    if let Some(path) = index {
+        let index = index::Index::load(path).unwrap_or_else(|e| {
+            error!("Couldn't load index {}: {}", path.display(), e);
+            process::exit(2);
+        });
+        let scan = scan::Scan { files: index.files, failures: index.failures };
+        let mut resolver = resolve::Resolver::from_libraries(index.libraries);
+        resolver.dlopen = args.detect_dlopen;
+        return (scan, resolver);
+    }
+    let mut scanner = scanner(args);
+    scanner.options.cache = cache.clone();
+    let progress = (args.progress || args.stats).then(|| Arc::new(progress::Progress::new()));
+    scanner.options.progress = progress.clone();
+    let display = progress.clone().filter(|_| args.progress).map(progress::show);
+    let scanned = match listed_files(args) {
+        Some(listed) => scanner.scan_files(listed),
+        None => scanner.scan_all(&inputs(args)),
+    };
+    drop(display);
+    let (scan, mut resolver) = scanned.unwrap_or_else(|e| {
+        match image_path(args) {
+            Some(path) => error!("Couldn't read image {}: {}", path.display(), e),
+            None => error!("Couldn't scan {}", e),
+        }
+        process::exit(2);
+    });
+    if let Some(progress) = progress.filter(|_| args.stats) {
+        progress.stats(&scan).print();
+    }
+    resolver.dlopen = args.detect_dlopen;
+    if let Some(cache) = cache {
+        info!("{} of {} files reused from the cache", cache.take_hits(), scan.files.len() + scan.failures.len());
+        if let Err(e) = cache.save() {
+            warn!("Couldn't save cache {}: {}", args.cache.as_deref().unwrap_or(Path::new("")).display(), e);
+        }
+    }
+    if args.fail_fast {
+        if let Some(failure) = scan.failures.iter().find(|f| !f.is_expected()) {
+            error!("Couldn't handle {}: {}", failure.path.display(), failure.message);
+            process::exit(2);
+        }
+    }
+    if let Some(path) = &args.save_index {
+        if let Err(e) = index::Index::build(&scan, &mut resolver, scan_metadata(args)).save(path) {
+            error!("Couldn't save index {}: {}", path.display(), e);
+        }
+    }
+    (scan, resolver)
+}
+
+/// Runs the `linux-hs-elf` command with the arguments of the process
+pub fn run() {
+    tracing_subscriber::fmt().with_writer(progress::stderr).init();
+    let args = Args::parse();
+    fd_budget::init(args.max_open_files);
+    match &args.command {
+        Some(Command::WhoNeeds(query)) => return who_needs(&args, query),
+        Some(Command::Deps(deps)) => return print_deps(&args, deps),
+        Some(Command::Daemon(opts)) => daemon(&args, opts),
+        Some(Command::GenFixtures(opts)) => return gen_fixtures(opts),
+        Some(Command::Check(check)) => return check_target(&args, check),
+        Some(Command::Merge(merge)) => return merge_fleet(merge),
+        Some(Command::Diff(diff)) => return diff_scans(&args, diff),
+        Some(Command::Image(_)) if args.image.is_some() || args.sysroot.is_some() => {
+            error!("The image subcommand can't be combined with --image or --sysroot");
+            process::exit(2);
+        },
+        Some(Command::Image(_)) | None => {},
+    }
+    if args.dry_run {
+        let estimate = dry_run::estimate(&host_inputs(&args), &scan_options(&args));
+        return dry_run::print(&estimate);
+    }
+    if args.check {
+        return check_missing(&args);
+    }
+    if args.watch {
+        watch(&args);
+    }
+    // found out before scanning, which may take long
+    if args.tui && !(io::stdin().is_terminal() && io::stdout().is_terminal()) {
+        error!("--tui needs stdin and stdout to be a terminal");
+        process::exit(2);
+    }
+    let (scan, mut resolver) = load_files(&args, None);
+    report::print(&args, &scan, &mut resolver);
+    check_strict(&args, &scan);
+}
+
+/// Writes the fixtures `opts` describe
+fn gen_fixtures(opts: &GenFixturesArgs) {
+    let set = match &opts.name {
+        Some(name) => {
+            let machine = elf::machine_number(&opts.machine).unwrap_or_else(|| {
+                error!("Unknown machine {}", opts.machine);
+                process::exit(2);
+            });
+            vec![fixtures::Fixture {
+                name: name.clone(),
+                bits: opts.bits,
+                big_endian: opts.big_endian,
+                machine,
+                needed: opts.needed.clone(),
+                soname: opts.soname.clone(),
+                interp: opts.interp.clone(),
+                section_headers: !opts.no_section_headers,
+                cut_section_headers: opts.cut_section_headers,
+                base: opts.base,
+                malformed: opts.malformed,
+            }]
+        },
+        None => fixtures::standard_set(),
+    };
+    if let Err(e) = fixtures::write(&opts.dir, &set) {
+        error!("Couldn't write fixtures to {}: {}", opts.dir.display(), e);
+        process::exit(2);
+    }
+    for fixture in &set {
+        println!("{}", fixture.name);
+    }
+}
+
+/// Directories `--watch` is woken up by changes in, with how deep below them to watch
+fn watched_dirs(args: &Args) -> Vec<(PathBuf, Option<usize>)> {
+    let depth = if args.recursive || args.max_depth.is_some() { args.max_depth } else { Some(0) };
+    host_inputs(args).into_iter()
+        .map(|input| {
+            if input.is_dir() {
+                (input, depth)
+            }
+            else {
+                // files are replaced by renaming over them, which only their directory sees
+                (input.parent().map_or_else(|| PathBuf::from("/"), Path::to_path_buf), Some(0))
+            }
+        })
+        .collect()
+}
+
+/// Rescans every `--poll-interval` seconds, printing the report and notifying `--webhook`
+/// whenever the dependency map changes
+fn watch(args: &Args) -> ! {
+    let mut last: Option<diff::Snapshot> = None;
+    let cache = scan_cache(args).unwrap_or_else(|| Arc::new(cache::Cache::in_memory(&scan_options(args))));
+    // images are only rescanned on a schedule
+    let mut watcher = match image_path(args) {
+        Some(_) => None,
+        None => inotify::Watcher::new()
+            .map_err(|e| warn!("Couldn't use inotify, rescanning every {} seconds: {}", args.poll_interval, e))
+            .ok(),
+    };
+    loop {
+        if let Some(watcher) = &watcher {
+            // directories created since the last scan are watched as well
+            for (dir, depth) in watched_dirs(args) {
+                watcher.add_tree(&dir, depth);
+            }
+        }
+        let (scan, mut resolver) = load_files_with(args, None, Some(cache.clone()));
+        let snapshot = diff::Snapshot::new(&scan.files, &mut resolver);
+        if last.as_ref() != Some(&snapshot) {
+            if let Some(old) = &last {
+                notify(args, old, &snapshot);
+            }
+            report::print(args, &scan, &mut resolver);
+            last = Some(snapshot);
+        }
+        let interval = Duration::from_secs(args.poll_interval);
+        match &mut watcher {
+            Some(watcher) => {
+                if let Err(e) = watcher.wait(interval, WATCH_SETTLE) {
+                    warn!("Couldn't wait for changes: {}", e);
+                    thread::sleep(interval);
+                }
+            },
+            None => thread::sleep(interval),
+        }
+    }
+}
+
+/// Rescans every `--interval` seconds, keeping the last runs for queries on `--listen`
+fn daemon(args: &Args, opts: &DaemonArgs) -> ! {
+    let listener = TcpListener::bind(&opts.listen).unwrap_or_else(|e| {
+        error!("Couldn't listen on {}: {}", opts.listen, e);
+        process::exit(2);
+    });
+    let history = Arc::new(Mutex::new(daemon::History::new(opts.keep)));
+    daemon::serve(listener, history.clone());
+    loop {
+        let (scan, mut resolver) = load_files(args, None);
+        let snapshot = diff::Snapshot::new(&scan.files, &mut resolver);
+        let previous = history.lock().unwrap().push(snapshot.clone(), scan_metadata(args)).cloned();
+        if let Some(old) = previous.filter(|old| *old != snapshot) {
+            notify(args, &old, &snapshot);
+        }
+        thread::sleep(Duration::from_secs(opts.interval));
+    }
+}
+
+/// Posts the changes between two snapshots to `--webhook`, if given
+fn notify(args: &Args, old: &diff::Snapshot, new: &diff::Snapshot) {
+    let url = match &args.webhook {
+        Some(url) => url,
+        None => return,
+    };
+    let payload = serde_json::json!({
+        "event": "dependencies-changed",
+        "root": escape::path(&scan_root(args)),
+        "metadata": scan_metadata(args),
+        "changes": diff::diff(old, new),
+    });
+    if let Err(e) = webhook::post(url, &payload.to_string()) {
+        warn!("Couldn't notify {}: {}", url, e);
+    }
+}
+
+/// Exits with status 2 under `--strict` if some binary couldn't be analyzed
+fn check_strict(args: &Args, scan: &scan::Scan) {
+    let failed = scan.summary().failed;
+    if args.strict && failed > 0 {
+        error!("{} files couldn't be analyzed", failed);
+        process::exit(2);
+    }
+}
+
+fn print_deps(args: &Args, deps: &DepsArgs) {
+    let mut loader = loader::Loader::new(&root(args));
+    let options = scan::ScanOptions { pe: args.pe, embedded: true, ..Default::default() };
+    let file = scanner(args).host_path(&deps.file);
+    if let Err(e) = commands::deps::print(&file, &options, &mut loader, deps.resolve, deps.transitive) {
+        error!("Couldn't handle {}: {}", deps.file.display(), e);
+        process::exit(2);
+    }
+}
+
+fn who_needs(args: &Args, query: &WhoNeedsArgs) {
+    let pattern = if query.regex {
+        pattern::Pattern::regex(&query.library).unwrap_or_else(|e| {
+            error!("Invalid regex: {}", e);
+            process::exit(2);
+        })
+    }
+    else {
+        pattern::Pattern::new(&query.library)
+    };
+    let (scan, mut resolver) = load_files(args, query.index.as_deref());
+    let matches = commands::who_needs::who_needs(&scan.files, &mut resolver, &pattern, query.transitive);
+    let found = if query.list {
+        commands::who_needs::print_list(&matches)
+    }
+    else {
+        commands::who_needs::print(&matches)
+    };
+    check_strict(args, &scan);
+    if !found {
+        let suggestions = commands::who_needs::suggestions(&scan.files, &query.library);
+        match suggestions.split_last() {
+            Some((last, [])) => eprintln!("no executables need '{}'; did you mean '{}'?", query.library, last),
+            Some((last, rest)) => eprintln!(
+                "no executables need '{}'; did you mean '{}' or '{}'?",
+                query.library, rest.join("', '"), last
+            ),
+            None => eprintln!("no executables need '{}'", query.library),
+        }
+        process::exit(1);
+    }
+}
+
+/// Exits with status 3 if some binary needs libraries that can't be found
+fn check_missing(args: &Args) {
+    let (scan, mut resolver) = load_files(args, None);
+    let mut loader;
+    let mut lookup = if image_path(args).is_none() {
+        loader = loader::Loader::new(&root(args));
+        commands::missing::Lookup::Loader(&mut loader)
+    }
+    else {
+        commands::missing::Lookup::Resolver(&mut resolver)
+    };
+    let ok = commands::missing::print(&scan.files, &mut lookup, &lib_filter(args));
+    check_strict(args, &scan);
+    if !ok {
+        process::exit(3);
+    }
+}
+
+fn check_target(args: &Args, check: &CheckArgs) {
+    let profile = profiles::Profile::load(&check.target).unwrap_or_else(|e| {
+        let names: Vec<&str> = profiles::BUILTIN.iter().map(|(name, _)| *name).collect();
+        error!("Couldn't load profile {}: {} (built-in profiles: {})", check.target, e, names.join(", "));
+        process::exit(2);
+    });
+    let (scan, _) = load_files(args, check.index.as_deref());
+    let ok = commands::check::print(&scan.files, &profile);
+    check_strict(args, &scan);
+    if !ok {
+        process::exit(1);
+    }
+}
+
+/// Snapshot of a diff argument, scanned if it's a directory, with files named relative to it
+fn snapshot(args: &Args, opts: &DiffArgs, path: &Path) -> diff::Snapshot {
+    if !path.is_dir() {
+        return diff::Snapshot::load(path).unwrap_or_else(|e| {
+            error!("Couldn't load scan {}: {}", path.display(), e);
+            process::exit(2);
+        });
+    }
+    let (scanner, input) = if opts.roots {
+        (Scanner { options: scanner(args).options, image: None, sysroot: Some(path.to_path_buf()), container: None }, PathBuf::from("/"))
+    }
+    else {
+        (Scanner { image: None, ..scanner(args) }, path.to_path_buf())
+    };
+    let (scan, mut resolver) = scanner.scan_all(&[input]).unwrap_or_else(|e| {
+        error!("Couldn't scan {}", e);
+        process::exit(2);
+    });
+    let root = escape::path(path);
+    let files: Vec<scan::ScannedFile> = scan.files.into_iter()
+        .map(|f| scan::ScannedFile { name: diff::relative(&escape::path(&f.path), &root), ..f })
+        .collect();
+    diff::Snapshot::new(&files, &mut resolver)
+}
+
+fn diff_scans(args: &Args, opts: &DiffArgs) {
+    let changes = diff::diff(&snapshot(args, opts, &opts.before), &snapshot(args, opts, &opts.after));
+    let changed = if opts.json {
+        serde_json::to_writer_pretty(io::stdout().lock(), &changes).expect("Could not write JSON");
+        println!();
+        !changes.is_empty()
+    }
+    else {
+        commands::diff::print(&changes)
+    };
+    if opts.exit_code && changed {
+        process::exit(1);
+    }
+}
+
+fn merge_fleet(merge: &MergeArgs) {
+    let mut fleet = fleet::Fleet::default();
+    for path in &merge.inputs {
+        if let Err(e) = fleet.add(path) {
+            error!("Couldn't load {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    }
+    if let Some(path) = &merge.save {
+        if let Err(e) = fleet.save(path) {
+            error!("Couldn't save fleet {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    }
+    let library = match &merge.needs {
+        Some(library) => library,
+        None => return commands::merge::print(&fleet),
+    };
+    let pattern = if merge.regex {
+        pattern::Pattern::regex(library).unwrap_or_else(|e| {
+            error!("Invalid regex: {}", e);
+            process::exit(2);
+        })
+    }
+    else {
+        pattern::Pattern::new(library)
+    };
+    let matches = commands::merge::who_needs(&fleet, &pattern, merge.transitive);
+    if !commands::merge::print_who_needs(&matches) {
+        eprintln!("no host has executables needing '{}'", library);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,641 @@
+//! Command line of the `linux-hs-elf` command.
+
+use std::path::{Path, PathBuf};
+use clap::{ArgEnum, Args as ClapArgs, Parser, Subcommand};
+use crate::{archive, container, daemon, escape, fixtures, output, packages, throttle};
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Libraries with their dependants, followed by the requested reports
+    Text,
+    /// Every library with its dependants and every executable with its needed libraries
+    Json,
+    /// A row per needed library of every executable
+    Csv,
+    /// Nodes and edges of the dependency graph
+    GraphJson,
+    /// Cypher `MERGE` statements loading the dependency graph into Neo4j
+    Cypher,
+    /// Self-contained HTML page with an interactive view of the dependency graph
+    HtmlGraph,
+    /// `binaries.parquet` and `edges.parquet` tables written into `--output-dir`
+    Parquet,
+    /// GitHub Actions `::error`/`::warning` annotations of missing dependencies, failures and,
+    /// with `--unsafe-symbols`, flagged imports
+    GhAnnotations,
+    /// The same findings as `path: error: title: message` lines
+    Errorformat,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// Every soname on its own
+    Lib,
+    /// Sonames of the same library family (`libfoo.so.1`, `libfoo.so.2`) under one heading
+    Family,
+    /// Every executable with the libraries it needs, like `ldd`
+    Exe,
+    /// Executables bucketed by program interpreter (glibc, musl, custom loaders), each bucket
+    /// with its own libraries
+    Interp,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathStyle {
+    /// File name only
+    Name,
+    /// Path relative to the scanned directory
+    Relative,
+    /// Absolute path
+    Absolute,
+}
+
+impl PathStyle {
+    pub fn display(self, path: &Path, scan_root: &Path) -> String {
+        let shown = match self {
+            PathStyle::Name => match archive::container_dir(path) {
+                Some(dir) => path.strip_prefix(dir).unwrap_or(path).to_path_buf(),
+                None => path.file_name().map(Path::new).unwrap_or(path).to_path_buf(),
+            },
+            PathStyle::Relative => path.strip_prefix(scan_root).unwrap_or(path).to_path_buf(),
+            PathStyle::Absolute => std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+        };
+        escape::path(&shown)
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    /// Directory with the binaries to scan
+    #[clap(short, long, global = true, parse(from_os_str), value_name = "executables-dir", default_value = "/")]
+    pub executables_dir: PathBuf,
+
+    /// Directories and files to scan instead of `--executables-dir`, merged into one report
+    #[clap(parse(from_os_str), value_name = "paths")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Also scan every directory on `$PATH`
+    #[clap(long)]
+    pub path: bool,
+
+    /// Raw ext2/3/4 partition or disk image to scan without mounting it. `--executables-dir` is
+    /// then a directory inside the image, and libraries are looked up in the image too
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub image: Option<PathBuf>,
+
+    /// Root filesystem on the host to scan, like an extracted container image or an embedded
+    /// rootfs: `--executables-dir` is a directory inside it, and libraries, interpreters and
+    /// `ld.so.conf` are looked up in it too
+    #[clap(long, global = true, parse(from_os_str), value_name = "dir", conflicts_with = "image")]
+    pub sysroot: Option<PathBuf>,
+
+    /// Only report binaries of these comma-separated architectures, e.g. `x86_64,aarch64`
+    #[clap(long, global = true, value_name = "arches", use_value_delimiter = true)]
+    pub arch: Vec<String>,
+
+    /// Report binaries sharing a GNU build-id once, under the first path they were found at
+    #[clap(long, global = true)]
+    pub dedupe: bool,
+
+    /// Save the scan results to be queried later with `--index`
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub save_index: Option<PathBuf>,
+
+    /// How scanned files are shown in reports
+    #[clap(long, arg_enum, global = true, default_value = "name")]
+    pub paths: PathStyle,
+
+    /// Keep rescanning, printing the report again whenever the dependencies change
+    #[clap(long)]
+    pub watch: bool,
+
+    /// Seconds between rescans in `--watch` mode, which also rescans as soon as inotify reports
+    /// a change
+    #[clap(long, value_name = "secs", default_value_t = 10)]
+    pub poll_interval: u64,
+
+    /// POST the changes found in `--watch` or `daemon` mode as JSON to this http:// URL
+    #[clap(long, global = true, value_name = "url")]
+    pub webhook: Option<String>,
+
+    /// Exit with status 2 after the report if any binary couldn't be analyzed
+    #[clap(long, global = true)]
+    pub strict: bool,
+
+    /// Stop at the first binary that can't be analyzed and exit with status 2
+    #[clap(long, global = true)]
+    pub fail_fast: bool,
+
+    /// List the binaries needing libraries that can't be found, directly or through other
+    /// libraries, instead of the report, and exit with status 3 if there are any
+    #[clap(long)]
+    pub check: bool,
+
+    /// Only count the files the scan would look at and estimate how long it would take from a
+    /// small sample, without running it
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Save the progress of the scan to this file every 30 seconds; it's removed once the scan
+    /// is complete
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Continue the scan from `--checkpoint` instead of starting over
+    #[clap(long, global = true, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Keep parse results in this file and reparse only the files changed since the last run
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub cache: Option<PathBuf>,
+
+    /// Reparse every file, rewriting `--cache` from scratch
+    #[clap(long, global = true)]
+    pub no_cache: bool,
+
+    /// Name identifying this host or image in machine-readable output, the hostname by default
+    #[clap(long, global = true, value_name = "name")]
+    pub label: Option<String>,
+
+    /// Scan subdirectories of `--executables-dir` too
+    #[clap(short, long, global = true)]
+    pub recursive: bool,
+
+    /// Scan at most this deep below `--executables-dir`, 1 being only its own files; implies
+    /// `--recursive`
+    #[clap(long, global = true, value_name = "depth")]
+    pub max_depth: Option<usize>,
+
+    /// Descend into symlinked directories when scanning recursively
+    #[clap(long, global = true)]
+    pub follow_symlinks: bool,
+
+    /// Don't descend into directories on other filesystems when scanning recursively
+    #[clap(long, global = true)]
+    pub one_file_system: bool,
+
+    /// Scan the files listed in this file, `-` for stdin, instead of walking directories: one
+    /// path per line, or NUL-separated as `find -print0` writes them
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub files_from: Option<PathBuf>,
+
+    /// Only scan files matching one of these comma-separated globs, relative to the scanned
+    /// directory: `*.so*` matches names at any depth, `usr/lib/**` everything in a directory
+    #[clap(long, global = true, value_name = "globs", use_value_delimiter = true)]
+    pub include: Vec<String>,
+
+    /// Leave out paths matching these comma-separated globs, with the syntax of `--ignore-file`
+    #[clap(long, global = true, value_name = "globs", use_value_delimiter = true)]
+    pub exclude: Vec<String>,
+
+    /// Leave out the paths matching the patterns of this file, in gitignore syntax
+    #[clap(long, global = true, parse(from_os_str), value_name = "file")]
+    pub ignore_file: Option<PathBuf>,
+
+    /// Also try to read FIFOs, sockets and device nodes, which are skipped by default since
+    /// reading them may block
+    #[clap(long, global = true)]
+    pub include_special: bool,
+
+    /// Keep at most this many files open at once, the default is derived from RLIMIT_NOFILE
+    #[clap(long, global = true, value_name = "n")]
+    pub max_open_files: Option<usize>,
+
+    /// Number of files parsed in parallel, one per CPU by default
+    #[clap(short, long, global = true, value_name = "n")]
+    pub jobs: Option<usize>,
+
+    /// Read at most this many files per second
+    #[clap(long, global = true, parse(try_from_str = throttle::rate), value_name = "files")]
+    pub throttle_files: Option<f64>,
+
+    /// Read at most this many megabytes per second
+    #[clap(long, global = true, parse(try_from_str = throttle::rate), value_name = "MB")]
+    pub throttle_mb: Option<f64>,
+
+    /// Show how far the scan got on stderr: files found and handled, throughput and the file
+    /// being read
+    #[clap(long, global = true)]
+    pub progress: bool,
+
+    /// Print the number of files scanned, analyzed, skipped and failed, the bytes read and the
+    /// time taken to stderr after the scan
+    #[clap(long, global = true)]
+    pub stats: bool,
+
+    /// List the files that couldn't be analyzed in the scan summary
+    #[clap(long)]
+    pub failed_paths: bool,
+
+    /// Also scan Windows executables, listing the DLLs from their import tables
+    #[clap(long, global = true)]
+    pub pe: bool,
+
+    /// Also scan native libraries inside zip, jar, wheel, aar and apk archives
+    #[clap(long, global = true)]
+    pub archives: bool,
+
+    /// Report which executables would work if the base image switched from glibc to musl
+    #[clap(long)]
+    pub musl_report: bool,
+
+    /// Categorize executables by the runtimes, GPU and GUI stacks in their dependency closures
+    #[clap(long)]
+    pub runtimes: bool,
+
+    /// List libraries in the standard library directories that nothing scanned needs
+    #[clap(long)]
+    pub orphans: bool,
+
+    /// Index the sonames scanned libraries provide, with the ones nothing needs and the needs nothing provides
+    #[clap(long)]
+    pub providers: bool,
+
+    /// Warn about deprecated DT_RPATH, relative search paths and paths from the build machine
+    #[clap(long)]
+    pub lint_rpath: bool,
+
+    /// List the distro packages owning the libraries the executables need, and the libraries no package owns
+    #[clap(long)]
+    pub packages: bool,
+
+    /// Package database naming the owners of libraries, detected under the sysroot by default
+    #[clap(long, arg_enum, global = true)]
+    pub package_manager: Option<packages::Manager>,
+
+    /// List distinct paths of binaries sharing a GNU build-id: copies and hardlinks
+    #[clap(long)]
+    pub find_duplicates: bool,
+
+    /// Report PIE, RELRO, NX, stack protector and text relocations per binary
+    #[clap(long)]
+    pub checksec: bool,
+
+    /// Report what 32-bit executables need to run on a 64-bit root
+    #[clap(long)]
+    pub compat32: bool,
+
+    /// Summarize binaries, dependencies, missing libraries and failures per directory
+    #[clap(long)]
+    pub by_dir: bool,
+
+    /// Report library families needed in several major versions and who needs the older ones
+    #[clap(long)]
+    pub fragmentation: bool,
+
+    /// List binaries using GNU indirect functions
+    #[clap(long)]
+    pub ifuncs: bool,
+
+    /// Report copies of OpenSSL, zlib, curl and SQLite linked statically into binaries
+    #[clap(long)]
+    pub embedded: bool,
+
+    /// Report the x86-64 microarchitecture levels (v2, v3, v4) binaries need
+    #[clap(long)]
+    pub isa_levels: bool,
+
+    /// Report thread-local storage use and libraries whose TLS model may break `dlopen`
+    #[clap(long)]
+    pub tls: bool,
+
+    /// Report the libraries that binaries importing `dlopen` name in their read-only data as
+    /// probable run-time dependencies, and follow them in dependency closures. A heuristic:
+    /// the names may never be loaded
+    #[clap(long)]
+    pub detect_dlopen: bool,
+
+    /// Report the on-disk size of the libraries the executables need, counted once for the
+    /// whole tree, and the largest libraries and dependency closures
+    #[clap(long)]
+    pub sizes: bool,
+
+    /// Flag binaries importing dangerous, deprecated or removed libc interfaces
+    #[clap(long)]
+    pub unsafe_symbols: bool,
+
+    /// Symbols flagged by `--unsafe-symbols`, one per line optionally followed by the reason,
+    /// instead of the built-in list
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub symbol_denylist: Option<PathBuf>,
+
+    /// Report which exported symbols of each needed library anything imports
+    #[clap(long)]
+    pub symbol_usage: bool,
+
+    /// Report the symbol versions (`GLIBC_2.34`) binaries require from each library, with the
+    /// binaries requiring each
+    #[clap(long)]
+    pub symbol_versions: bool,
+
+    /// List which library each undefined symbol binds to, flagging symbols no library exports
+    /// and needed libraries nothing binds to
+    #[clap(long)]
+    pub symbols: bool,
+
+    /// JSON rules file: expressions over each binary's record producing findings of the
+    /// severity and with the message of the rule
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub rules: Option<PathBuf>,
+
+    /// Map systemd services to the libraries their executables need
+    #[clap(long)]
+    pub services: bool,
+
+    /// Root filesystem containing the systemd units for `--services`, the sysroot or `/` by
+    /// default
+    #[clap(long, parse(from_os_str), value_name = "dir")]
+    pub units_root: Option<PathBuf>,
+
+    /// Rank libraries by how much of the dependency graph relies on them
+    #[clap(long)]
+    pub centrality: bool,
+
+    /// Group libraries and executables into functional clusters of the dependency graph
+    #[clap(long)]
+    pub clusters: bool,
+
+    /// Number of entries shown in rankings
+    #[clap(long, default_value_t = 20)]
+    pub top: usize,
+
+    /// Hide standard base libraries (libc, libm, ld-linux, ...) from the report
+    #[clap(long)]
+    pub hide_standard: bool,
+
+    /// Libraries hidden by `--hide-standard`, as comma-separated globs
+    #[clap(long, value_name = "patterns", use_value_delimiter = true)]
+    pub standard_libs: Option<Vec<String>>,
+
+    /// Libraries removed from the report, as comma-separated globs
+    #[clap(long, value_name = "patterns", use_value_delimiter = true)]
+    pub exclude_libs: Vec<String>,
+
+    /// List every library loaded along with each executable, not just the ones it needs
+    /// directly, and draw the edges between libraries in `--graph-output`
+    #[clap(long)]
+    pub transitive: bool,
+
+    /// Only report libraries matching one of these comma-separated globs
+    #[clap(long, value_name = "patterns", use_value_delimiter = true)]
+    pub filter: Vec<String>,
+
+    /// Leave libraries needed by fewer executables out of the listing
+    #[clap(long, value_name = "n", default_value_t = 0, hide_default_value = true)]
+    pub min_count: usize,
+
+    /// What the listing is sorted by
+    #[clap(long, arg_enum, default_value = "count")]
+    pub sort: output::text::SortKey,
+
+    /// Sort direction, descending for counts and ascending for names by default
+    #[clap(long, possible_values = ["asc", "desc"])]
+    pub order: Option<String>,
+
+    /// How libraries are grouped in the listing
+    #[clap(long, arg_enum, default_value = "lib")]
+    pub group_by: GroupBy,
+
+    /// Output format
+    #[clap(long, alias = "output-format", arg_enum, default_value = "text")]
+    pub format: Format,
+
+    /// Browse the libraries and executables in an interactive terminal explorer instead of
+    /// printing a report, searching, sorting and drilling down into dependants and dependencies
+    #[clap(long, conflicts_with = "sbom")]
+    pub tui: bool,
+
+    /// Write a software bill of materials of the executables and their libraries instead
+    #[clap(long, arg_enum, value_name = "kind")]
+    pub sbom: Option<output::sbom::Kind>,
+
+    /// Directory the files of `--format parquet` are written to
+    #[clap(long, parse(from_os_str), value_name = "dir", default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Also write the dependency graph as an adjacency matrix CSV
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub adjacency_csv: Option<PathBuf>,
+
+    /// Also write the dependency graph as a DOT or Mermaid diagram
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub graph_output: Option<PathBuf>,
+
+    /// Syntax of `--graph-output`
+    #[clap(long, arg_enum, default_value = "dot")]
+    pub graph_syntax: output::diagram::Syntax,
+
+    /// Draw one node per soname in `--graph-output`, merging its builds for different
+    /// architectures and the scanned library carrying it
+    #[clap(long)]
+    pub collapse_sonames: bool,
+
+    /// Draw the libraries that couldn't be found in `--graph-output` in red
+    #[clap(long)]
+    pub highlight_missing: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List executables that need the given library
+    WhoNeeds(WhoNeedsArgs),
+    /// List libraries needed by a single file
+    Deps(DepsArgs),
+    /// Rescan on a schedule and serve the differences between runs over HTTP
+    Daemon(DaemonArgs),
+    /// Write tiny synthetic ELF files for testing into a directory
+    GenFixtures(GenFixturesArgs),
+    /// Report which executables wouldn't run on a distro release and why
+    Check(CheckArgs),
+    /// Combine indexes saved on many hosts into one report keyed by host label
+    Merge(MergeArgs),
+    /// Compare the dynamic linkage of two directories or saved scans
+    Diff(DiffArgs),
+    /// Scan a `docker save` tarball or an OCI image layout without extracting it
+    Image(ImageArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ImageArgs {
+    /// `docker save` tarball, or OCI image layout directory or tarball
+    #[clap(parse(from_os_str), value_name = "image")]
+    pub archive: PathBuf,
+
+    /// Directories and files inside the image to scan, the whole image recursively by default
+    #[clap(parse(from_os_str), value_name = "paths")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Image scanned in indexes holding several, as `os/arch[/variant]` (`linux/arm64`); the
+    /// platform of a single `--arch`, or linux/amd64, by default
+    #[clap(long, parse(try_from_str = container::Platform::parse), value_name = "platform")]
+    pub platform: Option<container::Platform>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct DiffArgs {
+    /// Directory to scan, or an index saved with `--save-index` or `--format json` output
+    #[clap(parse(from_os_str), value_name = "before")]
+    pub before: PathBuf,
+
+    /// Directory to scan or saved scan to compare with
+    #[clap(parse(from_os_str), value_name = "after")]
+    pub after: PathBuf,
+
+    /// Treat directories as root filesystems, looking libraries up inside each like `--sysroot`
+    #[clap(long)]
+    pub roots: bool,
+
+    /// Print the changes as JSON
+    #[clap(long)]
+    pub json: bool,
+
+    /// Exit with 1 if anything changed
+    #[clap(long)]
+    pub exit_code: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct MergeArgs {
+    /// Indexes saved with `--save-index`, or fleets saved by an earlier merge
+    #[clap(parse(from_os_str), value_name = "file", required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Only list the hosts with executables needing this library, exact or a glob like
+    /// `libssl.so.*`
+    #[clap(long, value_name = "library")]
+    pub needs: Option<String>,
+
+    /// Treat the `--needs` library name as a regular expression
+    #[clap(long, requires = "needs")]
+    pub regex: bool,
+
+    /// Include executables needing the library indirectly
+    #[clap(long, requires = "needs")]
+    pub transitive: bool,
+
+    /// Write the merged fleet to this file, which can be merged again later
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub save: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct CheckArgs {
+    /// Built-in release profile (debian-11, debian-12, ubuntu-20.04, ubuntu-22.04, rhel-8,
+    /// rhel-9) or the path of a profile file
+    #[clap(long, alias = "target-profile", value_name = "profile")]
+    pub target: String,
+
+    /// Check an index saved with `--save-index` instead of scanning
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub index: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct GenFixturesArgs {
+    /// Directory to write to, created if needed
+    #[clap(parse(from_os_str), value_name = "dir")]
+    pub dir: PathBuf,
+
+    /// Write one fixture with this file name, described by the options below, instead of the
+    /// standard set
+    #[clap(long, value_name = "file")]
+    pub name: Option<String>,
+
+    /// ELF class of the fixture
+    #[clap(long, possible_values = ["32", "64"], default_value_t = 64)]
+    pub bits: u8,
+
+    /// Write a big-endian fixture
+    #[clap(long)]
+    pub big_endian: bool,
+
+    /// Target architecture, as printed in reports
+    #[clap(long, value_name = "arch", default_value = "x86_64")]
+    pub machine: String,
+
+    /// Comma-separated DT_NEEDED entries
+    #[clap(long, value_name = "libs", use_value_delimiter = true)]
+    pub needed: Vec<String>,
+
+    /// DT_SONAME of the fixture, making it a library
+    #[clap(long, value_name = "soname")]
+    pub soname: Option<String>,
+
+    /// Program interpreter
+    #[clap(long, value_name = "path")]
+    pub interp: Option<String>,
+
+    /// Leave out the section headers, so only the program headers describe the file
+    #[clap(long)]
+    pub no_section_headers: bool,
+
+    /// Cut the section headers off the end of the file, leaving `e_shoff` pointing past it
+    #[clap(long, conflicts_with = "no-section-headers")]
+    pub cut_section_headers: bool,
+
+    /// Load address, in hex with a `0x` prefix or in decimal; other than 0 it makes the fixture
+    /// a fixed-address executable
+    #[clap(long, parse(try_from_str = fixtures::address), value_name = "addr", default_value_t = 0)]
+    pub base: u64,
+
+    /// Break the fixture in the given way
+    #[clap(long, arg_enum, value_name = "how")]
+    pub malformed: Option<fixtures::Malformed>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct DaemonArgs {
+    /// Seconds between rescans
+    #[clap(long, parse(try_from_str = daemon::interval), value_name = "secs", default_value_t = 3600)]
+    pub interval: u64,
+
+    /// Number of runs kept for queries
+    #[clap(long, value_name = "runs", default_value_t = 10)]
+    pub keep: usize,
+
+    /// Address to answer queries on: `GET /runs` lists the kept runs, `GET /diff?from=&to=`
+    /// gives the changes between two of them (the last two by default)
+    #[clap(long, value_name = "addr", default_value = "127.0.0.1:7878")]
+    pub listen: String,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct DepsArgs {
+    /// File to analyze
+    #[clap(parse(from_os_str))]
+    pub file: PathBuf,
+
+    /// Show where the dynamic linker would load each library from
+    #[clap(long)]
+    pub resolve: bool,
+
+    /// Include libraries needed through other libraries
+    #[clap(long)]
+    pub transitive: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct WhoNeedsArgs {
+    /// Library soname, exact or a glob like `libssl.so.*`
+    pub library: String,
+
+    /// Treat the library name as a regular expression
+    #[clap(long)]
+    pub regex: bool,
+
+    /// Include executables needing the library indirectly
+    #[clap(long)]
+    pub transitive: bool,
+
+    /// Print only the matching executables, one per line, as `--paths` shows them
+    #[clap(long)]
+    pub list: bool,
+
+    /// Query an index saved with `--save-index` instead of scanning
+    #[clap(long, parse(from_os_str), value_name = "file")]
+    pub index: Option<PathBuf>,
+}
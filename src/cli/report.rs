@@ -0,0 +1,266 @@
+//! The report printed for a scan, in the format `--format` asks for.
+
+use std::fs;
+use std::io;
+use std::process;
+use tracing::error;
+use crate::{analysis, graph, output, packages, pattern, resolve, rules, scan, tui};
+use super::args::{Args, Format, GroupBy};
+use super::{image_path, root, scan_metadata};
+
+/// Libraries needed by nearly everything, hidden by `--hide-standard`
+const STANDARD_LIBS: &[&str] = &[
+    "libc.so.*", "libm.so.*", "libdl.so.*", "libpthread.so.*", "librt.so.*", "libutil.so.*",
+    "libresolv.so.*", "libgcc_s.so.*", "libstdc++.so.*", "ld-linux*.so.*", "ld-musl-*.so.*",
+    "linux-vdso.so.*", "linux-gate.so.*",
+];
+
+/// Libraries hidden by `--hide-standard` and `--exclude-libs`
+pub fn lib_filter(args: &Args) -> pattern::LibFilter {
+    let mut filter = pattern::LibFilter::default();
+    if args.hide_standard {
+        match &args.standard_libs {
+            Some(libs) => filter.hide(libs.iter().map(|l| pattern::Pattern::new(l))),
+            None => filter.hide(STANDARD_LIBS.iter().map(|l| pattern::Pattern::new(l))),
+        }
+    }
+    filter.hide(args.exclude_libs.iter().map(|l| pattern::Pattern::new(l)));
+    filter.show_only(args.filter.iter().map(|l| pattern::Pattern::new(l)));
+    filter
+}
+
+/// Prints the report for `scan` in the requested format
+pub fn print(args: &Args, scan: &scan::Scan, resolver: &mut resolve::Resolver) {
+    let files = &scan.files;
+    let filter = lib_filter(args);
+    let packages = package_database(args);
+    if let Some(path) = &args.adjacency_csv {
+        let graph = graph::DepGraph::build(files, resolver, &filter);
+        let file = fs::File::create(path).expect("Could not create adjacency CSV");
+        output::graph_json::write_adjacency_csv(io::BufWriter::new(file), &graph)
+            .expect("Could not write adjacency CSV");
+    }
+    if let Some(path) = &args.graph_output {
+        let graph = graph::DepGraph::build(files, resolver, &filter);
+        let options = output::diagram::Options {
+            syntax: args.graph_syntax,
+            transitive: args.transitive,
+            collapse: args.collapse_sonames,
+            highlight_missing: args.highlight_missing,
+        };
+        let file = fs::File::create(path).expect("Could not create graph file");
+        output::diagram::write(io::BufWriter::new(file), &graph, &options).expect("Could not write graph");
+    }
+    if args.tui {
+        let graph = graph::DepGraph::build(files, resolver, &filter);
+        if let Err(e) = tui::run(&graph) {
+            error!("Couldn't run the explorer: {}", e);
+            process::exit(2);
+        }
+        return;
+    }
+    if let Some(kind) = args.sbom {
+        // files inside images can't be read to hash them
+        output::sbom::write(io::stdout().lock(), kind, files, resolver, &filter, &scan_metadata(args), image_path(args).is_none())
+            .expect("Could not write SBOM");
+        println!();
+        return;
+    }
+    match args.format {
+        Format::Text => print_text(args, scan, resolver, &filter, packages.as_ref()),
+        Format::Json => {
+            output::json::write(io::stdout().lock(), scan, resolver, &filter, &scan_metadata(args), packages.as_ref())
+                .expect("Could not write JSON");
+            println!();
+        },
+        Format::Csv => {
+            output::csv::write(io::BufWriter::new(io::stdout().lock()), files, resolver, &filter)
+                .expect("Could not write CSV");
+        },
+        Format::GraphJson => {
+            let graph = graph::DepGraph::build(files, resolver, &filter);
+            let clusters = analysis::clusters::label_propagation(&graph);
+            output::graph_json::write(io::stdout().lock(), &graph, &clusters, scan, &scan_metadata(args))
+                .expect("Could not write graph");
+            println!();
+        },
+        Format::Cypher => {
+            let graph = graph::DepGraph::build(files, resolver, &filter);
+            output::cypher::write(io::stdout().lock(), &graph, &scan_metadata(args)).expect("Could not write graph");
+        },
+        Format::HtmlGraph => {
+            let graph = graph::DepGraph::build(files, resolver, &filter);
+            output::html_graph::write(io::stdout().lock(), &graph).expect("Could not write graph");
+        },
+        Format::GhAnnotations | Format::Errorformat => {
+            let denylist = args.unsafe_symbols.then(|| denylist(args));
+            let rules = rules(args);
+            let findings = output::annotations::findings(scan, resolver, &filter, denylist.as_ref(), &rules);
+            let out = io::stdout().lock();
+            let result = if args.format == Format::GhAnnotations {
+                output::annotations::write_github(out, &findings)
+            }
+            else {
+                output::annotations::write_errorformat(out, &findings)
+            };
+            result.expect("Could not write annotations");
+        },
+        Format::Parquet => {
+            if let Err(e) = output::parquet::write(&args.output_dir, files, resolver, &filter, &scan_metadata(args)) {
+                error!("Couldn't write Parquet files to {}: {}", args.output_dir.display(), e);
+                process::exit(2);
+            }
+        },
+    }
+}
+
+/// Package database of `--package-manager`, or of the one detected with `--packages`
+pub fn package_database(args: &Args) -> Option<packages::Database> {
+    if !args.packages && args.package_manager.is_none() {
+        return None;
+    }
+    if image_path(args).is_some() {
+        error!("Package databases inside images can't be read, scan a mounted or extracted copy with --sysroot");
+        process::exit(2);
+    }
+    let root = root(args);
+    let manager = match args.package_manager.or_else(|| packages::Manager::detect(&root)) {
+        Some(manager) => manager,
+        None => {
+            error!("No dpkg, rpm, pacman or apk database found in {}, pick one with --package-manager", root.display());
+            process::exit(2);
+        },
+    };
+    match packages::Database::load(&root, manager) {
+        Ok(database) => Some(database),
+        Err(e) => {
+            error!("Couldn't read the {} database in {}: {}", manager, root.display(), e);
+            process::exit(2);
+        },
+    }
+}
+
+/// Rules from `--rules`, none without it
+pub fn rules(args: &Args) -> Vec<rules::Rule> {
+    match &args.rules {
+        Some(path) => rules::load(path).unwrap_or_else(|e| {
+            error!("Couldn't read rules {}: {}", path.display(), e);
+            process::exit(2);
+        }),
+        None => vec![],
+    }
+}
+
+/// Symbols flagged by `--unsafe-symbols`
+pub fn denylist(args: &Args) -> analysis::unsafe_symbols::Denylist {
+    match &args.symbol_denylist {
+        Some(path) => analysis::unsafe_symbols::Denylist::load(path).unwrap_or_else(|e| {
+            error!("Couldn't read symbol denylist {}: {}", path.display(), e);
+            process::exit(2);
+        }),
+        None => analysis::unsafe_symbols::Denylist::default(),
+    }
+}
+
+fn print_text(
+    args: &Args, scan: &scan::Scan, resolver: &mut resolve::Resolver, filter: &pattern::LibFilter, packages: Option<&packages::Database>,
+) {
+    let files = &scan.files;
+    let closures = args.transitive.then_some(&mut *resolver);
+    let order = output::text::Order {
+        key: args.sort,
+        descending: match args.order.as_deref() {
+            Some(order) => order == "desc",
+            None => args.sort == output::text::SortKey::Count,
+        },
+        min_count: args.min_count,
+    };
+    match args.group_by {
+        GroupBy::Lib => output::text::print_libs(output::text::lib_map(files, closures, filter), &order),
+        GroupBy::Family => output::text::print_families(output::text::lib_map(files, closures, filter), &order),
+        GroupBy::Exe => output::text::print_exes(files, resolver, args.transitive, filter, &order, packages),
+        GroupBy::Interp => output::text::print_interpreters(files, resolver, args.transitive, filter, &order),
+    }
+    if args.musl_report {
+        analysis::musl::print_report(files);
+    }
+    if args.runtimes {
+        analysis::runtimes::print_report(files, resolver);
+    }
+    if args.orphans {
+        analysis::orphans::print_report(files, resolver);
+    }
+    if args.providers {
+        analysis::providers::print_report(files, filter);
+    }
+    if let Some(packages) = packages.filter(|_| args.packages) {
+        analysis::packages::print_report(files, resolver, packages, filter);
+    }
+    if args.find_duplicates {
+        analysis::duplicates::print_report(files);
+    }
+    if args.checksec {
+        analysis::checksec::print_report(files);
+    }
+    if args.lint_rpath {
+        // images aren't mounted, so only host paths can be checked for existence
+        analysis::rpaths::print_report(files, image_path(args).is_none().then_some(resolver.root.as_path()));
+    }
+    if args.compat32 {
+        analysis::compat32::print_report(files, resolver);
+    }
+    if args.by_dir {
+        analysis::directories::print_report(scan, resolver);
+    }
+    if args.fragmentation {
+        analysis::fragmentation::print_report(files, filter);
+    }
+    if args.ifuncs {
+        analysis::ifuncs::print_report(files);
+    }
+    if args.embedded {
+        analysis::embedded::print_report(files);
+    }
+    if args.isa_levels {
+        analysis::isa::print_report(files);
+    }
+    if args.tls {
+        analysis::tls::print_report(files);
+    }
+    if args.detect_dlopen {
+        analysis::dlopen::print_report(files, resolver, filter);
+    }
+    if args.sizes {
+        analysis::sizes::print_report(files, resolver, args.top);
+    }
+    if args.unsafe_symbols {
+        analysis::unsafe_symbols::print_report(files, &denylist(args));
+    }
+    if args.symbol_usage {
+        analysis::symbol_usage::print_report(files, resolver);
+    }
+    if args.symbols {
+        analysis::symbols::print_report(files, resolver);
+    }
+    if args.symbol_versions {
+        analysis::symbol_versions::print_report(files, filter);
+    }
+    if args.rules.is_some() {
+        rules::print_report(files, &rules(args));
+    }
+    if args.services {
+        let units_root = args.units_root.clone().unwrap_or_else(|| root(args));
+        analysis::services::print_report(&units_root, resolver, filter);
+    }
+    if args.centrality || args.clusters {
+        let graph = graph::DepGraph::build(files, resolver, filter);
+        if args.centrality {
+            analysis::centrality::print_report(&graph, args.top);
+        }
+        if args.clusters {
+            analysis::clusters::print_report(&graph);
+        }
+    }
+    output::text::print_not_dynamic(scan, args.failed_paths);
+    output::text::print_summary(scan, args.failed_paths);
+}
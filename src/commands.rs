@@ -0,0 +1,8 @@
+//! Subcommands answering specific questions about the scan results.
+
+pub mod check;
+pub mod deps;
+pub mod diff;
+pub mod merge;
+pub mod missing;
+pub mod who_needs;
@@ -0,0 +1,56 @@
+//! Portability check: which scanned binaries wouldn't run on a given distro release.
+
+use std::collections::BTreeMap;
+use crate::elf::BinaryFormat;
+use crate::profiles::{numbers, Profile};
+use crate::scan::ScannedFile;
+
+/// Reasons `file` wouldn't run on the release described by `profile`
+pub fn problems(file: &ScannedFile, profile: &Profile) -> Vec<String> {
+    let mut problems = vec![];
+    for lib in &file.info.needed {
+        if let Some(others) = profile.missing_soname(lib) {
+            problems.push(format!("needs {}, {} has {}", lib, profile.name, others.join(", ")));
+        }
+    }
+    for need in &file.info.version_needs {
+        // only the newest version missing from each family
+        let mut newest: BTreeMap<&str, (&str, &str)> = BTreeMap::new();
+        for version in &need.versions {
+            if let Some((family, has)) = profile.missing_version(version) {
+                let number = |v: &str| v.rsplit_once('_').and_then(|(_, n)| numbers(n));
+                match newest.get(family) {
+                    Some((seen, _)) if number(seen) >= number(version) => {},
+                    _ => { newest.insert(family, (version, has)); },
+                }
+            }
+        }
+        for (version, has) in newest.into_values() {
+            problems.push(format!("needs {} from {}, {} has {}", version, need.file, profile.name, has));
+        }
+    }
+    problems
+}
+
+/// Prints the binaries that would fail on the release with the reasons, returning whether all
+/// of them would work
+pub fn print(files: &[ScannedFile], profile: &Profile) -> bool {
+    let results: Vec<(&ScannedFile, Vec<String>)> = files.iter()
+        .filter(|f| f.info.format == BinaryFormat::Elf)
+        .map(|f| (f, problems(f, profile)))
+        .collect();
+    let failing: Vec<&(&ScannedFile, Vec<String>)> = results.iter()
+        .filter(|(_, problems)| !problems.is_empty())
+        .collect();
+    println!("{} compatibility ({} exes)", profile.name, results.len());
+    println!("\tok: {}, would fail: {}", results.len() - failing.len(), failing.len());
+    println!();
+    for (file, problems) in &failing {
+        println!("{}", file.name);
+        for problem in problems {
+            println!("\t! {}", problem);
+        }
+        println!();
+    }
+    failing.is_empty()
+}
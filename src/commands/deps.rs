@@ -0,0 +1,52 @@
+//! Dependencies of a single file, without walking any directory.
+
+use std::path::Path;
+use crate::elf::HandleError;
+use crate::escape;
+use crate::loader::Loader;
+use crate::scan::{ScanOptions, parse_file};
+
+/// Prints the libraries `path` needs, with where the dynamic linker would load them from if
+/// `resolve` is set and including indirect dependencies if `transitive` is set
+pub fn print(path: &Path, options: &ScanOptions, loader: &mut Loader, resolve: bool, transitive: bool) -> Result<(), HandleError> {
+    let info = parse_file(path, options)?;
+    let libs = if transitive {
+        loader.closure(path, &info)
+    }
+    else {
+        loader.needed(path, &info)
+    };
+    println!("{} ({})", escape::path(path), info.format);
+    if let Some(soname) = &info.soname {
+        println!("\tid {}", soname);
+    }
+    if let Some(interp) = &info.interp {
+        println!("\tinterp {}", interp);
+    }
+    if let Some(build_id) = &info.build_id {
+        println!("\tbuild-id {}", build_id);
+    }
+    let target = info.target();
+    for (kind, dirs) in [("rpath", &info.rpaths), ("runpath", &info.runpaths)] {
+        for dir in dirs {
+            match loader.expand(dir, path, &target) {
+                expanded if expanded == *dir => println!("\t{} {}", kind, dir),
+                expanded => println!("\t{} {} ({})", kind, dir, expanded),
+            }
+        }
+    }
+    for lib in libs {
+        if !resolve {
+            println!("\t=> {}", lib.soname);
+            continue;
+        }
+        match lib.found {
+            Some((found, source)) => println!("\t=> {} ({} via {})", lib.soname, escape::path(&found.path), source),
+            None => println!("\t=> {} (not found)", lib.soname),
+        }
+    }
+    for embedded in &info.embedded {
+        println!("\t=> {} (static)", embedded);
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! What changed in dynamic linkage between two scans, e.g. across an upgrade or a base-image
+//! swap: executables added and removed, the libraries each of them gained and lost, and
+//! libraries whose number of dependants changed.
+
+use std::collections::BTreeMap;
+use crate::diff::Changes;
+
+/// Prints the changes, returns `false` if there were none
+pub fn print(changes: &Changes) -> bool {
+    println!("added executables ({} exes)", changes.added_files.len());
+    for file in &changes.added_files {
+        println!("\t+ {}", file);
+    }
+    println!();
+    println!("removed executables ({} exes)", changes.removed_files.len());
+    for file in &changes.removed_files {
+        println!("\t- {}", file);
+    }
+    println!();
+    // files present before and after, with the libraries they gained and lost
+    let mut changed: BTreeMap<&str, (Vec<&str>, Vec<&str>)> = BTreeMap::new();
+    for dependency in changes.added_dependencies.iter().filter(|d| !changes.added_files.contains(&d.file)) {
+        changed.entry(&dependency.file).or_default().0.push(&dependency.library);
+    }
+    for dependency in changes.removed_dependencies.iter().filter(|d| !changes.removed_files.contains(&d.file)) {
+        changed.entry(&dependency.file).or_default().1.push(&dependency.library);
+    }
+    println!("changed dependencies ({} exes)", changed.len());
+    println!();
+    for (file, (added, removed)) in &changed {
+        println!("{} ({} added, {} removed)", file, added.len(), removed.len());
+        for lib in added {
+            println!("\t+ {}", lib);
+        }
+        for lib in removed {
+            println!("\t- {}", lib);
+        }
+        println!();
+    }
+    println!("changed dependant counts ({} libs)", changes.dependant_counts.len());
+    for change in &changes.dependant_counts {
+        println!("\t{} ({} -> {} exes)", change.library, change.before, change.after);
+    }
+    println!();
+    println!("libraries needed by nothing before ({} libs)", changes.added_libraries.len());
+    for lib in &changes.added_libraries {
+        println!("\t+ {}", lib);
+    }
+    println!();
+    println!("libraries needed by nothing anymore ({} libs)", changes.removed_libraries.len());
+    for lib in &changes.removed_libraries {
+        println!("\t- {}", lib);
+    }
+    println!();
+    println!("missing libraries ({} newly missing, {} found now)", changes.newly_missing.len(), changes.resolved.len());
+    for lib in &changes.newly_missing {
+        println!("\t! {} can't be found anymore", lib);
+    }
+    for lib in &changes.resolved {
+        println!("\t{} is found now", lib);
+    }
+    println!();
+    !changes.is_empty()
+}
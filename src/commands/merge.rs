@@ -0,0 +1,80 @@
+//! Cross-fleet reports over the merged scans of many hosts.
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::commands::who_needs::{self, User};
+use crate::escape;
+use crate::fleet::Fleet;
+use crate::index::Index;
+use crate::pattern::Pattern;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Scanned files of a host named by their full paths, which tell hosts' files apart better
+/// than their bare names
+fn files(index: &Index) -> Vec<ScannedFile> {
+    index.files.iter()
+        .map(|f| ScannedFile { name: escape::path(&f.path), ..f.clone() })
+        .collect()
+}
+
+/// Matched libraries with the hosts needing them and the executables on each
+pub fn who_needs(fleet: &Fleet, pattern: &Pattern, transitive: bool) -> BTreeMap<String, BTreeMap<String, Vec<User>>> {
+    let mut result: BTreeMap<String, BTreeMap<String, Vec<User>>> = BTreeMap::new();
+    for (label, index) in &fleet.hosts {
+        let mut resolver = Resolver::from_libraries(index.libraries.clone());
+        for (lib, users) in who_needs::who_needs(&files(index), &mut resolver, pattern, transitive) {
+            result.entry(lib).or_default().insert(label.clone(), users);
+        }
+    }
+    result
+}
+
+/// Prints the matches, returns `false` if there were none
+pub fn print_who_needs(matches: &BTreeMap<String, BTreeMap<String, Vec<User>>>) -> bool {
+    for (lib, hosts) in matches {
+        let exes: usize = hosts.values().map(Vec::len).sum();
+        println!("{} ({} hosts, {} exes)", lib, hosts.len(), exes);
+        for (label, users) in hosts {
+            println!("\t<= {} ({} exes)", label, users.len());
+            for user in users {
+                match &user.via {
+                    Some(via) => println!("\t\t{} (via {})", user.exe, via),
+                    None => println!("\t\t{}", user.exe),
+                }
+            }
+        }
+        println!();
+    }
+    !matches.is_empty()
+}
+
+/// Prints every host with its totals, then every library with the hosts having executables
+/// that need it directly
+pub fn print(fleet: &Fleet) {
+    println!("fleet ({} hosts)", fleet.hosts.len());
+    // library => host => exes
+    let mut users: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for (label, index) in &fleet.hosts {
+        let mut resolver = Resolver::from_libraries(index.libraries.clone());
+        let mut missing = BTreeSet::new();
+        for file in &index.files {
+            let target = file.info.target();
+            for lib in &file.info.needed {
+                *users.entry(lib).or_default().entry(label).or_default() += 1;
+                if resolver.resolve(lib, &target).is_none() {
+                    missing.insert(lib);
+                }
+            }
+        }
+        let root = index.metadata.as_ref().map_or("unknown root", |m| m.root.as_str());
+        println!("\t{}: {} exes in {}, {} missing libraries", label, index.files.len(), root, missing.len());
+    }
+    println!();
+    for (lib, hosts) in &users {
+        println!("{} ({} of {} hosts)", lib, hosts.len(), fleet.hosts.len());
+        for (label, exes) in hosts {
+            println!("\t<= {} ({} exes)", label, exes);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,86 @@
+//! CI check for binaries that would fail to start with "error while loading shared libraries":
+//! every needed library, direct or not, that can't be found on the scanned root.
+
+use std::collections::{HashSet, VecDeque};
+use crate::elf::BinaryFormat;
+use crate::loader::Loader;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Library the loader wouldn't find, `via` being the direct dependency needing it when it isn't
+/// needed directly
+#[derive(Debug, Clone)]
+pub struct Missing {
+    pub soname: String,
+    pub via: Option<String>,
+}
+
+/// Where libraries are looked for: as the dynamic linker would for a scan of the host,
+/// following the binaries' run-time search paths, and in the standard library directories of
+/// images and saved indexes
+pub enum Lookup<'a> {
+    Loader(&'a mut Loader),
+    Resolver(&'a mut Resolver),
+}
+
+/// Missing libraries of `file`'s dependency closure, leaving out those hidden by `filter`
+pub fn missing(file: &ScannedFile, lookup: &mut Lookup, filter: &LibFilter) -> Vec<Missing> {
+    let resolver = match lookup {
+        Lookup::Loader(loader) => {
+            return loader.closure(&file.path, &file.info).into_iter()
+                .filter(|r| r.found.is_none() && !filter.is_hidden(&r.soname))
+                .map(|r| Missing { soname: r.soname, via: r.via })
+                .collect();
+        },
+        Lookup::Resolver(resolver) => resolver,
+    };
+    let target = file.info.target();
+    let dlopen = resolver.dlopen;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut missing = vec![];
+    let mut queue: VecDeque<(String, Option<String>)> = file.info.needed.iter()
+        .map(|l| (l.clone(), None))
+        .collect();
+    if dlopen {
+        queue.extend(file.info.dlopen_needed().into_iter().map(|l| (l, Some("dlopen".to_string()))));
+    }
+    while let Some((soname, via)) = queue.pop_front() {
+        if !seen.insert(soname.clone()) {
+            continue;
+        }
+        match resolver.resolve(&soname, &target) {
+            Some(lib) => {
+                let via = via.unwrap_or_else(|| soname.clone());
+                queue.extend(lib.info.needs(dlopen).into_iter().map(|l| (l, Some(via.clone()))));
+            },
+            None if !filter.is_hidden(&soname) => missing.push(Missing { soname, via }),
+            None => {},
+        }
+    }
+    missing
+}
+
+/// Prints the ELF binaries with missing libraries, returning whether there were none
+pub fn print(files: &[ScannedFile], lookup: &mut Lookup, filter: &LibFilter) -> bool {
+    let elf: Vec<&ScannedFile> = files.iter().filter(|f| f.info.format == BinaryFormat::Elf).collect();
+    let failing: Vec<(&ScannedFile, Vec<Missing>)> = elf.iter()
+        .map(|&f| (f, missing(f, lookup, filter)))
+        .filter(|(_, missing)| !missing.is_empty())
+        .collect();
+    println!("missing libraries ({} exes)", elf.len());
+    println!("\tok: {}, missing libraries: {}", elf.len() - failing.len(), failing.len());
+    println!();
+    for (file, missing) in &failing {
+        let target = file.info.target();
+        println!("{}", file.name);
+        for lib in missing {
+            match &lib.via {
+                Some(via) => println!("\t! needs {} through {}, not found for {}", lib.soname, via, target),
+                None => println!("\t! needs {}, not found for {}", lib.soname, target),
+            }
+        }
+        println!();
+    }
+    failing.is_empty()
+}
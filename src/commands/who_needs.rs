@@ -0,0 +1,83 @@
+//! Reverse dependency query: which executables need a library.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use crate::pattern::{self, Pattern};
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Executable needing a matched library, `via` being the direct dependency it's reached
+/// through when it's needed only transitively
+#[derive(Debug, Clone)]
+pub struct User {
+    pub exe: String,
+    pub via: Option<String>,
+}
+
+/// Matched libraries with their users
+pub fn who_needs(
+    files: &[ScannedFile],
+    resolver: &mut Resolver,
+    pattern: &Pattern,
+    transitive: bool,
+) -> BTreeMap<String, Vec<User>> {
+    let mut result: BTreeMap<String, Vec<User>> = BTreeMap::new();
+    for file in files {
+        let target = file.info.target();
+        let mut seen: HashSet<String> = HashSet::new();
+        // (soname, direct dependency it's reached through)
+        let mut queue: VecDeque<(String, Option<String>)> = file.info.needed.iter()
+            .map(|l| (l.clone(), None))
+            .collect();
+        while let Some((soname, via)) = queue.pop_front() {
+            if !seen.insert(soname.clone()) {
+                continue;
+            }
+            if pattern.matches(&soname) {
+                result.entry(soname.clone()).or_default().push(User {
+                    exe: file.name.clone(),
+                    via: via.clone(),
+                });
+            }
+            if !transitive {
+                continue;
+            }
+            if let Some(lib) = resolver.resolve(&soname, &target) {
+                let via = via.unwrap_or_else(|| soname.clone());
+                queue.extend(lib.info.needed.iter().map(|l| (l.clone(), Some(via.clone()))));
+            }
+        }
+    }
+    result
+}
+
+/// Needed libraries and sonames of `files` with names close to `query`
+pub fn suggestions(files: &[ScannedFile], query: &str) -> Vec<String> {
+    let names = files.iter()
+        .flat_map(|f| f.info.needed.iter().chain(&f.info.soname))
+        .map(String::as_str);
+    pattern::suggestions(query, names).into_iter().map(str::to_string).collect()
+}
+
+/// Prints the matches, returns `false` if there were none
+pub fn print(matches: &BTreeMap<String, Vec<User>>) -> bool {
+    for (lib, users) in matches {
+        println!("{} ({} exes)", lib, users.len());
+        for user in users {
+            match &user.via {
+                Some(via) => println!("\t<= {} (via {})", user.exe, via),
+                None => println!("\t<= {}", user.exe),
+            }
+        }
+        println!()
+    }
+    !matches.is_empty()
+}
+
+/// Prints every matching executable once, one per line, returns `false` if there were none
+pub fn print_list(matches: &BTreeMap<String, Vec<User>>) -> bool {
+    let exes: BTreeSet<&str> = matches.values().flatten().map(|u| u.exe.as_str()).collect();
+    for exe in &exes {
+        println!("{}", exe);
+    }
+    !exes.is_empty()
+}
@@ -0,0 +1,684 @@
+//! Root filesystem of a container image, assembled in memory from its layers instead of being
+//! extracted to disk: `docker save` tarballs and OCI image layouts, as directories or tarballs.
+//!
+//! Layers are applied in order, with `.wh.<name>` whiteouts hiding a file of the layers below
+//! and `.wh..wh..opq` ones everything below in their directory. Only the contents of files that
+//! could be binaries are kept, so memory use stays close to the size of those. Image indexes
+//! holding several platforms are narrowed down to the requested one, so that an image gives the
+//! same results whatever host scans it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use crate::escape;
+use crate::ext4::FileType;
+use crate::image::{Filesystem, Stat};
+use crate::scan;
+use crate::tar;
+
+/// Symlinks followed before giving up, as with Linux's `ELOOP`
+const MAX_SYMLINKS: usize = 40;
+/// Image indexes nested in each other before giving up
+const MAX_INDEXES: usize = 8;
+
+const OPAQUE_WHITEOUT: &[u8] = b".wh..wh..opq";
+const WHITEOUT_PREFIX: &[u8] = b".wh.";
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Debug)]
+enum Kind {
+    Directory,
+    /// Only the first bytes of files that aren't binaries are kept
+    Regular { size: u64, data: Vec<u8> },
+    Symlink(Vec<u8>),
+    Other,
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    kind: Kind,
+    mode: u32,
+}
+
+impl Entry {
+    fn directory() -> Arc<Entry> {
+        Arc::new(Entry { kind: Kind::Directory, mode: 0o755 })
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self.kind, Kind::Directory)
+    }
+}
+
+/// `[{"Config": …, "RepoTags": […], "Layers": […]}]` from `docker save`
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerManifest {
+    #[serde(default)]
+    repo_tags: Vec<String>,
+    layers: Vec<String>,
+}
+
+/// Platform of an image in an index, `linux/amd64` or `linux/arm64/v8` on the command line
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Platform {
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub architecture: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Platform { os: "linux".to_string(), architecture: "amd64".to_string(), variant: None }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        match &self.variant {
+            Some(variant) => write!(f, "/{}", variant),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Platform {
+    /// Parses `os/architecture[/variant]`, or an architecture alone for a Linux one
+    pub fn parse(platform: &str) -> Result<Platform, String> {
+        let parts: Vec<&str> = platform.split('/').collect();
+        if parts.iter().any(|p| p.is_empty()) {
+            return Err(format!("Invalid platform {}, expected os/architecture[/variant]", platform));
+        }
+        let (os, architecture, variant) = match parts[..] {
+            [architecture] => ("linux", architecture, None),
+            [os, architecture] => (os, architecture, None),
+            [os, architecture, variant] => (os, architecture, Some(variant.to_string())),
+            _ => return Err(format!("Invalid platform {}, expected os/architecture[/variant]", platform)),
+        };
+        Ok(Platform { os: os.to_string(), architecture: architecture.to_string(), variant })
+    }
+
+    /// Linux platform of binaries of the architecture `arch`, named as `--arch` names it
+    pub fn of_arch(arch: &str) -> Option<Platform> {
+        let architecture = match arch {
+            "i386" => "386",
+            "x86_64" => "amd64",
+            "arm" => "arm",
+            "aarch64" => "arm64",
+            "riscv" => "riscv64",
+            "powerpc" => "ppc",
+            "powerpc64" => "ppc64le",
+            "mips" => "mips",
+            "s390" => "s390x",
+            _ => return None,
+        };
+        Some(Platform { architecture: architecture.to_string(), ..Platform::default() })
+    }
+
+    /// Whether an image of the platform `other` is one of this platform, any variant matching
+    /// if either doesn't name one
+    fn matches(&self, other: &Platform) -> bool {
+        self.os == other.os && self.architecture == other.architecture
+            && (self.variant.is_none() || other.variant.is_none() || self.variant == other.variant)
+    }
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+    platform: Option<Platform>,
+}
+
+/// OCI image index or manifest, which only differ in what they list
+#[derive(Deserialize)]
+struct OciManifest {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+/// Manifest in an index for `platform`, or the first one if the index doesn't tell platforms
+/// apart
+fn choose<'a>(manifests: &'a [Descriptor], platform: &Platform) -> io::Result<&'a Descriptor> {
+    if let Some(chosen) = manifests.iter().find(|m| m.platform.as_ref().is_some_and(|p| platform.matches(p))) {
+        return Ok(chosen);
+    }
+    // build attestations are listed with an `unknown` platform
+    let platforms: Vec<String> = manifests.iter()
+        .filter_map(|m| m.platform.as_ref())
+        .filter(|p| p.architecture != "unknown")
+        .map(Platform::to_string)
+        .collect();
+    if !platforms.is_empty() {
+        return Err(invalid(format!("No image for {} in the index, it has {}", platform, platforms.join(", "))));
+    }
+    manifests.iter()
+        .find(|m| m.platform.is_none())
+        .ok_or_else(|| invalid("No image manifest in the index".to_string()))
+}
+
+/// Path of a blob in an OCI layout, `sha256:<hex>` being at `blobs/sha256/<hex>`
+fn blob_path(digest: &str) -> io::Result<String> {
+    match digest.split_once(':') {
+        Some((algorithm, hex)) if !algorithm.contains('/') && !hex.contains('/') => Ok(format!("blobs/{}/{}", algorithm, hex)),
+        _ => Err(invalid(format!("Invalid digest {}", digest))),
+    }
+}
+
+/// Components of a path in a tarball, `None` if it leaves the root
+fn normalize(path: &[u8]) -> Option<Vec<u8>> {
+    let mut key = vec![];
+    for component in path.split(|&b| b == b'/').filter(|c| !c.is_empty() && *c != b".") {
+        if component == b".." {
+            return None;
+        }
+        key.push(b'/');
+        key.extend_from_slice(component);
+    }
+    Some(key)
+}
+
+/// Directory and name of a key
+fn split(key: &[u8]) -> (&[u8], &[u8]) {
+    let slash = key.iter().rposition(|&b| b == b'/').unwrap_or(0);
+    (&key[..slash], &key[(slash + 1).min(key.len())..])
+}
+
+/// Where the blobs of an image are read from
+enum Blobs {
+    /// OCI layout directory
+    Dir(PathBuf),
+    /// `docker save` or OCI layout tarball, with the offset and size of each member and the
+    /// targets of its symlinks
+    Tar { path: PathBuf, members: HashMap<Vec<u8>, (u64, u64)>, links: HashMap<Vec<u8>, Vec<u8>> },
+}
+
+impl Blobs {
+    fn tar(path: &Path) -> io::Result<Blobs> {
+        let mut file = File::open(path)?;
+        let mut magic = [0; 2];
+        let read = file.read(&mut magic)?;
+        if read == 2 && magic == [0x1f, 0x8b] {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "compressed image archives can't be read in place, decompress it first"));
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let (mut members, mut links) = (HashMap::new(), HashMap::new());
+        let mut reader = tar::Reader::new(file);
+        while let Some(header) = reader.next()? {
+            let name = match normalize(&header.name) {
+                Some(name) => name,
+                None => continue,
+            };
+            match header.kind {
+                tar::REGULAR => { members.insert(name, (header.offset, header.size)); },
+                // `docker save` links layers shared by several images
+                tar::SYMLINK => {
+                    let target = [split(&name).0, b"/", &header.link].concat();
+                    if let Some(target) = normalize_relative(&target) {
+                        links.insert(name, target);
+                    }
+                },
+                tar::HARD_LINK => {
+                    if let Some(target) = normalize(&header.link) {
+                        links.insert(name, target);
+                    }
+                },
+                _ => {},
+            }
+        }
+        Ok(Blobs::Tar { path: path.to_path_buf(), members, links })
+    }
+
+    fn open(&self, name: &str) -> io::Result<Box<dyn Read>> {
+        match self {
+            Blobs::Dir(dir) => Ok(Box::new(File::open(dir.join(name))?)),
+            Blobs::Tar { path, members, links } => {
+                let mut key = normalize(name.as_bytes()).unwrap_or_default();
+                for _ in 0..MAX_SYMLINKS {
+                    match links.get(&key) {
+                        Some(target) => key = target.clone(),
+                        None => break,
+                    }
+                }
+                let &(offset, size) = members.get(&key).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} isn't in the archive", name))
+                })?;
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(file.take(size)))
+            },
+        }
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        match self {
+            Blobs::Dir(dir) => dir.join(name).is_file(),
+            Blobs::Tar { members, .. } => normalize(name.as_bytes()).is_some_and(|k| members.contains_key(&k)),
+        }
+    }
+
+    fn json<T: for<'de> Deserialize<'de>>(&self, name: &str) -> io::Result<T> {
+        let mut contents = vec![];
+        self.open(name)?.read_to_end(&mut contents)?;
+        serde_json::from_slice(&contents).map_err(|e| invalid(format!("{}: {}", name, e)))
+    }
+
+    /// Paths of the image's layers, lowest first, those of the image for `platform` in indexes
+    fn layers(&self, platform: &Platform) -> io::Result<Vec<String>> {
+        if self.exists("manifest.json") {
+            let manifests: Vec<DockerManifest> = self.json("manifest.json")?;
+            let manifest = manifests.first().ok_or_else(|| invalid("No image in manifest.json".to_string()))?;
+            if manifests.len() > 1 {
+                warn!("The archive holds {} images, scanning the first one ({})", manifests.len(), manifest.repo_tags.join(", "));
+            }
+            return Ok(manifest.layers.clone());
+        }
+        if !self.exists("index.json") {
+            return Err(invalid("Neither a docker save archive nor an OCI image layout".to_string()));
+        }
+        let mut manifest: OciManifest = self.json("index.json")?;
+        for _ in 0..MAX_INDEXES {
+            if manifest.manifests.is_empty() {
+                return manifest.layers.iter().map(|l| blob_path(&l.digest)).collect();
+            }
+            let chosen = choose(&manifest.manifests, platform)?;
+            debug!("Reading image manifest {}", chosen.digest);
+            manifest = self.json(&blob_path(&chosen.digest)?)?;
+        }
+        Err(invalid("Image indexes nested too deeply".to_string()))
+    }
+}
+
+/// [`normalize`] for symlink targets, where `..` can point to a sibling
+fn normalize_relative(path: &[u8]) -> Option<Vec<u8>> {
+    let mut components: Vec<&[u8]> = vec![];
+    for component in path.split(|&b| b == b'/').filter(|c| !c.is_empty() && *c != b".") {
+        if component == b".." {
+            components.pop()?;
+        }
+        else {
+            components.push(component);
+        }
+    }
+    Some(components.iter().flat_map(|c| [b"/".as_slice(), c]).flatten().copied().collect())
+}
+
+/// Stream of a layer, decompressed if it's gzipped
+fn decompress(layer: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+    let mut layer = BufReader::new(layer);
+    let head = layer.fill_buf()?;
+    if head.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(MultiGzDecoder::new(layer)))
+    }
+    else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "zstd-compressed layers aren't supported"))
+    }
+    else {
+        Ok(Box::new(layer))
+    }
+}
+
+/// What a layer adds at a path
+enum Added {
+    Entry(Entry),
+    /// Hard link to an earlier path
+    Link(Vec<u8>),
+}
+
+/// Files of a container image with its layers applied, by absolute path, the root being the
+/// empty path
+pub struct Rootfs {
+    entries: BTreeMap<Vec<u8>, Arc<Entry>>,
+}
+
+impl fmt::Debug for Rootfs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rootfs").field("entries", &self.entries.len()).finish()
+    }
+}
+
+impl Rootfs {
+    /// Reads the image in the `docker save` or OCI layout tarball or the OCI layout directory
+    /// at `path`, the one for `platform` if it holds several
+    pub fn load(path: &Path, platform: &Platform) -> io::Result<Rootfs> {
+        let blobs = if path.is_dir() { Blobs::Dir(path.to_path_buf()) } else { Blobs::tar(path)? };
+        let layers = blobs.layers(platform)?;
+        let mut rootfs = Rootfs { entries: BTreeMap::from([(vec![], Entry::directory())]) };
+        for (i, layer) in layers.iter().enumerate() {
+            debug!("Applying layer {} of {}: {}", i + 1, layers.len(), layer);
+            let mut reader = tar::Reader::new(blobs.open(layer).and_then(decompress)?);
+            rootfs.apply(&mut reader).map_err(|e| io::Error::new(e.kind(), format!("layer {}: {}", layer, e)))?;
+        }
+        info!("{} files in {} layers", rootfs.entries.len(), layers.len());
+        Ok(rootfs)
+    }
+
+    /// Entries under the directory whose key followed by a slash is `prefix`, at any depth
+    fn descendants<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = (&'a Vec<u8>, &'a Arc<Entry>)> {
+        self.entries.range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(key, _)| key.starts_with(prefix))
+    }
+
+    /// Removes everything in the directory `key`
+    fn clear(&mut self, key: &[u8]) {
+        let prefix = [key, b"/"].concat();
+        let doomed: Vec<Vec<u8>> = self.descendants(&prefix).map(|(key, _)| key.clone()).collect();
+        for key in doomed {
+            self.entries.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.clear(key);
+        self.entries.remove(key);
+    }
+
+    /// Key and entry the absolute `path` leads to, following symlinks
+    fn resolve(&self, path: &[u8]) -> Option<(Vec<u8>, Arc<Entry>)> {
+        let mut pending: Vec<Vec<u8>> = path.split(|&b| b == b'/')
+            .filter(|c| !c.is_empty())
+            .rev()
+            .map(<[u8]>::to_vec)
+            .collect();
+        let mut key = vec![];
+        let mut entry = self.entries.get(key.as_slice())?.clone();
+        let mut symlinks = 0;
+        while let Some(component) = pending.pop() {
+            match component.as_slice() {
+                b"." => continue,
+                b".." => {
+                    key.truncate(split(&key).0.len());
+                    entry = self.entries.get(&key)?.clone();
+                    continue;
+                },
+                _ => {},
+            }
+            let candidate = [key.as_slice(), b"/", &component].concat();
+            let found = self.entries.get(&candidate)?;
+            if let Kind::Symlink(target) = &found.kind {
+                symlinks += 1;
+                if symlinks > MAX_SYMLINKS {
+                    return None;
+                }
+                if target.starts_with(b"/") {
+                    key.clear();
+                }
+                pending.extend(target.split(|&b| b == b'/').filter(|c| !c.is_empty()).rev().map(<[u8]>::to_vec));
+                continue;
+            }
+            key = candidate;
+            entry = found.clone();
+        }
+        Some((key, entry))
+    }
+
+    /// Adds `entry` at `key`, writing through symlinked parent directories the way extracting
+    /// the layer would
+    fn insert(&mut self, key: &[u8], entry: Arc<Entry>) {
+        let (dir, name) = split(key);
+        let dir = match self.resolve(dir) {
+            Some((dir, parent)) if parent.is_dir() => dir,
+            _ => {
+                // directories missing from the layer are created, replacing anything else
+                for end in (1..dir.len()).filter(|&i| dir[i] == b'/') {
+                    self.ensure_dir(&dir[..end]);
+                }
+                self.ensure_dir(dir);
+                dir.to_vec()
+            },
+        };
+        let key = [dir.as_slice(), b"/", name].concat();
+        if !entry.is_dir() && self.entries.get(&key).is_some_and(|e| e.is_dir()) {
+            self.clear(&key);
+        }
+        self.entries.insert(key, entry);
+    }
+
+    fn ensure_dir(&mut self, key: &[u8]) {
+        if !self.entries.get(key).is_some_and(|e| e.is_dir()) {
+            self.entries.insert(key.to_vec(), Entry::directory());
+        }
+    }
+
+    /// Applies a layer: its whiteouts first, which only hide files of the layers below, then
+    /// its files
+    fn apply<R: tar::Input>(&mut self, layer: &mut tar::Reader<R>) -> io::Result<()> {
+        let (mut opaque, mut whiteouts, mut added) = (vec![], vec![], vec![]);
+        while let Some(header) = layer.next()? {
+            let key = match normalize(&header.name) {
+                Some(key) if !key.is_empty() => key,
+                Some(_) => continue,
+                None => {
+                    warn!("Skipping {}, which leaves the image's root", escape::bytes(&header.name));
+                    continue;
+                },
+            };
+            let (dir, name) = split(&key);
+            if name == OPAQUE_WHITEOUT {
+                opaque.push(dir.to_vec());
+                continue;
+            }
+            if let Some(hidden) = name.strip_prefix(WHITEOUT_PREFIX) {
+                whiteouts.push([dir, b"/", hidden].concat());
+                continue;
+            }
+            let mode = header.mode & 0o7777;
+            let kind = match header.kind {
+                tar::DIRECTORY => Kind::Directory,
+                tar::SYMLINK => Kind::Symlink(header.link),
+                tar::HARD_LINK => {
+                    if let Some(target) = normalize(&header.link) {
+                        added.push((key, Added::Link(target)));
+                    }
+                    continue;
+                },
+                // contiguous files are regular ones everywhere but on a few old Unixes
+                tar::REGULAR | b'7' => {
+                    let mut data = vec![];
+                    layer.by_ref().take(scan::MAGIC_LEN as u64).read_to_end(&mut data)?;
+                    if scan::may_be_binary(&data) {
+                        layer.read_to_end(&mut data)?;
+                    }
+                    Kind::Regular { size: header.size, data }
+                },
+                _ => Kind::Other,
+            };
+            added.push((key, Added::Entry(Entry { kind, mode })));
+        }
+        for dir in opaque {
+            self.clear(&dir);
+        }
+        for key in whiteouts {
+            self.remove(&key);
+        }
+        for (key, added) in added {
+            match added {
+                Added::Entry(entry) => self.insert(&key, Arc::new(entry)),
+                Added::Link(target) => match self.entries.get(&target).cloned() {
+                    Some(entry) => self.insert(&key, entry),
+                    None => debug!("Skipping hard link {} to missing {}", escape::bytes(&key), escape::bytes(&target)),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Filesystem for Rootfs {
+    /// Key and entry
+    type Node = (Vec<u8>, Arc<Entry>);
+
+    fn lookup(&self, path: &[u8]) -> io::Result<Option<Self::Node>> {
+        Ok(self.resolve(path))
+    }
+
+    fn stat(&self, (_, entry): &Self::Node) -> Stat {
+        let (file_type, size) = match &entry.kind {
+            Kind::Directory => (FileType::Directory, 0),
+            Kind::Regular { size, .. } => (FileType::Regular, *size),
+            Kind::Symlink(target) => (FileType::Symlink, target.len() as u64),
+            Kind::Other => (FileType::Other, 0),
+        };
+        Stat { id: Arc::as_ptr(entry) as usize as u64, file_type, permissions: entry.mode, size }
+    }
+
+    fn read(&self, (_, entry): &Self::Node) -> io::Result<Vec<u8>> {
+        match &entry.kind {
+            Kind::Regular { data, .. } => Ok(data.clone()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a regular file")),
+        }
+    }
+
+    fn head(&self, (_, entry): &Self::Node, len: usize) -> io::Result<Vec<u8>> {
+        match &entry.kind {
+            Kind::Regular { data, .. } => Ok(data[..data.len().min(len)].to_vec()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a regular file")),
+        }
+    }
+
+    fn read_dir(&self, (key, _): &Self::Node) -> io::Result<Vec<(Vec<u8>, Self::Node)>> {
+        let prefix = [key.as_slice(), b"/"].concat();
+        Ok(self.descendants(&prefix)
+            .filter(|(child, _)| !child[prefix.len()..].contains(&b'/'))
+            .map(|(child, entry)| (child[prefix.len()..].to_vec(), (child.clone(), entry.clone())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+
+    const ELF: &[u8] = b"\x7fELF rest of the binary";
+    /// PAX extended header
+    const PAX: u8 = b'x';
+
+    /// Tarball of `(name, type, data or link target)` members
+    fn tarball(members: &[(&str, u8, &[u8])]) -> Vec<u8> {
+        let mut tar = vec![];
+        for &(name, kind, data) in members {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[100..107].copy_from_slice(b"0000755");
+            let (size, link) = if kind == tar::REGULAR || kind == PAX { (data.len(), &[][..]) } else { (0, data) };
+            header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+            header[156] = kind;
+            header[157..157 + link.len()].copy_from_slice(link);
+            header[257..263].copy_from_slice(b"ustar\0");
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(&data[..size]);
+            tar.resize(tar.len().div_ceil(512) * 512, 0);
+        }
+        tar.resize(tar.len() + 1024, 0);
+        tar
+    }
+
+    /// Rootfs of a `docker save` tarball holding `layers`
+    fn load(name: &str, layers: &[Vec<u8>]) -> io::Result<Rootfs> {
+        let names: Vec<String> = (0..layers.len()).map(|i| format!("{}/layer.tar", i)).collect();
+        let manifest = serde_json::json!([{"Config": "config.json", "RepoTags": ["app:1"], "Layers": names}]).to_string();
+        let mut members = vec![("manifest.json", tar::REGULAR, manifest.as_bytes())];
+        members.extend(names.iter().zip(layers).map(|(name, layer)| (name.as_str(), tar::REGULAR, layer.as_slice())));
+        let path = std::env::temp_dir().join(format!("linux-hs-elf-{}-{}", std::process::id(), name));
+        fs::write(&path, tarball(&members)).unwrap();
+        let rootfs = Rootfs::load(&path, &Platform::default());
+        fs::remove_file(&path).unwrap();
+        rootfs
+    }
+
+    fn contents(rootfs: &Rootfs, path: &str) -> Option<Vec<u8>> {
+        rootfs.lookup(path.as_bytes()).unwrap().map(|node| rootfs.read(&node).unwrap())
+    }
+
+    #[test]
+    fn applies_layers() {
+        let text = vec![b'#'; 100];
+        let rootfs = load("layers", &[
+            tarball(&[
+                ("usr/", tar::DIRECTORY, b""),
+                ("usr/bin/app", tar::REGULAR, ELF),
+                ("./usr/bin/old", tar::REGULAR, ELF),
+                ("usr/lib/", tar::DIRECTORY, b""),
+                ("lib", tar::SYMLINK, b"usr/lib"),
+                ("opt/dir/x", tar::REGULAR, ELF),
+                ("etc/motd", tar::REGULAR, &text),
+                ("../escape", tar::REGULAR, ELF),
+            ]),
+            tarball(&[
+                ("usr/bin/.wh.old", tar::REGULAR, b""),
+                ("opt/dir/.wh..wh..opq", tar::REGULAR, b""),
+                ("opt/dir/y", tar::REGULAR, ELF),
+                ("lib/libz.so.1", tar::REGULAR, ELF),
+                ("usr/bin/hard", tar::HARD_LINK, b"usr/bin/app"),
+            ]),
+        ]).unwrap();
+        assert_eq!(contents(&rootfs, "/usr/bin/app").as_deref(), Some(ELF));
+        assert_eq!(contents(&rootfs, "/usr/bin/old"), None);
+        assert_eq!(contents(&rootfs, "/opt/dir/x"), None);
+        assert_eq!(contents(&rootfs, "/opt/dir/y").as_deref(), Some(ELF));
+        assert_eq!(contents(&rootfs, "/escape"), None);
+        // written through the symlinked directory
+        assert_eq!(contents(&rootfs, "/usr/lib/libz.so.1").as_deref(), Some(ELF));
+        assert_eq!(contents(&rootfs, "/lib/../lib/libz.so.1").as_deref(), Some(ELF));
+
+        // only the start of files that can't be binaries is kept
+        let motd = rootfs.lookup(b"/etc/motd").unwrap().unwrap();
+        assert_eq!((rootfs.stat(&motd).size, rootfs.read(&motd).unwrap().len()), (100, scan::MAGIC_LEN));
+
+        let bin = rootfs.lookup(b"/usr/bin").unwrap().unwrap();
+        let listed = rootfs.read_dir(&bin).unwrap();
+        assert_eq!(listed.iter().map(|(name, _)| name.as_slice()).collect::<Vec<_>>(), [&b"app"[..], b"hard"]);
+        assert_eq!(rootfs.stat(&listed[0].1).id, rootfs.stat(&listed[1].1).id);
+    }
+
+    #[test]
+    fn damaged_images() {
+        let pax = tarball(&[("PaxHeaders/app", PAX, b"2 x\n"), ("app", tar::REGULAR, ELF)]);
+        assert_eq!(load("pax", &[pax]).unwrap_err().to_string(), "layer 0/layer.tar: Invalid PAX record");
+
+        let path = std::env::temp_dir().join(format!("linux-hs-elf-{}-empty", std::process::id()));
+        fs::write(&path, tarball(&[("other", tar::REGULAR, b"")])).unwrap();
+        let error = Rootfs::load(&path, &Platform::default()).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.to_string(), "Neither a docker save archive nor an OCI image layout");
+    }
+
+    #[test]
+    fn chooses_the_platform() {
+        let descriptor = |digest: &str, platform: Option<&str>| Descriptor {
+            digest: digest.to_string(),
+            platform: platform.map(|p| Platform::parse(p).unwrap()),
+        };
+        let index = [
+            descriptor("sha256:amd64", Some("linux/amd64")),
+            descriptor("sha256:arm64", Some("linux/arm64/v8")),
+            descriptor("sha256:attestation", Some("unknown/unknown")),
+        ];
+        assert_eq!(choose(&index, &Platform::parse("arm64").unwrap()).unwrap().digest, "sha256:arm64");
+        assert_eq!(choose(&index, &Platform::parse("linux/arm64/v8").unwrap()).unwrap().digest, "sha256:arm64");
+        assert_eq!(
+            choose(&index, &Platform::parse("linux/s390x").unwrap()).map(|d| &d.digest).unwrap_err().to_string(),
+            "No image for linux/s390x in the index, it has linux/amd64, linux/arm64/v8",
+        );
+        assert_eq!(choose(&[descriptor("sha256:only", None)], &Platform::default()).unwrap().digest, "sha256:only");
+        assert!(Platform::parse("linux//v8").is_err());
+        assert!(Platform::parse("a/b/c/d").is_err());
+        assert!(blob_path("sha256:../../etc").is_err());
+    }
+}
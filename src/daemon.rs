@@ -0,0 +1,223 @@
+//! Long-running mode rescanning on a schedule, keeping the last few results and serving their
+//! differences over HTTP.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use tracing::{debug, warn};
+use crate::diff::{self, Snapshot};
+use crate::metadata::Metadata;
+
+/// Longest a client may take to send each part of its request or read the answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request line or header read
+const MAX_LINE: u64 = 8192;
+/// Most headers read from a request
+const MAX_HEADERS: usize = 100;
+
+/// Parses `--interval`, which has to leave time between rescans
+pub fn interval(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(0) => Err("must be at least 1 second".to_owned()),
+        Ok(secs) => Ok(secs),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// One scan of the tree
+#[derive(Debug, Serialize)]
+pub struct Run {
+    pub id: usize,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub files: usize,
+    pub missing: Vec<String>,
+    pub metadata: Metadata,
+    #[serde(skip)]
+    pub snapshot: Snapshot,
+}
+
+/// The most recent runs, oldest first
+#[derive(Debug)]
+pub struct History {
+    runs: VecDeque<Run>,
+    keep: usize,
+    next_id: usize,
+}
+
+impl History {
+    pub fn new(keep: usize) -> Self {
+        History { runs: VecDeque::new(), keep: keep.max(2), next_id: 1 }
+    }
+
+    /// Records a run, dropping the oldest ones beyond the limit. Returns the previous snapshot.
+    pub fn push(&mut self, snapshot: Snapshot, metadata: Metadata) -> Option<&Snapshot> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let run = Run {
+            id: self.next_id,
+            time,
+            files: snapshot.needed.len(),
+            missing: snapshot.missing.iter().cloned().collect(),
+            metadata,
+            snapshot,
+        };
+        self.next_id += 1;
+        self.runs.push_back(run);
+        while self.runs.len() > self.keep {
+            self.runs.pop_front();
+        }
+        self.runs.iter().rev().nth(1).map(|r| &r.snapshot)
+    }
+
+    fn get(&self, id: usize) -> Option<&Run> {
+        self.runs.iter().find(|r| r.id == id)
+    }
+}
+
+/// Query parameter `name` of a request target like `/diff?from=1&to=2`
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+/// Status and JSON body answering `target`
+fn respond(history: &History, target: &str) -> (&'static str, String) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path {
+        "/runs" => ("200 OK", serde_json::to_string(&history.runs).unwrap_or_default()),
+        "/diff" => {
+            let latest = history.runs.back().map(|r| r.id).unwrap_or_default();
+            let id = |name, default: usize| match param(query, name) {
+                Some(v) => v.parse().ok(),
+                None => Some(default),
+            };
+            let (from, to) = match (id("from", latest.saturating_sub(1)), id("to", latest)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return ("400 Bad Request", r#"{"error":"run ids must be numbers"}"#.to_string()),
+            };
+            match (history.get(from), history.get(to)) {
+                (Some(old), Some(new)) => {
+                    let body = serde_json::json!({
+                        "from": from,
+                        "to": to,
+                        "changes": diff::diff(&old.snapshot, &new.snapshot),
+                    });
+                    ("200 OK", body.to_string())
+                },
+                _ => ("404 Not Found", r#"{"error":"no such runs"}"#.to_string()),
+            }
+        },
+        _ => ("404 Not Found", r#"{"error":"unknown endpoint, try /runs or /diff?from=&to="}"#.to_string()),
+    }
+}
+
+/// Line of the request, empty at its end, cut off after `MAX_LINE` bytes
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE).read_line(&mut line)?;
+    Ok(line)
+}
+
+fn handle(stream: TcpStream, history: &Mutex<History>) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_line(&mut reader)?;
+    // the headers don't matter, but closing the connection with them unread would reset it
+    let mut headers = 0;
+    while request.ends_with('\n') && headers < MAX_HEADERS && !read_line(&mut reader)?.trim_end().is_empty() {
+        headers += 1;
+    }
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    debug!("{} {}", method, target);
+    let too_long = !request.ends_with('\n') || headers == MAX_HEADERS;
+    let (status, body) = if too_long {
+        ("400 Bad Request", r#"{"error":"request too long"}"#.to_string())
+    }
+    else if method == "GET" {
+        respond(&history.lock().unwrap(), target)
+    }
+    else {
+        ("405 Method Not Allowed", r#"{"error":"only GET is supported"}"#.to_string())
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )?;
+    if too_long {
+        // some more of what's left unread, until the client closes, for the answer to reach it
+        stream.shutdown(Shutdown::Write)?;
+        io::copy(&mut reader.take(16 * MAX_LINE), &mut io::sink())?;
+    }
+    Ok(())
+}
+
+/// Answers queries about `history` on `listener` in a background thread, each in a thread of its
+/// own so that a slow client doesn't hold up the others
+pub fn serve(listener: TcpListener, history: Arc<Mutex<History>>) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let history = history.clone();
+            let result = stream.map(|s| thread::spawn(move || {
+                if let Err(e) = handle(s, &history) {
+                    warn!("Couldn't answer query: {}", e);
+                }
+            }));
+            if let Err(e) = result {
+                warn!("Couldn't accept query: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answer of a daemon with no runs yet to `request`
+    fn query(listener: &TcpListener, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        stream.write_all(request).unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        answer
+    }
+
+    #[test]
+    fn answers_queries() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        serve(listener.try_clone().unwrap(), Arc::new(Mutex::new(History::new(2))));
+        // a client that doesn't send anything doesn't hold up the others
+        let _idle = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let answer = query(&listener, b"GET /runs HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\n");
+        assert!(answer.starts_with("HTTP/1.1 200 OK\r\n"), "{}", answer);
+        assert!(answer.ends_with("\r\n\r\n[]"), "{}", answer);
+        let answer = query(&listener, b"GET /diff?from=x HTTP/1.1\r\n\r\n");
+        assert!(answer.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", answer);
+        let answer = query(&listener, b"DELETE /runs HTTP/1.1\r\n\r\n");
+        assert!(answer.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"), "{}", answer);
+
+        let mut long = b"GET /".to_vec();
+        long.resize(2 * MAX_LINE as usize, b'a');
+        assert!(query(&listener, &long).contains("request too long"));
+        let many = format!("GET /runs HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1));
+        assert!(query(&listener, many.as_bytes()).contains("request too long"));
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(interval("60"), Ok(60));
+        assert!(interval("0").is_err());
+        assert!(interval("-1").is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Changes in the dependency map between two scans.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::escape;
+use crate::index::Index;
+use crate::metadata::Metadata;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// What every scanned file needs, and which of it can't be found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub needed: BTreeMap<String, BTreeSet<String>>,
+    pub missing: BTreeSet<String>,
+}
+
+impl Snapshot {
+    pub fn new(files: &[ScannedFile], resolver: &mut Resolver) -> Self {
+        let mut snapshot = Snapshot::default();
+        for file in files {
+            let target = file.info.target();
+            for lib in &file.info.needed {
+                if resolver.resolve(lib, &target).is_none() {
+                    snapshot.missing.insert(lib.clone());
+                }
+            }
+            snapshot.needed.insert(file.name.clone(), file.info.needed.iter().cloned().collect());
+        }
+        snapshot
+    }
+
+    fn libraries(&self) -> BTreeSet<&String> {
+        self.needed.values().flatten().collect()
+    }
+
+    /// Number of files needing each library
+    fn dependants(&self) -> BTreeMap<&String, usize> {
+        let mut counts = BTreeMap::new();
+        for lib in self.needed.values().flatten() {
+            *counts.entry(lib).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Snapshot of a saved scan: an index saved with `--save-index` or `--format json` output.
+    /// Files are named by their paths relative to the scanned directory, so that scans of
+    /// different trees compare.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read(path)?;
+        if let Ok(index) = serde_json::from_slice::<Index>(&contents) {
+            let root = index.metadata.as_ref().map(|m| m.root.clone()).unwrap_or_default();
+            let files: Vec<ScannedFile> = index.files.iter()
+                .map(|f| ScannedFile { name: relative(&escape::path(&f.path), &root), ..f.clone() })
+                .collect();
+            return Ok(Snapshot::new(&files, &mut Resolver::from_libraries(index.libraries)));
+        }
+        let document: SavedDocument = serde_json::from_slice(&contents).map_err(io::Error::from)?;
+        let root = document.metadata.map(|m| m.root).unwrap_or_default();
+        let mut snapshot = Snapshot::default();
+        for exe in document.executables {
+            let needed = exe.needed.iter().map(|n| n.name.clone()).collect();
+            snapshot.missing.extend(exe.needed.into_iter().filter(|n| n.path.is_none()).map(|n| n.name));
+            snapshot.needed.insert(relative(&exe.path, &root), needed);
+        }
+        Ok(snapshot)
+    }
+}
+
+/// `path` relative to `root`, or as is outside of it
+pub fn relative(path: &str, root: &str) -> String {
+    match path.strip_prefix(root.trim_end_matches('/')) {
+        Some(rest) if rest.starts_with('/') => rest.trim_start_matches('/').to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// The parts of `--format json` output a snapshot is made of
+#[derive(Deserialize)]
+struct SavedDocument {
+    executables: Vec<SavedExecutable>,
+    metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct SavedExecutable {
+    path: String,
+    needed: Vec<SavedNeeded>,
+}
+
+#[derive(Deserialize)]
+struct SavedNeeded {
+    name: String,
+    path: Option<String>,
+}
+
+/// A file and one library it needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub file: String,
+    pub library: String,
+}
+
+/// Number of dependants of a library needed before and after
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CountChange {
+    pub library: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Changes {
+    pub added_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    /// Libraries nothing needed before
+    pub added_libraries: Vec<String>,
+    /// Libraries nothing needs anymore
+    pub removed_libraries: Vec<String>,
+    pub added_dependencies: Vec<Dependency>,
+    pub removed_dependencies: Vec<Dependency>,
+    /// Needed libraries that can no longer be found
+    pub newly_missing: Vec<String>,
+    /// Libraries that couldn't be found before but can now
+    pub resolved: Vec<String>,
+    /// Libraries needed before and after by a different number of files
+    pub dependant_counts: Vec<CountChange>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty() && self.removed_files.is_empty() && self.added_dependencies.is_empty()
+            && self.removed_dependencies.is_empty() && self.newly_missing.is_empty() && self.resolved.is_empty()
+            && self.dependant_counts.is_empty()
+    }
+}
+
+fn dependencies(snapshot: &Snapshot) -> BTreeSet<(&String, &String)> {
+    snapshot.needed.iter()
+        .flat_map(|(file, libs)| libs.iter().map(move |lib| (file, lib)))
+        .collect()
+}
+
+pub fn diff(old: &Snapshot, new: &Snapshot) -> Changes {
+    let names = |s: BTreeSet<&String>| -> Vec<String> { s.into_iter().cloned().collect() };
+    let deps = |s: BTreeSet<&(&String, &String)>| -> Vec<Dependency> {
+        s.into_iter().map(|(file, lib)| Dependency { file: file.to_string(), library: lib.to_string() }).collect()
+    };
+    let old_files: BTreeSet<&String> = old.needed.keys().collect();
+    let new_files: BTreeSet<&String> = new.needed.keys().collect();
+    let (old_libs, new_libs) = (old.libraries(), new.libraries());
+    let (old_deps, new_deps) = (dependencies(old), dependencies(new));
+    let new_dependants = new.dependants();
+    Changes {
+        added_files: names(new_files.difference(&old_files).copied().collect()),
+        removed_files: names(old_files.difference(&new_files).copied().collect()),
+        added_libraries: names(new_libs.difference(&old_libs).copied().collect()),
+        removed_libraries: names(old_libs.difference(&new_libs).copied().collect()),
+        added_dependencies: deps(new_deps.difference(&old_deps).collect()),
+        removed_dependencies: deps(old_deps.difference(&new_deps).collect()),
+        newly_missing: names(new.missing.difference(&old.missing).collect()),
+        resolved: names(old.missing.difference(&new.missing).filter(|l| new_libs.contains(l)).collect()),
+        dependant_counts: old.dependants().into_iter()
+            .filter_map(|(library, before)| {
+                let after = *new_dependants.get(library)?;
+                (after != before).then(|| CountChange { library: library.clone(), before, after })
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,97 @@
+//! What a scan would cover, without parsing everything: file counts, bytes to read and a
+//! duration estimated from parsing a small sample.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::archive;
+use crate::escape;
+use crate::fd_budget;
+use crate::scan::{self, Scan, ScanOptions};
+
+/// Files parsed to estimate the duration, spread over the whole listing
+const SAMPLE_SIZE: usize = 20;
+
+pub struct Estimate {
+    pub files: usize,
+    pub archives: usize,
+    pub bytes: u64,
+    pub sampled: usize,
+    /// Threads the scan would use
+    pub jobs: usize,
+    /// Time the whole scan would take at the sample's bytes per second on every thread or the
+    /// throttled rate, `None` if nothing could be sampled
+    pub duration: Option<Duration>,
+}
+
+/// Estimate for a scan of every directory and file in `inputs`
+pub fn estimate(inputs: &[PathBuf], options: &ScanOptions) -> Estimate {
+    let entries: Vec<(PathBuf, String, u64)> = inputs.iter()
+        .flat_map(|input| {
+            if input.is_dir() {
+                scan::entries(input, &options.walk)
+            }
+            else {
+                vec![(input.clone(), escape::path(input))]
+            }
+        })
+        .filter_map(|(path, name)| {
+            let metadata = fs::metadata(&path).ok();
+            // skipped by the scan, and reading them here could block
+            if metadata.as_ref().and_then(scan::special_kind).is_some() && !options.include_special {
+                return None;
+            }
+            Some((path, name, metadata.map_or(0, |m| m.len())))
+        })
+        .collect();
+    let archives = entries.iter().filter(|(path, _, _)| options.archives && archive::is_archive(path)).count();
+    let bytes: u64 = entries.iter().map(|(_, _, size)| size).sum();
+
+    let step = entries.len().div_ceil(SAMPLE_SIZE).max(1);
+    let (mut sampled, mut sampled_bytes) = (0, 0);
+    let mut scan = Scan::default();
+    let options = ScanOptions { fail_fast: false, checkpoint: None, ..options.clone() };
+    let started = Instant::now();
+    for (path, name, size) in entries.iter().step_by(step) {
+        if options.archives && archive::is_archive(path) {
+            scan::scan_archive(path.clone(), name, &options, &mut scan);
+        }
+        else {
+            let _ = scan::parse_file(path, &options);
+        }
+        sampled += 1;
+        sampled_bytes += size;
+    }
+    let elapsed = started.elapsed();
+    let duration = (sampled_bytes > 0)
+        .then(|| elapsed.mul_f64(bytes as f64 / sampled_bytes as f64) / options.threads() as u32)
+        .map(|duration| duration.max(options.throttle.minimum(entries.len() as u64, bytes)));
+    Estimate { files: entries.len() - archives, archives, bytes, sampled, jobs: options.threads(), duration }
+}
+
+/// Size with a binary unit, e.g. `12.3 MiB`
+pub fn human_bytes(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return if unit == "B" { format!("{} B", bytes) } else { format!("{:.1} {}", size, unit) };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} TiB", size)
+}
+
+pub fn print(estimate: &Estimate) {
+    println!("dry run");
+    println!("\tfiles: {}, archives: {}", estimate.files, estimate.archives);
+    println!("\tbytes: {} ({})", estimate.bytes, human_bytes(estimate.bytes));
+    println!("\topen files limit: {}", fd_budget::limit());
+    println!("\tjobs: {}", estimate.jobs);
+    match estimate.duration {
+        Some(duration) => println!(
+            "\testimated duration: {:.1}s (from {} sampled files)", duration.as_secs_f64(), estimate.sampled
+        ),
+        None => println!("\testimated duration: unknown"),
+    }
+    println!();
+}
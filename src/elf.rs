@@ -0,0 +1,986 @@
+use object::{Endian, ReadCache, ReadRef, SectionIndex, StringTable, Endianness};
+use std::collections::HashSet;
+use object::elf::{self, FileHeader32, FileHeader64, DT_NEEDED, DT_STRTAB, DT_STRSZ, PT_INTERP, SHT_DYNSYM};
+use object::read::elf::{FileHeader, ProgramHeader, SectionHeader, SectionTable, Dyn, Sym, Rel, Rela};
+use serde::{Deserialize, Serialize};
+use tracing::{warn, debug};
+use crate::escape;
+use crate::fd_budget;
+use crate::signatures::Embedded;
+
+#[derive(Debug)]
+pub enum HandleError {
+    IoError(std::io::Error),
+    ObjectReadError(object::read::Error),
+    NoDynamic,
+    /// Executable without a dynamic section
+    Static,
+    /// ELF file that isn't meant to be loaded, like a relocatable object or a core dump
+    Unsupported(&'static str),
+    NotElf,
+    Malformed(&'static str),
+    /// FIFO, socket, device node or other file that isn't read unless asked to
+    Special(&'static str),
+}
+
+impl HandleError {
+    /// Short name of the error's kind, used to group failures
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HandleError::IoError(e) if e.kind() == std::io::ErrorKind::PermissionDenied => "permission-denied",
+            HandleError::IoError(_) => "io",
+            HandleError::ObjectReadError(_) => "parse",
+            HandleError::NoDynamic => "no-dynamic",
+            HandleError::Static => "static",
+            HandleError::Unsupported(_) => "unsupported",
+            HandleError::NotElf => "not-elf",
+            HandleError::Malformed(_) => "malformed",
+            HandleError::Special(_) => "special",
+        }
+    }
+}
+
+impl std::fmt::Display for HandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandleError::IoError(e) => write!(f, "IO error: {}", e),
+            HandleError::ObjectReadError(e) => write!(f, "object read error: {}", e),
+            HandleError::NoDynamic => write!(f, "no dynamic section"),
+            HandleError::Static => write!(f, "statically linked"),
+            HandleError::Unsupported(kind) => write!(f, "unsupported ELF file: {}", kind),
+            HandleError::NotElf => write!(f, "not an ELF file"),
+            HandleError::Malformed(e) => write!(f, "malformed ELF: {}", e),
+            HandleError::Special(kind) => write!(f, "skipped {}", kind),
+        }
+    }
+}
+
+/// Container format of a scanned binary
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryFormat {
+    #[default]
+    Elf,
+    /// Windows executable or DLL
+    Pe,
+    MachO,
+    /// WebAssembly module
+    Wasm,
+}
+
+impl std::fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryFormat::Elf => write!(f, "elf"),
+            BinaryFormat::Pe => write!(f, "pe"),
+            BinaryFormat::MachO => write!(f, "macho"),
+            BinaryFormat::Wasm => write!(f, "wasm"),
+        }
+    }
+}
+
+/// Dynamic linking information of a single ELF file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ElfInfo {
+    /// Format the rest of the fields were read from
+    #[serde(default)]
+    pub format: BinaryFormat,
+    /// Architecture name derived from `e_machine`
+    pub arch: String,
+    /// ELF class, 32 or 64
+    pub bits: u8,
+    /// Byte order, from `EI_DATA` for ELF files
+    #[serde(default)]
+    pub big_endian: bool,
+    /// `EI_OSABI` of ELF files, 0 (System V) for other formats
+    #[serde(default)]
+    pub osabi: u8,
+    /// Program interpreter from `PT_INTERP`
+    pub interp: Option<String>,
+    /// File size in bytes
+    pub size: u64,
+    /// Permission bits of the file, including setuid and setgid, 0 where there are none (archive
+    /// members)
+    #[serde(default)]
+    pub mode: u32,
+    /// Position-independent executable: `ET_DYN` with an interpreter or `DF_1_PIE`
+    #[serde(default)]
+    pub pie: bool,
+    /// `DT_NEEDED` entries, in dynamic section order
+    pub needed: Vec<String>,
+    /// Name the file is identified by as a library, the install name for Mach-O dylibs
+    #[serde(default)]
+    pub soname: Option<String>,
+    /// Run-time library search paths: `DT_RPATH` entries for ELF files, `LC_RPATH` for Mach-O
+    #[serde(default)]
+    pub rpaths: Vec<String>,
+    /// `DT_RUNPATH` entries, searched after `LD_LIBRARY_PATH` unlike `DT_RPATH`
+    #[serde(default)]
+    pub runpaths: Vec<String>,
+    /// Undefined symbols of the dynamic symbol table
+    pub imports: Vec<String>,
+    /// Imports bound weakly, which the loader leaves null if no library defines them
+    #[serde(default)]
+    pub weak_imports: Vec<String>,
+    /// Global and weak symbols the dynamic symbol table defines
+    #[serde(default)]
+    pub exports: Vec<String>,
+    /// Symbol version requirements from `.gnu.version_r`
+    pub version_needs: Vec<VersionNeed>,
+    /// Shared object names and paths mentioned in read-only data, collected only for files
+    /// importing `dlopen`
+    pub dlopen_strings: Vec<String>,
+    /// Indirect functions resolved at load time
+    #[serde(default)]
+    pub ifuncs: Ifuncs,
+    /// Thread-local storage use
+    #[serde(default)]
+    pub tls: Tls,
+    /// x86-64 microarchitecture level needed (1 for the baseline, 2 to 4 for x86-64-v2 to v4),
+    /// from the ISA-needed property of `.note.gnu.property`
+    #[serde(default)]
+    pub isa_level: Option<u8>,
+    /// Libraries linked in statically, recognized by their signatures
+    #[serde(default)]
+    pub embedded: Vec<Embedded>,
+    #[serde(default)]
+    pub hardening: Hardening,
+    /// Hex digest of the `.note.gnu.build-id` note, the same for identical builds
+    #[serde(default)]
+    pub build_id: Option<String>,
+}
+
+/// Exploit mitigations the file was linked with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hardening {
+    /// `PT_GNU_RELRO` is present, so relocated data is made read-only after loading
+    pub relro: bool,
+    /// Symbols are bound at load time (`DT_BIND_NOW`, `DF_BIND_NOW` or `DF_1_NOW`), which with
+    /// RELRO leaves no writable GOT
+    pub bind_now: bool,
+    /// `PT_GNU_STACK` without the execute flag
+    pub nx: bool,
+    /// Relocations patch read-only segments (`DT_TEXTREL` or `DF_TEXTREL`)
+    pub textrel: bool,
+}
+
+/// Thread-local storage of a file and the models its accesses use
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tls {
+    /// Size of the `PT_TLS` segment in memory, 0 without one
+    pub size: u64,
+    /// `DF_STATIC_TLS` is set
+    pub static_tls: bool,
+    /// Relocations of the general and local dynamic models
+    pub dynamic_relocs: usize,
+    /// Relocations of the initial-exec model
+    pub initial_exec_relocs: usize,
+}
+
+impl Tls {
+    pub fn used(&self) -> bool {
+        self.size > 0 || self.dynamic_relocs > 0 || self.initial_exec_relocs > 0
+    }
+
+    /// Needs a slot in the static TLS block allocated at startup. Loaded with `dlopen`, a
+    /// library like that can fail with "cannot allocate memory in static TLS block"
+    pub fn initial_exec(&self) -> bool {
+        self.static_tls || self.initial_exec_relocs > 0
+    }
+}
+
+/// GNU indirect function usage. Their resolvers run during relocation, before the program
+/// (and in static binaries, before libc) is initialized.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ifuncs {
+    /// Defined `STT_GNU_IFUNC` symbols in `.dynsym` and `.symtab`
+    pub symbols: usize,
+    /// `R_*_IRELATIVE` relocations
+    pub irelative: usize,
+}
+
+impl Ifuncs {
+    pub fn used(&self) -> bool {
+        self.symbols > 0 || self.irelative > 0
+    }
+}
+
+/// Architecture and ELF class a library has to match to be loaded into a process
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Target {
+    pub arch: String,
+    pub bits: u8,
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}-bit)", self.arch, self.bits)
+    }
+}
+
+impl ElfInfo {
+    pub fn target(&self) -> Target {
+        Target { arch: self.arch.clone(), bits: self.bits }
+    }
+
+    /// Architecture, class, byte order and OS ABI, e.g. `x86_64, 64-bit, little-endian, sysv`
+    pub fn abi(&self) -> String {
+        let endian = if self.big_endian { "big-endian" } else { "little-endian" };
+        format!("{}, {}-bit, {}, {}", self.arch, self.bits, endian, osabi_name(self.osabi))
+    }
+
+    /// The `dlopen_strings` naming a library rather than a path that aren't needed already:
+    /// probable runtime dependencies, going by nothing but strings
+    pub fn dlopen_needed(&self) -> Vec<String> {
+        self.dlopen_strings.iter().filter(|s| !s.contains('/') && !self.needed.contains(s)).cloned().collect()
+    }
+
+    /// `needed`, followed by the probable `dlopen` dependencies with `dlopen`
+    pub fn needs(&self, dlopen: bool) -> Vec<String> {
+        let mut needs = self.needed.clone();
+        if dlopen {
+            needs.extend(self.dlopen_needed());
+        }
+        needs
+    }
+}
+
+/// Conventional name of an `EI_OSABI` value
+pub fn osabi_name(osabi: u8) -> String {
+    let name = match osabi {
+        elf::ELFOSABI_SYSV => "sysv",
+        elf::ELFOSABI_HPUX => "hpux",
+        elf::ELFOSABI_NETBSD => "netbsd",
+        elf::ELFOSABI_GNU => "gnu",
+        elf::ELFOSABI_SOLARIS => "solaris",
+        elf::ELFOSABI_FREEBSD => "freebsd",
+        elf::ELFOSABI_OPENBSD => "openbsd",
+        elf::ELFOSABI_ARM => "arm",
+        elf::ELFOSABI_STANDALONE => "standalone",
+        _ => return format!("osabi-{}", osabi),
+    };
+    name.to_string()
+}
+
+/// Versions required from one library, e.g. `libc.so.6` → `GLIBC_2.34`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct VersionNeed {
+    pub file: String,
+    pub versions: Vec<String>,
+}
+
+/// Conventional names of `e_machine` values
+const MACHINES: &[(u16, &str)] = &[
+    (elf::EM_386, "i386"),
+    (elf::EM_X86_64, "x86_64"),
+    (elf::EM_ARM, "arm"),
+    (elf::EM_AARCH64, "aarch64"),
+    (elf::EM_RISCV, "riscv"),
+    (elf::EM_PPC, "powerpc"),
+    (elf::EM_PPC64, "powerpc64"),
+    (elf::EM_MIPS, "mips"),
+    (elf::EM_S390, "s390"),
+    (elf::EM_SPARC, "sparc"),
+    (elf::EM_SPARCV9, "sparc64"),
+    (elf::EM_IA_64, "ia64"),
+];
+
+/// Conventional name of an `e_machine` value
+pub fn machine_name(machine: u16) -> String {
+    match MACHINES.iter().find(|(m, _)| *m == machine) {
+        Some((_, name)) => name.to_string(),
+        None => format!("machine-{}", machine),
+    }
+}
+
+/// `e_machine` value of an architecture name, the reverse of [`machine_name`]
+pub fn machine_number(name: &str) -> Option<u16> {
+    MACHINES.iter().find(|(_, n)| *n == name).map(|(m, _)| *m)
+}
+
+/// Names the dynamic section refers to
+struct DynamicNames {
+    needed: Vec<String>,
+    soname: Option<String>,
+    rpaths: Vec<String>,
+    runpaths: Vec<String>,
+}
+
+/// Dynamic entries, with the index of the string table section linked to them if any
+type Dynamic<'data, H> = (&'data [<H as FileHeader>::Dyn], Option<SectionIndex>);
+
+/// Entries of the dynamic section with the index of its linked string table, or those of the
+/// `PT_DYNAMIC` segment if the section headers have been stripped
+fn dynamic<'data, H, R>(
+    bin_data: R, endian: Endianness, header: &H, sections: &SectionTable<'data, H, R>,
+) -> Result<Option<Dynamic<'data, H>>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+    R: ReadRef<'data>,
+{
+    if let Some((dynamic, link)) = sections.dynamic(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+        return Ok(Some((dynamic, Some(link))));
+    }
+    for segment in header.program_headers(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+        if let Some(dynamic) = segment.dynamic(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+            debug!("No dynamic section, reading the PT_DYNAMIC segment");
+            return Ok(Some((dynamic, None)));
+        }
+    }
+    Ok(None)
+}
+
+/// Section headers of the file, none if the table is missing or lies past the end of the file,
+/// as stripping tools leave it, so that only the program headers are read
+fn sections<'data, H, R>(bin_data: R, endian: Endianness, header: &H) -> SectionTable<'data, H, R>
+where
+    H: FileHeader<Endian = Endianness>,
+    R: ReadRef<'data>,
+{
+    header.sections(endian, bin_data).unwrap_or_else(|e| {
+        debug!("Ignoring the section headers: {}", e);
+        SectionTable::new(&[], StringTable::default())
+    })
+}
+
+/// File offset of the virtual `address`, mapped through the `PT_LOAD` segments
+fn file_offset<'data, H, R>(bin_data: R, endian: Endianness, header: &H, address: u64) -> Option<u64>
+where
+    H: FileHeader<Endian = Endianness>,
+    R: ReadRef<'data>,
+{
+    header.program_headers(endian, bin_data).ok()?.iter()
+        .filter(|segment| segment.p_type(endian) == elf::PT_LOAD)
+        .find_map(|segment| {
+            let start: u64 = segment.p_vaddr(endian).into();
+            let offset = address.checked_sub(start).filter(|&o| o < segment.p_filesz(endian).into())?;
+            Some(segment.p_offset(endian).into() + offset)
+        })
+}
+
+/// `DT_NEEDED` entries, `DT_SONAME` and the search paths
+fn extract_libs<'data, H, R>(bin_data: R, endian: Endianness, header: &H, sections: &SectionTable<'data, H, R>) -> Result<DynamicNames, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+    R: ReadRef<'data>,
+{
+    let dyn_sec = dynamic(bin_data, endian, header, sections)?
+        .ok_or(HandleError::NoDynamic)?;
+    let mut libs_offs: Vec<u64> = vec![];
+    let mut soname_offs: Option<u64> = None;
+    let mut rpath_offs: Vec<u64> = vec![];
+    let mut runpath_offs: Vec<u64> = vec![];
+    let mut dt_strtab: u64 = 0;
+    let mut dt_strsz: u64 = 0;
+    for dyn_element in dyn_sec.0 {
+        let tag32 = dyn_element.tag32(endian);
+        match tag32 {
+            Some(DT_NEEDED) => {
+                let offs = dyn_element.d_val(endian).into();
+                debug!("Found required dyn library at offset {}", offs);
+                libs_offs.push(offs);
+            },
+            Some(elf::DT_SONAME) => {
+                soname_offs = Some(dyn_element.d_val(endian).into());
+            },
+            Some(elf::DT_RPATH) => rpath_offs.push(dyn_element.d_val(endian).into()),
+            Some(elf::DT_RUNPATH) => runpath_offs.push(dyn_element.d_val(endian).into()),
+            Some(DT_STRTAB) => {
+                dt_strtab = dyn_element.d_val(endian).into();
+            },
+            Some(DT_STRSZ) => {
+                dt_strsz = dyn_element.d_val(endian).into();
+            }
+            Some(_) => {},
+            None => warn!("Dynamic element's tag {} does not fit into u32", dyn_element.d_tag(endian).into()),
+        }
+    }
+    let libs_offs = libs_offs.iter()
+        .map(|n| u32::try_from(*n).ok());
+    // DT_STRTAB is a virtual address, the linked section gives the actual string table
+    let str_table = match dyn_sec.1.and_then(|link| sections.strings(endian, bin_data, link).ok()) {
+        Some(str_table) => str_table,
+        None => {
+            let offset = file_offset(bin_data, endian, header, dt_strtab).unwrap_or(dt_strtab);
+            StringTable::new(bin_data, offset, offset + dt_strsz)
+        },
+    };
+    let mut libs: Vec<String> = vec![];
+    for offs in libs_offs {
+        let offs = if let Some(offs) = offs {
+            offs
+        }
+        else {
+            warn!("Couldn't convert offset to u32");
+            continue;
+        };
+        let name = str_table.get(offs)
+            .map(escape::bytes);
+        if let Ok(name) = name {
+            libs.push(name);
+        }
+        else {
+            warn!("Couldn't get lib name by offset {}, strtab {}", offs, dt_strtab);
+            continue;
+        }
+    }
+    let string = |offs: u64| u32::try_from(offs).ok()
+        .and_then(|offs| str_table.get(offs).ok())
+        .map(escape::bytes);
+    // both are colon-separated lists
+    let paths = |offs: &[u64]| offs.iter()
+        .filter_map(|&o| string(o))
+        .flat_map(|list| list.split(':').filter(|p| !p.is_empty()).map(str::to_string).collect::<Vec<_>>())
+        .collect();
+    Ok(DynamicNames {
+        needed: libs,
+        soname: soname_offs.and_then(string),
+        rpaths: paths(&rpath_offs),
+        runpaths: paths(&runpath_offs),
+    })
+}
+
+/// Undefined dynamic symbols, and those of them that are weak
+fn extract_imports<H>(bin_data: &[u8], endian: Endianness, sections: &SectionTable<H>) -> Result<(Vec<String>, Vec<String>), HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let symbols = sections.symbols(endian, bin_data, SHT_DYNSYM)
+        .map_err(HandleError::ObjectReadError)?;
+    let mut imports = vec![];
+    let mut weak = vec![];
+    for symbol in symbols.iter() {
+        if !symbol.is_undefined(endian) {
+            continue;
+        }
+        match symbol.name(endian, symbols.strings()) {
+            Ok(name) if !name.is_empty() => {
+                if symbol.st_bind() == elf::STB_WEAK {
+                    weak.push(escape::bytes(name));
+                }
+                imports.push(escape::bytes(name));
+            },
+            Ok(_) => {},
+            Err(e) => warn!("Couldn't get dynamic symbol name: {}", e),
+        }
+    }
+    Ok((imports, weak))
+}
+
+fn extract_exports<H>(bin_data: &[u8], endian: Endianness, sections: &SectionTable<H>) -> Result<Vec<String>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let symbols = sections.symbols(endian, bin_data, SHT_DYNSYM)
+        .map_err(HandleError::ObjectReadError)?;
+    let mut exports = vec![];
+    for symbol in symbols.iter() {
+        let global = matches!(symbol.st_bind(), elf::STB_GLOBAL | elf::STB_WEAK | elf::STB_GNU_UNIQUE);
+        let hidden = matches!(symbol.st_visibility(), elf::STV_HIDDEN | elf::STV_INTERNAL);
+        if symbol.is_undefined(endian) || !global || hidden {
+            continue;
+        }
+        match symbol.name(endian, symbols.strings()) {
+            Ok(name) if !name.is_empty() => exports.push(escape::bytes(name)),
+            Ok(_) => {},
+            Err(e) => warn!("Couldn't get dynamic symbol name: {}", e),
+        }
+    }
+    exports.sort();
+    exports.dedup();
+    Ok(exports)
+}
+
+fn extract_version_needs<H>(bin_data: &[u8], endian: Endianness, sections: &SectionTable<H>) -> Result<Vec<VersionNeed>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let (mut verneeds, link) = match sections.gnu_verneed(endian, bin_data)
+        .map_err(HandleError::ObjectReadError)? {
+        Some(v) => v,
+        None => return Ok(vec![]),
+    };
+    let strings = sections.strings(endian, bin_data, link)
+        .map_err(HandleError::ObjectReadError)?;
+    let mut needs = vec![];
+    while let Some((verneed, mut vernauxs)) = verneeds.next()
+        .map_err(HandleError::ObjectReadError)? {
+        let file = verneed.file(endian, strings)
+            .map_err(HandleError::ObjectReadError)?;
+        let mut need = VersionNeed {
+            file: escape::bytes(file),
+            versions: vec![],
+        };
+        while let Some(vernaux) = vernauxs.next()
+            .map_err(HandleError::ObjectReadError)? {
+            let name = vernaux.name(endian, strings)
+                .map_err(HandleError::ObjectReadError)?;
+            need.versions.push(escape::bytes(name));
+        }
+        needs.push(need);
+    }
+    Ok(needs)
+}
+
+/// Whether a string looks like the file name of a shared object: `libfoo.so`, `libfoo.so.1.2`,
+/// `pam_unix.so`, but not a suffix, a format string or a message
+fn is_shared_object_name(name: &str) -> bool {
+    let Some((stem, version)) = name.split_once(".so") else { return false };
+    stem.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && stem.chars().all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+        && version.split('.').skip(1).all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        && (version.is_empty() || version.starts_with('.'))
+}
+
+/// Finds NUL-terminated strings in `.rodata` that look like shared object names
+fn extract_so_strings<H>(bin_data: &[u8], endian: Endianness, sections: &SectionTable<H>) -> Result<Vec<String>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let mut names = vec![];
+    // `.rodata`, and `.rodata.str1.1` and the like of binaries linked without merging them
+    let rodata = sections.iter()
+        .filter(|s| sections.section_name(endian, s).is_ok_and(|n| n == b".rodata" || n.starts_with(b".rodata.")));
+    for section in rodata {
+        let data = section.data(endian, bin_data).map_err(HandleError::ObjectReadError)?;
+        for s in data.split(|b| *b == 0) {
+            let s = match std::str::from_utf8(s) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            if is_shared_object_name(s.rsplit('/').next().unwrap_or(s)) && !names.iter().any(|n| n == s) {
+                names.push(s.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Relocation type applying an indirect function's resolver on `machine`
+fn irelative_type(machine: u16) -> Option<u32> {
+    match machine {
+        elf::EM_386 => Some(elf::R_386_IRELATIVE),
+        elf::EM_X86_64 => Some(elf::R_X86_64_IRELATIVE),
+        elf::EM_ARM => Some(elf::R_ARM_IRELATIVE),
+        elf::EM_AARCH64 => Some(elf::R_AARCH64_IRELATIVE),
+        elf::EM_PPC => Some(elf::R_PPC_IRELATIVE),
+        elf::EM_PPC64 => Some(elf::R_PPC64_IRELATIVE),
+        elf::EM_S390 => Some(elf::R_390_IRELATIVE),
+        elf::EM_SPARC | elf::EM_SPARCV9 => Some(elf::R_SPARC_IRELATIVE),
+        // R_RISCV_IRELATIVE, missing from `object`
+        elf::EM_RISCV => Some(58),
+        _ => None,
+    }
+}
+
+/// Types of the relocations in every `SHT_REL` and `SHT_RELA` section
+fn relocation_types<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>) -> Result<Vec<u32>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let is_mips64el = header.is_mips64el(endian);
+    let mut types = vec![];
+    for section in sections.iter() {
+        if let Some(rels) = section.rel(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+            types.extend(rels.0.iter().map(|r| r.r_type(endian)));
+        }
+        if let Some(relas) = section.rela(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+            types.extend(relas.0.iter().map(|r| r.r_type(endian, is_mips64el)));
+        }
+    }
+    Ok(types)
+}
+
+fn extract_ifuncs<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>, relocations: &[u32]) -> Result<Ifuncs, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let mut names = HashSet::new();
+    for kind in [SHT_DYNSYM, elf::SHT_SYMTAB] {
+        let symbols = sections.symbols(endian, bin_data, kind)
+            .map_err(HandleError::ObjectReadError)?;
+        for symbol in symbols.iter() {
+            if symbol.st_type() == elf::STT_GNU_IFUNC && !symbol.is_undefined(endian) {
+                names.insert(symbol.name(endian, symbols.strings()).unwrap_or_default());
+            }
+        }
+    }
+    let irelative = match irelative_type(header.e_machine(endian)) {
+        Some(irelative) => relocations.iter().filter(|&&r| r == irelative).count(),
+        None => 0,
+    };
+    Ok(Ifuncs { symbols: names.len(), irelative })
+}
+
+/// Relocation types of the dynamic (general and local dynamic) and the initial-exec TLS models
+/// on `machine`
+fn tls_relocation_types(machine: u16) -> (&'static [u32], &'static [u32]) {
+    match machine {
+        elf::EM_386 => (
+            &[elf::R_386_TLS_DTPMOD32, elf::R_386_TLS_DTPOFF32],
+            &[elf::R_386_TLS_TPOFF, elf::R_386_TLS_TPOFF32],
+        ),
+        elf::EM_X86_64 => (&[elf::R_X86_64_DTPMOD64, elf::R_X86_64_DTPOFF64], &[elf::R_X86_64_TPOFF64]),
+        elf::EM_ARM => (&[elf::R_ARM_TLS_DTPMOD32, elf::R_ARM_TLS_DTPOFF32], &[elf::R_ARM_TLS_TPOFF32]),
+        elf::EM_AARCH64 => (&[elf::R_AARCH64_TLS_DTPMOD, elf::R_AARCH64_TLS_DTPREL], &[elf::R_AARCH64_TLS_TPREL]),
+        _ => (&[], &[]),
+    }
+}
+
+fn extract_tls<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>, relocations: &[u32]) -> Result<Tls, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let segments = header.program_headers(endian, bin_data)
+        .map_err(HandleError::ObjectReadError)?;
+    let size = segments.iter()
+        .find(|s| s.p_type(endian) == elf::PT_TLS)
+        .map_or(0, |s| s.p_memsz(endian).into());
+    let mut static_tls = false;
+    if let Some((dynamic, _)) = dynamic(bin_data, endian, header, sections)? {
+        for element in dynamic {
+            if element.tag32(endian) == Some(elf::DT_FLAGS) {
+                static_tls |= element.d_val(endian).into() & u64::from(elf::DF_STATIC_TLS) != 0;
+            }
+        }
+    }
+    let (dynamic, initial_exec) = tls_relocation_types(header.e_machine(endian));
+    Ok(Tls {
+        size,
+        static_tls,
+        dynamic_relocs: relocations.iter().filter(|r| dynamic.contains(r)).count(),
+        initial_exec_relocs: relocations.iter().filter(|r| initial_exec.contains(r)).count(),
+    })
+}
+
+/// Whether the file is a position-independent executable rather than a library or a
+/// fixed-address executable
+fn extract_pie<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>, interp: bool) -> Result<bool, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    if header.e_type(endian) != elf::ET_DYN {
+        return Ok(false);
+    }
+    if interp {
+        return Ok(true);
+    }
+    // static PIEs have no interpreter
+    if let Some((dynamic, _)) = dynamic(bin_data, endian, header, sections)? {
+        for element in dynamic {
+            if element.tag32(endian) == Some(elf::DT_FLAGS_1) && element.d_val(endian).into() & u64::from(elf::DF_1_PIE) != 0 {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn extract_hardening<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>) -> Result<Hardening, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let mut hardening = Hardening::default();
+    for segment in header.program_headers(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+        match segment.p_type(endian) {
+            elf::PT_GNU_RELRO => hardening.relro = true,
+            elf::PT_GNU_STACK => hardening.nx = segment.p_flags(endian) & elf::PF_X == 0,
+            _ => {},
+        }
+    }
+    if let Some((dynamic, _)) = dynamic(bin_data, endian, header, sections)? {
+        for element in dynamic {
+            let value: u64 = element.d_val(endian).into();
+            match element.tag32(endian) {
+                Some(elf::DT_BIND_NOW) => hardening.bind_now = true,
+                Some(elf::DT_TEXTREL) => hardening.textrel = true,
+                Some(elf::DT_FLAGS) => {
+                    hardening.bind_now |= value & u64::from(elf::DF_BIND_NOW) != 0;
+                    hardening.textrel |= value & u64::from(elf::DF_TEXTREL) != 0;
+                },
+                Some(elf::DT_FLAGS_1) => hardening.bind_now |= value & u64::from(elf::DF_1_NOW) != 0,
+                _ => {},
+            }
+        }
+    }
+    Ok(hardening)
+}
+
+/// Digest of the GNU build-id note, in hex
+fn extract_build_id<H>(bin_data: &[u8], endian: Endianness, sections: &SectionTable<H>) -> Result<Option<String>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    for section in sections.iter() {
+        let mut notes = match section.notes(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+            Some(notes) => notes,
+            None => continue,
+        };
+        while let Some(note) = notes.next().map_err(HandleError::ObjectReadError)? {
+            if note.name() == b"GNU" && note.n_type(endian) == elf::NT_GNU_BUILD_ID {
+                return Ok(Some(note.desc().iter().map(|b| format!("{:02x}", b)).collect()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// `GNU_PROPERTY_X86_ISA_1_NEEDED`, bit `n` meaning x86-64-v`n + 1`
+const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc000_8002;
+
+/// Highest x86-64 level set in the ISA-needed property, `None` without the property
+fn extract_isa_level<H>(bin_data: &[u8], endian: Endianness, header: &H, sections: &SectionTable<H>) -> Result<Option<u8>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let align = if header.is_class_64() { 8 } else { 4 };
+    let read_u32 = |data: &[u8]| data.get(..4).map(|b| endian.read_u32_bytes(b.try_into().unwrap()));
+    for section in sections.iter() {
+        let mut notes = match section.notes(endian, bin_data).map_err(HandleError::ObjectReadError)? {
+            Some(notes) => notes,
+            None => continue,
+        };
+        while let Some(note) = notes.next().map_err(HandleError::ObjectReadError)? {
+            if note.name() != b"GNU" || note.n_type(endian) != elf::NT_GNU_PROPERTY_TYPE_0 {
+                continue;
+            }
+            let mut desc = note.desc();
+            while let (Some(kind), Some(size)) = (read_u32(desc), desc.get(4..).and_then(read_u32)) {
+                let data = desc.get(8..8 + size as usize)
+                    .ok_or(HandleError::Malformed("Truncated GNU property"))?;
+                if kind == GNU_PROPERTY_X86_ISA_1_NEEDED {
+                    let bits = read_u32(data).unwrap_or_default();
+                    return Ok(Some(((u32::BITS - bits.leading_zeros()) as u8).max(1)));
+                }
+                desc = desc.get(8 + (size as usize).div_ceil(align) * align..).unwrap_or_default();
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn extract_interp<H>(bin_data: &[u8], endian: Endianness, header: &H) -> Result<Option<String>, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let segments = header.program_headers(endian, bin_data)
+        .map_err(HandleError::ObjectReadError)?;
+    for segment in segments {
+        if segment.p_type(endian) != PT_INTERP {
+            continue;
+        }
+        let data = segment.data(endian, bin_data)
+            .map_err(|_| HandleError::Malformed("Invalid PT_INTERP segment"))?;
+        let data = data.split(|b| *b == 0).next().unwrap_or(data);
+        return Ok(Some(escape::bytes(data)));
+    }
+    Ok(None)
+}
+
+/// What a missing dynamic section means for a file of type `e_type`
+fn without_dynamic(e: HandleError, e_type: u16) -> HandleError {
+    match (e, e_type) {
+        (HandleError::NoDynamic, elf::ET_EXEC) => HandleError::Static,
+        (HandleError::NoDynamic, elf::ET_REL) => HandleError::Unsupported("relocatable object"),
+        (HandleError::NoDynamic, elf::ET_CORE) => HandleError::Unsupported("core dump"),
+        (e, _) => e,
+    }
+}
+
+fn extract_info<H>(bin_data: &[u8], endian: Endianness, header: &H) -> Result<ElfInfo, HandleError>
+where
+    H: FileHeader<Endian = Endianness>,
+{
+    let sections = sections(bin_data, endian, header);
+    let DynamicNames { needed, soname, rpaths, runpaths } = extract_libs(bin_data, endian, header, &sections)
+        .map_err(|e| without_dynamic(e, header.e_type(endian)))?;
+    let (imports, weak_imports) = extract_imports(bin_data, endian, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read dynamic symbols: {:?}", e);
+            (vec![], vec![])
+        });
+    let exports = extract_exports(bin_data, endian, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read dynamic symbols: {:?}", e);
+            vec![]
+        });
+    let version_needs = extract_version_needs(bin_data, endian, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read version requirements: {:?}", e);
+            vec![]
+        });
+    let dlopen_strings = if imports.iter().any(|s| s == "dlopen" || s == "dlmopen") {
+        extract_so_strings(bin_data, endian, &sections)
+            .unwrap_or_else(|e| {
+                warn!("Couldn't read .rodata: {:?}", e);
+                vec![]
+            })
+    }
+    else {
+        vec![]
+    };
+    let interp = extract_interp(bin_data, endian, header)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read program interpreter: {:?}", e);
+            None
+        });
+    let relocations = relocation_types(bin_data, endian, header, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read relocations: {:?}", e);
+            vec![]
+        });
+    let ifuncs = extract_ifuncs(bin_data, endian, header, &sections, &relocations)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read indirect functions: {:?}", e);
+            Ifuncs::default()
+        });
+    let isa_level = if header.e_machine(endian) == elf::EM_X86_64 {
+        extract_isa_level(bin_data, endian, header, &sections)
+            .unwrap_or_else(|e| {
+                warn!("Couldn't read GNU properties: {:?}", e);
+                None
+            })
+    }
+    else {
+        None
+    };
+    let pie = extract_pie(bin_data, endian, header, &sections, interp.is_some())
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read dynamic flags: {:?}", e);
+            false
+        });
+    let hardening = extract_hardening(bin_data, endian, header, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read hardening flags: {:?}", e);
+            Hardening::default()
+        });
+    let build_id = extract_build_id(bin_data, endian, &sections)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read build-id: {:?}", e);
+            None
+        });
+    let tls = extract_tls(bin_data, endian, header, &sections, &relocations)
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read thread-local storage: {:?}", e);
+            Tls::default()
+        });
+    Ok(ElfInfo {
+        format: BinaryFormat::Elf,
+        arch: machine_name(header.e_machine(endian)),
+        bits: if header.is_class_64() { 64 } else { 32 },
+        big_endian: endian.is_big_endian(),
+        osabi: header.e_ident().os_abi,
+        interp,
+        size: bin_data.len() as u64,
+        pie,
+        needed,
+        soname,
+        rpaths,
+        runpaths,
+        imports,
+        weak_imports,
+        exports,
+        version_needs,
+        dlopen_strings,
+        ifuncs,
+        tls,
+        isa_level,
+        hardening,
+        build_id,
+        ..ElfInfo::default()
+    })
+}
+
+/// `DT_NEEDED` entries of the ELF file at `path`, reading only its headers, dynamic section and
+/// dynamic string table rather than the whole file
+pub fn read_needed(path: &std::path::Path) -> Result<Vec<String>, HandleError> {
+    let _permit = fd_budget::acquire();
+    let file = std::fs::File::open(path).map_err(HandleError::IoError)?;
+    let cache = ReadCache::new(file);
+    let data = &cache;
+    let names = match object::FileKind::parse(data).map_err(|_| HandleError::NotElf)? {
+        object::FileKind::Elf32 => {
+            let elf_header = FileHeader32::<object::Endianness>::parse(data)
+                .map_err(HandleError::ObjectReadError)?;
+            let endian = elf_header.endian().map_err(HandleError::ObjectReadError)?;
+            let sections = sections(data, endian, elf_header);
+            extract_libs(data, endian, elf_header, &sections).map_err(|e| without_dynamic(e, elf_header.e_type(endian)))?
+        },
+        object::FileKind::Elf64 => {
+            let elf_header = FileHeader64::<object::Endianness>::parse(data)
+                .map_err(HandleError::ObjectReadError)?;
+            let endian = elf_header.endian().map_err(HandleError::ObjectReadError)?;
+            let sections = sections(data, endian, elf_header);
+            extract_libs(data, endian, elf_header, &sections).map_err(|e| without_dynamic(e, elf_header.e_type(endian)))?
+        },
+        _ => return Err(HandleError::NotElf),
+    };
+    Ok(names.needed)
+}
+
+pub fn parse_elf<P>(path: P) -> Result<ElfInfo, HandleError>
+where
+    P: AsRef<std::path::Path>
+{
+    let bin_data = fd_budget::read(path.as_ref())
+        .map_err(HandleError::IoError)?;
+    parse_elf_data(&bin_data)
+}
+
+/// Parses an ELF file already read into memory
+pub fn parse_elf_data(bin_data: &[u8]) -> Result<ElfInfo, HandleError> {
+    let kind = object::FileKind::parse(bin_data).map_err(|e| {
+        // anything without the ELF magic is most likely a script or data file
+        if bin_data.starts_with(&elf::ELFMAG) { HandleError::ObjectReadError(e) } else { HandleError::NotElf }
+    })?;
+
+    match kind {
+        object::FileKind::Elf32 => {
+            debug!("Parsing elf32 file");
+            let elf_header = FileHeader32::<object::Endianness>::parse(bin_data)
+                .map_err(HandleError::ObjectReadError)?;
+            let endian = elf_header.endian().map_err(HandleError::ObjectReadError)?;
+            extract_info(bin_data, endian, elf_header)
+        },
+        object::FileKind::Elf64 => {
+            debug!("Parsing elf64 file");
+            let elf_header = FileHeader64::<object::Endianness>::parse(bin_data)
+                .map_err(HandleError::ObjectReadError)?;
+            let endian = elf_header.endian().map_err(HandleError::ObjectReadError)?;
+            extract_info(bin_data, endian, elf_header)
+        },
+        _ => Err(HandleError::NotElf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{build, Fixture};
+
+    #[test]
+    fn reads_files_without_section_headers() {
+        let variant = |bits: u8, f: &dyn Fn(&mut Fixture)| {
+            let mut fixture = Fixture::new("app", &["libz.so.1", "libc.so.6"]);
+            fixture.bits = bits;
+            f(&mut fixture);
+            build(&fixture)
+        };
+        for bits in [32, 64] {
+            for data in [
+                variant(bits, &|_| {}),
+                variant(bits, &|f| f.section_headers = false),
+                variant(bits, &|f| f.cut_section_headers = true),
+            ] {
+                let info = parse_elf_data(&data).unwrap();
+                assert_eq!((info.needed.as_slice(), info.bits), (&["libz.so.1".to_string(), "libc.so.6".to_string()][..], bits));
+
+                let path = std::env::temp_dir().join(format!("linux-hs-elf-{}-stripped-{}", std::process::id(), bits));
+                std::fs::write(&path, &data).unwrap();
+                let needed = read_needed(&path);
+                std::fs::remove_file(&path).unwrap();
+                assert_eq!(needed.unwrap(), ["libz.so.1", "libc.so.6"]);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Lossless text form of file names and paths that need not be UTF-8. Bytes that aren't valid
+//! UTF-8 are percent-encoded, as is `%` itself, so the original can always be recovered.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Escapes arbitrary bytes, keeping valid UTF-8 as is
+pub fn bytes(mut data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len());
+    loop {
+        let (valid, rest) = match std::str::from_utf8(data) {
+            Ok(s) => (s, &[][..]),
+            Err(e) => {
+                let (valid, rest) = data.split_at(e.valid_up_to());
+                (std::str::from_utf8(valid).unwrap_or_default(), rest)
+            },
+        };
+        result.push_str(&valid.replace('%', "%25"));
+        match rest.split_first() {
+            Some((b, rest)) => {
+                result.push_str(&format!("%{:02X}", b));
+                data = rest;
+            },
+            None => return result,
+        }
+    }
+}
+
+/// Reverses [`bytes`]
+pub fn unescape_bytes(s: &str) -> Vec<u8> {
+    let data = s.as_bytes();
+    let mut result = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let hex = data.get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (data[i], hex) {
+            (b'%', Some(b)) => {
+                result.push(b);
+                i += 3;
+            },
+            (b, _) => {
+                result.push(b);
+                i += 1;
+            },
+        }
+    }
+    result
+}
+
+#[cfg(unix)]
+pub fn os_str(s: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    bytes(s.as_bytes())
+}
+
+/// Names that aren't valid Unicode can't be escaped losslessly outside Unix
+#[cfg(not(unix))]
+pub fn os_str(s: &OsStr) -> String {
+    bytes(s.to_string_lossy().as_bytes())
+}
+
+pub fn unescape_os_str(s: &str) -> OsString {
+    os_string(unescape_bytes(s))
+}
+
+/// Name made of the raw `bytes` a Unix system stores
+#[cfg(unix)]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn path(p: &Path) -> String {
+    os_str(p.as_os_str())
+}
+
+/// Serde adapter storing a `PathBuf` in its escaped form
+pub mod serde_path {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(p: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&path(p))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(PathBuf::from(unescape_os_str(&s)))
+    }
+}
@@ -0,0 +1,564 @@
+//! Read-only ext2/3/4 reader working on raw images, so disk images can be scanned without
+//! mounting them.
+//!
+//! Supports extent-mapped and block-mapped files, hashed directories (read linearly), fast
+//! symlinks and the part of inline data kept in the inode. Whole-disk images are searched for the
+//! first ext partition in their MBR or GPT partition table.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::debug;
+use crate::fd_budget;
+
+const SUPERBLOCK_OFFSET: u64 = 1024;
+const MAGIC: u16 = 0xef53;
+const ROOT_INODE: u32 = 2;
+const MAX_SYMLINKS: usize = 40;
+/// Largest `s_log_block_size`, for 64 KiB blocks
+const MAX_LOG_BLOCK_SIZE: u32 = 6;
+/// Most GPT partition entries read
+const MAX_GPT_ENTRIES: u32 = 1024;
+
+const INCOMPAT_META_BG: u32 = 0x10;
+const INCOMPAT_64BIT: u32 = 0x80;
+
+const EXTENTS_FL: u32 = 0x8_0000;
+const INLINE_DATA_FL: u32 = 0x1000_0000;
+const EXTENT_MAGIC: u16 = 0xf30a;
+
+const S_IFMT: u16 = 0o170000;
+const S_IFDIR: u16 = 0o040000;
+const S_IFREG: u16 = 0o100000;
+const S_IFLNK: u16 = 0o120000;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Inode {
+    mode: u16,
+    pub size: u64,
+    flags: u32,
+    /// `i_block`: the block map, extent tree root, fast symlink target or inline data
+    block: [u8; 60],
+}
+
+impl Inode {
+    pub fn file_type(&self) -> FileType {
+        match self.mode & S_IFMT {
+            S_IFREG => FileType::Regular,
+            S_IFDIR => FileType::Directory,
+            S_IFLNK => FileType::Symlink,
+            _ => FileType::Other,
+        }
+    }
+
+    /// Permission bits, including setuid and setgid
+    pub fn permissions(&self) -> u32 {
+        u32::from(self.mode & 0o7777)
+    }
+}
+
+/// Directory entry
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: Vec<u8>,
+    pub inode: u32,
+}
+
+/// ext filesystem in an image file
+pub struct Ext4 {
+    file: RefCell<File>,
+    _permit: fd_budget::Permit,
+    /// Offset of the filesystem in the image
+    start: u64,
+    /// Bytes of the image from `start` on, which no file can be larger than
+    len: u64,
+    block_size: u64,
+    inodes_count: u32,
+    inodes_per_group: u32,
+    inode_size: u64,
+    desc_size: u64,
+    first_data_block: u64,
+    wide: bool,
+}
+
+/// Whether an ext superblock is at `start`
+fn has_superblock(file: &mut File, start: u64) -> bool {
+    let mut magic = [0u8; 2];
+    file.seek(SeekFrom::Start(start + SUPERBLOCK_OFFSET + 0x38)).is_ok()
+        && file.read_exact(&mut magic).is_ok()
+        && u16::from_le_bytes(magic) == MAGIC
+}
+
+/// Byte offsets of the partitions in an MBR or GPT partition table
+fn partitions(file: &mut File) -> io::Result<Vec<u64>> {
+    let mut mbr = [0u8; 512];
+    file.seek(SeekFrom::Start(0))?;
+    if file.read_exact(&mut mbr).is_err() || mbr[510..] != [0x55, 0xaa] {
+        return Ok(vec![]);
+    }
+    let entries: Vec<(u8, u64)> = (0..4)
+        .map(|i| &mbr[446 + i * 16..446 + (i + 1) * 16])
+        .map(|e| (e[4], u64::from(u32_at(e, 8)) * 512))
+        .filter(|(kind, _)| *kind != 0)
+        .collect();
+    if !entries.iter().any(|(kind, _)| *kind == 0xee) {
+        return Ok(entries.into_iter().map(|(_, start)| start).collect());
+    }
+    let mut header = [0u8; 92];
+    file.seek(SeekFrom::Start(512))?;
+    file.read_exact(&mut header)?;
+    if &header[..8] != b"EFI PART" {
+        return Err(invalid("Invalid GPT header"));
+    }
+    let (table, count, entry_size) = (u64_at(&header, 72), u32_at(&header, 80), u32_at(&header, 84) as usize);
+    if !(48..=4096).contains(&entry_size) || count > MAX_GPT_ENTRIES {
+        return Err(invalid("Invalid GPT entry size or count"));
+    }
+    let mut entries = vec![0u8; count as usize * entry_size];
+    file.seek(SeekFrom::Start(table * 512))?;
+    file.read_exact(&mut entries)?;
+    Ok(entries.chunks(entry_size)
+        .filter(|e| e[..16].iter().any(|&b| b != 0))
+        .map(|e| u64_at(e, 32) * 512)
+        .collect())
+}
+
+impl Ext4 {
+    /// Opens the filesystem in a partition image, or the first one in a whole-disk image
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let permit = fd_budget::acquire();
+        let mut file = File::open(path)?;
+        let start = if has_superblock(&mut file, 0) {
+            0
+        }
+        else {
+            let partitions = partitions(&mut file)?;
+            partitions.into_iter()
+                .find(|&start| has_superblock(&mut file, start))
+                .ok_or_else(|| invalid("No ext2/3/4 filesystem found"))?
+        };
+        debug!("Filesystem at offset {}", start);
+        let mut sb = [0u8; 1024];
+        file.seek(SeekFrom::Start(start + SUPERBLOCK_OFFSET))?;
+        file.read_exact(&mut sb)?;
+        let incompat = u32_at(&sb, 0x60);
+        if incompat & INCOMPAT_META_BG != 0 {
+            return Err(invalid("meta_bg filesystems aren't supported"));
+        }
+        let wide = incompat & INCOMPAT_64BIT != 0;
+        let log_block_size = u32_at(&sb, 0x18);
+        if log_block_size > MAX_LOG_BLOCK_SIZE {
+            return Err(invalid("Invalid block size"));
+        }
+        let len = file.metadata()?.len().saturating_sub(start);
+        let inode_size = if u32_at(&sb, 0x4c) >= 1 { u64::from(u16_at(&sb, 0x58)) } else { 128 };
+        Ok(Ext4 {
+            file: RefCell::new(file),
+            _permit: permit,
+            start,
+            len,
+            block_size: 1024 << log_block_size,
+            inodes_count: u32_at(&sb, 0),
+            inodes_per_group: u32_at(&sb, 0x28),
+            inode_size,
+            desc_size: if wide { u64::from(u16_at(&sb, 0xfe)).max(32) } else { 32 },
+            first_data_block: u64::from(u32_at(&sb, 0x14)),
+            wide,
+        })
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(self.start + offset))?;
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn block(&self, block: u64) -> io::Result<Vec<u8>> {
+        self.read_at(block * self.block_size, self.block_size as usize)
+    }
+
+    pub fn inode(&self, number: u32) -> io::Result<Inode> {
+        if number == 0 || number > self.inodes_count || self.inodes_per_group == 0 {
+            return Err(invalid("Invalid inode number"));
+        }
+        let group = u64::from((number - 1) / self.inodes_per_group);
+        let index = u64::from((number - 1) % self.inodes_per_group);
+        let desc = self.read_at((self.first_data_block + 1) * self.block_size + group * self.desc_size, self.desc_size as usize)?;
+        let mut table = u64::from(u32_at(&desc, 8));
+        if self.wide && self.desc_size >= 64 {
+            table |= u64::from(u32_at(&desc, 0x28)) << 32;
+        }
+        let raw = self.read_at(table * self.block_size + index * self.inode_size, 128)?;
+        Ok(Inode {
+            mode: u16_at(&raw, 0),
+            size: u64::from(u32_at(&raw, 4)) | u64::from(u32_at(&raw, 0x6c)) << 32,
+            flags: u32_at(&raw, 0x20),
+            block: raw[0x28..0x28 + 60].try_into().unwrap(),
+        })
+    }
+
+    /// (file block, disk block, count) runs of an extent tree node
+    fn extents(&self, node: &[u8], runs: &mut Vec<(u64, u64, u64)>) -> io::Result<()> {
+        if node.len() < 12 || u16_at(node, 0) != EXTENT_MAGIC {
+            return Err(invalid("Invalid extent header"));
+        }
+        let (entries, depth) = (u16_at(node, 2) as usize, u16_at(node, 6));
+        for i in 0..entries {
+            let e = node.get(12 + i * 12..24 + i * 12).ok_or_else(|| invalid("Truncated extent node"))?;
+            if depth == 0 {
+                let len = u16_at(e, 4);
+                // lengths above 32768 mark uninitialized extents, which read as zeros
+                if len <= 32768 {
+                    let start = u64::from(u16_at(e, 6)) << 32 | u64::from(u32_at(e, 8));
+                    runs.push((u64::from(u32_at(e, 0)), start, u64::from(len)));
+                }
+            }
+            else {
+                let leaf = u64::from(u16_at(e, 8)) << 32 | u64::from(u32_at(e, 4));
+                self.extents(&self.block(leaf)?, runs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Disk blocks of an indirect block at `level` (0 being a data block), appended to `blocks`
+    fn indirect(&self, block: u64, level: u32, blocks: &mut Vec<u64>, wanted: u64) -> io::Result<()> {
+        if blocks.len() as u64 >= wanted {
+            return Ok(());
+        }
+        if level == 0 {
+            blocks.push(block);
+            return Ok(());
+        }
+        if block == 0 {
+            // hole: every block below it is a hole too
+            let span = (self.block_size / 4).pow(level);
+            blocks.extend(std::iter::repeat_n(0, span.min(wanted - blocks.len() as u64) as usize));
+            return Ok(());
+        }
+        let data = self.block(block)?;
+        for i in 0..self.block_size as usize / 4 {
+            self.indirect(u64::from(u32_at(&data, i * 4)), level - 1, blocks, wanted)?;
+        }
+        Ok(())
+    }
+
+    /// Contents of a file, directory or slow symlink
+    pub fn read(&self, inode: &Inode) -> io::Result<Vec<u8>> {
+        self.read_head(inode, u64::MAX)
+    }
+
+    /// First `len` bytes of a file, all of it if it's shorter
+    pub fn read_head(&self, inode: &Inode, len: u64) -> io::Result<Vec<u8>> {
+        // checked before allocating, since the size comes from the image
+        if inode.size > self.len {
+            return Err(invalid("File larger than the image"));
+        }
+        let size = inode.size.min(len) as usize;
+        if inode.flags & INLINE_DATA_FL != 0 {
+            // data beyond the inode lives in an extended attribute, which isn't read
+            return Ok(inode.block[..size.min(60)].to_vec());
+        }
+        let mut data = vec![0u8; size];
+        let block_size = self.block_size as usize;
+        if inode.flags & EXTENTS_FL != 0 {
+            let mut runs = vec![];
+            self.extents(&inode.block, &mut runs)?;
+            for (file_block, disk_block, count) in runs {
+                for i in 0..count {
+                    let offset = (file_block + i) as usize * block_size;
+                    if offset >= size {
+                        break;
+                    }
+                    let block = self.block(disk_block + i)?;
+                    let len = block_size.min(size - offset);
+                    data[offset..offset + len].copy_from_slice(&block[..len]);
+                }
+            }
+        }
+        else {
+            let wanted = (size as u64).div_ceil(self.block_size);
+            let mut blocks = vec![];
+            for i in 0..15usize {
+                let level = i.saturating_sub(11) as u32;
+                self.indirect(u64::from(u32_at(&inode.block, i * 4)), level, &mut blocks, wanted)?;
+            }
+            for (i, block) in blocks.into_iter().enumerate().filter(|(_, b)| *b != 0) {
+                let offset = i * block_size;
+                let len = block_size.min(size - offset);
+                data[offset..offset + len].copy_from_slice(&self.block(block)?[..len]);
+            }
+        }
+        Ok(data)
+    }
+
+    pub fn read_dir(&self, inode: &Inode) -> io::Result<Vec<Entry>> {
+        let data = self.read(inode)?;
+        // inline directories start with the parent's inode number instead of `.` and `..`
+        let mut pos = if inode.flags & INLINE_DATA_FL != 0 { 4 } else { 0 };
+        let mut entries = vec![];
+        while pos + 8 <= data.len() {
+            let number = u32_at(&data, pos);
+            let rec_len = u16_at(&data, pos + 4) as usize;
+            let name_len = data[pos + 6] as usize;
+            if rec_len < 8 {
+                return Err(invalid("Invalid directory entry"));
+            }
+            let name = data.get(pos + 8..pos + 8 + name_len).unwrap_or_default();
+            if number != 0 && name != b"." && name != b".." {
+                entries.push(Entry { name: name.to_vec(), inode: number });
+            }
+            pos += rec_len;
+        }
+        Ok(entries)
+    }
+
+    fn symlink_target(&self, inode: &Inode) -> io::Result<Vec<u8>> {
+        if inode.size < 60 && inode.flags & EXTENTS_FL == 0 {
+            Ok(inode.block[..inode.size as usize].to_vec())
+        }
+        else {
+            self.read(inode)
+        }
+    }
+
+    /// Inode number of an absolute path, following symlinks inside the filesystem
+    pub fn lookup(&self, path: &[u8]) -> io::Result<Option<u32>> {
+        let mut pending: Vec<Vec<u8>> = path.split(|&b| b == b'/')
+            .filter(|c| !c.is_empty())
+            .rev()
+            .map(<[u8]>::to_vec)
+            .collect();
+        // inodes of the directories walked so far, for `..`
+        let mut stack = vec![ROOT_INODE];
+        let mut symlinks = 0;
+        while let Some(component) = pending.pop() {
+            match component.as_slice() {
+                b"." => continue,
+                b".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                    continue;
+                },
+                _ => {},
+            }
+            let dir = self.inode(*stack.last().unwrap())?;
+            if dir.file_type() != FileType::Directory {
+                return Ok(None);
+            }
+            let entry = match self.read_dir(&dir)?.into_iter().find(|e| e.name == component) {
+                Some(e) => e,
+                None => return Ok(None),
+            };
+            let inode = self.inode(entry.inode)?;
+            if inode.file_type() == FileType::Symlink {
+                symlinks += 1;
+                if symlinks > MAX_SYMLINKS {
+                    return Ok(None);
+                }
+                let target = self.symlink_target(&inode)?;
+                if target.starts_with(b"/") {
+                    stack.truncate(1);
+                }
+                pending.extend(target.split(|&b| b == b'/').filter(|c| !c.is_empty()).rev().map(<[u8]>::to_vec));
+                continue;
+            }
+            stack.push(entry.inode);
+        }
+        Ok(stack.last().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use super::*;
+
+    const BLOCK: usize = 1024;
+    /// Block of the inode table, 16 inodes of 128 bytes
+    const INODE_TABLE: usize = 5;
+
+    struct Image(Vec<u8>);
+
+    impl Image {
+        fn put(&mut self, offset: usize, bytes: &[u8]) {
+            self.0[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        fn inode(&mut self, number: usize, mode: u16, size: u32, flags: u32, block: &[u8]) {
+            let at = INODE_TABLE * BLOCK + (number - 1) * 128;
+            self.put(at, &mode.to_le_bytes());
+            self.put(at + 4, &size.to_le_bytes());
+            self.put(at + 0x20, &flags.to_le_bytes());
+            self.put(at + 0x28, block);
+        }
+
+        fn dir(&mut self, block: usize, entries: &[(u32, &str)]) {
+            let mut at = block * BLOCK;
+            for (i, (inode, name)) in entries.iter().enumerate() {
+                let len = if i + 1 == entries.len() { block * BLOCK + BLOCK - at } else { (8 + name.len()).next_multiple_of(4) };
+                self.put(at, &inode.to_le_bytes());
+                self.put(at + 4, &(len as u16).to_le_bytes());
+                self.put(at + 6, &[name.len() as u8, 0]);
+                self.put(at + 8, name.as_bytes());
+                at += len;
+            }
+        }
+    }
+
+    /// Inode 12 is `/hello`, block-mapped; 13 `/big`, 1500 bytes in one extent over 2 blocks;
+    /// 14 `/link`, a fast symlink to `sub/../hello`; 15 the directory `/sub`
+    fn image() -> Image {
+        let mut image = Image(vec![0; 16 * BLOCK]);
+        let sb = BLOCK;
+        image.put(sb, &16u32.to_le_bytes());
+        image.put(sb + 0x14, &1u32.to_le_bytes());
+        image.put(sb + 0x28, &16u32.to_le_bytes());
+        image.put(sb + 0x38, &MAGIC.to_le_bytes());
+        // group descriptor in the block after the superblock
+        image.put(2 * BLOCK + 8, &(INODE_TABLE as u32).to_le_bytes());
+        image.inode(2, S_IFDIR | 0o755, BLOCK as u32, 0, &10u32.to_le_bytes());
+        image.dir(10, &[(2, "."), (2, ".."), (12, "hello"), (13, "big"), (14, "link"), (15, "sub")]);
+        image.inode(12, S_IFREG | 0o4755, 11, 0, &11u32.to_le_bytes());
+        image.put(11 * BLOCK, b"hello world");
+        let mut extents = vec![];
+        for value in [EXTENT_MAGIC, 1, 4, 0] {
+            extents.extend_from_slice(&value.to_le_bytes());
+        }
+        extents.extend_from_slice(&[0; 4]);
+        // file block 0, 2 blocks from block 12
+        extents.extend_from_slice(&0u32.to_le_bytes());
+        extents.extend_from_slice(&2u16.to_le_bytes());
+        extents.extend_from_slice(&0u16.to_le_bytes());
+        extents.extend_from_slice(&12u32.to_le_bytes());
+        image.inode(13, S_IFREG | 0o644, 1500, EXTENTS_FL, &extents);
+        for (i, byte) in image.0[12 * BLOCK..12 * BLOCK + 1500].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        image.inode(14, S_IFLNK | 0o777, 12, 0, b"sub/../hello");
+        image.inode(15, S_IFDIR | 0o755, BLOCK as u32, 0, &14u32.to_le_bytes());
+        image.dir(14, &[(15, "."), (2, "..")]);
+        image
+    }
+
+    /// Filesystem in an image of `data`, whose file is gone once it's open
+    fn open(name: &str, data: &[u8]) -> io::Result<Ext4> {
+        let path: PathBuf = std::env::temp_dir().join(format!("linux-hs-elf-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let fs = Ext4::open(&path);
+        fs::remove_file(&path).unwrap();
+        fs
+    }
+
+    fn error<T>(result: io::Result<T>) -> String {
+        result.err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn reads_files() {
+        let fs = open("plain.img", &image().0).unwrap();
+        let root = fs.inode(ROOT_INODE).unwrap();
+        let names: Vec<Vec<u8>> = fs.read_dir(&root).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, [&b"hello"[..], b"big", b"link", b"sub"]);
+        let hello = fs.inode(fs.lookup(b"/hello").unwrap().unwrap()).unwrap();
+        assert_eq!((hello.file_type(), hello.permissions()), (FileType::Regular, 0o4755));
+        assert_eq!(fs.read(&hello).unwrap(), b"hello world");
+        assert_eq!(fs.read_head(&hello, 4).unwrap(), b"hell");
+        let big = fs.inode(fs.lookup(b"/big").unwrap().unwrap()).unwrap();
+        let expected: Vec<u8> = (0..1500).map(|i| i as u8).collect();
+        assert_eq!(fs.read(&big).unwrap(), expected);
+        assert_eq!(fs.read_head(&big, 1100).unwrap(), expected[..1100]);
+        // through the symlink and `..`
+        assert_eq!(fs.lookup(b"/link").unwrap(), Some(12));
+        assert_eq!(fs.lookup(b"/sub/../sub/./../big").unwrap(), Some(13));
+        assert_eq!(fs.lookup(b"/missing").unwrap(), None);
+        assert_eq!(fs.lookup(b"/hello/x").unwrap(), None);
+        // in the first partition of a whole-disk image
+        let mut disk = vec![0; 2048];
+        disk[446 + 4] = 0x83;
+        disk[446 + 8..446 + 12].copy_from_slice(&4u32.to_le_bytes());
+        disk[510..512].copy_from_slice(&[0x55, 0xaa]);
+        disk.extend_from_slice(&image().0);
+        let fs = open("disk.img", &disk).unwrap();
+        let hello = fs.inode(fs.lookup(b"/link").unwrap().unwrap()).unwrap();
+        assert_eq!(fs.read(&hello).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn truncated() {
+        let image = image().0;
+        assert_eq!(error(open("empty.img", &[])), "No ext2/3/4 filesystem found");
+        // the superblock cut short
+        assert!(open("superblock.img", &image[..1100]).is_err());
+        // the extent's second block missing
+        let fs = open("truncated.img", &image[..13 * BLOCK + 100]).unwrap();
+        let big = fs.inode(13).unwrap();
+        assert_eq!(fs.read(&big).map_err(|e| e.kind()).err(), Some(io::ErrorKind::UnexpectedEof));
+        assert_eq!(fs.read_head(&big, 100).unwrap().len(), 100);
+        // cut after the inode table: the inodes are there, the data isn't
+        let fs = open("short.img", &image[..7 * BLOCK]).unwrap();
+        let root = fs.inode(ROOT_INODE).unwrap();
+        assert_eq!(root.file_type(), FileType::Directory);
+        assert_eq!(fs.read_dir(&root).map_err(|e| e.kind()).err(), Some(io::ErrorKind::UnexpectedEof));
+        assert!(fs.lookup(b"/hello").is_err());
+        // inodes past the ones the filesystem has
+        assert_eq!(error(fs.inode(17)), "Invalid inode number");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut image = image();
+        // a size of 4 GiB, an extent past the end of the image, and one with a bad header
+        image.put(INODE_TABLE * BLOCK + 11 * 128 + 0x6c, &1u32.to_le_bytes());
+        image.put(INODE_TABLE * BLOCK + 12 * 128 + 0x28 + 20, &1000u32.to_le_bytes());
+        image.put(INODE_TABLE * BLOCK + 13 * 128 + 0x28, &[0; 4]);
+        image.inode(16, S_IFREG, 100, EXTENTS_FL, &[0; 60]);
+        let fs = open("range.img", &image.0).unwrap();
+        assert_eq!(error(fs.read(&fs.inode(12).unwrap())), "File larger than the image");
+        assert_eq!(fs.read(&fs.inode(13).unwrap()).map_err(|e| e.kind()).err(), Some(io::ErrorKind::UnexpectedEof));
+        assert_eq!(error(fs.read(&fs.inode(16).unwrap())), "Invalid extent header");
+        assert_eq!(error(fs.inode(0)), "Invalid inode number");
+        // the block size field
+        let mut image = self::image();
+        image.put(BLOCK + 0x18, &(MAX_LOG_BLOCK_SIZE + 1).to_le_bytes());
+        assert_eq!(error(open("block-size.img", &image.0)), "Invalid block size");
+        // a GPT with more entries than any real one
+        let mut disk = vec![0; 1024];
+        disk[446 + 4] = 0xee;
+        disk[510..512].copy_from_slice(&[0x55, 0xaa]);
+        disk[512..520].copy_from_slice(b"EFI PART");
+        disk[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+        disk[512 + 80..512 + 84].copy_from_slice(&u32::MAX.to_le_bytes());
+        disk[512 + 84..512 + 88].copy_from_slice(&128u32.to_le_bytes());
+        assert_eq!(error(open("gpt.img", &disk)), "Invalid GPT entry size or count");
+    }
+}
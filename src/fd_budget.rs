@@ -0,0 +1,129 @@
+//! Budget of files the scanner keeps open at once, shared by everything reading binaries, so
+//! that concurrent work queues for a descriptor instead of failing with `EMFILE`.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use tracing::debug;
+
+/// Descriptors left for stdio, sockets, indexes and the like when deriving the budget from
+/// `RLIMIT_NOFILE`
+const RESERVED: usize = 32;
+/// Budget used when the limit can't be read
+const FALLBACK: usize = 256;
+/// `EMFILE` on Linux
+const TOO_MANY_OPEN_FILES: i32 = 24;
+
+static BUDGET: OnceLock<Budget> = OnceLock::new();
+
+struct Budget {
+    limit: usize,
+    open: Mutex<usize>,
+    /// Most files open at once so far
+    peak: AtomicUsize,
+    freed: Condvar,
+}
+
+/// Open file counted against the budget until dropped
+pub struct Permit {
+    budget: &'static Budget,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.budget.open.lock().unwrap() -= 1;
+        self.budget.freed.notify_all();
+    }
+}
+
+/// Soft `RLIMIT_NOFILE` of this process, `None` if unlimited or unknown
+fn rlimit_nofile() -> Option<usize> {
+    let limits = fs::read_to_string("/proc/self/limits").ok()?;
+    let line = limits.lines().find(|l| l.starts_with("Max open files"))?;
+    line["Max open files".len()..].split_whitespace().next()?.parse().ok()
+}
+
+/// Sets the budget to `max_open_files`, or to what `RLIMIT_NOFILE` leaves after the reserved
+/// descriptors; has no effect once any file was read
+pub fn init(max_open_files: Option<usize>) {
+    let limit = max_open_files
+        .or_else(|| rlimit_nofile().map(|n| n.saturating_sub(RESERVED)))
+        .unwrap_or(FALLBACK)
+        .max(1);
+    debug!("Keeping at most {} files open", limit);
+    let _ = BUDGET.set(Budget { limit, open: Mutex::new(0), peak: AtomicUsize::new(0), freed: Condvar::new() });
+}
+
+fn budget() -> &'static Budget {
+    if BUDGET.get().is_none() {
+        init(None);
+    }
+    BUDGET.get().unwrap()
+}
+
+/// Files that may be open at once
+pub fn limit() -> usize {
+    budget().limit
+}
+
+/// Most files that were open at once so far
+pub fn peak() -> usize {
+    budget().peak.load(Ordering::Relaxed)
+}
+
+/// Waits until a file can be opened within the budget
+pub fn acquire() -> Permit {
+    let budget = budget();
+    let mut open = budget.open.lock().unwrap();
+    while *open >= budget.limit {
+        open = budget.freed.wait(open).unwrap();
+    }
+    *open += 1;
+    budget.peak.fetch_max(*open, Ordering::Relaxed);
+    Permit { budget }
+}
+
+/// [`fs::read`] within the budget, waiting for other files to be closed if the process runs
+/// out of descriptors anyway
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    with_file(path, |path| fs::read(path))
+}
+
+/// Reads the first `len` bytes of the file at `path`, and the rest of it only if `accept`s them;
+/// `None` if it doesn't
+pub fn read_if(path: &Path, len: usize, accept: impl Fn(&[u8]) -> bool) -> io::Result<Option<Vec<u8>>> {
+    with_file(path, |path| {
+        let mut file = fs::File::open(path)?;
+        let mut data = vec![];
+        (&mut file).take(len as u64).read_to_end(&mut data)?;
+        if !accept(&data) {
+            return Ok(None);
+        }
+        file.read_to_end(&mut data)?;
+        Ok(Some(data))
+    })
+}
+
+/// Runs `read` on `path` within the budget like [`read`]
+fn with_file<T>(path: &Path, read: impl Fn(&Path) -> io::Result<T>) -> io::Result<T> {
+    loop {
+        let _permit = acquire();
+        match read(path) {
+            Err(e) if e.raw_os_error() == Some(TOO_MANY_OPEN_FILES) => {
+                debug!("Out of file descriptors reading {}, waiting", path.display());
+                let budget = budget();
+                let open = budget.open.lock().unwrap();
+                // our own permit is still held, so anyone else holding one will wake us
+                if *open > 1 {
+                    drop(budget.freed.wait(open).unwrap());
+                }
+                else {
+                    return Err(e);
+                }
+            },
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Tiny synthetic ELF files for testing: 32 and 64-bit, either endianness, with the chosen
+//! `DT_NEEDED` entries, with or without section headers, and deliberately broken variants.
+//!
+//! Files have a single `PT_LOAD` segment mapping them whole at their base address, 0 unless
+//! they're fixed-address executables, so virtual addresses are file offsets plus the base.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use clap::ArgEnum;
+use object::elf;
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Malformed {
+    /// Cut in the middle, so the section headers are past the end of the file
+    Truncated,
+    /// `.dynamic` section pointing outside the file
+    BadSectionOffset,
+    /// Invalid ELF class in `e_ident`
+    BadClass,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub name: String,
+    pub bits: u8,
+    pub big_endian: bool,
+    pub machine: u16,
+    pub needed: Vec<String>,
+    pub soname: Option<String>,
+    pub interp: Option<String>,
+    pub section_headers: bool,
+    /// Section headers cut off the end of the file with `e_shoff` and `e_shnum` still set, the
+    /// way stripping them by truncation leaves files
+    pub cut_section_headers: bool,
+    /// Load address of a fixed-address executable (`ET_EXEC`), 0 for a position-independent
+    /// file (`ET_DYN`)
+    pub base: u64,
+    pub malformed: Option<Malformed>,
+}
+
+impl Fixture {
+    /// Well-formed little-endian x86_64 executable named `name` needing `needed`
+    pub fn new(name: &str, needed: &[&str]) -> Self {
+        Fixture {
+            name: name.to_string(),
+            bits: 64,
+            big_endian: false,
+            machine: elf::EM_X86_64,
+            needed: needed.iter().map(|n| n.to_string()).collect(),
+            soname: None,
+            interp: Some("/lib64/ld-linux-x86-64.so.2".to_string()),
+            section_headers: true,
+            cut_section_headers: false,
+            base: 0,
+            malformed: None,
+        }
+    }
+}
+
+/// Fixed-endianness, fixed-class writer
+struct Out {
+    buf: Vec<u8>,
+    big_endian: bool,
+    bits: u8,
+}
+
+impl Out {
+    fn u16(&mut self, v: u16) {
+        let bytes = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.buf.extend_from_slice(&bytes);
+    }
+
+    fn u32(&mut self, v: u32) {
+        let bytes = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.buf.extend_from_slice(&bytes);
+    }
+
+    fn u64(&mut self, v: u64) {
+        let bytes = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.buf.extend_from_slice(&bytes);
+    }
+
+    /// Address-sized value
+    fn word(&mut self, v: u64) {
+        if self.bits == 64 {
+            self.u64(v);
+        }
+        else {
+            self.u32(v as u32);
+        }
+    }
+
+    fn pad_to(&mut self, offset: u64) {
+        self.buf.resize(offset as usize, 0);
+    }
+}
+
+/// Parses an address given on the command line, in hex with a `0x` prefix or in decimal
+pub fn address(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn align(offset: u64, to: u64) -> u64 {
+    offset.div_ceil(to) * to
+}
+
+/// String table with the offsets of the strings added to it
+#[derive(Default)]
+struct Strings {
+    data: Vec<u8>,
+}
+
+impl Strings {
+    fn add(&mut self, s: &str) -> u32 {
+        if self.data.is_empty() {
+            self.data.push(0);
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        offset
+    }
+}
+
+struct Section {
+    name: u32,
+    kind: u32,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+/// Contents of the fixture's file
+pub fn build(fixture: &Fixture) -> Vec<u8> {
+    let wide = fixture.bits == 64;
+    let word: u64 = if wide { 8 } else { 4 };
+    let (ehsize, phentsize, shentsize) = if wide { (64, 56, 64) } else { (52, 32, 40) };
+    let dynent = 2 * word;
+
+    let mut dynstr = Strings::default();
+    let needed: Vec<u32> = fixture.needed.iter().map(|n| dynstr.add(n)).collect();
+    let soname = fixture.soname.as_ref().map(|s| dynstr.add(s));
+    dynstr.add("");
+    let interp = fixture.interp.as_ref().map(|i| {
+        let mut bytes = i.as_bytes().to_vec();
+        bytes.push(0);
+        bytes
+    });
+    let mut dynamic: Vec<(u32, u64)> = needed.iter().map(|&n| (elf::DT_NEEDED, u64::from(n))).collect();
+    if let Some(soname) = soname {
+        dynamic.push((elf::DT_SONAME, u64::from(soname)));
+    }
+
+    let phnum: u64 = if interp.is_some() { 3 } else { 2 };
+    let interp_offset = ehsize + phnum * phentsize;
+    let interp_size = interp.as_ref().map_or(0, |i| i.len() as u64);
+    let dynstr_offset = interp_offset + interp_size;
+    let dynstr_size = dynstr.data.len() as u64;
+    let dynamic_offset = align(dynstr_offset + dynstr_size, word);
+    dynamic.push((elf::DT_STRTAB, fixture.base + dynstr_offset));
+    dynamic.push((elf::DT_STRSZ, dynstr_size));
+    dynamic.push((elf::DT_NULL, 0));
+    let dynamic_size = dynamic.len() as u64 * dynent;
+
+    let mut shstr = Strings::default();
+    let mut sections = vec![];
+    if interp.is_some() {
+        sections.push(Section { name: shstr.add(".interp"), kind: elf::SHT_PROGBITS, offset: interp_offset, size: interp_size, link: 0, entsize: 0 });
+    }
+    let dynstr_index = sections.len() as u32 + 1;
+    sections.push(Section { name: shstr.add(".dynstr"), kind: elf::SHT_STRTAB, offset: dynstr_offset, size: dynstr_size, link: 0, entsize: 0 });
+    let dynamic_section_offset = match fixture.malformed {
+        Some(Malformed::BadSectionOffset) => 0xffff_ff00,
+        _ => dynamic_offset,
+    };
+    sections.push(Section { name: shstr.add(".dynamic"), kind: elf::SHT_DYNAMIC, offset: dynamic_section_offset, size: dynamic_size, link: dynstr_index, entsize: dynent });
+    let shstrtab_name = shstr.add(".shstrtab");
+    let shstrtab_offset = dynamic_offset + dynamic_size;
+    sections.push(Section { name: shstrtab_name, kind: elf::SHT_STRTAB, offset: shstrtab_offset, size: shstr.data.len() as u64, link: 0, entsize: 0 });
+    let shoff = align(shstrtab_offset + shstr.data.len() as u64, word);
+    let shnum = sections.len() as u64 + 1;
+    let size = match (fixture.section_headers, fixture.cut_section_headers) {
+        (true, false) => shoff + shnum * shentsize,
+        (true, true) => shoff,
+        (false, _) => shstrtab_offset,
+    };
+
+    let mut out = Out { buf: vec![], big_endian: fixture.big_endian, bits: fixture.bits };
+    out.buf.extend_from_slice(&elf::ELFMAG);
+    out.buf.push(match fixture.malformed {
+        Some(Malformed::BadClass) => 7,
+        _ if wide => elf::ELFCLASS64,
+        _ => elf::ELFCLASS32,
+    });
+    out.buf.push(if fixture.big_endian { elf::ELFDATA2MSB } else { elf::ELFDATA2LSB });
+    out.buf.push(elf::EV_CURRENT);
+    out.pad_to(16);
+    out.u16(if fixture.base == 0 { elf::ET_DYN } else { elf::ET_EXEC });
+    out.u16(fixture.machine);
+    out.u32(u32::from(elf::EV_CURRENT));
+    out.word(0);
+    out.word(ehsize);
+    out.word(if fixture.section_headers { shoff } else { 0 });
+    out.u32(0);
+    out.u16(ehsize as u16);
+    out.u16(phentsize as u16);
+    out.u16(phnum as u16);
+    out.u16(shentsize as u16);
+    out.u16(if fixture.section_headers { shnum as u16 } else { 0 });
+    out.u16(if fixture.section_headers { shnum as u16 - 1 } else { 0 });
+
+    let mut segments = vec![(elf::PT_LOAD, 0, size, elf::PF_R)];
+    if interp.is_some() {
+        segments.push((elf::PT_INTERP, interp_offset, interp_size, elf::PF_R));
+    }
+    segments.push((elf::PT_DYNAMIC, dynamic_offset, dynamic_size, elf::PF_R | elf::PF_W));
+    for (kind, offset, filesz, flags) in segments {
+        out.u32(kind);
+        if wide {
+            out.u32(flags);
+        }
+        out.word(offset);
+        out.word(fixture.base + offset);
+        out.word(fixture.base + offset);
+        out.word(filesz);
+        out.word(filesz);
+        if !wide {
+            out.u32(flags);
+        }
+        out.word(if kind == elf::PT_LOAD { 0x1000 } else { word });
+    }
+
+    if let Some(interp) = &interp {
+        out.buf.extend_from_slice(interp);
+    }
+    out.buf.extend_from_slice(&dynstr.data);
+    out.pad_to(dynamic_offset);
+    for (tag, value) in dynamic {
+        out.word(u64::from(tag));
+        out.word(value);
+    }
+    if fixture.section_headers {
+        out.buf.extend_from_slice(&shstr.data);
+        out.pad_to(shoff);
+        out.buf.resize(out.buf.len() + shentsize as usize, 0);
+        for section in sections {
+            out.u32(section.name);
+            out.u32(section.kind);
+            out.word(if section.kind == elf::SHT_DYNAMIC { u64::from(elf::SHF_ALLOC | elf::SHF_WRITE) } else { u64::from(elf::SHF_ALLOC) });
+            out.word(fixture.base + section.offset);
+            out.word(section.offset);
+            out.word(section.size);
+            out.u32(section.link);
+            out.u32(0);
+            out.word(if section.entsize > 0 { word } else { 1 });
+            out.word(section.entsize);
+        }
+    }
+    if fixture.cut_section_headers {
+        out.buf.truncate(shoff as usize);
+    }
+    if fixture.malformed == Some(Malformed::Truncated) {
+        out.buf.truncate(out.buf.len() / 2);
+    }
+    out.buf
+}
+
+/// The standard set of fixtures: every class and endianness, a library, a file without section
+/// headers and the malformed variants
+pub fn standard_set() -> Vec<Fixture> {
+    let variant = |name: &str, f: &dyn Fn(&mut Fixture)| {
+        let mut fixture = Fixture::new(name, &["libfoo.so.1", "libc.so.6"]);
+        f(&mut fixture);
+        fixture
+    };
+    vec![
+        variant("elf64-le", &|_| {}),
+        variant("elf32-le", &|f| {
+            f.bits = 32;
+            f.machine = elf::EM_386;
+            f.interp = Some("/lib/ld-linux.so.2".to_string());
+        }),
+        variant("elf64-be", &|f| {
+            f.big_endian = true;
+            f.machine = elf::EM_PPC64;
+            f.interp = Some("/lib64/ld64.so.1".to_string());
+        }),
+        variant("elf32-be", &|f| {
+            f.bits = 32;
+            f.big_endian = true;
+            f.machine = elf::EM_PPC;
+            f.interp = Some("/lib/ld.so.1".to_string());
+        }),
+        variant("libfoo.so.1", &|f| {
+            f.needed = vec!["libc.so.6".to_string()];
+            f.soname = Some("libfoo.so.1".to_string());
+            f.interp = None;
+        }),
+        variant("no-section-headers", &|f| f.section_headers = false),
+        variant("malformed-truncated", &|f| f.malformed = Some(Malformed::Truncated)),
+        variant("malformed-section-offset", &|f| f.malformed = Some(Malformed::BadSectionOffset)),
+        variant("malformed-class", &|f| f.malformed = Some(Malformed::BadClass)),
+    ]
+}
+
+/// Writes the fixtures into `dir`, creating it if needed
+pub fn write(dir: &Path, fixtures: &[Fixture]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for fixture in fixtures {
+        fs::write(dir.join(&fixture.name), build(fixture))?;
+    }
+    Ok(())
+}
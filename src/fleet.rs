@@ -0,0 +1,58 @@
+//! Saved scans of many hosts or images combined into one inventory, keyed by host label.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::index::Index;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Fleet {
+    pub hosts: BTreeMap<String, Index>,
+}
+
+/// Either kind of file `merge` takes
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Saved {
+    Fleet(Fleet),
+    Index(Index),
+}
+
+impl Fleet {
+    /// Adds the hosts of the index or merged fleet saved at `path`. Indexes without metadata
+    /// are labeled with their file name.
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let reader = BufReader::new(fs::File::open(path)?);
+        match serde_json::from_reader(reader).map_err(io::Error::from)? {
+            Saved::Fleet(fleet) => fleet.hosts.into_iter().for_each(|(label, index)| self.insert(label, index)),
+            Saved::Index(index) => {
+                let label = match &index.metadata {
+                    Some(metadata) => metadata.label.clone(),
+                    None => path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+                };
+                self.insert(label, index);
+            },
+        }
+        Ok(())
+    }
+
+    /// Adds a host, keeping only the newest scan of hosts seen more than once
+    fn insert(&mut self, label: String, index: Index) {
+        let time = |index: &Index| index.metadata.as_ref().map(|m| m.time);
+        if let Some(existing) = self.hosts.get(&label) {
+            warn!("Several scans of {}, keeping the newest", label);
+            if time(existing) > time(&index) {
+                return;
+            }
+        }
+        self.hosts.insert(label, index);
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+}
@@ -0,0 +1,113 @@
+//! Dependency graph over the scanned files and the libraries reachable from them.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use crate::elf::Target;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A file found by the scan
+    Scanned,
+    /// A library reached through `DT_NEEDED`
+    Library,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub kind: NodeKind,
+    /// File on disk, `None` for missing libraries
+    pub path: Option<PathBuf>,
+    pub arch: Option<String>,
+    pub size: Option<u64>,
+    /// `DT_SONAME` of scanned libraries, the name of library nodes
+    pub soname: Option<String>,
+    /// Library that couldn't be found on disk
+    pub missing: bool,
+    /// Shortest distance from a scanned file, 0 for scanned files
+    pub depth: usize,
+}
+
+/// Directed graph with an edge from each file to every library it needs
+#[derive(Debug, Default)]
+pub struct DepGraph {
+    pub nodes: Vec<Node>,
+    /// Outgoing edges, indexed by node
+    pub edges: Vec<Vec<usize>>,
+}
+
+impl DepGraph {
+    /// Builds the graph of `files`, leaving out libraries hidden by `filter`
+    pub fn build(files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter) -> Self {
+        let mut graph = DepGraph::default();
+        let mut libs: HashMap<(String, Target), usize> = HashMap::new();
+        let mut queue: VecDeque<(usize, Target)> = VecDeque::new();
+        let mut lib_node = |graph: &mut DepGraph, soname: &str, target: &Target, depth: usize, queue: &mut VecDeque<(usize, Target)>| {
+            *libs.entry((soname.to_string(), target.clone())).or_insert_with(|| {
+                let id = graph.add_node(soname.to_string(), NodeKind::Library);
+                graph.nodes[id].depth = depth;
+                graph.nodes[id].arch = Some(target.arch.clone());
+                graph.nodes[id].soname = Some(soname.to_string());
+                queue.push_back((id, target.clone()));
+                id
+            })
+        };
+        for file in files {
+            let id = graph.add_node(file.name.clone(), NodeKind::Scanned);
+            let node = &mut graph.nodes[id];
+            node.path = Some(file.path.clone());
+            node.arch = Some(file.info.arch.clone());
+            node.size = Some(file.info.size);
+            node.soname = file.info.soname.clone();
+            let target = file.info.target();
+            for soname in file.info.needed.iter().filter(|l| !filter.is_hidden(l)) {
+                let lib = lib_node(&mut graph, soname, &target, 1, &mut queue);
+                graph.add_edge(id, lib);
+            }
+        }
+        while let Some((id, target)) = queue.pop_front() {
+            let lib = match resolver.resolve(&graph.nodes[id].name, &target) {
+                Some(lib) => lib,
+                None => {
+                    graph.nodes[id].missing = true;
+                    continue;
+                },
+            };
+            let node = &mut graph.nodes[id];
+            node.path = Some(lib.path.clone());
+            node.size = Some(lib.info.size);
+            let depth = node.depth + 1;
+            for soname in lib.info.needed.clone().iter().filter(|l| !filter.is_hidden(l)) {
+                let lib = lib_node(&mut graph, soname, &target, depth, &mut queue);
+                graph.add_edge(id, lib);
+            }
+        }
+        graph
+    }
+
+    fn add_node(&mut self, name: String, kind: NodeKind) -> usize {
+        self.nodes.push(Node { name, kind, path: None, arch: None, size: None, soname: None, missing: false, depth: 0 });
+        self.edges.push(vec![]);
+        self.nodes.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if !self.edges[from].contains(&to) {
+            self.edges[from].push(to);
+        }
+    }
+
+    /// Incoming edges, indexed by node
+    pub fn reverse_edges(&self) -> Vec<Vec<usize>> {
+        let mut reverse = vec![vec![]; self.nodes.len()];
+        for (from, targets) in self.edges.iter().enumerate() {
+            for &to in targets {
+                reverse[to].push(from);
+            }
+        }
+        reverse
+    }
+}
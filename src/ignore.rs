@@ -0,0 +1,129 @@
+//! Paths a scan leaves out: `--exclude` globs and ignore files in gitignore syntax, and the
+//! `--include` globs files must match.
+//!
+//! Paths are matched relative to the scanned directory. As in `.gitignore`, a pattern without a
+//! slash matches the name of a file or directory at any depth, a pattern with one is anchored
+//! to the scanned directory, `**` stands for any number of directories, a trailing slash only
+//! matches directories and `!` re-includes what an earlier pattern excluded. Files in excluded
+//! directories stay excluded, and those directories aren't walked at all.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::pattern::glob_match;
+
+/// One pattern of an ignore file or of `--include` / `--exclude`
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Path components, `**` being any number of them
+    components: Vec<Vec<u8>>,
+    /// Matches the path from the scanned directory rather than the last name only
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl Rule {
+    /// Parses a line of an ignore file, `None` for blank lines and comments
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let components: Vec<Vec<u8>> = line.split('/')
+            .filter(|c| !c.is_empty())
+            .map(|c| c.as_bytes().to_vec())
+            .collect();
+        (!components.is_empty()).then_some(Rule { components, anchored, dir_only, negated })
+    }
+
+    fn matches(&self, path: &[&[u8]], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            match_components(&self.components, path)
+        }
+        else {
+            path.last().is_some_and(|name| glob_match(&self.components[0], name))
+        }
+    }
+}
+
+fn match_components(pattern: &[Vec<u8>], path: &[&[u8]]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // a trailing `**` matches what's inside a directory, not the directory itself
+        Some((first, [])) if first == b"**" => !path.is_empty(),
+        Some((first, rest)) if first == b"**" => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first()
+            .is_some_and(|(name, path)| glob_match(first, name) && match_components(rest, path)),
+    }
+}
+
+/// Whether the last of `rules` matching `path` excludes it
+fn last_match_excludes(rules: &[Rule], path: &[&[u8]], is_dir: bool) -> bool {
+    rules.iter().rev().find(|r| r.matches(path, is_dir)).is_some_and(|r| !r.negated)
+}
+
+/// Which paths below the scanned directory are scanned
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    exclude: Vec<Rule>,
+    include: Vec<Rule>,
+}
+
+impl PathFilter {
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.include.is_empty()
+    }
+
+    /// Excludes the paths matching `pattern`, a line of an ignore file
+    pub fn exclude(&mut self, pattern: &str) {
+        self.exclude.extend(Rule::parse(pattern));
+    }
+
+    /// Scans only files matching `pattern` or one of the other included ones
+    pub fn include(&mut self, pattern: &str) {
+        self.include.extend(Rule::parse(pattern));
+    }
+
+    /// Adds the patterns of the ignore file at `path`
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        for line in fs::read_to_string(path)?.lines() {
+            self.exclude(line);
+        }
+        Ok(())
+    }
+
+    /// Whether the directory at `relative` is excluded, so that it isn't walked
+    pub fn excludes_dir(&self, relative: &[u8]) -> bool {
+        let path = components(relative);
+        last_match_excludes(&self.exclude, &path, true)
+    }
+
+    /// Whether the file at `relative` is scanned, its directories not being excluded
+    pub fn admits_file(&self, relative: &[u8]) -> bool {
+        let path = components(relative);
+        if last_match_excludes(&self.exclude, &path, false) {
+            return false;
+        }
+        // included by name, or by one of its directories
+        self.include.is_empty() || self.include.iter().filter(|r| !r.negated).any(|rule| {
+            (1..=path.len()).any(|len| rule.matches(&path[..len], len < path.len()))
+        })
+    }
+}
+
+fn components(relative: &[u8]) -> Vec<&[u8]> {
+    relative.split(|&b| b == b'/').filter(|c| !c.is_empty()).collect()
+}
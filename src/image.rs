@@ -0,0 +1,271 @@
+//! Scanning a directory inside a raw filesystem image or a container image, with libraries
+//! resolved from the image's own library directories.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use crate::elf::{parse_elf_data, ElfInfo, HandleError};
+use crate::escape;
+pub use crate::ext4::FileType;
+use crate::ext4::{Ext4, Inode};
+use crate::resolve::{Library, Resolver, DEFAULT_LIB_DIRS};
+use crate::scan::{self, Scan, ScanOptions, ScannedFile, Walk};
+use crate::throttle::Throttle;
+
+/// What a node of an image's filesystem is
+#[derive(Debug, Clone, Copy)]
+pub struct Stat {
+    /// Identifies the node within the filesystem, the same for hard links
+    pub id: u64,
+    pub file_type: FileType,
+    /// Permission bits, including setuid and setgid
+    pub permissions: u32,
+    pub size: u64,
+}
+
+/// Read-only filesystem scanned in place, without mounting or extracting it
+pub trait Filesystem {
+    /// File, directory or symlink
+    type Node;
+
+    /// What the absolute `path` leads to, following symlinks inside the filesystem
+    fn lookup(&self, path: &[u8]) -> io::Result<Option<Self::Node>>;
+
+    fn stat(&self, node: &Self::Node) -> Stat;
+
+    /// Contents of a regular file
+    fn read(&self, node: &Self::Node) -> io::Result<Vec<u8>>;
+
+    /// First `len` bytes of a regular file, all of it if it's shorter
+    fn head(&self, node: &Self::Node, len: usize) -> io::Result<Vec<u8>> {
+        let mut data = self.read(node)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Names and nodes in a directory, symlinks among them not followed
+    fn read_dir(&self, node: &Self::Node) -> io::Result<Vec<(Vec<u8>, Self::Node)>>;
+}
+
+impl Filesystem for Ext4 {
+    type Node = (u32, Inode);
+
+    fn lookup(&self, path: &[u8]) -> io::Result<Option<Self::Node>> {
+        match Ext4::lookup(self, path)? {
+            Some(n) => Ok(Some((n, self.inode(n)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn stat(&self, (number, inode): &Self::Node) -> Stat {
+        Stat { id: u64::from(*number), file_type: inode.file_type(), permissions: inode.permissions(), size: inode.size }
+    }
+
+    fn read(&self, (_, inode): &Self::Node) -> io::Result<Vec<u8>> {
+        Ext4::read(self, inode)
+    }
+
+    fn head(&self, (_, inode): &Self::Node, len: usize) -> io::Result<Vec<u8>> {
+        Ext4::read_head(self, inode, len as u64)
+    }
+
+    fn read_dir(&self, (_, inode): &Self::Node) -> io::Result<Vec<(Vec<u8>, Self::Node)>> {
+        Ext4::read_dir(self, inode)?.into_iter()
+            .map(|e| Ok((e.name, (e.inode, self.inode(e.inode)?))))
+            .collect()
+    }
+}
+
+/// File contents of `path` in the image, following symlinks. `None` for anything but regular
+/// files.
+fn read_file<F: Filesystem>(fs: &F, path: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    match fs.lookup(path)? {
+        Some(node) if fs.stat(&node).file_type == FileType::Regular => fs.read(&node).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Names in the directory at `path`
+fn list<F: Filesystem>(fs: &F, path: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    let node = match fs.lookup(path)? {
+        Some(node) if fs.stat(&node).file_type == FileType::Directory => node,
+        _ => return Ok(vec![]),
+    };
+    let mut names: Vec<Vec<u8>> = fs.read_dir(&node)?.into_iter().map(|(name, _)| name).collect();
+    names.sort();
+    Ok(names)
+}
+
+fn join(dir: &[u8], name: &[u8]) -> Vec<u8> {
+    let mut path = dir.to_vec();
+    if !path.ends_with(b"/") {
+        path.push(b'/');
+    }
+    path.extend_from_slice(name);
+    path
+}
+
+fn to_path(path: &[u8]) -> PathBuf {
+    PathBuf::from(escape::unescape_os_str(&escape::bytes(path)))
+}
+
+/// Libraries in the image's standard library directories, by file name
+fn libraries<F: Filesystem>(fs: &F) -> io::Result<BTreeMap<String, Vec<Library>>> {
+    let mut dirs: Vec<Vec<u8>> = vec![];
+    for dir in DEFAULT_LIB_DIRS {
+        for name in list(fs, dir.as_bytes())? {
+            if name.windows(7).any(|w| w == b"-linux-") {
+                dirs.push(join(dir.as_bytes(), &name));
+            }
+        }
+        dirs.push(dir.as_bytes().to_vec());
+    }
+    // merged-usr images have /lib pointing to /usr/lib
+    let mut seen = HashSet::new();
+    let mut unique = vec![];
+    for dir in dirs {
+        if let Some(node) = fs.lookup(&dir)? {
+            if seen.insert(fs.stat(&node).id) {
+                unique.push(dir);
+            }
+        }
+    }
+    let mut libraries: BTreeMap<String, Vec<Library>> = BTreeMap::new();
+    for dir in unique {
+        for name in list(fs, &dir)? {
+            if !name.windows(3).any(|w| w == b".so") {
+                continue;
+            }
+            let path = join(&dir, &name);
+            let data = match read_file(fs, &path)? {
+                Some(data) => data,
+                None => continue,
+            };
+            match parse_elf_data(&data) {
+                Ok(info) => libraries.entry(escape::bytes(&name)).or_default()
+                    .push(Library { path: to_path(&path), info }),
+                Err(e) => debug!("Couldn't parse library {}: {}", escape::bytes(&path), e),
+            }
+        }
+    }
+    Ok(libraries)
+}
+
+/// Path, escaped name and node of a file to scan
+type Found<N> = (Vec<u8>, String, N);
+
+/// Files a scan of `dir` looks at, walking the tree the way scans of host directories do
+fn entries<F: Filesystem>(fs: &F, dir: &[u8], walk: &Walk) -> io::Result<Vec<Found<F::Node>>> {
+    let root = match fs.lookup(dir)? {
+        Some(node) if fs.stat(&node).file_type == FileType::Directory => node,
+        _ => return Err(io::Error::new(io::ErrorKind::NotFound, "No such directory in the image")),
+    };
+    let name = |path: &[u8]| escape::bytes(path[dir.len()..].strip_prefix(b"/").unwrap_or(&path[dir.len()..]));
+    let mut walked = HashSet::from([fs.stat(&root).id]);
+    let mut files = vec![];
+    // node ids of the files with whether their best path so far is a symlink, its depth and
+    // the path, as when walking host directories
+    let mut unique: HashMap<u64, (bool, usize, Vec<u8>, F::Node)> = HashMap::new();
+    let mut pending = vec![(dir.to_vec(), root, 1)];
+    while let Some((path, node, depth)) = pending.pop() {
+        let mut children = fs.read_dir(&node)?;
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        for (child, node) in children {
+            let path = join(&path, &child);
+            let link = fs.stat(&node).file_type == FileType::Symlink;
+            let node = match if link { fs.lookup(&path)? } else { Some(node) } {
+                Some(node) => node,
+                None => continue,
+            };
+            let stat = fs.stat(&node);
+            let relative = &path[dir.len()..];
+            if stat.file_type == FileType::Directory {
+                let descend = walk.recursive && (walk.follow_symlinks || !link)
+                    && walk.max_depth.is_none_or(|max| depth < max)
+                    && !walk.filter.excludes_dir(relative);
+                if descend && walked.insert(stat.id) {
+                    pending.push((path, node, depth + 1));
+                }
+            }
+            else if !walk.filter.admits_file(relative) {
+                debug!("Leaving out {}", escape::bytes(&path));
+            }
+            else if !walk.recursive {
+                files.push((name(&path), path, node));
+            }
+            else {
+                match unique.get(&stat.id) {
+                    Some(best) if (best.0, best.1, &best.2) <= (link, depth, &path) => {
+                        debug!("{} is the same file as {}", escape::bytes(&path), escape::bytes(&best.2));
+                    },
+                    _ => { unique.insert(stat.id, (link, depth, path, node)); },
+                }
+            }
+        }
+    }
+    files.extend(unique.into_values().map(|(_, _, path, node)| (name(&path), path, node)));
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files.into_iter().map(|(name, path, node)| (path, name, node)).collect())
+}
+
+/// Scans the directory `dir` of the filesystem in `image`
+pub fn scan(image: &Path, dir: &Path, options: &ScanOptions) -> io::Result<(Scan, Resolver)> {
+    scan_fs(&Ext4::open(image)?, dir, options)
+}
+
+/// Scans the directory `dir` of `fs`
+pub fn scan_fs<F: Filesystem>(fs: &F, dir: &Path, options: &ScanOptions) -> io::Result<(Scan, Resolver)> {
+    let dir = escape::unescape_bytes(&escape::path(dir));
+    let mut result = Scan::default();
+    let mut throttle = Throttle::new(options.throttle);
+    let entries = entries(fs, &dir, &options.walk)?;
+    if let Some(progress) = &options.progress {
+        progress.discovered(entries.len());
+    }
+    for (path, shown, node) in entries {
+        if let Some(progress) = &options.progress {
+            progress.start(&shown);
+            progress.processed();
+        }
+        let stat = fs.stat(&node);
+        match stat.file_type {
+            FileType::Regular => {},
+            FileType::Other => {
+                result.fail(to_path(&path), HandleError::Special("special file"), options);
+                continue;
+            },
+            FileType::Directory | FileType::Symlink => continue,
+        }
+        debug!("Handling file {}", shown);
+        // only the magic of files that can't be binaries is read, as on the host
+        let data = fs.head(&node, scan::MAGIC_LEN)
+            .and_then(|head| if scan::may_be_binary(&head) { fs.read(&node).map(Some) } else { Ok(None) })
+            .map_err(HandleError::IoError);
+        let read = match &data {
+            Ok(None) => stat.size.min(scan::MAGIC_LEN as u64),
+            _ => stat.size,
+        };
+        throttle.file(read);
+        if let Some(progress) = &options.progress {
+            progress.read(read);
+        }
+        let outcome = data.and_then(|data| data.ok_or(HandleError::NotElf))
+            .and_then(|data| scan::parse_data(&data, options))
+            .map(|info| ElfInfo { mode: stat.permissions, ..info });
+        match outcome {
+            Ok(info) => result.files.push(ScannedFile { name: shown, path: to_path(&path), info }),
+            Err(e) => {
+                debug!("Couldn't handle {}: {}", shown, e);
+                if result.fail(to_path(&path), e, options) {
+                    break;
+                }
+            },
+        }
+    }
+    let libraries = libraries(fs).unwrap_or_else(|e| {
+        warn!("Couldn't read the image's libraries: {}", e);
+        BTreeMap::new()
+    });
+    Ok((result, Resolver::from_libraries(libraries)))
+}
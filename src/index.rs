@@ -0,0 +1,47 @@
+//! Saved scan results, so that queries don't need to rescan the tree.
+
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::resolve::{Library, Resolver};
+use crate::metadata::Metadata;
+use crate::scan::{Failure, Scan, ScannedFile};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    pub files: Vec<ScannedFile>,
+    #[serde(default)]
+    pub failures: Vec<Failure>,
+    /// Libraries found for every soname in the closures of `files`
+    pub libraries: BTreeMap<String, Vec<Library>>,
+    /// Missing from indexes saved by older versions
+    #[serde(default)]
+    pub metadata: Option<Metadata>,
+}
+
+impl Index {
+    /// Resolves the closures of the scanned files and puts everything into an index
+    pub fn build(scan: &Scan, resolver: &mut Resolver, metadata: Metadata) -> Self {
+        for file in &scan.files {
+            resolver.closure_of(&file.info);
+        }
+        Index {
+            files: scan.files.clone(),
+            failures: scan.failures.clone(),
+            libraries: resolver.libraries(),
+            metadata: Some(metadata),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(fs::File::open(path)?);
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+}
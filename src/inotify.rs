@@ -0,0 +1,146 @@
+//! Just enough of Linux's inotify to wake `--watch` up as soon as something changes in the
+//! watched directories, instead of waiting for the next rescan. Elsewhere [`Watcher::new`]
+//! fails, and `--watch` falls back to rescanning every `--poll-interval` seconds.
+
+#[cfg(target_os = "linux")]
+pub use self::linux::Watcher;
+#[cfg(not(target_os = "linux"))]
+pub use self::unsupported::Watcher;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::fs::{self, File};
+    use std::io::{self, Read};
+    use std::os::raw::c_int;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+    use libc::{inotify_add_watch, inotify_init1, poll, pollfd, IN_CLOEXEC, POLLIN};
+    use libc::{IN_ATTRIB, IN_CLOSE_WRITE, IN_CREATE, IN_DELETE, IN_DELETE_SELF, IN_MODIFY, IN_MOVED_FROM, IN_MOVED_TO,
+        IN_MOVE_SELF, IN_ONLYDIR};
+    use tracing::debug;
+
+    /// Everything that adds, removes, replaces or changes the permissions of a file
+    const MASK: u32 = IN_MODIFY | IN_ATTRIB | IN_CLOSE_WRITE | IN_MOVED_FROM | IN_MOVED_TO | IN_CREATE | IN_DELETE
+        | IN_DELETE_SELF | IN_MOVE_SELF | IN_ONLYDIR;
+
+    /// Watches on a set of directories
+    pub struct Watcher {
+        file: File,
+    }
+
+    impl Watcher {
+        pub fn new() -> io::Result<Self> {
+            // SAFETY: no pointers involved, and the descriptor returned is owned by the `File` alone
+            let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Watcher { file: unsafe { File::from_raw_fd(fd) } })
+        }
+
+        /// Watches `dir` itself; watching a directory again is a no-op
+        pub fn add(&self, dir: &Path) -> io::Result<()> {
+            let path = CString::new(dir.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            // SAFETY: `path` is a NUL-terminated string that outlives the call
+            if unsafe { inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), MASK) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        /// Watches `dir` and, up to `depth` levels down, the directories in it, without following
+        /// symlinks. Directories that can't be watched are skipped.
+        pub fn add_tree(&self, dir: &Path, depth: Option<usize>) {
+            if let Err(e) = self.add(dir) {
+                debug!("Couldn't watch {}: {}", dir.display(), e);
+                return;
+            }
+            if depth == Some(0) {
+                return;
+            }
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => return,
+            };
+            for entry in entries.flatten() {
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    self.add_tree(&entry.path(), depth.map(|d| d - 1));
+                }
+            }
+        }
+
+        /// Waits up to `timeout` for something to change, then until nothing has changed for
+        /// `settle` so that a file being copied in is rescanned once. Returns whether anything did.
+        pub fn wait(&mut self, timeout: Duration, settle: Duration) -> io::Result<bool> {
+            let deadline = Instant::now() + timeout;
+            let mut changed = false;
+            loop {
+                let left = if changed { settle } else { deadline.saturating_duration_since(Instant::now()) };
+                let mut fds = pollfd { fd: self.file.as_raw_fd(), events: POLLIN, revents: 0 };
+                // SAFETY: `fds` is a single valid `pollfd` for the duration of the call
+                let ready = unsafe { poll(&mut fds, 1, left.as_millis().min(c_int::MAX as u128) as c_int) };
+                match ready {
+                    n if n < 0 => {
+                        let e = io::Error::last_os_error();
+                        if e.kind() != io::ErrorKind::Interrupted {
+                            return Err(e);
+                        }
+                    },
+                    0 => return Ok(changed),
+                    _ => {
+                        // which files changed doesn't matter: the tree is walked again, and only
+                        // the files the cache finds changed are parsed again
+                        let mut buffer = [0; 4096];
+                        changed |= self.file.read(&mut buffer)? > 0;
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use std::io;
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub struct Watcher {}
+
+    impl Watcher {
+        pub fn new() -> io::Result<Self> {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "inotify is only available on Linux"))
+        }
+
+        pub fn add_tree(&self, _dir: &Path, _depth: Option<usize>) {}
+
+        pub fn wait(&mut self, timeout: Duration, _settle: Duration) -> io::Result<bool> {
+            std::thread::sleep(timeout);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn wakes_up_on_changes() {
+        let dir = std::env::temp_dir().join(format!("linux-hs-elf-{}-inotify", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut watcher = Watcher::new().unwrap();
+        watcher.add_tree(&dir, Some(1));
+        assert!(!watcher.wait(Duration::from_millis(50), Duration::from_millis(10)).unwrap());
+
+        fs::write(dir.join("sub/lib.so"), b"\x7fELF").unwrap();
+        assert!(watcher.wait(Duration::from_secs(5), Duration::from_millis(10)).unwrap());
+        assert!(!watcher.wait(Duration::from_millis(50), Duration::from_millis(10)).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+//! Dependency extraction from ELF binaries, and from PE, Mach-O and WebAssembly ones: which
+//! shared libraries they need, where those are found and reports over whole trees of binaries.
+//!
+//! [`get_needed_libs`] reads the libraries a single file needs. [`Scanner`] scans a directory
+//! on the host, in a filesystem image or in a container image the way the `linux-hs-elf` command
+//! does, into a [`Scan`] of the parsed files and a [`Resolver`] finding the libraries they need.
+//! The reports over those are the command's, which [`run`] is.
+
+mod analysis;
+mod archive;
+mod cache;
+mod checkpoint;
+mod cli;
+mod commands;
+mod container;
+mod daemon;
+mod diff;
+mod digest;
+mod dry_run;
+mod elf;
+mod escape;
+mod ext4;
+mod fd_budget;
+mod fixtures;
+mod fleet;
+mod graph;
+mod ignore;
+mod image;
+mod index;
+mod inotify;
+mod loader;
+mod macho;
+mod metadata;
+mod output;
+mod packages;
+mod pattern;
+mod pe;
+mod profiles;
+mod progress;
+mod resolve;
+mod rules;
+mod root;
+mod scan;
+mod signatures;
+mod sqlite;
+mod tar;
+mod throttle;
+mod tui;
+mod wasm;
+mod webhook;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub use crate::cli::run;
+pub use crate::container::{Platform, Rootfs};
+pub use crate::elf::{BinaryFormat, ElfInfo, Hardening, HandleError, Ifuncs, Target, Tls, VersionNeed};
+pub use crate::ignore::PathFilter;
+pub use crate::resolve::{Library, Resolver};
+pub use crate::scan::{Failure, Scan, ScanOptions, ScannedFile, Summary, Walk};
+pub use crate::signatures::Embedded;
+pub use crate::throttle::Limits;
+
+/// Direct dependencies of one binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryDeps {
+    pub path: PathBuf,
+    /// Needed libraries, in the order the loader looks them up
+    pub needed: Vec<String>,
+    /// The binary's own soname if it's a library
+    pub soname: Option<String>,
+    pub interp: Option<String>,
+    /// Run-time library search paths
+    pub rpaths: Vec<String>,
+}
+
+impl From<&ScannedFile> for BinaryDeps {
+    fn from(file: &ScannedFile) -> Self {
+        BinaryDeps {
+            path: file.path.clone(),
+            needed: file.info.needed.clone(),
+            soname: file.info.soname.clone(),
+            interp: file.info.interp.clone(),
+            rpaths: file.info.rpaths.clone(),
+        }
+    }
+}
+
+/// Libraries the binary at `path` needs directly. Only the parts of ELF files naming them are
+/// read; files of other formats are read whole.
+pub fn get_needed_libs<P: AsRef<Path>>(path: P) -> Result<Vec<String>, HandleError> {
+    match elf::read_needed(path.as_ref()) {
+        Err(HandleError::NotElf) => scan::parse_file(path.as_ref(), &ScanOptions::default()).map(|info| info.needed),
+        result => result,
+    }
+}
+
+/// Scans directories of binaries with the same options as the command line
+#[derive(Debug, Clone, Default)]
+pub struct Scanner {
+    pub options: ScanOptions,
+    /// ext2/3/4 image the scanned directories are in, the host filesystem if `None`
+    pub image: Option<PathBuf>,
+    /// Host directory holding the root filesystem the scanned directories are in, like an
+    /// extracted container image; libraries are looked up there too
+    pub sysroot: Option<PathBuf>,
+    /// Container image the scanned directories are in, taking precedence over `image`
+    pub container: Option<Arc<container::Rootfs>>,
+}
+
+impl Scanner {
+    pub fn new(options: ScanOptions) -> Self {
+        Scanner { options, image: None, sysroot: None, container: None }
+    }
+
+    /// Host path of `path` on the scanned filesystem, `path` itself without a sysroot
+    pub fn host_path(&self, path: &Path) -> PathBuf {
+        match &self.sysroot {
+            Some(sysroot) => root::join(sysroot, &escape::path(path)),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Scan options with absolute symlinks pointing into the sysroot
+    fn options(&self) -> ScanOptions {
+        ScanOptions { root: self.sysroot.clone(), ..self.options.clone() }
+    }
+
+    /// Resolver looking up libraries on the scanned host filesystem
+    fn resolver(&self) -> Resolver {
+        match &self.sysroot {
+            Some(sysroot) => Resolver::with_root(sysroot.clone()),
+            None => Resolver::new(),
+        }
+    }
+
+    /// Parses every file in `dir`, returning the results with a resolver finding the libraries
+    /// they need on the same filesystem, `dir` being a path inside the image or sysroot if any
+    pub fn scan(&self, dir: &Path) -> io::Result<(Scan, Resolver)> {
+        match (&self.container, &self.image) {
+            (Some(rootfs), _) => image::scan_fs(&**rootfs, dir, &self.options),
+            (None, Some(image)) => image::scan(image, dir, &self.options),
+            (None, None) => {
+                let dir = self.host_path(dir);
+                fs::read_dir(&dir)?;
+                Ok((scan::scan_dir(&dir, &self.options()), self.resolver()))
+            },
+        }
+    }
+
+    /// Scans every directory in `inputs` and parses the files among them into one result, files
+    /// reached through several inputs being listed once. Inputs in images are all directories.
+    pub fn scan_all(&self, inputs: &[PathBuf]) -> io::Result<(Scan, Resolver)> {
+        let mut result: Option<(Scan, Resolver)> = None;
+        for input in inputs {
+            let host = self.host_path(input);
+            let in_image = self.image.is_some() || self.container.is_some();
+            let scanned = match if in_image { Ok(None) } else { fs::metadata(&host).map(Some) } {
+                Ok(Some(metadata)) if !metadata.is_dir() => Ok((scan::scan_file(&host, &self.options()), self.resolver())),
+                Ok(_) => self.scan(input),
+                Err(e) => Err(e),
+            };
+            let (scan, resolver) = scanned.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", input.display(), e)))?;
+            match &mut result {
+                Some((all, _)) => all.merge(scan),
+                None => result = Some((scan, resolver)),
+            }
+        }
+        Ok(result.unwrap_or_else(|| (Scan::default(), self.resolver())))
+    }
+
+    /// Parses the files at `paths`, skipping the directories among them. Only host directories
+    /// and sysroots can be scanned this way.
+    pub fn scan_files(&self, paths: &[PathBuf]) -> io::Result<(Scan, Resolver)> {
+        if self.image.is_some() || self.container.is_some() {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "Lists of files can't be scanned in images"));
+        }
+        let paths: Vec<PathBuf> = paths.iter().map(|p| self.host_path(p)).collect();
+        Ok((scan::scan_paths(&paths, &self.options()), self.resolver()))
+    }
+
+    /// Parses the whole binary at `path` like a scan does, `path` being inside the sysroot if any.
+    /// Files in images can't be parsed this way.
+    pub fn parse(&self, path: &Path) -> Result<ElfInfo, HandleError> {
+        scan::parse_file(&self.host_path(path), &self.options())
+    }
+
+    /// Direct dependencies of every binary in `dir` that could be analyzed
+    pub fn deps(&self, dir: &Path) -> io::Result<Vec<BinaryDeps>> {
+        let (scan, _) = self.scan(dir)?;
+        Ok(scan.files.iter().map(BinaryDeps::from).collect())
+    }
+}
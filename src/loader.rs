@@ -0,0 +1,519 @@
+//! Where the dynamic linker would load each needed library from, following glibc's search
+//! order: `DT_RPATH` of the object and of the executable unless the object has `DT_RUNPATH`,
+//! `LD_LIBRARY_PATH`, `DT_RUNPATH`, `/etc/ld.so.cache` (or the directories listed in
+//! `/etc/ld.so.conf` if there's no cache), then the default directories. `$ORIGIN` in search
+//! paths stands for the directory of the object they belong to, `$LIB` for the system's library
+//! directory and `$PLATFORM` for the processor type.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use crate::elf::{parse_elf, ElfInfo, Target};
+use crate::escape;
+use crate::pattern::Pattern;
+use crate::resolve::{standard_lib_dirs, Library};
+use crate::root;
+
+const CACHE_MAGIC: &[u8] = b"glibc-ld.so.cache1.1";
+/// Format written before glibc 2.32, possibly followed by the current one
+const OLD_CACHE_MAGIC: &[u8] = b"ld.so-1.7.0";
+/// `include` levels followed in `ld.so.conf` before giving up
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Step of the search order a library was found by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The needed name was a path
+    Path,
+    Rpath,
+    LdLibraryPath,
+    Runpath,
+    Cache,
+    Conf,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Path => "path",
+            Source::Rpath => "rpath",
+            Source::LdLibraryPath => "LD_LIBRARY_PATH",
+            Source::Runpath => "runpath",
+            Source::Cache => "ld.so.cache",
+            Source::Conf => "ld.so.conf",
+            Source::Default => "default path",
+        })
+    }
+}
+
+/// Needed library with the file the loader would use, `found` being `None` if it wouldn't
+/// find any
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub soname: String,
+    pub found: Option<(Library, Source)>,
+    /// Direct dependency of the executable the library is needed through, `None` if the
+    /// executable needs it itself
+    pub via: Option<String>,
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn string_at(data: &[u8], offset: usize) -> Option<String> {
+    let rest = data.get(offset..)?;
+    let end = rest.iter().position(|&b| b == 0)?;
+    Some(escape::bytes(&rest[..end]))
+}
+
+/// Library paths by soname in a little-endian `ld.so.cache`, in the cache's order
+fn parse_cache(data: &[u8]) -> Option<HashMap<String, Vec<String>>> {
+    let start = if data.starts_with(CACHE_MAGIC) {
+        0
+    }
+    else if data.starts_with(OLD_CACHE_MAGIC) {
+        // the current format follows the old entries, 8-byte aligned
+        let count = u32_at(data, 12)? as usize;
+        (16 + count * 12).next_multiple_of(8)
+    }
+    else {
+        return None;
+    };
+    // string offsets are relative to the header of the current format
+    let cache = data.get(start..).filter(|c| c.starts_with(CACHE_MAGIC))?;
+    let count = u32_at(cache, 20)? as usize;
+    let mut libraries: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in (0..count).map(|i| 48 + i * 24) {
+        let key = string_at(cache, u32_at(cache, entry + 4)? as usize)?;
+        let value = string_at(cache, u32_at(cache, entry + 8)? as usize)?;
+        libraries.entry(key).or_default().push(value);
+    }
+    Some(libraries)
+}
+
+/// Directories listed in the `ld.so.conf` at `path`, a host path under `root`, following
+/// `include` lines
+fn parse_conf(root: &Path, path: &Path, depth: usize) -> Vec<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return vec![],
+    };
+    let mut dirs = vec![];
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(included) = line.strip_prefix("include").filter(|r| r.starts_with([' ', '\t'])) {
+            if depth >= MAX_INCLUDE_DEPTH {
+                warn!("Too many nested includes in {}", path.display());
+                continue;
+            }
+            for file in included.split_whitespace().flat_map(|glob| expand(root, path, glob)) {
+                dirs.extend(parse_conf(root, &file, depth + 1));
+            }
+        }
+        else if !line.is_empty() && !line.starts_with("hwcap") {
+            dirs.extend(line.split([':', ',', ' ', '\t']).filter(|d| !d.is_empty()).map(str::to_string));
+        }
+    }
+    dirs
+}
+
+/// Files matched by an `include` glob of the configuration file at `conf`, the glob being
+/// allowed in the last component only
+fn expand(root: &Path, conf: &Path, glob: &str) -> Vec<PathBuf> {
+    let (dir, name) = glob.rsplit_once('/').unwrap_or(("", glob));
+    let dir = if glob.starts_with('/') {
+        root::join(root, dir)
+    }
+    else {
+        conf.parent().unwrap_or(root).join(dir)
+    };
+    let pattern = Pattern::new(name);
+    let mut files: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries.flatten()
+            .filter(|e| pattern.matches(&escape::os_str(&e.file_name())))
+            .map(|e| e.path())
+            .collect(),
+        Err(_) => vec![],
+    };
+    files.sort();
+    files
+}
+
+/// Debian-style multiarch tuple of `target`, naming directories like `/lib/x86_64-linux-gnu`
+fn multiarch_tuple(target: &Target) -> Option<&'static str> {
+    match (target.arch.as_str(), target.bits) {
+        ("x86_64", 64) => Some("x86_64-linux-gnu"),
+        ("x86_64", 32) => Some("x86_64-linux-gnux32"),
+        ("i386", _) => Some("i386-linux-gnu"),
+        ("aarch64", 64) => Some("aarch64-linux-gnu"),
+        ("arm", _) => Some("arm-linux-gnueabihf"),
+        ("powerpc64", _) => Some("powerpc64le-linux-gnu"),
+        ("riscv", 64) => Some("riscv64-linux-gnu"),
+        ("s390", 64) => Some("s390x-linux-gnu"),
+        _ => None,
+    }
+}
+
+/// Value of `$LIB` for `target` on the filesystem at `root`: its multiarch directory if there's
+/// one, `lib64` or `lib` otherwise
+pub fn lib_token(root: &Path, target: &Target) -> String {
+    if let Some(tuple) = multiarch_tuple(target) {
+        if root::join(root, &format!("/lib/{}", tuple)).is_dir() {
+            return format!("lib/{}", tuple);
+        }
+    }
+    if target.bits == 64 && root::join(root, "/lib64").is_dir() {
+        "lib64".to_string()
+    }
+    else {
+        "lib".to_string()
+    }
+}
+
+/// Value of `$PLATFORM`, the processor type the kernel passes in `AT_PLATFORM`
+fn platform_token(target: &Target) -> &str {
+    match target.arch.as_str() {
+        "i386" => "i686",
+        arch => arch,
+    }
+}
+
+/// `dir` with the `$NAME` and `${NAME}` tokens named in `values` replaced, other `$`s kept
+fn substitute(dir: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::new();
+    let mut rest = dir;
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.split_once('}') {
+                Some(split) => split,
+                None => ("", rest),
+            },
+            None => {
+                let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                rest.split_at(end)
+            },
+        };
+        match values.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => {
+                result.push_str(value);
+                rest = after;
+            },
+            None => result.push('$'),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Search path entry of an object in the directory `origin` with its `$ORIGIN`, `$LIB` and
+/// `$PLATFORM` tokens expanded for `target` on the filesystem at `root`
+pub fn expand_tokens(root: &Path, dir: &str, origin: &Path, target: &Target) -> String {
+    if !dir.contains('$') {
+        return dir.to_string();
+    }
+    let lib = lib_token(root, target);
+    let origin = escape::path(origin);
+    substitute(dir, &[("ORIGIN", &origin), ("LIB", &lib), ("PLATFORM", platform_token(target))])
+}
+
+/// Finds needed libraries the way the dynamic linker of the analyzed system would
+pub struct Loader {
+    /// Root of the analyzed filesystem, `/` for the host
+    root: PathBuf,
+    ld_library_path: Vec<String>,
+    /// Contents of `ld.so.cache`, `None` if there's none
+    cache: Option<HashMap<String, Vec<String>>>,
+    conf_dirs: Vec<String>,
+    default_dirs: Vec<PathBuf>,
+    /// Files parsed so far by host path without their symbols, `None` if they aren't ELF
+    /// libraries
+    parsed: HashMap<PathBuf, Option<ElfInfo>>,
+}
+
+impl Loader {
+    /// Loader of the filesystem tree at `root` with `LD_LIBRARY_PATH` taken from the environment
+    pub fn new(root: &Path) -> Self {
+        let ld_library_path = env::var("LD_LIBRARY_PATH").unwrap_or_default()
+            .split([':', ';'])
+            .filter(|d| !d.is_empty())
+            .map(str::to_string)
+            .collect();
+        let cache = fs::read(root::join(root, "/etc/ld.so.cache")).ok().and_then(|data| {
+            let cache = parse_cache(&data);
+            if cache.is_none() {
+                warn!("Couldn't read ld.so.cache, using ld.so.conf");
+            }
+            cache
+        });
+        Loader {
+            root: root.to_path_buf(),
+            ld_library_path,
+            cache,
+            conf_dirs: parse_conf(root, &root::join(root, "/etc/ld.so.conf"), 0),
+            default_dirs: standard_lib_dirs(root),
+            parsed: HashMap::new(),
+        }
+    }
+
+    /// Host path of a search path entry of an object for `target` in `origin`, a host path
+    fn search_dir(&self, dir: &str, origin: &Path, target: &Target) -> PathBuf {
+        let dir = if dir.contains('$') {
+            substitute(dir, &[("LIB", &lib_token(&self.root, target)), ("PLATFORM", platform_token(target))])
+        }
+        else {
+            dir.to_string()
+        };
+        let with_origin = substitute(&dir, &[("ORIGIN", &escape::path(origin))]);
+        if with_origin != dir {
+            PathBuf::from(escape::unescape_os_str(&with_origin))
+        }
+        else if dir.starts_with('/') {
+            root::join(&self.root, &dir)
+        }
+        else {
+            PathBuf::from(escape::unescape_os_str(&dir))
+        }
+    }
+
+    /// Directory a search path entry of the object at `path` stands for on the analyzed
+    /// filesystem, tokens expanded
+    pub fn expand(&self, dir: &str, path: &Path, target: &Target) -> String {
+        let real = root::canonicalize(&self.root, path).unwrap_or_else(|| path.to_path_buf());
+        let origin = real.parent().and_then(|o| o.strip_prefix(&self.root).ok()).unwrap_or(Path::new(""));
+        expand_tokens(&self.root, dir, &Path::new("/").join(origin), target)
+    }
+
+    /// `path` if it's a library loadable by `object`
+    fn load(&mut self, path: PathBuf, object: &ElfInfo) -> Option<Library> {
+        let real = root::canonicalize(&self.root, &path).filter(|p| p.is_file())?;
+        let info = self.parsed.entry(real.clone())
+            .or_insert_with(|| {
+                let info = parse_elf(&real).map_err(|e| debug!("Skipping {}: {}", real.display(), e)).ok()?;
+                // libraries are cloned into every result, the symbols aren't needed for loading
+                Some(ElfInfo { imports: vec![], weak_imports: vec![], exports: vec![], version_needs: vec![], dlopen_strings: vec![], ..info })
+            })
+            .clone()?;
+        let library = Library { path, info };
+        library.compatible_with(&object.target()).then_some(library)
+    }
+
+    /// Where the direct dependency `soname` of the object at `path` is loaded from, `exe` being
+    /// the executable the object is loaded into if the object isn't the executable itself
+    pub fn resolve(&mut self, soname: &str, path: &Path, object: &ElfInfo, exe: Option<(&Path, &ElfInfo)>) -> Option<(Library, Source)> {
+        if soname.contains('/') {
+            return self.load(root::join(&self.root, soname), object).map(|l| (l, Source::Path));
+        }
+        let origin = |p: &Path| p.parent().map(Path::to_path_buf).unwrap_or_default();
+        // the executable's origin is where it really is, since the kernel resolves its symlinks
+        let exe_origin = |p: &Path| origin(&root::canonicalize(&self.root, p).unwrap_or_else(|| p.to_path_buf()));
+        let object_origin = if exe.is_some() { origin(path) } else { exe_origin(path) };
+        let target = object.target();
+        let mut searches: Vec<(Source, Vec<PathBuf>)> = vec![];
+        if object.runpaths.is_empty() {
+            let mut dirs: Vec<PathBuf> = object.rpaths.iter().map(|d| self.search_dir(d, &object_origin, &target)).collect();
+            if let Some((exe_path, exe)) = exe.filter(|(_, exe)| exe.runpaths.is_empty()) {
+                dirs.extend(exe.rpaths.iter().map(|d| self.search_dir(d, &exe_origin(exe_path), &target)));
+            }
+            searches.push((Source::Rpath, dirs));
+        }
+        searches.push((Source::LdLibraryPath, self.ld_library_path.iter().map(|d| self.search_dir(d, Path::new(""), &target)).collect()));
+        searches.push((Source::Runpath, object.runpaths.iter().map(|d| self.search_dir(d, &object_origin, &target)).collect()));
+        for (source, dirs) in searches {
+            for dir in dirs {
+                if let Some(library) = self.load(dir.join(escape::unescape_os_str(soname)), object) {
+                    return Some((library, source));
+                }
+            }
+        }
+        let cached: Option<Vec<PathBuf>> = self.cache.as_ref()
+            .map(|cache| cache.get(soname).into_iter().flatten().map(|p| root::join(&self.root, p)).collect());
+        let (source, candidates) = match cached {
+            Some(paths) => (Source::Cache, paths),
+            None => {
+                let dirs = self.conf_dirs.iter().map(|d| root::join(&self.root, d));
+                (Source::Conf, dirs.map(|d| d.join(escape::unescape_os_str(soname))).collect())
+            },
+        };
+        for candidate in candidates {
+            if let Some(library) = self.load(candidate, object) {
+                return Some((library, source));
+            }
+        }
+        let defaults: Vec<PathBuf> = self.default_dirs.iter().map(|d| d.join(escape::unescape_os_str(soname))).collect();
+        defaults.into_iter().find_map(|candidate| self.load(candidate, object)).map(|l| (l, Source::Default))
+    }
+
+    /// Direct dependencies of the executable `info` at `path`
+    pub fn needed(&mut self, path: &Path, info: &ElfInfo) -> Vec<Resolution> {
+        info.needed.iter()
+            .map(|soname| Resolution { soname: soname.clone(), found: self.resolve(soname, path, info, None), via: None })
+            .collect()
+    }
+
+    /// Every library loaded along with the executable `info` at `path`, breadth-first like the
+    /// loader; a soname already loaded isn't searched for again
+    pub fn closure(&mut self, path: &Path, info: &ElfInfo) -> Vec<Resolution> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut result = vec![];
+        let mut queue: VecDeque<(String, PathBuf, ElfInfo, Option<String>)> = info.needed.iter()
+            .map(|soname| (soname.clone(), path.to_path_buf(), info.clone(), None))
+            .collect();
+        while let Some((soname, parent_path, parent, via)) = queue.pop_front() {
+            if !seen.insert(soname.clone()) {
+                continue;
+            }
+            let exe = (parent_path != path).then_some((path, info));
+            let found = self.resolve(&soname, &parent_path, &parent, exe);
+            if let Some((library, _)) = &found {
+                let via = via.clone().unwrap_or_else(|| soname.clone());
+                queue.extend(library.info.needed.iter()
+                    .map(|n| (n.clone(), library.path.clone(), library.info.clone(), Some(via.clone()))));
+            }
+            result.push(Resolution { soname, found, via });
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("linux-hs-elf-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn target(arch: &str, bits: u8) -> Target {
+        Target { arch: arch.to_string(), bits }
+    }
+
+    #[test]
+    fn substitutes_tokens() {
+        let values = [("ORIGIN", "/opt/app/bin"), ("LIB", "lib64"), ("PLATFORM", "x86_64")];
+        for (dir, expected) in [
+            ("/usr/lib", "/usr/lib"),
+            ("$ORIGIN/../lib", "/opt/app/bin/../lib"),
+            ("${ORIGIN}/../lib", "/opt/app/bin/../lib"),
+            ("$ORIGINAL/lib", "$ORIGINAL/lib"),
+            ("/usr/${LIB}/tls", "/usr/lib64/tls"),
+            ("/usr/$LIB/$PLATFORM", "/usr/lib64/x86_64"),
+            ("/opt/$X/lib", "/opt/$X/lib"),
+            ("/opt/${X}/lib", "/opt/${X}/lib"),
+            ("/opt/${ORIGIN/lib", "/opt/${ORIGIN/lib"),
+            ("/opt/lib$", "/opt/lib$"),
+            ("$$ORIGIN", "$/opt/app/bin"),
+        ] {
+            assert_eq!(substitute(dir, &values), expected, "{}", dir);
+        }
+    }
+
+    #[test]
+    fn expands_tokens_for_the_target() {
+        let root = temp_root("loader-tokens");
+        fs::create_dir_all(root.join("lib/x86_64-linux-gnu")).unwrap();
+        fs::create_dir_all(root.join("lib64")).unwrap();
+        let origin = Path::new("/opt/app");
+        for (dir, target, expected) in [
+            ("$ORIGIN/lib", target("x86_64", 64), "/opt/app/lib"),
+            ("/${LIB}", target("x86_64", 64), "/lib/x86_64-linux-gnu"),
+            ("/$LIB", target("aarch64", 64), "/lib64"),
+            ("/$LIB", target("arm", 32), "/lib"),
+            ("/usr/lib/$PLATFORM", target("i386", 32), "/usr/lib/i686"),
+            ("/usr/lib/$PLATFORM", target("aarch64", 64), "/usr/lib/aarch64"),
+            ("/usr/lib/$X", target("x86_64", 64), "/usr/lib/$X"),
+            ("/usr/lib/${LIB", target("x86_64", 64), "/usr/lib/${LIB"),
+        ] {
+            assert_eq!(expand_tokens(&root, dir, origin, &target), expected, "{} for {}", dir, target);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Cache in the current format with `entries` as (soname, path) pairs
+    fn cache(entries: &[(&str, &str)]) -> Vec<u8> {
+        let strings_start = 48 + entries.len() * 24;
+        let (mut table, mut strings) = (vec![], vec![]);
+        for (key, value) in entries {
+            table.extend_from_slice(&0x0303i32.to_le_bytes());
+            for s in [key, value] {
+                table.extend_from_slice(&((strings_start + strings.len()) as u32).to_le_bytes());
+                strings.extend_from_slice(s.as_bytes());
+                strings.push(0);
+            }
+            table.extend_from_slice(&[0; 12]);
+        }
+        let mut data = CACHE_MAGIC.to_vec();
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        data.resize(48, 0);
+        data.extend(table);
+        data.extend(strings);
+        data
+    }
+
+    #[test]
+    fn parses_caches() {
+        let entries = [
+            ("libc.so.6", "/lib/x86_64-linux-gnu/libc.so.6"),
+            ("libz.so.1", "/usr/lib/x86_64-linux-gnu/libz.so.1"),
+            ("libc.so.6", "/lib32/libc.so.6"),
+        ];
+        let new = cache(&entries);
+        // old entries are (flags, key, value) with offsets into the string table of the
+        // current format, which parse_cache skips
+        let mut old = OLD_CACHE_MAGIC.to_vec();
+        old.push(0);
+        old.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        old.resize(16 + entries.len() * 12, 0);
+        old.resize(old.len().next_multiple_of(8), 0);
+        old.extend_from_slice(&new);
+        for data in [&new, &old] {
+            let libraries = parse_cache(data).unwrap();
+            assert_eq!(libraries.len(), 2);
+            assert_eq!(libraries["libc.so.6"], ["/lib/x86_64-linux-gnu/libc.so.6", "/lib32/libc.so.6"]);
+            assert_eq!(libraries["libz.so.1"], ["/usr/lib/x86_64-linux-gnu/libz.so.1"]);
+        }
+        // the old format alone isn't read
+        let mut old_only = OLD_CACHE_MAGIC.to_vec();
+        old_only.push(0);
+        old_only.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(parse_cache(&old_only), None);
+        assert_eq!(parse_cache(b"not a cache"), None);
+        assert_eq!(parse_cache(&new[..new.len() - 10]), None);
+        assert_eq!(parse_cache(&new[..60]), None);
+        assert_eq!(parse_cache(&cache(&[])).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn follows_includes() {
+        let root = temp_root("loader-conf");
+        fs::create_dir_all(root.join("etc/ld.so.conf.d/nested")).unwrap();
+        fs::write(root.join("etc/ld.so.conf"), "include /etc/ld.so.conf.d/*.conf\n/usr/local/lib # local\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/b.conf"), "/opt/b/lib:/opt/b/lib64\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/a.conf"), "hwcap 0 nosegneg\ninclude nested/*.conf\n/opt/a/lib\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/nested/c.conf"), "# comment\n\n/opt/c/lib, /opt/c/lib64\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/ignored.txt"), "/opt/ignored\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/include.conf"), "includes/lib\n").unwrap();
+        assert_eq!(parse_conf(&root, &root.join("etc/ld.so.conf"), 0), [
+            "/opt/c/lib", "/opt/c/lib64", "/opt/a/lib",
+            "/opt/b/lib", "/opt/b/lib64",
+            "includes/lib",
+            "/usr/local/lib",
+        ]);
+        assert!(parse_conf(&root, &root.join("etc/missing.conf"), 0).is_empty());
+
+        // a file including itself is read once per level up to the limit
+        fs::write(root.join("etc/loop.conf"), "include loop.conf\n/opt/loop\n").unwrap();
+        assert_eq!(parse_conf(&root, &root.join("etc/loop.conf"), 0), vec!["/opt/loop"; MAX_INCLUDE_DEPTH + 1]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
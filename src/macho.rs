@@ -0,0 +1,192 @@
+//! Dylib dependencies of Mach-O files found in mixed artifact directories.
+
+use object::Endianness;
+use object::macho::{self, FatArch32, FatArch64, MachHeader32, MachHeader64};
+use object::read::macho::{FatArch, FatHeader, LoadCommandVariant, MachHeader};
+use tracing::debug;
+use crate::elf::{BinaryFormat, ElfInfo, HandleError};
+use crate::escape;
+
+/// Conventional name of a `cputype` value
+fn cpu_name(cputype: u32) -> String {
+    let name = match cputype {
+        macho::CPU_TYPE_X86 => "i386",
+        macho::CPU_TYPE_X86_64 => "x86_64",
+        macho::CPU_TYPE_ARM => "arm",
+        macho::CPU_TYPE_ARM64 => "aarch64",
+        macho::CPU_TYPE_POWERPC => "powerpc",
+        macho::CPU_TYPE_POWERPC64 => "powerpc64",
+        _ => return format!("cputype-{}", cputype),
+    };
+    name.to_string()
+}
+
+fn extract_info<Mach>(bin_data: &[u8], header: &Mach, size: u64) -> Result<ElfInfo, HandleError>
+where
+    Mach: MachHeader<Endian = Endianness>,
+{
+    let endian = header.endian().map_err(HandleError::ObjectReadError)?;
+    let mut commands = header.load_commands(endian, bin_data, 0)
+        .map_err(HandleError::ObjectReadError)?;
+    let mut info = ElfInfo {
+        format: BinaryFormat::MachO,
+        arch: cpu_name(header.cputype(endian)),
+        bits: if header.is_type_64() { 64 } else { 32 },
+        size,
+        ..ElfInfo::default()
+    };
+    while let Some(command) = commands.next().map_err(HandleError::ObjectReadError)? {
+        let string = |s| command.string(endian, s)
+            .map(escape::bytes)
+            .map_err(HandleError::ObjectReadError);
+        // LC_LOAD_DYLIB and its weak, reexport and upward variants
+        match command.variant().map_err(HandleError::ObjectReadError)? {
+            LoadCommandVariant::Dylib(dylib) => info.needed.push(string(dylib.dylib.name)?),
+            LoadCommandVariant::IdDylib(dylib) => info.soname = Some(string(dylib.dylib.name)?),
+            LoadCommandVariant::Rpath(rpath) => info.rpaths.push(string(rpath.path)?),
+            _ => {},
+        }
+    }
+    Ok(info)
+}
+
+/// Parses the load commands of a Mach-O file already read into memory. Of universal binaries
+/// only the first architecture is read.
+pub fn parse_macho_data(bin_data: &[u8]) -> Result<ElfInfo, HandleError> {
+    let size = bin_data.len() as u64;
+    let slice = match object::FileKind::parse(bin_data).map_err(HandleError::ObjectReadError)? {
+        object::FileKind::MachOFat32 => {
+            let arches = FatHeader::parse_arch32(bin_data).map_err(HandleError::ObjectReadError)?;
+            let arch: &FatArch32 = arches.first().ok_or(HandleError::Malformed("Empty universal binary"))?;
+            arch.data(bin_data).map_err(HandleError::ObjectReadError)?
+        },
+        object::FileKind::MachOFat64 => {
+            let arches = FatHeader::parse_arch64(bin_data).map_err(HandleError::ObjectReadError)?;
+            let arch: &FatArch64 = arches.first().ok_or(HandleError::Malformed("Empty universal binary"))?;
+            arch.data(bin_data).map_err(HandleError::ObjectReadError)?
+        },
+        _ => bin_data,
+    };
+    match object::FileKind::parse(slice).map_err(HandleError::ObjectReadError)? {
+        object::FileKind::MachO32 => {
+            debug!("Parsing macho32 file");
+            let header = MachHeader32::<Endianness>::parse(slice, 0).map_err(HandleError::ObjectReadError)?;
+            extract_info(slice, header, size)
+        },
+        object::FileKind::MachO64 => {
+            debug!("Parsing macho64 file");
+            let header = MachHeader64::<Endianness>::parse(slice, 0).map_err(HandleError::ObjectReadError)?;
+            extract_info(slice, header, size)
+        },
+        _ => Err(HandleError::NotElf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(big_endian: bool, value: u32) -> [u8; 4] {
+        if big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+
+    /// Load command `cmd` of the fixed fields `fields`, followed by `name` padded with NULs to a
+    /// multiple of 8 bytes
+    fn command(big_endian: bool, cmd: u32, fields: &[u32], name: &str) -> Vec<u8> {
+        let size = (8 + 4 * fields.len() + name.len() + 1).next_multiple_of(8);
+        let mut command: Vec<u8> = [cmd, size as u32].iter().chain(fields).flat_map(|&v| word(big_endian, v)).collect();
+        command.extend_from_slice(name.as_bytes());
+        command.resize(size, 0);
+        command
+    }
+
+    fn dylib(big_endian: bool, cmd: u32, name: &str) -> Vec<u8> {
+        command(big_endian, cmd, &[24, 2, 0x10000, 0x10000], name)
+    }
+
+    fn file(bits: u8, big_endian: bool, cputype: u32, commands: &[Vec<u8>]) -> Vec<u8> {
+        let magic = if bits == 64 { macho::MH_MAGIC_64 } else { macho::MH_MAGIC };
+        let size = commands.iter().map(Vec::len).sum::<usize>() as u32;
+        let mut fields = vec![magic, cputype, 0, macho::MH_DYLIB, commands.len() as u32, size, 0];
+        if bits == 64 {
+            fields.push(0);
+        }
+        let mut file: Vec<u8> = fields.into_iter().flat_map(|v| word(big_endian, v)).collect();
+        file.extend(commands.concat());
+        file
+    }
+
+    /// Universal binary of `slices`, given with their cputype
+    fn fat(slices: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut header = vec![macho::FAT_MAGIC, slices.len() as u32];
+        let mut offset = 8 + 20 * slices.len();
+        for (cputype, slice) in slices {
+            header.extend([*cputype, 0, offset as u32, slice.len() as u32, 0]);
+            offset += slice.len();
+        }
+        let mut file: Vec<u8> = header.into_iter().flat_map(u32::to_be_bytes).collect();
+        slices.iter().for_each(|(_, slice)| file.extend_from_slice(slice));
+        file
+    }
+
+    fn dylib64() -> Vec<u8> {
+        file(64, false, macho::CPU_TYPE_X86_64, &[
+            dylib(false, macho::LC_ID_DYLIB, "@rpath/libfoo.1.dylib"),
+            dylib(false, macho::LC_LOAD_DYLIB, "/usr/lib/libSystem.B.dylib"),
+            command(false, macho::LC_UUID, &[1, 2, 3, 4], ""),
+            dylib(false, macho::LC_LOAD_WEAK_DYLIB, "@rpath/libbar.dylib"),
+            command(false, macho::LC_RPATH, &[12], "@loader_path/../lib"),
+        ])
+    }
+
+    fn kind<T>(result: Result<T, HandleError>) -> &'static str {
+        result.err().map_or("", |e| e.kind())
+    }
+
+    #[test]
+    fn reads_load_commands() {
+        let data = dylib64();
+        let info = parse_macho_data(&data).unwrap();
+        assert_eq!(info.format, BinaryFormat::MachO);
+        assert_eq!((info.arch.as_str(), info.bits, info.size), ("x86_64", 64, data.len() as u64));
+        assert_eq!(info.soname.as_deref(), Some("@rpath/libfoo.1.dylib"));
+        assert_eq!(info.needed, ["/usr/lib/libSystem.B.dylib", "@rpath/libbar.dylib"]);
+        assert_eq!(info.rpaths, ["@loader_path/../lib"]);
+
+        // only the first slice of a universal binary is read
+        let ppc = file(32, true, macho::CPU_TYPE_POWERPC, &[dylib(true, macho::LC_LOAD_DYLIB, "/usr/lib/libSystem.B.dylib")]);
+        let universal = fat(&[(macho::CPU_TYPE_POWERPC, &ppc), (macho::CPU_TYPE_X86_64, &data)]);
+        let info = parse_macho_data(&universal).unwrap();
+        assert_eq!((info.arch.as_str(), info.bits, info.size), ("powerpc", 32, universal.len() as u64));
+        assert_eq!((info.needed.as_slice(), info.soname), (&["/usr/lib/libSystem.B.dylib".to_string()][..], None));
+        assert_eq!(parse_macho_data(&file(64, false, 0x1234, &[])).unwrap().arch, "cputype-4660");
+    }
+
+    #[test]
+    fn malformed() {
+        let data = dylib64();
+        assert_eq!(kind(parse_macho_data(&data[..data.len() - 20])), "parse");
+        assert_eq!(kind(parse_macho_data(&data[..16])), "parse");
+        // more commands than there are
+        let mut many = data.clone();
+        many[16..20].copy_from_slice(&100u32.to_le_bytes());
+        assert_eq!(kind(parse_macho_data(&many)), "parse");
+        // a command running past the others, or a name outside its command
+        let mut long = data.clone();
+        long[32 + 4..32 + 8].copy_from_slice(&4096u32.to_le_bytes());
+        assert_eq!(kind(parse_macho_data(&long)), "parse");
+        let mut name = data.clone();
+        name[32 + 8..32 + 12].copy_from_slice(&200u32.to_le_bytes());
+        assert_eq!(kind(parse_macho_data(&name)), "parse");
+        // a slice past the end of the universal binary, or none at all
+        let mut universal = fat(&[(macho::CPU_TYPE_X86_64, &data)]);
+        universal[8 + 12..8 + 16].copy_from_slice(&100_000u32.to_be_bytes());
+        assert_eq!(kind(parse_macho_data(&universal)), "parse");
+        let mut empty = fat(&[]);
+        empty.resize(64, 0);
+        assert_eq!(kind(parse_macho_data(&empty)), "malformed");
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(64, 0);
+        assert_eq!(kind(parse_macho_data(&elf)), "not-elf");
+    }
+}
@@ -0,0 +1,57 @@
+//! Where, when and how a scan was made, embedded in machine-readable output so that results
+//! collected from many hosts or images stay attributable once aggregated.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::escape;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metadata {
+    /// `--label`, or the hostname without one
+    pub label: String,
+    pub host: String,
+    /// Scanned directory
+    pub root: String,
+    /// Filesystem image the directory is in, if any
+    pub image: Option<String>,
+    /// Seconds since the Unix epoch, `SOURCE_DATE_EPOCH` if it's set
+    pub time: u64,
+    pub version: String,
+    /// Command line arguments of the run
+    pub arguments: Vec<String>,
+}
+
+fn hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `SOURCE_DATE_EPOCH`, so that output stays reproducible, or the current time
+fn time() -> u64 {
+    match env::var("SOURCE_DATE_EPOCH").ok().and_then(|t| t.trim().parse().ok()) {
+        Some(time) => time,
+        None => SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+    }
+}
+
+impl Metadata {
+    /// Metadata of a scan of `root` made now by this process
+    pub fn new(label: Option<&str>, root: &Path, image: Option<&Path>) -> Self {
+        let host = hostname();
+        Metadata {
+            label: label.map_or_else(|| host.clone(), str::to_string),
+            host,
+            root: escape::path(root),
+            image: image.map(escape::path),
+            time: time(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            arguments: env::args_os().skip(1).map(|a| escape::os_str(&a)).collect(),
+        }
+    }
+}
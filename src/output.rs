@@ -0,0 +1,12 @@
+//! Ways of presenting the scan results.
+
+pub mod annotations;
+pub mod csv;
+pub mod cypher;
+pub mod diagram;
+pub mod graph_json;
+pub mod html_graph;
+pub mod json;
+pub mod parquet;
+pub mod sbom;
+pub mod text;
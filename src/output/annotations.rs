@@ -0,0 +1,126 @@
+//! Findings as CI log annotations: GitHub Actions workflow commands
+//! (`::error file=...::message`) or compiler-style `file: error: message` lines that editors and
+//! other CI systems parse with an `errorformat`.
+
+use std::io::{self, Write};
+use tracing::warn;
+use crate::analysis::unsafe_symbols::Denylist;
+use crate::escape;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::rules::{Rule, Severity};
+use crate::scan::Scan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Notice => "notice",
+        }
+    }
+}
+
+/// Problem with one binary
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub level: Level,
+    pub file: String,
+    pub title: String,
+    pub message: String,
+}
+
+/// Missing dependencies and files that couldn't be analyzed, plus imports on `denylist` if given
+/// and violations of `rules`
+pub fn findings(
+    scan: &Scan,
+    resolver: &mut Resolver,
+    filter: &LibFilter,
+    denylist: Option<&Denylist>,
+    rules: &[Rule],
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    for file in &scan.files {
+        let path = escape::path(&file.path);
+        let target = file.info.target();
+        for lib in file.info.needed.iter().filter(|l| !filter.is_hidden(l)) {
+            if resolver.resolve(lib, &target).is_none() {
+                findings.push(Finding {
+                    level: Level::Error,
+                    file: path.clone(),
+                    title: "missing dependency".to_string(),
+                    message: format!("needs {}, which isn't found for {}", lib, target),
+                });
+            }
+        }
+        for (symbol, reason) in denylist.into_iter().flat_map(|d| d.flagged(&file.info.imports)) {
+            let message = if reason.is_empty() {
+                format!("imports {}", symbol)
+            }
+            else {
+                format!("imports {}: {}", symbol, reason)
+            };
+            findings.push(Finding { level: Level::Warning, file: path.clone(), title: "unsafe symbol".to_string(), message });
+        }
+        for rule in rules {
+            match rule.matches(file) {
+                Ok(true) => findings.push(Finding {
+                    level: match rule.severity {
+                        Severity::Error => Level::Error,
+                        Severity::Warning => Level::Warning,
+                        Severity::Notice => Level::Notice,
+                    },
+                    file: path.clone(),
+                    title: rule.name.clone(),
+                    message: rule.message.clone(),
+                }),
+                Ok(false) => {},
+                Err(e) => warn!("Couldn't evaluate rule {} for {}: {}", rule.name, file.name, e),
+            }
+        }
+    }
+    for failure in scan.failures.iter().filter(|f| !f.is_expected()) {
+        findings.push(Finding {
+            level: Level::Warning,
+            file: escape::path(&failure.path),
+            title: "not analyzed".to_string(),
+            message: failure.message.clone(),
+        });
+    }
+    findings
+}
+
+/// Escapes the message of a workflow command
+fn gh_data(s: &str) -> String {
+    s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a property value of a workflow command
+fn gh_property(s: &str) -> String {
+    gh_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+pub fn write_github<W: Write>(mut out: W, findings: &[Finding]) -> io::Result<()> {
+    for f in findings {
+        writeln!(
+            out,
+            "::{} file={},title={}::{}",
+            f.level.name(), gh_property(&f.file), gh_property(&f.title), gh_data(&f.message)
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_errorformat<W: Write>(mut out: W, findings: &[Finding]) -> io::Result<()> {
+    for f in findings {
+        writeln!(out, "{}: {}: {}: {}", f.file, f.level.name(), f.title, f.message.replace('\n', " "))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! CSV with one row per needed library of every executable.
+
+use std::io::{self, Write};
+use crate::escape;
+use crate::output::graph_json::csv_field;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Writes the rows, leaving out the libraries hidden by `filter`. Executables needing no
+/// library get a row with empty library columns.
+pub fn write<W: Write>(mut out: W, files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter) -> io::Result<()> {
+    writeln!(out, "executable,path,format,library,resolved")?;
+    for file in files {
+        let target = file.info.target();
+        let exe = format!(
+            "{},{},{}",
+            csv_field(&file.name), csv_field(&escape::path(&file.path)), file.info.format
+        );
+        let mut needed = file.info.needed.iter().filter(|l| !filter.is_hidden(l)).peekable();
+        if needed.peek().is_none() {
+            writeln!(out, "{},,", exe)?;
+        }
+        for lib in needed {
+            let resolved = resolver.resolve(lib, &target).map(|l| escape::path(&l.path)).unwrap_or_default();
+            writeln!(out, "{},{},{}", exe, csv_field(lib), csv_field(&resolved))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Cypher statements loading the dependency graph into Neo4j. Statements use `MERGE` keyed on
+//! the executable's path and the library's soname and architecture, so scans of many hosts can
+//! be loaded into one database. A `Scan` node carrying the scan's metadata is linked to the
+//! executables it found, telling the hosts apart.
+
+use std::io::{self, Write};
+use crate::escape;
+use crate::graph::{DepGraph, Node, NodeKind};
+use crate::metadata::Metadata;
+
+/// Single-quoted Cypher string literal
+fn literal(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Pattern matching the node by its key properties
+fn pattern(var: &str, node: &Node) -> String {
+    match node.kind {
+        NodeKind::Scanned => format!(
+            "({}:Executable {{path: {}}})",
+            var, literal(&node.path.as_deref().map(escape::path).unwrap_or_default())
+        ),
+        NodeKind::Library => format!(
+            "({}:Library {{name: {}, arch: {}}})",
+            var, literal(&node.name), literal(node.arch.as_deref().unwrap_or_default())
+        ),
+    }
+}
+
+/// Pattern matching the scan node by its key properties
+fn scan_pattern(metadata: &Metadata) -> String {
+    format!("(s:Scan {{label: {}, time: {}}})", literal(&metadata.label), metadata.time)
+}
+
+pub fn write<W: Write>(mut out: W, graph: &DepGraph, metadata: &Metadata) -> io::Result<()> {
+    let mut props = vec![
+        format!("s.host = {}", literal(&metadata.host)),
+        format!("s.root = {}", literal(&metadata.root)),
+        format!("s.version = {}", literal(&metadata.version)),
+        format!("s.arguments = [{}]", metadata.arguments.iter().map(|a| literal(a)).collect::<Vec<_>>().join(", ")),
+    ];
+    if let Some(image) = &metadata.image {
+        props.push(format!("s.image = {}", literal(image)));
+    }
+    writeln!(out, "MERGE {} SET {};", scan_pattern(metadata), props.join(", "))?;
+    for node in &graph.nodes {
+        let mut props = vec![];
+        if node.kind == NodeKind::Scanned {
+            props.push(format!("n.name = {}", literal(&node.name)));
+            props.push(format!("n.arch = {}", literal(node.arch.as_deref().unwrap_or_default())));
+        }
+        else if let Some(path) = &node.path {
+            props.push(format!("n.path = {}", literal(&escape::path(path))));
+        }
+        if let Some(size) = node.size {
+            props.push(format!("n.size = {}", size));
+        }
+        if node.kind == NodeKind::Library {
+            props.push(format!("n.missing = {}", node.missing));
+        }
+        writeln!(out, "MERGE {} SET {};", pattern("n", node), props.join(", "))?;
+    }
+    for (from, targets) in graph.edges.iter().enumerate() {
+        for &to in targets {
+            writeln!(
+                out,
+                "MATCH {}, {} MERGE (a)-[:NEEDS]->(b);",
+                pattern("a", &graph.nodes[from]), pattern("b", &graph.nodes[to])
+            )?;
+        }
+    }
+    for node in graph.nodes.iter().filter(|n| n.kind == NodeKind::Scanned) {
+        writeln!(out, "MATCH {}, {} MERGE (s)-[:FOUND]->(e);", scan_pattern(metadata), pattern("e", node))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! The dependency graph as a Graphviz DOT or Mermaid diagram: executables are boxes, libraries
+//! ellipses (Mermaid stadiums). Only the edges from scanned files are drawn unless the edges
+//! between libraries are asked for too.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use clap::ArgEnum;
+use crate::graph::{DepGraph, NodeKind};
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub syntax: Syntax,
+    /// Draw the edges from libraries to the libraries they need
+    pub transitive: bool,
+    /// One node per soname: a library's builds for different architectures and the scanned
+    /// file carrying the soname are drawn as one
+    pub collapse: bool,
+    /// Draw libraries that couldn't be found, and the edges to them, in red
+    pub highlight_missing: bool,
+}
+
+/// Node of the diagram, standing for one or more graph nodes
+struct Shape<'a> {
+    label: &'a str,
+    library: bool,
+    missing: bool,
+}
+
+/// Nodes and edges to draw, by diagram node
+fn layout<'a>(graph: &'a DepGraph, options: &Options) -> (Vec<Shape<'a>>, Vec<(usize, usize)>) {
+    let mut shapes: Vec<Shape> = vec![];
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut shape_of = vec![None; graph.nodes.len()];
+    // without library edges, libraries that no scanned file needs directly aren't drawn
+    let mut drawn: Vec<bool> = graph.nodes.iter().map(|n| options.transitive || n.kind == NodeKind::Scanned).collect();
+    for (from, targets) in graph.edges.iter().enumerate() {
+        if graph.nodes[from].kind == NodeKind::Scanned {
+            targets.iter().for_each(|&to| drawn[to] = true);
+        }
+    }
+    for (index, node) in graph.nodes.iter().enumerate().filter(|(index, _)| drawn[*index]) {
+        let key = node.soname.as_deref().filter(|_| options.collapse);
+        let existing = key.and_then(|key| ids.get(key).copied());
+        let shape = existing.unwrap_or_else(|| {
+            shapes.push(Shape { label: key.unwrap_or(&node.name), library: false, missing: false });
+            shapes.len() - 1
+        });
+        if let Some(key) = key {
+            ids.insert(key, shape);
+        }
+        shapes[shape].library |= node.kind == NodeKind::Library;
+        shapes[shape].missing |= node.missing;
+        shape_of[index] = Some(shape);
+    }
+    let mut seen = HashSet::new();
+    let mut edges = vec![];
+    for (from, targets) in graph.edges.iter().enumerate() {
+        if !options.transitive && graph.nodes[from].kind != NodeKind::Scanned {
+            continue;
+        }
+        for &to in targets {
+            if let (Some(a), Some(b)) = (shape_of[from], shape_of[to]) {
+                if a != b && seen.insert((a, b)) {
+                    edges.push((a, b));
+                }
+            }
+        }
+    }
+    (shapes, edges)
+}
+
+/// Double-quoted DOT string
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Mermaid label text, which can't contain double quotes
+fn mermaid_label(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+pub fn write<W: Write>(mut out: W, graph: &DepGraph, options: &Options) -> io::Result<()> {
+    let (shapes, edges) = layout(graph, options);
+    let highlighted = |id: usize| options.highlight_missing && shapes[id].missing;
+    match options.syntax {
+        Syntax::Dot => {
+            writeln!(out, "digraph dependencies {{")?;
+            writeln!(out, "\trankdir=LR;")?;
+            for (id, shape) in shapes.iter().enumerate() {
+                let mut attrs = vec![
+                    format!("label={}", dot_string(shape.label)),
+                    format!("shape={}", if shape.library { "ellipse" } else { "box" }),
+                ];
+                if highlighted(id) {
+                    attrs.push("color=red, fontcolor=red, style=dashed".to_string());
+                }
+                writeln!(out, "\tn{} [{}];", id, attrs.join(", "))?;
+            }
+            for (from, to) in edges {
+                if highlighted(to) {
+                    writeln!(out, "\tn{} -> n{} [color=red];", from, to)?;
+                }
+                else {
+                    writeln!(out, "\tn{} -> n{};", from, to)?;
+                }
+            }
+            writeln!(out, "}}")
+        },
+        Syntax::Mermaid => {
+            writeln!(out, "graph LR")?;
+            for (id, shape) in shapes.iter().enumerate() {
+                let label = mermaid_label(shape.label);
+                if shape.library {
+                    writeln!(out, "\tn{}([\"{}\"])", id, label)?;
+                }
+                else {
+                    writeln!(out, "\tn{}[\"{}\"]", id, label)?;
+                }
+            }
+            for (index, (from, to)) in edges.iter().enumerate() {
+                writeln!(out, "\tn{} --> n{}", from, to)?;
+                if highlighted(*to) {
+                    writeln!(out, "\tlinkStyle {} stroke:red", index)?;
+                }
+            }
+            if options.highlight_missing && shapes.iter().any(|s| s.missing) {
+                writeln!(out, "\tclassDef missing stroke:red,stroke-dasharray:4,color:red")?;
+                for id in (0..shapes.len()).filter(|&id| shapes[id].missing) {
+                    writeln!(out, "\tclass n{} missing", id)?;
+                }
+            }
+            Ok(())
+        },
+    }
+}
@@ -0,0 +1,91 @@
+//! Plain nodes/edges JSON of the dependency graph, plus an adjacency matrix CSV.
+
+use std::io::{self, Write};
+use serde::Serialize;
+use crate::escape;
+use crate::graph::{DepGraph, NodeKind};
+use crate::metadata::Metadata;
+use crate::scan::{Scan, Summary};
+
+#[derive(Serialize)]
+struct JsonNode<'a> {
+    id: usize,
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    path: Option<String>,
+    arch: Option<&'a str>,
+    size: Option<u64>,
+    missing: bool,
+    depth: usize,
+    cluster: usize,
+}
+
+#[derive(Serialize)]
+struct JsonEdge {
+    source: usize,
+    target: usize,
+}
+
+#[derive(Serialize)]
+struct JsonGraph<'a> {
+    nodes: Vec<JsonNode<'a>>,
+    edges: Vec<JsonEdge>,
+    summary: Summary<'a>,
+    metadata: &'a Metadata,
+}
+
+pub fn kind_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Scanned => "executable",
+        NodeKind::Library => "library",
+    }
+}
+
+/// Writes the graph with the summary and metadata of the scan it was built from, `clusters`
+/// giving the cluster id of every node
+pub fn write<'a, W: Write>(
+    out: W, graph: &'a DepGraph, clusters: &[usize], scan: &'a Scan, metadata: &'a Metadata,
+) -> serde_json::Result<()> {
+    let nodes = graph.nodes.iter().enumerate()
+        .map(|(id, n)| JsonNode {
+            id,
+            name: &n.name,
+            kind: kind_name(n.kind),
+            path: n.path.as_deref().map(escape::path),
+            arch: n.arch.as_deref(),
+            size: n.size,
+            missing: n.missing,
+            depth: n.depth,
+            cluster: clusters[id],
+        })
+        .collect();
+    let edges = graph.edges.iter().enumerate()
+        .flat_map(|(source, targets)| targets.iter().map(move |&target| JsonEdge { source, target }))
+        .collect();
+    serde_json::to_writer_pretty(out, &JsonGraph { nodes, edges, summary: scan.summary(), metadata })
+}
+
+/// CSV field, quoted if needed
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+    else {
+        s.to_string()
+    }
+}
+
+/// Writes the adjacency matrix, rows being dependents and columns dependencies
+pub fn write_adjacency_csv<W: Write>(mut out: W, graph: &DepGraph) -> io::Result<()> {
+    let names: Vec<String> = graph.nodes.iter().map(|n| csv_field(&n.name)).collect();
+    writeln!(out, ",{}", names.join(","))?;
+    for (from, name) in names.iter().enumerate() {
+        let mut row = vec!["0"; names.len()];
+        for &to in &graph.edges[from] {
+            row[to] = "1";
+        }
+        writeln!(out, "{},{}", name, row.join(","))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Self-contained HTML page with an interactive force-directed view of the dependency graph.
+
+use std::io::{self, Write};
+use serde::Serialize;
+use crate::escape;
+use crate::graph::DepGraph;
+use crate::output::graph_json::kind_name;
+
+const TEMPLATE: &str = include_str!("graph.html");
+
+#[derive(Serialize)]
+struct HtmlNode<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    path: Option<String>,
+    missing: bool,
+}
+
+#[derive(Serialize)]
+struct HtmlGraph<'a> {
+    nodes: Vec<HtmlNode<'a>>,
+    edges: Vec<(usize, usize)>,
+}
+
+pub fn write<W: Write>(mut out: W, graph: &DepGraph) -> io::Result<()> {
+    let nodes = graph.nodes.iter()
+        .map(|n| HtmlNode {
+            name: &n.name,
+            kind: kind_name(n.kind),
+            path: n.path.as_deref().map(escape::path),
+            missing: n.missing,
+        })
+        .collect();
+    let edges = graph.edges.iter().enumerate()
+        .flat_map(|(source, targets)| targets.iter().map(move |&target| (source, target)))
+        .collect();
+    let json = serde_json::to_string(&HtmlGraph { nodes, edges })?;
+    // a name containing `</script>` mustn't end the script element
+    let json = json.replace("</", "<\\/");
+    out.write_all(TEMPLATE.replace("/*GRAPH*/", &json).as_bytes())
+}
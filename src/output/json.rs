@@ -0,0 +1,103 @@
+//! Structured JSON of the scan: every library with the executables needing it, and every
+//! executable with the libraries it needs.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use serde::Serialize;
+use crate::analysis::checksec;
+use crate::elf::{self, BinaryFormat};
+use crate::escape;
+use crate::metadata::Metadata;
+use crate::packages::Database;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::{Scan, Summary};
+
+#[derive(Serialize)]
+struct Library<'a> {
+    name: &'a str,
+    dependants: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Needed<'a> {
+    name: &'a str,
+    /// Where the library was found, `None` if it's missing
+    path: Option<String>,
+    /// Size of the library found, in bytes
+    size: Option<u64>,
+    /// Package owning the library, with a package database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<&'a str>,
+    /// Symbol versions required from the library
+    versions: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Executable<'a> {
+    name: &'a str,
+    path: String,
+    format: String,
+    arch: &'a str,
+    bits: u8,
+    /// `little` or `big`
+    endian: &'a str,
+    osabi: String,
+    build_id: Option<&'a str>,
+    /// Mitigations of ELF files
+    hardening: Option<checksec::Checks>,
+    needed: Vec<Needed<'a>>,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    libraries: Vec<Library<'a>>,
+    executables: Vec<Executable<'a>>,
+    summary: Summary<'a>,
+    metadata: &'a Metadata,
+}
+
+/// Writes the document, leaving out the libraries hidden by `filter` and naming the owners of
+/// libraries if there's a `packages` database
+pub fn write<W: Write>(
+    out: W, scan: &Scan, resolver: &mut Resolver, filter: &LibFilter, metadata: &Metadata, packages: Option<&'_ Database>,
+) -> serde_json::Result<()> {
+    let mut libraries: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut executables = vec![];
+    for file in &scan.files {
+        let target = file.info.target();
+        let mut needed = vec![];
+        for lib in file.info.needed.iter().filter(|l| !filter.is_hidden(l)) {
+            libraries.entry(lib).or_default().push(&file.name);
+            let found = resolver.resolve(lib, &target);
+            let path = found.map(|l| escape::path(&l.path));
+            let size = found.map(|l| l.info.size);
+            let package = found.and_then(|l| packages?.owner(&l.path));
+            let versions = file.info.version_needs.iter()
+                .filter(|n| n.file == *lib)
+                .flat_map(|n| n.versions.iter().map(String::as_str))
+                .collect();
+            needed.push(Needed { name: lib, path, size, package, versions });
+        }
+        executables.push(Executable {
+            name: &file.name,
+            path: escape::path(&file.path),
+            format: file.info.format.to_string(),
+            arch: &file.info.arch,
+            bits: file.info.bits,
+            endian: if file.info.big_endian { "big" } else { "little" },
+            osabi: elf::osabi_name(file.info.osabi),
+            build_id: file.info.build_id.as_deref(),
+            hardening: (file.info.format == BinaryFormat::Elf).then(|| checksec::checks(&file.info)),
+            needed,
+        });
+    }
+    let libraries = libraries.into_iter()
+        .map(|(name, mut dependants)| {
+            dependants.sort();
+            Library { name, dependants }
+        })
+        .collect();
+    let document = Document { libraries, executables, summary: scan.summary(), metadata };
+    serde_json::to_writer_pretty(out, &document)
+}
@@ -0,0 +1,605 @@
+//! Apache Parquet tables of the scanned binaries and their direct dependencies.
+//!
+//! Each table is written as a single row group with one uncompressed, plain-encoded data page
+//! per column, which every Parquet reader understands. The file metadata is serialized with the
+//! Thrift compact protocol by hand.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::escape;
+use crate::metadata::Metadata;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::scan::ScannedFile;
+
+/// Values of one column
+enum Values {
+    /// UTF-8 strings, `None` being null
+    Utf8(Vec<Option<String>>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Boolean(Vec<bool>),
+}
+
+struct Column {
+    name: &'static str,
+    values: Values,
+}
+
+impl Column {
+    fn utf8(name: &'static str, values: Vec<Option<String>>) -> Self {
+        Column { name, values: Values::Utf8(values) }
+    }
+
+    fn len(&self) -> usize {
+        match &self.values {
+            Values::Utf8(v) => v.len(),
+            Values::Int32(v) => v.len(),
+            Values::Int64(v) => v.len(),
+            Values::Boolean(v) => v.len(),
+        }
+    }
+
+    /// Parquet physical type
+    fn physical_type(&self) -> i32 {
+        match self.values {
+            Values::Boolean(_) => 0,
+            Values::Int32(_) => 1,
+            Values::Int64(_) => 2,
+            Values::Utf8(_) => 6,
+        }
+    }
+
+    fn optional(&self) -> bool {
+        matches!(self.values, Values::Utf8(_))
+    }
+
+    /// Definition levels and plain-encoded values of a data page
+    fn page(&self) -> Vec<u8> {
+        let mut page = vec![];
+        match &self.values {
+            Values::Utf8(values) => {
+                let levels = definition_levels(values.iter().map(Option::is_some));
+                page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+                page.extend_from_slice(&levels);
+                for value in values.iter().flatten() {
+                    page.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    page.extend_from_slice(value.as_bytes());
+                }
+            },
+            Values::Int32(values) => values.iter().for_each(|v| page.extend_from_slice(&v.to_le_bytes())),
+            Values::Int64(values) => values.iter().for_each(|v| page.extend_from_slice(&v.to_le_bytes())),
+            Values::Boolean(values) => {
+                let mut bytes = vec![0u8; values.len().div_ceil(8)];
+                for (i, _) in values.iter().enumerate().filter(|(_, v)| **v) {
+                    bytes[i / 8] |= 1 << (i % 8);
+                }
+                page.extend_from_slice(&bytes);
+            },
+        }
+        page
+    }
+}
+
+/// RLE runs of 1-bit definition levels
+fn definition_levels<I: Iterator<Item = bool>>(defined: I) -> Vec<u8> {
+    let mut out = Thrift::default();
+    let mut run: Option<(bool, u64)> = None;
+    for d in defined {
+        run = match run {
+            Some((value, count)) if value == d => Some((value, count + 1)),
+            Some((value, count)) => {
+                out.varint(count << 1);
+                out.buf.push(u8::from(value));
+                Some((d, 1))
+            },
+            None => Some((d, 1)),
+        };
+    }
+    if let Some((value, count)) = run {
+        out.varint(count << 1);
+        out.buf.push(u8::from(value));
+    }
+    out.buf
+}
+
+/// Thrift compact protocol writer
+#[derive(Default)]
+struct Thrift {
+    buf: Vec<u8>,
+    /// Id of the last field written in each enclosing struct
+    last: Vec<i16>,
+}
+
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+impl Thrift {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last.last_mut().expect("field outside a struct");
+        let delta = id - *last;
+        *last = id;
+        if (1..=15).contains(&delta) {
+            self.buf.push((delta as u8) << 4 | kind);
+        }
+        else {
+            self.buf.push(kind);
+            self.zigzag(i64::from(id));
+        }
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.field(id, I32);
+        self.zigzag(i64::from(v));
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.field(id, I64);
+        self.zigzag(v);
+    }
+
+    fn binary(&mut self, id: i16, v: &[u8]) {
+        self.field(id, BINARY);
+        self.varint(v.len() as u64);
+        self.buf.extend_from_slice(v);
+    }
+
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | kind);
+        }
+        else {
+            self.buf.push(0xf0 | kind);
+            self.varint(len as u64);
+        }
+    }
+
+    /// Starts a struct, as a field if `id` is given or a list element otherwise
+    fn begin(&mut self, id: Option<i16>) {
+        if let Some(id) = id {
+            self.field(id, STRUCT);
+        }
+        self.last.push(0);
+    }
+
+    fn end(&mut self) {
+        self.buf.push(0);
+        self.last.pop();
+    }
+}
+
+/// Parquet file with the given columns, which all have the same length, and key-value metadata
+fn encode(columns: &[Column], key_values: &[(&str, String)]) -> Vec<u8> {
+    let rows = columns.first().map_or(0, Column::len);
+    let mut file = b"PAR1".to_vec();
+    // (data page offset, total size) of every column chunk
+    let mut chunks = vec![];
+    for column in columns {
+        let page = column.page();
+        let mut header = Thrift::default();
+        header.begin(None);
+        header.i32(1, 0);
+        header.i32(2, page.len() as i32);
+        header.i32(3, page.len() as i32);
+        header.begin(Some(5));
+        header.i32(1, rows as i32);
+        header.i32(2, 0);
+        header.i32(3, 3);
+        header.i32(4, 3);
+        header.end();
+        header.end();
+        chunks.push((file.len(), header.buf.len() + page.len()));
+        file.extend_from_slice(&header.buf);
+        file.extend_from_slice(&page);
+    }
+
+    let mut meta = Thrift::default();
+    meta.begin(None);
+    meta.i32(1, 1);
+    meta.list(2, STRUCT, columns.len() + 1);
+    meta.begin(None);
+    meta.binary(4, b"schema");
+    meta.i32(5, columns.len() as i32);
+    meta.end();
+    for column in columns {
+        meta.begin(None);
+        meta.i32(1, column.physical_type());
+        meta.i32(3, i32::from(column.optional()));
+        meta.binary(4, column.name.as_bytes());
+        if column.optional() {
+            // UTF8
+            meta.i32(6, 0);
+        }
+        meta.end();
+    }
+    meta.i64(3, rows as i64);
+    meta.list(4, STRUCT, 1);
+    meta.begin(None);
+    meta.list(1, STRUCT, columns.len());
+    for (column, (offset, size)) in columns.iter().zip(&chunks) {
+        meta.begin(None);
+        meta.i64(2, *offset as i64);
+        meta.begin(Some(3));
+        meta.i32(1, column.physical_type());
+        meta.list(2, I32, 2);
+        meta.zigzag(0);
+        meta.zigzag(3);
+        meta.list(3, BINARY, 1);
+        meta.varint(column.name.len() as u64);
+        meta.buf.extend_from_slice(column.name.as_bytes());
+        meta.i32(4, 0);
+        meta.i64(5, rows as i64);
+        meta.i64(6, *size as i64);
+        meta.i64(7, *size as i64);
+        meta.i64(9, *offset as i64);
+        meta.end();
+        meta.end();
+    }
+    meta.i64(2, chunks.iter().map(|(_, size)| *size as i64).sum());
+    meta.i64(3, rows as i64);
+    meta.end();
+    meta.list(5, STRUCT, key_values.len());
+    for (key, value) in key_values {
+        meta.begin(None);
+        meta.binary(1, key.as_bytes());
+        meta.binary(2, value.as_bytes());
+        meta.end();
+    }
+    meta.binary(6, concat!("linux-hs-elf ", env!("CARGO_PKG_VERSION")).as_bytes());
+    meta.end();
+
+    file.extend_from_slice(&meta.buf);
+    file.extend_from_slice(&(meta.buf.len() as u32).to_le_bytes());
+    file.extend_from_slice(b"PAR1");
+    file
+}
+
+/// One row per scanned file
+fn binaries(files: &[ScannedFile]) -> Vec<Column> {
+    let strings = |f: fn(&ScannedFile) -> Option<String>| files.iter().map(f).collect();
+    vec![
+        Column::utf8("path", strings(|f| Some(escape::path(&f.path)))),
+        Column::utf8("name", strings(|f| Some(f.name.clone()))),
+        Column::utf8("format", strings(|f| Some(f.info.format.to_string()))),
+        Column::utf8("arch", strings(|f| Some(f.info.arch.clone()))),
+        Column { name: "bits", values: Values::Int32(files.iter().map(|f| i32::from(f.info.bits)).collect()) },
+        Column { name: "size", values: Values::Int64(files.iter().map(|f| f.info.size as i64).collect()) },
+        Column::utf8("interp", strings(|f| f.info.interp.clone())),
+        Column::utf8("soname", strings(|f| f.info.soname.clone())),
+        Column { name: "needed", values: Values::Int32(files.iter().map(|f| f.info.needed.len() as i32).collect()) },
+    ]
+}
+
+/// One row per direct dependency of a scanned file
+fn edges(files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter) -> Vec<Column> {
+    let (mut paths, mut libraries, mut resolved, mut missing) = (vec![], vec![], vec![], vec![]);
+    for file in files {
+        let target = file.info.target();
+        for lib in file.info.needed.iter().filter(|l| !filter.is_hidden(l)) {
+            let found = resolver.resolve(lib, &target).map(|l| escape::path(&l.path));
+            paths.push(Some(escape::path(&file.path)));
+            libraries.push(Some(lib.clone()));
+            missing.push(found.is_none());
+            resolved.push(found);
+        }
+    }
+    vec![
+        Column::utf8("path", paths),
+        Column::utf8("library", libraries),
+        Column::utf8("resolved", resolved),
+        Column { name: "missing", values: Values::Boolean(missing) },
+    ]
+}
+
+/// Writes `binaries.parquet` and `edges.parquet` into `dir`, with `metadata` as JSON under the
+/// `linux-hs-elf.metadata` key
+pub fn write(
+    dir: &Path, files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter, metadata: &Metadata,
+) -> io::Result<()> {
+    let key_values = [("linux-hs-elf.metadata", serde_json::to_string(metadata).map_err(io::Error::from)?)];
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("binaries.parquet"), encode(&binaries(files), &key_values))?;
+    fs::write(dir.join("edges.parquet"), encode(&edges(files, resolver, filter), &key_values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Thrift compact protocol value
+    #[derive(Debug)]
+    enum Node {
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Node>),
+        Struct(Vec<(i16, Node)>),
+    }
+
+    impl Node {
+        fn field(&self, id: i16) -> Result<&Node, String> {
+            match self {
+                Node::Struct(fields) => fields.iter().find(|(i, _)| *i == id).map(|(_, n)| n).ok_or(format!("no field {}", id)),
+                _ => Err("not a struct".to_string()),
+            }
+        }
+
+        fn int(&self, id: i16) -> Result<i64, String> {
+            match self.field(id)? {
+                Node::Int(v) => Ok(*v),
+                _ => Err(format!("field {} isn't an integer", id)),
+            }
+        }
+
+        fn list(&self, id: i16) -> Result<&[Node], String> {
+            match self.field(id)? {
+                Node::List(items) => Ok(items),
+                _ => Err(format!("field {} isn't a list", id)),
+            }
+        }
+
+        fn string(&self, id: i16) -> Result<String, String> {
+            match self.field(id)? {
+                Node::Binary(v) => String::from_utf8(v.clone()).map_err(|e| e.to_string()),
+                _ => Err(format!("field {} isn't binary", id)),
+            }
+        }
+    }
+
+    /// Bounds-checked reader of what [`encode`] writes
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn bytes(&mut self, len: usize) -> Result<&[u8], String> {
+            let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len()).ok_or("truncated")?;
+            let bytes = &self.data[self.pos..end];
+            self.pos = end;
+            Ok(bytes)
+        }
+
+        fn u8(&mut self) -> Result<u8, String> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn u32(&mut self) -> Result<u32, String> {
+            Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+        }
+
+        fn varint(&mut self) -> Result<u64, String> {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = self.u8()?;
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte < 0x80 {
+                    return Ok(value);
+                }
+            }
+            Err("varint too long".to_string())
+        }
+
+        fn zigzag(&mut self) -> Result<i64, String> {
+            let v = self.varint()?;
+            Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+        }
+
+        fn value(&mut self, kind: u8) -> Result<Node, String> {
+            match kind {
+                I32 | I64 => self.zigzag().map(Node::Int),
+                BINARY => {
+                    let len = self.varint()? as usize;
+                    Ok(Node::Binary(self.bytes(len)?.to_vec()))
+                },
+                LIST => {
+                    let header = self.u8()?;
+                    let len = if header >> 4 == 15 { self.varint()? as usize } else { usize::from(header >> 4) };
+                    (0..len).map(|_| self.value(header & 0xf)).collect::<Result<_, _>>().map(Node::List)
+                },
+                STRUCT => {
+                    let (mut fields, mut last) = (vec![], 0);
+                    loop {
+                        let header = self.u8()?;
+                        if header == 0 {
+                            return Ok(Node::Struct(fields));
+                        }
+                        last = if header >> 4 == 0 { self.zigzag()? as i16 } else { last + i16::from(header >> 4) };
+                        fields.push((last, self.value(header & 0xf)?));
+                    }
+                },
+                other => Err(format!("unexpected type {}", other)),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Cell {
+        Null,
+        Str(String),
+        Int(i64),
+        Bool(bool),
+    }
+
+    fn cells(column: &Column) -> Vec<Cell> {
+        match &column.values {
+            Values::Utf8(v) => v.iter().map(|s| s.clone().map_or(Cell::Null, Cell::Str)).collect(),
+            Values::Int32(v) => v.iter().map(|&i| Cell::Int(i64::from(i))).collect(),
+            Values::Int64(v) => v.iter().map(|&i| Cell::Int(i)).collect(),
+            Values::Boolean(v) => v.iter().map(|&b| Cell::Bool(b)).collect(),
+        }
+    }
+
+    /// Values of one page of `rows` values of a column of type `kind`
+    fn decode_page(page: &mut Reader, kind: i64, optional: bool, rows: usize) -> Result<Vec<Cell>, String> {
+        let mut defined = vec![true; rows];
+        if optional {
+            let len = page.u32()? as usize;
+            let mut levels = Reader { data: page.bytes(len)?, pos: 0 };
+            defined.clear();
+            while defined.len() < rows {
+                let run = levels.varint()?;
+                if run & 1 != 0 {
+                    return Err("bit-packed levels".to_string());
+                }
+                let value = levels.u8()? != 0;
+                defined.extend(std::iter::repeat_n(value, (run >> 1) as usize));
+            }
+        }
+        let bits = if kind == 0 { page.bytes(rows.div_ceil(8))?.to_vec() } else { vec![] };
+        defined.iter().enumerate()
+            .map(|(i, &defined)| Ok(match (kind, defined) {
+                (_, false) => Cell::Null,
+                (0, _) => Cell::Bool(bits[i / 8] & 1 << (i % 8) != 0),
+                (1, _) => Cell::Int(i64::from(page.u32()? as i32)),
+                (2, _) => Cell::Int(i64::from_le_bytes(page.bytes(8)?.try_into().unwrap())),
+                (6, _) => {
+                    let len = page.u32()? as usize;
+                    Cell::Str(String::from_utf8(page.bytes(len)?.to_vec()).map_err(|e| e.to_string())?)
+                },
+                (other, _) => return Err(format!("unexpected physical type {}", other)),
+            }))
+            .collect()
+    }
+
+    /// Names and values of the columns of a file, and its key-value metadata
+    type Table = (Vec<(String, Vec<Cell>)>, Vec<(String, String)>);
+
+    fn read(file: &[u8]) -> Result<Table, String> {
+        if file.len() < 12 || !file.starts_with(b"PAR1") || !file.ends_with(b"PAR1") {
+            return Err("not a Parquet file".to_string());
+        }
+        let footer_len = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let footer = (file.len() - 8).checked_sub(footer_len).filter(|&start| start >= 4).ok_or("footer out of range")?;
+        let meta = Reader { data: &file[footer..file.len() - 8], pos: 0 }.value(STRUCT)?;
+        let rows = meta.int(3)? as usize;
+        let schema = meta.list(2)?;
+        let chunks = meta.list(4)?.first().ok_or("no row group")?.list(1)?;
+        if schema.len() != chunks.len() + 1 {
+            return Err("schema and column chunks disagree".to_string());
+        }
+        let mut columns = vec![];
+        for (element, chunk) in schema[1..].iter().zip(chunks) {
+            let chunk = chunk.field(3)?;
+            let (offset, size) = (chunk.int(9)? as usize, chunk.int(7)? as usize);
+            if offset < 4 || offset.checked_add(size).is_none_or(|end| end > footer) {
+                return Err("column chunk out of range".to_string());
+            }
+            let mut reader = Reader { data: &file[offset..offset + size], pos: 0 };
+            let header = reader.value(STRUCT)?;
+            if header.field(5)?.int(1)? as usize != rows || chunk.int(5)? as usize != rows {
+                return Err("wrong number of values".to_string());
+            }
+            let mut page = Reader { data: reader.bytes(header.int(3)? as usize)?, pos: 0 };
+            let values = decode_page(&mut page, element.int(1)?, element.int(3)? == 1, rows)?;
+            if page.pos != page.data.len() || reader.pos != size {
+                return Err("trailing bytes in the column chunk".to_string());
+            }
+            columns.push((element.string(4)?, values));
+        }
+        let key_values = meta.list(5)?.iter()
+            .map(|kv| Ok((kv.string(1)?, kv.string(2)?)))
+            .collect::<Result<_, String>>()?;
+        Ok((columns, key_values))
+    }
+
+    /// Columns of every type, 20 rows so that lists take the long header and levels several runs
+    fn columns() -> Vec<Column> {
+        let rows = 0..20;
+        vec![
+            Column::utf8("name", rows.clone().map(|i| (i % 3 != 0).then(|| format!("lib{}.so", i))).collect()),
+            Column { name: "bits", values: Values::Int32(rows.clone().map(|i| i * 1000 - 7).collect()) },
+            Column { name: "size", values: Values::Int64(rows.clone().map(|i| i64::from(i) << 40).collect()) },
+            Column { name: "missing", values: Values::Boolean(rows.map(|i| i % 5 == 0).collect()) },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let columns = columns();
+        let file = encode(&columns, &[("key", "välue".to_string())]);
+        let (decoded, key_values) = read(&file).unwrap();
+        let expected: Vec<(String, Vec<Cell>)> = columns.iter().map(|c| (c.name.to_string(), cells(c))).collect();
+        assert_eq!(decoded, expected);
+        assert_eq!(key_values, [("key".to_string(), "välue".to_string())]);
+        // tables without rows are valid too
+        let empty = [Column::utf8("path", vec![]), Column { name: "missing", values: Values::Boolean(vec![]) }];
+        assert_eq!(read(&encode(&empty, &[])).unwrap().0, [("path".to_string(), vec![]), ("missing".to_string(), vec![])]);
+    }
+
+    #[test]
+    fn read_by_the_parquet_crate() {
+        use parquet::basic::{ConvertedType, Repetition, Type};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field;
+
+        let columns = columns();
+        let path = std::env::temp_dir().join(format!("linux-hs-elf-{}-crate.parquet", std::process::id()));
+        fs::write(&path, encode(&columns, &[("key", "välue".to_string())])).unwrap();
+        let reader = SerializedFileReader::new(fs::File::open(&path).unwrap()).unwrap();
+        let meta = reader.metadata().file_metadata();
+        assert_eq!(meta.num_rows(), 20);
+        assert_eq!(meta.created_by(), Some(concat!("linux-hs-elf ", env!("CARGO_PKG_VERSION"))));
+        let key_values: Vec<_> = meta.key_value_metadata().unwrap().iter().map(|kv| (kv.key.as_str(), kv.value.as_deref())).collect();
+        assert_eq!(key_values, [("key", Some("välue"))]);
+
+        let schema: Vec<_> = meta.schema_descr().columns().iter()
+            .map(|c| (c.name().to_string(), c.physical_type(), c.converted_type(), c.self_type().get_basic_info().repetition()))
+            .collect();
+        assert_eq!(schema, [
+            ("name".to_string(), Type::BYTE_ARRAY, ConvertedType::UTF8, Repetition::OPTIONAL),
+            ("bits".to_string(), Type::INT32, ConvertedType::NONE, Repetition::REQUIRED),
+            ("size".to_string(), Type::INT64, ConvertedType::NONE, Repetition::REQUIRED),
+            ("missing".to_string(), Type::BOOLEAN, ConvertedType::NONE, Repetition::REQUIRED),
+        ]);
+
+        let rows: Vec<Vec<Cell>> = reader.get_row_iter(None).unwrap()
+            .map(|row| row.unwrap().get_column_iter().map(|(_, field)| match field {
+                Field::Null => Cell::Null,
+                Field::Str(s) => Cell::Str(s.clone()),
+                Field::Int(i) => Cell::Int(i64::from(*i)),
+                Field::Long(i) => Cell::Int(*i),
+                Field::Bool(b) => Cell::Bool(*b),
+                other => panic!("unexpected value {:?}", other),
+            }).collect())
+            .collect();
+        let expected: Vec<Vec<Cell>> = (0..20).map(|row| columns.iter().map(|c| cells(c).swap_remove(row)).collect()).collect();
+        assert_eq!(rows, expected);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn malformed() {
+        let file = encode(&columns(), &[]);
+        // cut short, or with the final magic kept but the footer cut
+        assert_eq!(read(&file[..file.len() - 1]), Err("not a Parquet file".to_string()));
+        let mut truncated = file[..file.len() - 20].to_vec();
+        truncated.extend_from_slice(&file[file.len() - 8..]);
+        assert!(read(&truncated).is_err());
+        // a footer longer than the file
+        let mut long_footer = file.clone();
+        let at = long_footer.len() - 8;
+        long_footer[at..at + 4].copy_from_slice(&(file.len() as u32).to_le_bytes());
+        assert_eq!(read(&long_footer), Err("footer out of range".to_string()));
+        // the end of the last page cut, so that its column chunk runs into the footer
+        let footer = file.len() - 8 - u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let cut = [&file[..footer - 8], &file[footer..]].concat();
+        assert_eq!(read(&cut), Err("column chunk out of range".to_string()));
+    }
+}
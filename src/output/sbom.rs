@@ -0,0 +1,354 @@
+//! Software bills of materials of the scanned executables and the shared libraries they load,
+//! as SPDX 2.3 or CycloneDX 1.5 JSON.
+//!
+//! Every scanned file is a component, and so is every library of their dependency closures, the
+//! ones that weren't found included. Library versions come from the file names the sonames
+//! point to (`libssl.so.3` → `libssl.so.3.0.2`), or from the sonames themselves.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use clap::ArgEnum;
+use serde::Serialize;
+use tracing::warn;
+use crate::digest;
+use crate::elf::{BinaryFormat, Target};
+use crate::escape;
+use crate::metadata::Metadata;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::root;
+use crate::scan::ScannedFile;
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Spdx,
+    Cyclonedx,
+}
+
+/// A scanned file or a library one of them loads
+pub struct Component {
+    pub library: bool,
+    pub name: String,
+    pub version: Option<String>,
+    /// Path on the analyzed system, `None` for libraries that weren't found
+    pub path: Option<String>,
+    pub sha256: Option<String>,
+    /// Indices of the components it needs
+    pub needs: Vec<usize>,
+}
+
+/// Name of the library a soname belongs to, `libssl` for `libssl.so.3`
+fn library_name(soname: &str) -> &str {
+    soname.split_once(".so").map_or(soname, |(name, _)| name)
+}
+
+/// Version of the library `path` provides as `soname`
+fn library_version(soname: &str, path: Option<&Path>) -> Option<String> {
+    let real = path.and_then(|p| p.file_name()).and_then(|n| n.to_str());
+    if let Some(rest) = real.and_then(|n| n.strip_prefix(soname)).and_then(|r| r.strip_prefix('.')) {
+        return Some(format!("{}.{}", soname.split_once(".so.")?.1, rest));
+    }
+    soname.split_once(".so.").map(|(_, version)| version.to_string())
+}
+
+/// Components of the ELF files of `files`, first and in order, and of their closures, leaving
+/// out the libraries hidden by `filter`. Files are hashed if `host`, i.e. not inside an image.
+pub fn components(files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter, host: bool) -> Vec<Component> {
+    let system_path = |path: &Path, root: &Path| match path.strip_prefix(root) {
+        Ok(relative) => escape::path(&Path::new("/").join(relative)),
+        Err(_) => escape::path(path),
+    };
+    let hash = |path: &Path| {
+        if !host {
+            return None;
+        }
+        digest::sha256_file(path)
+            .map_err(|e| warn!("Couldn't hash {}: {}", path.display(), e))
+            .ok()
+    };
+    let mut components: Vec<Component> = vec![];
+    let mut by_path: HashMap<PathBuf, usize> = HashMap::new();
+    // components with the sonames they need, for the target they were built for
+    let mut pending: Vec<(usize, Vec<String>, Target)> = vec![];
+    for file in files.iter().filter(|f| f.info.format == BinaryFormat::Elf) {
+        by_path.insert(file.path.clone(), components.len());
+        pending.push((components.len(), file.info.needs(resolver.dlopen), file.info.target()));
+        components.push(Component {
+            library: file.info.soname.is_some(),
+            name: match &file.info.soname {
+                Some(soname) => library_name(soname).to_string(),
+                None => file.path.file_name().map_or_else(|| file.name.clone(), escape::os_str),
+            },
+            version: file.info.soname.as_deref().and_then(|s| library_version(s, None)),
+            path: Some(system_path(&file.path, &resolver.root)),
+            sha256: hash(&file.path),
+            needs: vec![],
+        });
+    }
+    let mut missing: HashMap<String, usize> = HashMap::new();
+    while let Some((index, needed, target)) = pending.pop() {
+        for soname in needed.iter().filter(|l| !filter.is_hidden(l)) {
+            let dlopen = resolver.dlopen;
+            let lib = resolver.resolve(soname, &target).map(|l| (l.path.clone(), l.info.needs(dlopen), l.info.target()));
+            let needed_index = match lib {
+                Some((path, _, _)) if by_path.contains_key(&path) => by_path[&path],
+                Some((path, needs, target)) => {
+                    let real = if host { root::canonicalize(&resolver.root, &path) } else { None };
+                    let component = components.len();
+                    by_path.insert(path.clone(), component);
+                    pending.push((component, needs, target));
+                    components.push(Component {
+                        library: true,
+                        name: library_name(soname).to_string(),
+                        version: library_version(soname, real.as_deref()),
+                        path: Some(system_path(&path, &resolver.root)),
+                        sha256: hash(&path),
+                        needs: vec![],
+                    });
+                    component
+                },
+                None => *missing.entry(soname.clone()).or_insert_with(|| {
+                    components.push(Component {
+                        library: true,
+                        name: library_name(soname).to_string(),
+                        version: library_version(soname, None),
+                        path: None,
+                        sha256: None,
+                        needs: vec![],
+                    });
+                    components.len() - 1
+                }),
+            };
+            if !components[index].needs.contains(&needed_index) {
+                components[index].needs.push(needed_index);
+            }
+        }
+    }
+    components
+}
+
+/// `seconds` since the Unix epoch as an RFC 3339 UTC timestamp
+fn timestamp(seconds: u64) -> String {
+    let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+    // civil date from days since 1970-01-01, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum<'a> {
+    algorithm: &'static str,
+    checksum_value: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage<'a> {
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_info: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package_file_name: Option<&'a str>,
+    download_location: &'static str,
+    files_analyzed: bool,
+    primary_package_purpose: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument<'a> {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: &'a str,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage<'a>>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+fn spdx_id(index: usize) -> String {
+    format!("SPDXRef-Package-{}", index)
+}
+
+fn write_spdx<W: Write>(out: W, components: &[Component], scanned: usize, metadata: &Metadata) -> serde_json::Result<()> {
+    let packages = components.iter().enumerate()
+        .map(|(i, c)| SpdxPackage {
+            spdx_id: spdx_id(i),
+            name: &c.name,
+            version_info: c.version.as_deref(),
+            package_file_name: c.path.as_deref(),
+            download_location: "NOASSERTION",
+            files_analyzed: false,
+            primary_package_purpose: if c.library { "LIBRARY" } else { "APPLICATION" },
+            checksums: c.sha256.iter().map(|h| SpdxChecksum { algorithm: "SHA256", checksum_value: h }).collect(),
+            comment: c.path.is_none().then_some("not found on the analyzed system"),
+        })
+        .collect();
+    let described = (0..scanned).map(|i| SpdxRelationship {
+        spdx_element_id: "SPDXRef-DOCUMENT".to_string(),
+        relationship_type: "DESCRIBES",
+        related_spdx_element: spdx_id(i),
+    });
+    let dependencies = components.iter().enumerate()
+        .flat_map(|(i, c)| c.needs.iter().map(move |&n| SpdxRelationship {
+            spdx_element_id: spdx_id(i),
+            relationship_type: "DEPENDS_ON",
+            related_spdx_element: spdx_id(n),
+        }));
+    let document = SpdxDocument {
+        spdx_version: "SPDX-2.3",
+        data_license: "CC0-1.0",
+        spdx_id: "SPDXRef-DOCUMENT",
+        name: &metadata.label,
+        document_namespace: format!(
+            "https://spdx.org/spdxdocs/linux-hs-elf-{}-{}",
+            metadata.label.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "-"), metadata.time
+        ),
+        creation_info: SpdxCreationInfo {
+            created: timestamp(metadata.time),
+            creators: vec![format!("Tool: linux-hs-elf-{}", metadata.version)],
+        },
+        packages,
+        relationships: described.chain(dependencies).collect(),
+    };
+    serde_json::to_writer_pretty(out, &document)
+}
+
+#[derive(Serialize)]
+struct CycloneDxHash<'a> {
+    alg: &'static str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct CycloneDxProperty<'a> {
+    name: &'static str,
+    value: &'a str,
+}
+
+#[derive(Serialize)]
+struct CycloneDxComponent<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<CycloneDxProperty<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTool<'a> {
+    name: &'static str,
+    version: &'a str,
+}
+
+#[derive(Serialize)]
+struct CycloneDxMetadata<'a> {
+    timestamp: String,
+    tools: Vec<CycloneDxTool<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDocument<'a> {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: CycloneDxMetadata<'a>,
+    components: Vec<CycloneDxComponent<'a>>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+fn bom_ref(index: usize) -> String {
+    format!("component-{}", index)
+}
+
+fn write_cyclonedx<W: Write>(out: W, components: &[Component], metadata: &Metadata) -> serde_json::Result<()> {
+    let document = CycloneDxDocument {
+        bom_format: "CycloneDX",
+        spec_version: "1.5",
+        version: 1,
+        metadata: CycloneDxMetadata {
+            timestamp: timestamp(metadata.time),
+            tools: vec![CycloneDxTool { name: "linux-hs-elf", version: &metadata.version }],
+        },
+        components: components.iter().enumerate()
+            .map(|(i, c)| CycloneDxComponent {
+                kind: if c.library { "library" } else { "application" },
+                bom_ref: bom_ref(i),
+                name: &c.name,
+                version: c.version.as_deref(),
+                hashes: c.sha256.iter().map(|h| CycloneDxHash { alg: "SHA-256", content: h }).collect(),
+                properties: match &c.path {
+                    Some(path) => vec![CycloneDxProperty { name: "linux-hs-elf:path", value: path }],
+                    None => vec![CycloneDxProperty { name: "linux-hs-elf:missing", value: "true" }],
+                },
+            })
+            .collect(),
+        dependencies: components.iter().enumerate()
+            .map(|(i, c)| CycloneDxDependency { reference: bom_ref(i), depends_on: c.needs.iter().map(|&n| bom_ref(n)).collect() })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(out, &document)
+}
+
+/// Writes the bill of materials of the ELF files of `files`
+pub fn write<W: Write>(
+    out: W, kind: Kind, files: &[ScannedFile], resolver: &mut Resolver, filter: &LibFilter, metadata: &Metadata, host: bool,
+) -> serde_json::Result<()> {
+    let components = components(files, resolver, filter, host);
+    let scanned = files.iter().filter(|f| f.info.format == BinaryFormat::Elf).count();
+    match kind {
+        Kind::Spdx => write_spdx(out, &components, scanned, metadata),
+        Kind::Cyclonedx => write_cyclonedx(out, &components, metadata),
+    }
+}
@@ -0,0 +1,340 @@
+//! The human-readable listing of libraries and their dependants.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::Path;
+use clap::ArgEnum;
+use crate::elf::{BinaryFormat, Target};
+use crate::escape;
+use crate::loader;
+use crate::packages::Database;
+use crate::pattern::LibFilter;
+use crate::resolve::Resolver;
+use crate::root;
+use crate::scan::{Scan, ScannedFile};
+
+/// Library name up to the `.so` suffix, so `libfoo.so.1.2.3` belongs to `libfoo`
+pub fn soname_family(soname: &str) -> &str {
+    let mut start = 0;
+    while let Some(i) = soname[start..].find(".so") {
+        let end = start + i + 3;
+        if end == soname.len() || soname[end..].starts_with('.') {
+            return &soname[..start + i];
+        }
+        start = end;
+    }
+    soname
+}
+
+/// Needed library with the direct dependency it's reached through, `None` for direct ones
+type Needed = (String, Option<String>);
+
+/// Libraries `file` needs with the direct dependency each is reached through, `None` for the
+/// direct ones. With a resolver that's the whole closure, each soname once however many paths
+/// lead to it, so dependency cycles end, and the probable `dlopen` dependencies if the resolver
+/// follows them, reached through `dlopen`.
+fn needed(file: &ScannedFile, resolver: Option<&mut Resolver>) -> Vec<Needed> {
+    let resolver = match resolver {
+        Some(resolver) => resolver,
+        None => return file.info.needed.iter().map(|l| (l.clone(), None)).collect(),
+    };
+    let target = file.info.target();
+    let dlopen = resolver.dlopen;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut result = vec![];
+    let mut queue: VecDeque<Needed> = file.info.needed.iter()
+        .map(|l| (l.clone(), None))
+        .collect();
+    if dlopen {
+        queue.extend(file.info.dlopen_needed().into_iter().map(|l| (l, Some("dlopen".to_string()))));
+    }
+    while let Some((soname, via)) = queue.pop_front() {
+        if !seen.insert(soname.clone()) {
+            continue;
+        }
+        if let Some(lib) = resolver.resolve(&soname, &target) {
+            let via = via.clone().unwrap_or_else(|| soname.clone());
+            queue.extend(lib.info.needs(dlopen).into_iter().map(|l| (l, Some(via.clone()))));
+        }
+        result.push((soname, via));
+    }
+    result
+}
+
+/// Libraries with the executables needing them, tagged with the format of non-ELF users. With
+/// a resolver, executables needing a library only through others are included and tagged with
+/// the direct dependency it comes from.
+pub fn lib_map<'a>(
+    files: impl IntoIterator<Item = &'a ScannedFile>,
+    mut resolver: Option<&mut Resolver>,
+    filter: &LibFilter,
+) -> BTreeMap<String, Vec<String>> {
+    let files: Vec<&ScannedFile> = files.into_iter().collect();
+    // the same soname stands for different libraries on different targets
+    let targets: HashSet<Target> = files.iter()
+        .filter(|f| f.info.format == BinaryFormat::Elf)
+        .map(|f| f.info.target())
+        .collect();
+    let mut lib_map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in files {
+        for (lib, via) in needed(file, resolver.as_deref_mut()).into_iter().filter(|(l, _)| !filter.is_hidden(l)) {
+            let lib = match file.info.format {
+                BinaryFormat::Elf if targets.len() > 1 => format!("{} [{}]", lib, file.info.target()),
+                BinaryFormat::Elf => lib,
+                format => format!("{} [{}]", lib, format),
+            };
+            let exe = match via {
+                Some(via) => format!("{} (via {})", file.name, via),
+                None => file.name.clone(),
+            };
+            lib_map.entry(lib).or_default().push(exe);
+        }
+    }
+    for exes in lib_map.values_mut() {
+        exes.sort();
+    }
+    lib_map
+}
+
+/// Library with the executables needing it
+type LibEntry = (String, Vec<String>);
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Number of dependants, or of needed libraries for executables
+    Count,
+    Name,
+}
+
+/// Order of the listing and the libraries left out of it
+#[derive(Debug, Clone, Copy)]
+pub struct Order {
+    pub key: SortKey,
+    pub descending: bool,
+    /// Libraries needed by fewer executables are left out
+    pub min_count: usize,
+}
+
+impl Default for Order {
+    /// Most needed first
+    fn default() -> Self {
+        Order { key: SortKey::Count, descending: true, min_count: 0 }
+    }
+}
+
+impl Order {
+    /// Compares entries by name and count, ties in name order
+    fn compare(&self, a: (&str, usize), b: (&str, usize)) -> Ordering {
+        let ordering = match self.key {
+            SortKey::Count => a.1.cmp(&b.1),
+            SortKey::Name => a.0.cmp(b.0),
+        };
+        let ordering = if self.descending { ordering.reverse() } else { ordering };
+        ordering.then(a.0.cmp(b.0))
+    }
+}
+
+fn sorted(lib_map: BTreeMap<String, Vec<String>>, order: &Order) -> Vec<LibEntry> {
+    let mut lib_list: Vec<LibEntry> = lib_map.into_iter()
+        .filter(|(_, exes)| exes.len() >= order.min_count)
+        .collect();
+    lib_list.sort_by(|a, b| order.compare((&a.0, a.1.len()), (&b.0, b.1.len())));
+    lib_list
+}
+
+pub fn print_libs(lib_map: BTreeMap<String, Vec<String>>, order: &Order) {
+    for (lib, exes) in sorted(lib_map, order) {
+        println!("{} ({} exes)", lib, exes.len());
+        for exe in exes {
+            println!("\t<= {}", exe);
+        }
+        println!()
+    }
+}
+
+/// Prints libraries grouped by family, each version with its own dependants
+pub fn print_families(lib_map: BTreeMap<String, Vec<String>>, order: &Order) {
+    let mut families: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for (lib, exes) in lib_map {
+        families.entry(soname_family(&lib).to_string()).or_default().insert(lib, exes);
+    }
+    let mut families: Vec<(String, Vec<LibEntry>, usize)> = families.into_iter()
+        .map(|(family, versions)| {
+            let users = versions.values().map(Vec::len).sum();
+            (family, sorted(versions, order), users)
+        })
+        .filter(|(_, versions, _)| !versions.is_empty())
+        .collect();
+    families.sort_by(|a, b| order.compare((&a.0, a.2), (&b.0, b.2)));
+    for (family, versions, users) in families {
+        println!("{} ({} versions, {} exes)", family, versions.len(), users);
+        for (lib, exes) in versions {
+            println!("\t{} ({} exes)", lib, exes.len());
+            for exe in exes {
+                println!("\t\t<= {}", exe);
+            }
+        }
+        println!()
+    }
+}
+
+/// Prints every file with the libraries it needs and where they were found, like `ldd`,
+/// indirect ones included with `transitive`
+/// Libraries are annotated with the packages owning them if there's a `packages` database
+pub fn print_exes(
+    files: &[ScannedFile], resolver: &mut Resolver, transitive: bool, filter: &LibFilter, order: &Order, packages: Option<&Database>,
+) {
+    let mut exes: Vec<(&ScannedFile, Vec<Needed>)> = files.iter()
+        .map(|file| {
+            let closure = if transitive { Some(&mut *resolver) } else { None };
+            let libs = needed(file, closure).into_iter().filter(|(l, _)| !filter.is_hidden(l)).collect();
+            (file, libs)
+        })
+        .collect();
+    exes.sort_by(|a, b| order.compare((&a.0.name, a.1.len()), (&b.0.name, b.1.len())));
+    for (file, libs) in exes {
+        let target = file.info.target();
+        match file.info.format {
+            BinaryFormat::Elf => println!("{} ({} libs)", file.name, libs.len()),
+            format => println!("{} [{}] ({} libs)", file.name, format, libs.len()),
+        }
+        if file.info.format == BinaryFormat::Elf {
+            println!("\tarch {}", file.info.abi());
+        }
+        if let Some(build_id) = &file.info.build_id {
+            println!("\tbuild-id {}", build_id);
+        }
+        if let Some(interp) = &file.info.interp {
+            if resolver.has_file(interp) {
+                println!("\tinterp {}", interp);
+            }
+            else {
+                println!("\tinterp {} (not found)", interp);
+            }
+        }
+        // shown as a path on the analyzed system
+        let origin = root::canonicalize(&resolver.root, &file.path)
+            .and_then(|p| p.parent().and_then(|o| o.strip_prefix(&resolver.root).ok()).map(|o| Path::new("/").join(o)))
+            .unwrap_or_default();
+        for (kind, dirs) in [("rpath", &file.info.rpaths), ("runpath", &file.info.runpaths)] {
+            for dir in dirs {
+                match loader::expand_tokens(&resolver.root, dir, &origin, &target) {
+                    expanded if expanded == *dir => println!("\t{} {}", kind, dir),
+                    expanded => println!("\t{} {} ({})", kind, dir, expanded),
+                }
+            }
+        }
+        for (lib, via) in libs {
+            let (found, package) = match resolver.resolve(&lib, &target) {
+                Some(found) => (escape::path(&found.path), packages.and_then(|p| p.owner(&found.path))),
+                None => ("not found".to_string(), None),
+            };
+            let package = package.map(|p| format!(" [{}]", p)).unwrap_or_default();
+            match via {
+                Some(via) => println!("\t=> {} ({}){} (via {})", lib, found, package, via),
+                None => println!("\t=> {} ({}){}", lib, found, package),
+            }
+        }
+        println!()
+    }
+}
+
+/// Directories the standard program interpreters are installed in
+const LOADER_DIRS: &[&str] = &["/lib", "/lib64", "/lib32", "/libx32", "/usr/lib", "/usr/lib64"];
+
+/// Kind of program interpreter: `glibc x86-64`, `musl aarch64`, `custom` for other loaders and
+/// standard loader names outside the standard directories, `none` for files without one
+pub fn interp_kind(interp: Option<&str>) -> String {
+    let interp = match interp {
+        Some(i) => i,
+        None => return "none".to_string(),
+    };
+    let (dir, name) = interp.rsplit_once('/').unwrap_or(("", interp));
+    if !LOADER_DIRS.contains(&dir) {
+        return "custom".to_string();
+    }
+    let arch = |loader: &str| loader.split_once(".so").map(|(arch, _)| arch.replace('_', "-"));
+    if let Some(arch) = name.strip_prefix("ld-musl-").and_then(arch) {
+        format!("musl {}", arch)
+    }
+    else if name == "ld-linux.so.2" {
+        "glibc i386".to_string()
+    }
+    else if let Some(arch) = name.strip_prefix("ld-linux-").and_then(arch) {
+        format!("glibc {}", arch)
+    }
+    else if name.starts_with("ld.so.") || name.starts_with("ld64.so.") {
+        "glibc".to_string()
+    }
+    else {
+        "custom".to_string()
+    }
+}
+
+/// Prints executables bucketed by program interpreter, largest bucket first, each with the
+/// libraries its executables need, indirectly too with a resolver
+pub fn print_interpreters(files: &[ScannedFile], resolver: &mut Resolver, transitive: bool, filter: &LibFilter, order: &Order) {
+    let mut buckets: BTreeMap<(String, Option<&str>), Vec<&ScannedFile>> = BTreeMap::new();
+    for file in files {
+        let interp = file.info.interp.as_deref();
+        buckets.entry((interp_kind(interp), interp)).or_default().push(file);
+    }
+    let mut buckets: Vec<_> = buckets.into_iter().collect();
+    buckets.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+    for ((kind, interp), files) in buckets {
+        match interp {
+            Some(interp) => println!("{} {} ({} exes)", kind, interp, files.len()),
+            None => println!("{} ({} exes)", kind, files.len()),
+        }
+        let closures = if transitive { Some(&mut *resolver) } else { None };
+        for (lib, exes) in sorted(lib_map(files.iter().copied(), closures, filter), order) {
+            println!("\t{} ({} exes)", lib, exes.len());
+            for exe in exes {
+                println!("\t\t<= {}", exe);
+            }
+        }
+        if let Some(interp) = interp.filter(|i| !resolver.has_file(i)) {
+            println!("\t! {} doesn't exist, these can't be started", interp);
+        }
+        println!()
+    }
+}
+
+/// Prints the files that were read but aren't dynamically linked binaries, by kind. Static
+/// executables and unsupported ELF files are always listed, the rest only if `paths` is set.
+pub fn print_not_dynamic(scan: &Scan, paths: bool) {
+    let summary = scan.summary();
+    let count: usize = summary.not_dynamic.values().map(Vec::len).sum();
+    if count == 0 {
+        return;
+    }
+    println!("not dynamically linked ({} files)", count);
+    for (kind, failures) in &summary.not_dynamic {
+        println!("\t{}: {}", kind, failures.len());
+        if !paths && *kind != "static" && *kind != "unsupported" {
+            continue;
+        }
+        for failure in failures {
+            println!("\t\t{} ({})", escape::path(&failure.path), failure.message);
+        }
+    }
+    println!();
+}
+
+/// Prints how many files were analyzed and why the rest couldn't be, listing the failed files
+/// if `paths` is set
+pub fn print_summary(scan: &Scan, paths: bool) {
+    let summary = scan.summary();
+    println!("scan summary");
+    println!("\tanalyzed: {}, skipped: {}, failed: {}", summary.analyzed, summary.skipped, summary.failed);
+    for (kind, count) in &summary.by_kind {
+        println!("\t{}: {}", kind, count);
+        if !paths {
+            continue;
+        }
+        for failure in summary.failures.iter().filter(|f| f.kind == *kind) {
+            println!("\t\t{} ({})", escape::path(&failure.path), failure.message);
+        }
+    }
+    println!();
+}
@@ -0,0 +1,230 @@
+//! Distro packages owning the files of the analyzed system, from the package manager's database:
+//! dpkg's `info/*.list` files, RPM's `rpmdb.sqlite`, pacman's local database or apk's
+//! `installed` file.
+//!
+//! Files are matched by their directory with symlinks resolved, so a library found under
+//! `/usr/lib` is owned by the package listing it under `/lib` on merged-`/usr` systems.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use clap::ArgEnum;
+use tracing::warn;
+use crate::root;
+use crate::sqlite;
+
+const RPM_DATABASES: &[&str] = &["/usr/lib/sysimage/rpm/rpmdb.sqlite", "/var/lib/rpm/rpmdb.sqlite"];
+/// Berkeley DB and NDB databases of older and SUSE releases
+const RPM_LEGACY_DATABASES: &[&str] = &["/var/lib/rpm/Packages", "/usr/lib/sysimage/rpm/Packages.db", "/var/lib/rpm/Packages.db"];
+
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_OLDFILENAMES: u32 = 1027;
+const RPMTAG_DIRINDEXES: u32 = 1116;
+const RPMTAG_BASENAMES: u32 = 1117;
+const RPMTAG_DIRNAMES: u32 = 1118;
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    Dpkg,
+    Rpm,
+    Pacman,
+    Apk,
+}
+
+impl fmt::Display for Manager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Manager::Dpkg => "dpkg",
+            Manager::Rpm => "rpm",
+            Manager::Pacman => "pacman",
+            Manager::Apk => "apk",
+        })
+    }
+}
+
+impl Manager {
+    /// Package manager whose database is installed under `root`
+    pub fn detect(root: &Path) -> Option<Manager> {
+        let exists = |path: &str| root::join(root, path).exists();
+        if exists("/var/lib/dpkg/status") {
+            Some(Manager::Dpkg)
+        }
+        else if RPM_DATABASES.iter().chain(RPM_LEGACY_DATABASES).any(|p| exists(p)) {
+            Some(Manager::Rpm)
+        }
+        else if exists("/var/lib/pacman/local") {
+            Some(Manager::Pacman)
+        }
+        else if exists("/lib/apk/db/installed") {
+            Some(Manager::Apk)
+        }
+        else {
+            None
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// (package, absolute path) of every file dpkg installed
+fn dpkg_files(root: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(root::join(root, "/var/lib/dpkg/info"))? {
+        let path = entry?.path();
+        let package = match path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".list")) {
+            // `libc6:amd64` for packages installed for several architectures
+            Some(name) => name.split(':').next().unwrap_or(name).to_string(),
+            None => continue,
+        };
+        for line in fs::read_to_string(&path)?.lines().filter(|l| l.starts_with('/')) {
+            files.push((package.clone(), line.to_string()));
+        }
+    }
+    Ok(files)
+}
+
+/// Paths of a pacman `files` or `desc` section, e.g. `%FILES%`
+fn pacman_section<'a>(contents: &'a str, section: &'a str) -> impl Iterator<Item = &'a str> {
+    contents.lines()
+        .skip_while(move |l| *l != section)
+        .skip(1)
+        .take_while(|l| !l.is_empty())
+}
+
+fn pacman_files(root: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(root::join(root, "/var/lib/pacman/local"))? {
+        let dir = entry?.path();
+        let desc = match fs::read_to_string(dir.join("desc")) {
+            Ok(desc) => desc,
+            Err(_) => continue,
+        };
+        let package = match pacman_section(&desc, "%NAME%").next() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        for path in pacman_section(&fs::read_to_string(dir.join("files"))?, "%FILES%") {
+            files.push((package.clone(), format!("/{}", path)));
+        }
+    }
+    Ok(files)
+}
+
+fn apk_files(root: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut files = vec![];
+    let (mut package, mut dir) = (String::new(), String::new());
+    for line in fs::read_to_string(root::join(root, "/lib/apk/db/installed"))?.lines() {
+        match line.split_once(':') {
+            Some(("P", name)) => package = name.to_string(),
+            Some(("F", path)) => dir = path.to_string(),
+            Some(("R", name)) => files.push((package.clone(), format!("/{}/{}", dir, name))),
+            _ => {},
+        }
+    }
+    Ok(files)
+}
+
+/// Name and files of an RPM header blob: index entry count, data length, the entries and the data
+fn rpm_header(blob: &[u8]) -> io::Result<(String, Vec<String>)> {
+    let u32_at = |offset: usize| -> io::Result<u32> {
+        blob.get(offset..offset + 4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| invalid("Truncated RPM header"))
+    };
+    let entries = u32_at(0)? as usize;
+    let data_start = 8 + 16 * entries;
+    let data = blob.get(data_start..).ok_or_else(|| invalid("Truncated RPM header"))?;
+    // tag → (offset, count)
+    let mut tags = HashMap::new();
+    for i in 0..entries {
+        let entry = 8 + 16 * i;
+        tags.insert(u32_at(entry)?, (u32_at(entry + 8)? as usize, u32_at(entry + 12)? as usize));
+    }
+    let strings = |tag: u32| -> io::Result<Vec<String>> {
+        let Some(&(offset, count)) = tags.get(&tag) else { return Ok(vec![]) };
+        data.get(offset..)
+            .map(|d| d.split(|&b| b == 0).take(count).map(|s| String::from_utf8_lossy(s).into_owned()).collect())
+            .ok_or_else(|| invalid("RPM header string out of bounds"))
+    };
+    let name = strings(RPMTAG_NAME)?.into_iter().next().ok_or_else(|| invalid("RPM header without a name"))?;
+    let mut files = strings(RPMTAG_OLDFILENAMES)?;
+    let (dirs, basenames) = (strings(RPMTAG_DIRNAMES)?, strings(RPMTAG_BASENAMES)?);
+    if let Some(&(offset, count)) = tags.get(&RPMTAG_DIRINDEXES) {
+        for (i, basename) in basenames.iter().enumerate().take(count) {
+            let index = u32_at(data_start + offset + 4 * i)? as usize;
+            let dir = dirs.get(index).ok_or_else(|| invalid("RPM directory index out of bounds"))?;
+            files.push(format!("{}{}", dir, basename));
+        }
+    }
+    Ok((name, files))
+}
+
+fn rpm_files(root: &Path) -> io::Result<Vec<(String, String)>> {
+    let path = match RPM_DATABASES.iter().map(|p| root::join(root, p)).find(|p| p.exists()) {
+        Some(path) => path,
+        None => return Err(io::Error::new(io::ErrorKind::Unsupported, "only SQLite RPM databases are supported")),
+    };
+    let mut files = vec![];
+    // hnum, blob
+    for row in sqlite::Database::open(&path)?.rows("Packages")? {
+        let blob = match row.get(1) {
+            Some(sqlite::Value::Bytes(blob)) => blob,
+            _ => continue,
+        };
+        match rpm_header(blob) {
+            Ok((name, paths)) => files.extend(paths.into_iter().map(|p| (name.clone(), p))),
+            Err(e) => warn!("Skipping RPM header in {}: {}", path.display(), e),
+        }
+    }
+    Ok(files)
+}
+
+/// Owners of the files of an analyzed system
+pub struct Database {
+    pub manager: Manager,
+    root: PathBuf,
+    /// Packages by canonical directory and file name
+    owners: HashMap<(PathBuf, OsString), String>,
+}
+
+impl Database {
+    /// Reads `manager`'s database under `root`
+    pub fn load(root: &Path, manager: Manager) -> io::Result<Database> {
+        let files = match manager {
+            Manager::Dpkg => dpkg_files(root)?,
+            Manager::Rpm => rpm_files(root)?,
+            Manager::Pacman => pacman_files(root)?,
+            Manager::Apk => apk_files(root)?,
+        };
+        let mut dirs: HashMap<String, Option<PathBuf>> = HashMap::new();
+        let mut owners = HashMap::new();
+        for (package, path) in files {
+            let (dir, name) = match path.rsplit_once('/') {
+                Some((dir, name)) if !name.is_empty() => (dir, name),
+                _ => continue,
+            };
+            let canonical = dirs.entry(dir.to_string())
+                .or_insert_with(|| root::canonicalize(root, &root::join(root, dir)));
+            if let Some(dir) = canonical {
+                owners.entry((dir.clone(), OsString::from(name))).or_insert(package);
+            }
+        }
+        Ok(Database { manager, root: root.to_path_buf(), owners })
+    }
+
+    /// Package owning `path`, a host path under the root, or the file it links to
+    pub fn owner(&self, path: &Path) -> Option<&str> {
+        let key = |path: &Path| -> Option<(PathBuf, OsString)> {
+            let dir = root::canonicalize(&self.root, path.parent()?)?;
+            Some((dir, path.file_name()?.to_os_string()))
+        };
+        key(path).and_then(|k| self.owners.get(&k))
+            .or_else(|| root::canonicalize(&self.root, path).and_then(|p| key(&p)).and_then(|k| self.owners.get(&k)))
+            .map(String::as_str)
+    }
+}
@@ -0,0 +1,154 @@
+//! Matching of library names against user-supplied patterns.
+
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Exact(String),
+    /// Shell-style glob with `*`, `?` and `[...]`
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Glob if `pattern` contains glob metacharacters, exact match otherwise
+    pub fn new(pattern: &str) -> Self {
+        if pattern.contains(['*', '?', '[']) {
+            Pattern::Glob(pattern.to_string())
+        }
+        else {
+            Pattern::Exact(pattern.to_string())
+        }
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Pattern::Regex)
+    }
+
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            Pattern::Exact(p) => p == s,
+            Pattern::Glob(p) => glob_match(p.as_bytes(), s.as_bytes()),
+            Pattern::Regex(r) => r.is_match(s),
+        }
+    }
+}
+
+/// Parses a `[...]` class at the start of `pattern`, returning whether `c` is in it and the
+/// length of the class. `None` if the class isn't terminated.
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(pattern.get(i), Some(b'!') | Some(b'^'));
+    if negate {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    while i < pattern.len() {
+        if pattern[i] == b']' && !first {
+            return Some((found != negate, i + 1));
+        }
+        first = false;
+        if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            found |= pattern[i] <= c && c <= pattern[i + 2];
+            i += 3;
+        }
+        else {
+            found |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+pub fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // position after the last `*` and the text position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while i < s.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, i));
+                p += 1;
+                continue;
+            },
+            Some(b'?') => Some(1),
+            Some(b'[') => match match_class(&pattern[p..], s[i]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                None => (s[i] == b'[').then_some(1),
+            },
+            Some(&c) => (c == s[i]).then_some(1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                i += 1;
+            },
+            (None, Some((bp, bi))) => {
+                p = bp;
+                i = bi + 1;
+                backtrack = Some((bp, bi + 1));
+            },
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Up to three of `names` close to `query`, closest first: within a few edits of it, or
+/// starting with its literal part (up to the first glob metacharacter)
+pub fn suggestions<'a, I: IntoIterator<Item = &'a str>>(query: &str, names: I) -> Vec<&'a str> {
+    let literal = query.split(['*', '?', '[']).next().unwrap_or_default();
+    let max_distance = (query.len() / 4).max(2);
+    let mut close: Vec<(usize, &str)> = names.into_iter()
+        .filter_map(|name| {
+            let distance = edit_distance(query.as_bytes(), name.as_bytes());
+            let prefix = literal.len() >= 3 && (name.starts_with(literal) || literal.starts_with(name));
+            (distance <= max_distance || prefix).then_some((distance, name))
+        })
+        .collect();
+    close.sort_unstable();
+    close.dedup();
+    close.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// Library names hidden from reports
+#[derive(Debug, Clone, Default)]
+pub struct LibFilter {
+    hidden: Vec<Pattern>,
+    /// Patterns one of which shown libraries must match, any library if empty
+    shown: Vec<Pattern>,
+}
+
+impl LibFilter {
+    pub fn hide<I: IntoIterator<Item = Pattern>>(&mut self, patterns: I) {
+        self.hidden.extend(patterns);
+    }
+
+    /// Hides every library matching none of `patterns`
+    pub fn show_only<I: IntoIterator<Item = Pattern>>(&mut self, patterns: I) {
+        self.shown.extend(patterns);
+    }
+
+    pub fn is_hidden(&self, lib: &str) -> bool {
+        self.hidden.iter().any(|p| p.matches(lib))
+            || (!self.shown.is_empty() && !self.shown.iter().any(|p| p.matches(lib)))
+    }
+}
@@ -0,0 +1,192 @@
+//! DLL import tables of Windows executables, as found in Wine prefixes, shares and firmware.
+
+use object::{Architecture, Object};
+use object::read::pe::{ImageNtHeaders, PeFile, PeFile32, PeFile64};
+use tracing::debug;
+use crate::elf::{BinaryFormat, ElfInfo, HandleError};
+use crate::escape;
+
+fn arch_name(arch: Architecture) -> String {
+    let name = match arch {
+        Architecture::I386 => "i386",
+        Architecture::X86_64 => "x86_64",
+        Architecture::Arm => "arm",
+        Architecture::Aarch64 => "aarch64",
+        _ => return format!("{:?}", arch).to_lowercase(),
+    };
+    name.to_string()
+}
+
+fn extract_info<Pe: ImageNtHeaders>(file: &PeFile<Pe>, size: u64) -> Result<ElfInfo, HandleError> {
+    let mut needed: Vec<String> = vec![];
+    let mut imports = vec![];
+    for import in file.imports().map_err(HandleError::ObjectReadError)? {
+        // DLL names are case-insensitive, `KERNEL32.dll` and `kernel32.dll` are the same library
+        let library = escape::bytes(import.library()).to_lowercase();
+        if !needed.contains(&library) {
+            needed.push(library);
+        }
+        imports.push(escape::bytes(import.name()));
+    }
+    Ok(ElfInfo {
+        format: BinaryFormat::Pe,
+        arch: arch_name(file.architecture()),
+        bits: if file.is_64() { 64 } else { 32 },
+        size,
+        needed,
+        imports,
+        ..ElfInfo::default()
+    })
+}
+
+/// Parses the import table of a PE file already read into memory
+pub fn parse_pe_data(bin_data: &[u8]) -> Result<ElfInfo, HandleError> {
+    let size = bin_data.len() as u64;
+    match object::FileKind::parse(bin_data).map_err(HandleError::ObjectReadError)? {
+        object::FileKind::Pe32 => {
+            debug!("Parsing pe32 file");
+            let file = PeFile32::parse(bin_data).map_err(HandleError::ObjectReadError)?;
+            extract_info(&file, size)
+        },
+        object::FileKind::Pe64 => {
+            debug!("Parsing pe64 file");
+            let file = PeFile64::parse(bin_data).map_err(HandleError::ObjectReadError)?;
+            extract_info(&file, size)
+        },
+        _ => Err(HandleError::NotElf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::pe;
+
+    /// Offset of the section table, after the DOS header, the signature and the COFF header
+    fn section_table(bits: u8) -> usize {
+        0x40 + 4 + 20 + if bits == 64 { 240 } else { 224 }
+    }
+
+    /// Offset of `bytes` appended to `strings`, NUL-terminated and padded to an even length
+    fn push(strings: &mut Vec<u8>, bytes: &[u8]) -> usize {
+        let at = strings.len();
+        strings.extend_from_slice(bytes);
+        strings.resize((strings.len() + 1).next_multiple_of(2), 0);
+        at
+    }
+
+    /// `.idata` section at `rva` importing `imports` from every DLL, by ordinal where a name is
+    /// empty
+    fn idata(bits: u8, rva: u32, imports: &[(&str, &[&str])]) -> Vec<u8> {
+        let thunk = usize::from(bits / 8);
+        let descriptors = 20 * (imports.len() + 1);
+        let strings_at = descriptors + imports.iter().map(|(_, names)| thunk * (names.len() + 1)).sum::<usize>();
+        let (mut table, mut thunks, mut strings) = (vec![], vec![], vec![]);
+        for (dll, names) in imports {
+            let first_thunk = rva as usize + descriptors + thunks.len();
+            let name = rva as usize + strings_at + push(&mut strings, dll.as_bytes());
+            for value in [first_thunk, 0, 0, name, first_thunk] {
+                table.extend_from_slice(&(value as u32).to_le_bytes());
+            }
+            for name in names.iter() {
+                let value = match name.is_empty() {
+                    true => 1 << (bits - 1) | 7,
+                    // a hint, then the name
+                    false => (rva as usize + strings_at + push(&mut strings, &[b"\0\0", name.as_bytes()].concat())) as u64,
+                };
+                thunks.extend_from_slice(&value.to_le_bytes()[..thunk]);
+            }
+            thunks.extend(vec![0; thunk]);
+        }
+        [table, vec![0; 20], thunks, strings].concat()
+    }
+
+    /// Executable of `bits` for `machine` whose only section imports `imports`
+    fn file(bits: u8, machine: u16, imports: &[(&str, &[&str])]) -> Vec<u8> {
+        let (rva, offset) = (0x1000u32, 0x200usize);
+        let section = idata(bits, rva, imports);
+        let mut file = vec![0; offset + section.len().next_multiple_of(0x200)];
+        let mut put = |at: usize, bytes: &[u8]| file[at..at + bytes.len()].copy_from_slice(bytes);
+        put(0, b"MZ");
+        put(0x3c, &0x40u32.to_le_bytes());
+        put(0x40, b"PE\0\0");
+        put(0x44, &machine.to_le_bytes());
+        put(0x46, &1u16.to_le_bytes());
+        let optional = section_table(bits) - 0x58;
+        put(0x54, &(optional as u16).to_le_bytes());
+        put(0x56, &(pe::IMAGE_FILE_EXECUTABLE_IMAGE | pe::IMAGE_FILE_DLL).to_le_bytes());
+        let magic = if bits == 64 { pe::IMAGE_NT_OPTIONAL_HDR64_MAGIC } else { pe::IMAGE_NT_OPTIONAL_HDR32_MAGIC };
+        put(0x58, &magic.to_le_bytes());
+        // section and file alignment, and 16 data directories, the import one pointing at the
+        // start of the section
+        put(0x58 + 32, &0x1000u32.to_le_bytes());
+        put(0x58 + 36, &0x200u32.to_le_bytes());
+        let directories = section_table(bits) - 16 * 8;
+        put(directories - 4, &16u32.to_le_bytes());
+        put(directories + 8, &rva.to_le_bytes());
+        put(directories + 12, &(section.len() as u32).to_le_bytes());
+        let header = section_table(bits);
+        put(header, b".idata\0\0");
+        put(header + 8, &(section.len() as u32).to_le_bytes());
+        put(header + 12, &rva.to_le_bytes());
+        put(header + 16, &(section.len().next_multiple_of(0x200) as u32).to_le_bytes());
+        put(header + 20, &(offset as u32).to_le_bytes());
+        put(offset, &section);
+        file
+    }
+
+    fn imports() -> Vec<(&'static str, &'static [&'static str])> {
+        vec![
+            ("KERNEL32.dll", &["ExitProcess", "GetLastError"]),
+            ("msvcrt.dll", &["printf", ""]),
+            ("kernel32.dll", &["Sleep"]),
+        ]
+    }
+
+    fn kind<T>(result: Result<T, HandleError>) -> &'static str {
+        result.err().map_or("", |e| e.kind())
+    }
+
+    #[test]
+    fn reads_import_tables() {
+        let data = file(64, pe::IMAGE_FILE_MACHINE_AMD64, &imports());
+        let info = parse_pe_data(&data).unwrap();
+        assert_eq!(info.format, BinaryFormat::Pe);
+        assert_eq!((info.arch.as_str(), info.bits, info.size), ("x86_64", 64, data.len() as u64));
+        // DLLs are named in lower case, once, and imports by ordinal have no name
+        assert_eq!(info.needed, ["kernel32.dll", "msvcrt.dll"]);
+        assert_eq!(info.imports, ["ExitProcess", "GetLastError", "printf", "Sleep"]);
+
+        let info = parse_pe_data(&file(32, pe::IMAGE_FILE_MACHINE_I386, &imports())).unwrap();
+        assert_eq!((info.arch.as_str(), info.bits), ("i386", 32));
+        assert_eq!(info.imports, ["ExitProcess", "GetLastError", "printf", "Sleep"]);
+        let info = parse_pe_data(&file(64, pe::IMAGE_FILE_MACHINE_ARM64, &[])).unwrap();
+        assert_eq!((info.arch.as_str(), info.needed.len()), ("aarch64", 0));
+    }
+
+    #[test]
+    fn malformed() {
+        let data = file(64, pe::IMAGE_FILE_MACHINE_AMD64, &imports());
+        // cut in the headers, the section table or the section
+        for len in [0x60, section_table(64) + 20, 0x210] {
+            assert_eq!(kind(parse_pe_data(&data[..len])), "parse", "{:#x}", len);
+        }
+        // more sections than the file holds
+        let mut sections = data.clone();
+        sections[0x46..0x48].copy_from_slice(&1000u16.to_le_bytes());
+        assert_eq!(kind(parse_pe_data(&sections)), "parse");
+        // an optional header running past the file, or too short for its data directories
+        let mut optional = data.clone();
+        optional[0x54..0x56].copy_from_slice(&0xf000u16.to_le_bytes());
+        assert_eq!(kind(parse_pe_data(&optional)), "parse");
+        optional[0x54..0x56].copy_from_slice(&16u16.to_le_bytes());
+        assert_eq!(kind(parse_pe_data(&optional)), "parse");
+        // a DLL name outside the section
+        let mut name = data.clone();
+        name[0x200 + 12..0x200 + 16].copy_from_slice(&0x8000u32.to_le_bytes());
+        assert_eq!(kind(parse_pe_data(&name)), "parse");
+        let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+        elf.resize(64, 0);
+        assert_eq!(kind(parse_pe_data(&elf)), "not-elf");
+    }
+}
@@ -0,0 +1,80 @@
+//! Distro release profiles: the newest symbol versions of the base runtime libraries and the
+//! sonames the release's packages provide.
+//!
+//! Profiles are JSON files; the built-in ones are compiled in from `profiles/`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::output::text::soname_family;
+
+/// Built-in profiles by the name `check --target` accepts
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("debian-11", include_str!("../profiles/debian-11.json")),
+    ("debian-12", include_str!("../profiles/debian-12.json")),
+    ("ubuntu-20.04", include_str!("../profiles/ubuntu-20.04.json")),
+    ("ubuntu-22.04", include_str!("../profiles/ubuntu-22.04.json")),
+    ("rhel-8", include_str!("../profiles/rhel-8.json")),
+    ("rhel-9", include_str!("../profiles/rhel-9.json")),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Release name shown in reports, e.g. `Debian 11`
+    pub name: String,
+    /// Newest version of every symbol version family, e.g. `GLIBC` → `2.31`
+    pub versions: BTreeMap<String, String>,
+    /// Sonames installable from the release's repositories
+    pub sonames: Vec<String>,
+}
+
+/// Dotted version as numbers, `None` for names like `GLIBC_PRIVATE`
+pub fn numbers(version: &str) -> Option<Vec<u32>> {
+    version.split('.').map(|n| n.parse().ok()).collect()
+}
+
+/// Library a soname is a version of, ignoring a version in the name itself so that
+/// `libpython3.9.so.1.0` and `libpython3.11.so.1.0`, or `libldap-2.4.so.2` and
+/// `libldap-2.5.so.0`, are the same library
+fn library(soname: &str) -> &str {
+    static VERSION: OnceLock<Regex> = OnceLock::new();
+    let version = VERSION.get_or_init(|| Regex::new(r"(?:-\d+(?:\.\d+)*|\d+\.\d+[a-z]?)$").unwrap());
+    let family = soname_family(soname);
+    version.find(family).map_or(family, |m| &family[..m.start()])
+}
+
+impl Profile {
+    /// Built-in profile `name`, or the profile in the file at that path
+    pub fn load(name: &str) -> io::Result<Self> {
+        let data = match BUILTIN.iter().find(|(n, _)| *n == name) {
+            Some((_, data)) => data.to_string(),
+            None => fs::read_to_string(Path::new(name))?,
+        };
+        serde_json::from_str(&data).map_err(io::Error::from)
+    }
+
+    /// Family of symbol version `version` (e.g. `GLIBC` for `GLIBC_2.34`) with the newest
+    /// version of it the release has, if that's older. Unknown families aren't judged
+    pub fn missing_version<'a>(&self, version: &'a str) -> Option<(&'a str, &str)> {
+        let (family, number) = version.rsplit_once('_')?;
+        let newest = self.versions.get(family)?;
+        (numbers(number)? > numbers(newest)?).then_some((family, newest.as_str()))
+    }
+
+    /// Versions of the library `soname` belongs to the release has, if it doesn't have
+    /// `soname` itself. Libraries the profile knows nothing about aren't judged
+    pub fn missing_soname(&self, soname: &str) -> Option<Vec<&str>> {
+        if self.sonames.iter().any(|s| s == soname) {
+            return None;
+        }
+        let others: Vec<&str> = self.sonames.iter()
+            .filter(|s| library(s) == library(soname))
+            .map(String::as_str)
+            .collect();
+        (!others.is_empty()).then_some(others)
+    }
+}
@@ -0,0 +1,261 @@
+//! Progress of a running scan, shown on stderr so that scanning a whole root filesystem doesn't
+//! look stuck, and the statistics of a finished one.
+//!
+//! The progress line is redrawn in place on a terminal and printed every few seconds otherwise.
+//! Log messages written through [`stderr`] clear it first, so the two don't garble each other.
+
+use std::io::{self, IsTerminal, Write};
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::fd_budget;
+use crate::scan::Scan;
+
+/// How often the line is redrawn on a terminal
+const REDRAW: Duration = Duration::from_millis(100);
+/// How often it's printed when stderr isn't a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether a progress line is on the terminal now, guarding every write to stderr
+static DRAWN: Mutex<bool> = Mutex::new(false);
+
+/// Rows and columns of the terminal on the file descriptor `fd`, if it's one
+#[cfg(unix)]
+pub fn window_size(fd: c_int) -> Option<(usize, usize)> {
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: TIOCGWINSZ only writes a `winsize` to the pointer, which is valid for the call
+    match unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_row > 0 && size.ws_col > 0 => Some((usize::from(size.ws_row), usize::from(size.ws_col))),
+        _ => None,
+    }
+}
+
+/// The size of terminals isn't asked for elsewhere
+#[cfg(not(unix))]
+pub fn window_size(_fd: c_int) -> Option<(usize, usize)> {
+    None
+}
+
+/// Width of the terminal on stderr, 80 columns if it can't be told
+fn columns() -> usize {
+    window_size(2).map_or(80, |(_, columns)| columns)
+}
+
+/// Counters updated by the threads of a scan
+#[derive(Debug)]
+pub struct Progress {
+    started: Instant,
+    /// Files found while listing the scanned directories
+    listed: AtomicU64,
+    /// Files the scan looks at, known once the listing is done
+    total: AtomicU64,
+    processed: AtomicU64,
+    bytes: AtomicU64,
+    /// Whether the directories are still being listed
+    listing: AtomicBool,
+    /// Name of the file or directory handled last
+    current: Mutex<String>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress {
+            started: Instant::now(),
+            listed: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            processed: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            listing: AtomicBool::new(false),
+            current: Mutex::new(String::new()),
+        }
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Progress::default()
+    }
+
+    /// Accounts for `count` files found in the directory `name` being listed
+    pub fn listed(&self, name: &str, count: usize) {
+        self.listing.store(true, Ordering::Relaxed);
+        self.listed.fetch_add(count as u64, Ordering::Relaxed);
+        name.clone_into(&mut self.current.lock().unwrap());
+    }
+
+    /// Ends a listing that found `count` files to look at
+    pub fn discovered(&self, count: usize) {
+        self.listing.store(false, Ordering::Relaxed);
+        self.total.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Marks the file `name` as being handled
+    pub fn start(&self, name: &str) {
+        name.clone_into(&mut self.current.lock().unwrap());
+    }
+
+    /// Accounts for `bytes` read from disk
+    pub fn read(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Accounts for a file handled, whether it was read or its result came from the cache
+    pub fn processed(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// One line telling how far the scan got
+    pub fn line(&self) -> String {
+        let current = self.current.lock().unwrap().clone();
+        if self.listing.load(Ordering::Relaxed) {
+            return format!("listing: {} files found, {}", self.listed.load(Ordering::Relaxed), current);
+        }
+        let elapsed = self.started.elapsed().as_secs_f64().max(0.001);
+        let processed = self.processed.load(Ordering::Relaxed);
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        format!(
+            "{}/{} files, {} ({}/s, {:.0} files/s), {}",
+            processed, self.total.load(Ordering::Relaxed), megabytes(bytes), megabytes((bytes as f64 / elapsed) as u64),
+            processed as f64 / elapsed, current,
+        )
+    }
+
+    /// Statistics of the finished `scan` this progress was kept for
+    pub fn stats(&self, scan: &Scan) -> Stats {
+        let summary = scan.summary();
+        Stats {
+            scanned: self.processed.load(Ordering::Relaxed),
+            analyzed: summary.analyzed,
+            skipped: summary.skipped,
+            failed: summary.failed,
+            bytes: self.bytes.load(Ordering::Relaxed),
+            elapsed: self.started.elapsed(),
+            open_files_limit: fd_budget::limit(),
+            peak_open_files: fd_budget::peak(),
+        }
+    }
+}
+
+/// Bytes in megabytes (10^6 bytes) with one decimal
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+/// Prints `line` over the progress line, or below the last one when it's not redrawn in place
+fn draw(line: &str, in_place: bool) {
+    let mut drawn = DRAWN.lock().unwrap();
+    let mut stderr = io::stderr().lock();
+    let _ = if in_place {
+        // cut to the terminal's width so that the line never wraps and can be overwritten
+        let line: String = line.chars().take(columns().saturating_sub(1)).collect();
+        *drawn = true;
+        write!(stderr, "\r\x1b[K{}", line)
+    }
+    else {
+        writeln!(stderr, "{}", line)
+    };
+    let _ = stderr.flush();
+}
+
+/// Clears the progress line if there's one
+fn clear(drawn: &mut bool) {
+    if std::mem::take(drawn) {
+        let _ = write!(io::stderr(), "\r\x1b[K");
+    }
+}
+
+/// Progress line kept up to date by a thread of its own until dropped
+pub struct Display {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Starts showing `progress` on stderr
+pub fn show(progress: Arc<Progress>) -> Display {
+    let stop = Arc::new(AtomicBool::new(false));
+    let in_place = io::stderr().is_terminal();
+    let thread = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let interval = if in_place { REDRAW } else { LOG_INTERVAL };
+            let mut drawn_at = Instant::now();
+            while !stop.load(Ordering::Relaxed) {
+                thread::sleep(REDRAW);
+                if drawn_at.elapsed() >= interval {
+                    draw(&progress.line(), in_place);
+                    drawn_at = Instant::now();
+                }
+            }
+        })
+    };
+    Display { stop, thread: Some(thread) }
+}
+
+impl Drop for Display {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        clear(&mut DRAWN.lock().unwrap());
+    }
+}
+
+/// Stderr clearing the progress line before anything is written to it, for log messages
+pub struct Writer;
+
+/// [`Writer`], to be passed to `tracing_subscriber`'s `with_writer`
+pub fn stderr() -> Writer {
+    Writer
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut drawn = DRAWN.lock().unwrap();
+        clear(&mut drawn);
+        io::stderr().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Counts of a finished scan
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// Files looked at
+    pub scanned: u64,
+    /// Binaries analyzed
+    pub analyzed: usize,
+    /// Files that aren't dynamically linked binaries, or are special files
+    pub skipped: usize,
+    /// Files that couldn't be analyzed
+    pub failed: usize,
+    /// Bytes read from disk, not counting results reused from the cache
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// Files that may be open at once, and the most that were
+    pub open_files_limit: usize,
+    pub peak_open_files: usize,
+}
+
+impl Stats {
+    /// Prints the statistics to stderr, in the layout of the text report's sections
+    pub fn print(&self) {
+        let mut drawn = DRAWN.lock().unwrap();
+        clear(&mut drawn);
+        let secs = self.elapsed.as_secs_f64();
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(stderr, "scan statistics");
+        let _ = writeln!(stderr, "\tfiles scanned: {}", self.scanned);
+        let _ = writeln!(stderr, "\tbinaries analyzed: {}", self.analyzed);
+        let _ = writeln!(stderr, "\tskipped: {}", self.skipped);
+        let _ = writeln!(stderr, "\tfailed: {}", self.failed);
+        let _ = writeln!(stderr, "\tbytes read: {}", megabytes(self.bytes));
+        let _ = writeln!(stderr, "\topen files: at most {} at once, limit {}", self.peak_open_files, self.open_files_limit);
+        let _ = writeln!(stderr, "\telapsed: {:.2}s ({}/s)", secs, megabytes((self.bytes as f64 / secs.max(0.001)) as u64));
+    }
+}
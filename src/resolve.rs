@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::elf::{ElfInfo, Target, parse_elf};
+use crate::escape;
+use crate::root;
+
+pub const DEFAULT_LIB_DIRS: &[&str] = &["/lib", "/lib64", "/usr/lib", "/usr/lib64", "/usr/local/lib"];
+
+/// Shared library found on disk for some soname
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    #[serde(with = "crate::escape::serde_path")]
+    pub path: PathBuf,
+    pub info: ElfInfo,
+}
+
+/// Finds shared libraries by soname in the standard library directories, caching the results
+pub struct Resolver {
+    /// Root of the analyzed filesystem, `/` for the host
+    pub root: PathBuf,
+    pub dirs: Vec<PathBuf>,
+    /// Follow the probable `dlopen` dependencies of binaries and libraries in closures too
+    pub dlopen: bool,
+    cache: HashMap<String, Vec<Library>>,
+}
+
+/// Standard library directories under `root`, including multiarch ones like
+/// `/usr/lib/x86_64-linux-gnu`
+pub fn standard_lib_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    for dir in DEFAULT_LIB_DIRS {
+        let dir = root::join(root, dir);
+        if let Ok(entries) = fs::read_dir(&dir) {
+            let mut multiarch_dirs: Vec<PathBuf> = entries.flatten()
+                .filter(|e| e.file_name().to_str().is_some_and(|n| n.contains("-linux-")))
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect();
+            multiarch_dirs.sort();
+            dirs.extend(multiarch_dirs);
+        }
+        dirs.push(dir);
+    }
+    // merged-usr systems have /lib pointing to /usr/lib
+    let mut seen = HashSet::new();
+    dirs.retain(|d| seen.insert(root::canonicalize(root, d).unwrap_or_else(|| d.clone())));
+    dirs
+}
+
+impl Library {
+    /// Whether the library can be loaded into a process of the given target
+    pub fn compatible_with(&self, target: &Target) -> bool {
+        self.info.arch == target.arch && self.info.bits == target.bits
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::with_root(PathBuf::from("/"))
+    }
+
+    /// Resolver looking up libraries in the filesystem tree at `root`
+    pub fn with_root(root: PathBuf) -> Self {
+        Resolver { dirs: standard_lib_dirs(&root), root, dlopen: false, cache: HashMap::new() }
+    }
+
+    /// Resolver answering only from previously resolved `libraries`, never touching the disk
+    pub fn from_libraries(libraries: BTreeMap<String, Vec<Library>>) -> Self {
+        Resolver { root: PathBuf::from("/"), dirs: vec![], dlopen: false, cache: libraries.into_iter().collect() }
+    }
+
+    /// Every soname looked up so far, with all the libraries found for it
+    pub fn libraries(&self) -> BTreeMap<String, Vec<Library>> {
+        self.cache.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    fn find(&self, soname: &str) -> Vec<Library> {
+        let candidates: Vec<PathBuf> = if soname.contains('/') {
+            vec![root::join(&self.root, soname)]
+        }
+        else {
+            self.dirs.iter().map(|d| d.join(escape::unescape_os_str(soname))).collect()
+        };
+        let mut found = vec![];
+        for path in candidates {
+            // symlinks are followed inside the root, not on the host
+            let real = match root::canonicalize(&self.root, &path) {
+                Some(p) if p.is_file() => p,
+                _ => continue,
+            };
+            match parse_elf(&real) {
+                Ok(info) => {
+                    debug!("Found {} at {}", soname, path.display());
+                    found.push(Library { path, info });
+                },
+                Err(e) => warn!("Couldn't parse library {}: {}", path.display(), e),
+            }
+        }
+        found
+    }
+
+    /// Every library named `soname` in the search directories, of any architecture
+    pub fn candidates(&mut self, soname: &str) -> &[Library] {
+        if !self.cache.contains_key(soname) {
+            let libs = self.find(soname);
+            self.cache.insert(soname.to_string(), libs);
+        }
+        &self.cache[soname]
+    }
+
+    /// Whether the analyzed filesystem has a file at `path`. Resolvers built from a list of
+    /// libraries only know those, so any of them with the same file name counts.
+    pub fn has_file(&self, path: &str) -> bool {
+        if !self.dirs.is_empty() {
+            return root::canonicalize(&self.root, &root::join(&self.root, path)).is_some_and(|p| p.is_file());
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.cache.values().flatten().any(|l| l.path.file_name().is_some_and(|n| escape::os_str(n) == name))
+    }
+
+    /// Looks up the library providing `soname` to `target`, `None` if it can't be found
+    pub fn resolve(&mut self, soname: &str, target: &Target) -> Option<&Library> {
+        self.candidates(soname).iter().find(|l| l.compatible_with(target))
+    }
+
+    /// All sonames reachable from `needed` for `target`, direct dependencies first.
+    /// Unresolved sonames are included but not expanded.
+    pub fn closure(&mut self, needed: &[String], target: &Target) -> Vec<String> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<String> = needed.iter().cloned().collect();
+        let mut result = vec![];
+        while let Some(soname) = queue.pop_front() {
+            if !seen.insert(soname.clone()) {
+                continue;
+            }
+            let dlopen = self.dlopen;
+            if let Some(lib) = self.resolve(&soname, target) {
+                queue.extend(lib.info.needs(dlopen));
+            }
+            result.push(soname);
+        }
+        result
+    }
+
+    /// [`closure`](Self::closure) of the libraries `info` needs
+    pub fn closure_of(&mut self, info: &ElfInfo) -> Vec<String> {
+        self.closure(&info.needs(self.dlopen), &info.target())
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
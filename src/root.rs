@@ -0,0 +1,68 @@
+//! Paths inside the filesystem tree being analyzed, which need not be the host's. Absolute paths
+//! and symlinks found in binaries are interpreted relative to the tree's root, the way they would
+//! be on the system the tree belongs to, so the host's own `/lib` or `/etc` never leak in.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use crate::escape;
+
+/// Symlinks followed before giving up, as with Linux's `ELOOP`
+const MAX_SYMLINKS: usize = 40;
+
+/// `path`, absolute on the analyzed system and escaped, as a host path under `root`
+pub fn join(root: &Path, path: &str) -> PathBuf {
+    let mut result = root.to_path_buf();
+    result.extend(path.split('/').filter(|c| !c.is_empty()).map(escape::unescape_os_str));
+    result
+}
+
+/// Resolves every symlink in `path`, a host path under `root`, with absolute link targets taken
+/// relative to `root` and `..` never leaving it. Paths outside `root` are canonicalized as is.
+/// `None` if the path doesn't exist.
+pub fn canonicalize(root: &Path, path: &Path) -> Option<PathBuf> {
+    let relative = match path.strip_prefix(root) {
+        Ok(r) => r,
+        Err(_) => return fs::canonicalize(path).ok(),
+    };
+    let mut pending: Vec<OsString> = components(relative);
+    pending.reverse();
+    let mut resolved: Vec<OsString> = vec![];
+    let mut links = 0;
+    while let Some(component) = pending.pop() {
+        if component == ".." {
+            resolved.pop();
+            continue;
+        }
+        let mut candidate = root.to_path_buf();
+        candidate.extend(&resolved);
+        candidate.push(&component);
+        let metadata = fs::symlink_metadata(&candidate).ok()?;
+        if !metadata.file_type().is_symlink() {
+            resolved.push(component);
+            continue;
+        }
+        links += 1;
+        if links > MAX_SYMLINKS {
+            return None;
+        }
+        let target = fs::read_link(&candidate).ok()?;
+        if target.has_root() {
+            resolved.clear();
+        }
+        pending.extend(components(&target).into_iter().rev());
+    }
+    let mut result = root.to_path_buf();
+    result.extend(resolved);
+    Some(result)
+}
+
+fn components(path: &Path) -> Vec<OsString> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,579 @@
+//! User-defined rules: expressions evaluated against each scanned binary, producing findings of
+//! the severity and with the message the rule gives.
+//!
+//! Rules files are JSON arrays of `{"name", "severity", "when", "message"}` objects. `when` is a
+//! small CEL-like expression over the `binary` record, e.g.
+//! `binary.setuid && !binary.pie` or `"libssl.so.1.1" in binary.needed && binary.path.startsWith("/opt")`:
+//!
+//! * literals: `"strings"`, integers, `true`, `false`, `null` and lists `[...]`
+//! * `binary.<field>`, see [`FIELDS`]
+//! * `!`, `&&`, `||`, `==`, `!=`, `<`, `<=`, `>`, `>=`, parentheses
+//! * `x in list`, and `s in string` for substrings
+//! * methods `startsWith`, `endsWith`, `contains`, `matches` (a regular expression) and `size`;
+//!   the lowercase `startswith` and `endswith` work too
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::warn;
+use crate::analysis::checksec;
+use crate::escape;
+use crate::scan::ScannedFile;
+
+/// Fields of the `binary` record
+pub const FIELDS: &[&str] = &[
+    "path", "name", "format", "arch", "bits", "size", "mode", "setuid", "setgid", "pie", "interp",
+    "soname", "needed", "rpaths", "runpaths", "imports", "exports", "embedded", "isa_level",
+    "relro", "nx", "canary", "textrel", "build_id",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Notice => "notice",
+        }
+    }
+}
+
+/// Rule as written in the rules file
+#[derive(Deserialize)]
+struct Spec {
+    name: String,
+    severity: Severity,
+    when: String,
+    message: String,
+}
+
+pub struct Rule {
+    pub name: String,
+    pub severity: Severity,
+    pub message: String,
+    when: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+        }
+    }
+}
+
+fn strings(values: &[String]) -> Value {
+    Value::List(values.iter().cloned().map(Value::Str).collect())
+}
+
+fn optional(value: Option<String>) -> Value {
+    value.map_or(Value::Null, Value::Str)
+}
+
+fn field(file: &ScannedFile, name: &str) -> Value {
+    let info = &file.info;
+    match name {
+        "path" => Value::Str(escape::path(&file.path)),
+        "name" => Value::Str(file.name.clone()),
+        "format" => Value::Str(info.format.to_string()),
+        "arch" => Value::Str(info.arch.clone()),
+        "bits" => Value::Int(i64::from(info.bits)),
+        "size" => Value::Int(info.size as i64),
+        "mode" => Value::Int(i64::from(info.mode)),
+        "setuid" => Value::Bool(info.mode & 0o4000 != 0),
+        "setgid" => Value::Bool(info.mode & 0o2000 != 0),
+        "pie" => Value::Bool(info.pie),
+        "interp" => optional(info.interp.clone()),
+        "soname" => optional(info.soname.clone()),
+        "needed" => strings(&info.needed),
+        "rpaths" => strings(&info.rpaths),
+        "runpaths" => strings(&info.runpaths),
+        "imports" => strings(&info.imports),
+        "exports" => strings(&info.exports),
+        "embedded" => Value::List(info.embedded.iter().map(|e| Value::Str(e.to_string())).collect()),
+        "isa_level" => info.isa_level.map_or(Value::Null, |l| Value::Int(i64::from(l))),
+        "relro" => Value::Str(checksec::checks(info).relro.to_string()),
+        "nx" => Value::Bool(info.hardening.nx),
+        "canary" => Value::Bool(checksec::checks(info).canary),
+        "textrel" => Value::Bool(info.hardening.textrel),
+        "build_id" => optional(info.build_id.clone()),
+        _ => unreachable!("unknown field {}", name),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    List(Vec<Expr>),
+    Field(&'static str),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Op, Box<Expr>, Box<Expr>),
+    Method(Box<Expr>, String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Int(i64),
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &["&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", "[", "]", ",", "."];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        }
+        else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, e)) => value.push(e),
+                        None => return Err("unterminated string".to_string()),
+                    },
+                    Some((_, ch)) => value.push(ch),
+                    None => return Err("unterminated string".to_string()),
+                }
+            };
+            tokens.push(Token::Str(value));
+            rest = &rest[end..];
+        }
+        else if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let value = rest[..end].parse().map_err(|e| format!("invalid number: {}", e))?;
+            tokens.push(Token::Int(value));
+            rest = &rest[end..];
+        }
+        else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        else {
+            let punct = PUNCTUATION.iter().find(|p| rest.starts_with(*p))
+                .ok_or_else(|| format!("unexpected '{}'", c))?;
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, lowest precedence first: `||`, `&&`, `!`, comparisons, methods
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), String> {
+        if self.eat(punct) { Ok(()) } else { Err(format!("expected '{}'", punct)) }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.eat("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.postfix()?;
+        let op = match self.peek() {
+            Some(Token::Punct("==")) => Op::Eq,
+            Some(Token::Punct("!=")) => Op::Ne,
+            Some(Token::Punct("<")) => Op::Lt,
+            Some(Token::Punct("<=")) => Op::Le,
+            Some(Token::Punct(">")) => Op::Gt,
+            Some(Token::Punct(">=")) => Op::Ge,
+            Some(Token::Ident(i)) if i == "in" => Op::In,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(self.postfix()?)))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        while self.eat(".") {
+            let method = match self.next() {
+                Some(Token::Ident(name)) => name,
+                _ => return Err("expected a method name after '.'".to_string()),
+            };
+            self.expect("(")?;
+            let args = self.list(")")?;
+            expr = Expr::Method(Box::new(expr), method, args);
+        }
+        Ok(expr)
+    }
+
+    /// Comma-separated expressions up to `close`
+    fn list(&mut self, close: &'static str) -> Result<Vec<Expr>, String> {
+        let mut items = vec![];
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.or()?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::Str(s))),
+            Some(Token::Int(i)) => Ok(Expr::Literal(Value::Int(i))),
+            Some(Token::Punct("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Some(Token::Punct("[")) => Ok(Expr::List(self.list("]")?)),
+            Some(Token::Ident(i)) => match i.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                "binary" => {
+                    self.expect(".")?;
+                    match self.next() {
+                        Some(Token::Ident(name)) => FIELDS.iter().find(|f| **f == name)
+                            .map(|f| Expr::Field(f))
+                            .ok_or_else(|| format!("unknown field binary.{}", name)),
+                        _ => Err("expected a field name after 'binary.'".to_string()),
+                    }
+                },
+                _ => Err(format!("unknown name '{}'", i)),
+            },
+            Some(Token::Punct(p)) => Err(format!("unexpected '{}'", p)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?} after the expression", token)),
+    }
+}
+
+fn truthy(value: Value) -> Result<bool, String> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        other => Err(format!("expected a bool, got {}", other.kind())),
+    }
+}
+
+fn compare(op: Op, left: Value, right: Value) -> Result<bool, String> {
+    let ordering = match (&left, &right) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        Op::Eq => Ok(left == right),
+        Op::Ne => Ok(left != right),
+        Op::In => match (&left, &right) {
+            (_, Value::List(items)) => Ok(items.contains(&left)),
+            (Value::Str(needle), Value::Str(haystack)) => Ok(haystack.contains(needle.as_str())),
+            _ => Err(format!("can't look for a {} in a {}", left.kind(), right.kind())),
+        },
+        _ => {
+            let ordering = ordering
+                .ok_or_else(|| format!("can't order a {} and a {}", left.kind(), right.kind()))?;
+            Ok(match op {
+                Op::Lt => ordering.is_lt(),
+                Op::Le => ordering.is_le(),
+                Op::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        },
+    }
+}
+
+fn call(target: Value, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match (target, method, args.as_slice()) {
+        (Value::Str(s), "startsWith" | "startswith", [Value::Str(p)]) => Ok(Value::Bool(s.starts_with(p.as_str()))),
+        (Value::Str(s), "endsWith" | "endswith", [Value::Str(p)]) => Ok(Value::Bool(s.ends_with(p.as_str()))),
+        (Value::Str(s), "contains", [Value::Str(p)]) => Ok(Value::Bool(s.contains(p.as_str()))),
+        (Value::Str(s), "matches", [Value::Str(p)]) => Regex::new(p)
+            .map(|r| Value::Bool(r.is_match(&s)))
+            .map_err(|e| format!("invalid regex: {}", e)),
+        (Value::Str(s), "size", []) => Ok(Value::Int(s.chars().count() as i64)),
+        (Value::List(items), "contains", [item]) => Ok(Value::Bool(items.contains(item))),
+        (Value::List(items), "size", []) => Ok(Value::Int(items.len() as i64)),
+        (target, _, _) => Err(format!(
+            "no method {} of a {} taking {} arguments", method, target.kind(), args.len()
+        )),
+    }
+}
+
+fn eval(expr: &Expr, file: &ScannedFile) -> Result<Value, String> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::List(items) => items.iter().map(|i| eval(i, file)).collect::<Result<_, _>>().map(Value::List),
+        Expr::Field(name) => Ok(field(file, name)),
+        Expr::Not(e) => Ok(Value::Bool(!truthy(eval(e, file)?)?)),
+        Expr::And(a, b) => Ok(Value::Bool(truthy(eval(a, file)?)? && truthy(eval(b, file)?)?)),
+        Expr::Or(a, b) => Ok(Value::Bool(truthy(eval(a, file)?)? || truthy(eval(b, file)?)?)),
+        Expr::Compare(op, a, b) => compare(*op, eval(a, file)?, eval(b, file)?).map(Value::Bool),
+        Expr::Method(target, method, args) => {
+            let args = args.iter().map(|a| eval(a, file)).collect::<Result<_, _>>()?;
+            call(eval(target, file)?, method, args)
+        },
+    }
+}
+
+/// Whether `expr` refers to `binary.<field>`
+fn reads(expr: &Expr, field: &str) -> bool {
+    match expr {
+        Expr::Literal(_) => false,
+        Expr::Field(name) => *name == field,
+        Expr::List(items) => items.iter().any(|i| reads(i, field)),
+        Expr::Not(e) => reads(e, field),
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Compare(_, a, b) => reads(a, field) || reads(b, field),
+        Expr::Method(target, _, args) => reads(target, field) || args.iter().any(|a| reads(a, field)),
+    }
+}
+
+impl Rule {
+    /// Whether the rule's expression refers to `binary.<field>`
+    pub fn reads(&self, field: &str) -> bool {
+        reads(&self.when, field)
+    }
+
+    /// Whether `file` violates the rule
+    pub fn matches(&self, file: &ScannedFile) -> Result<bool, String> {
+        eval(&self.when, file).and_then(truthy)
+    }
+}
+
+/// Rules from the JSON file at `path`, with every expression parsed
+pub fn load(path: &Path) -> io::Result<Vec<Rule>> {
+    let specs: Vec<Spec> = serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::from)?;
+    specs.into_iter()
+        .map(|spec| {
+            let when = parse(&spec.when).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("rule {}: {}", spec.name, e))
+            })?;
+            Ok(Rule { name: spec.name, severity: spec.severity, message: spec.message, when })
+        })
+        .collect()
+}
+
+/// Prints the binaries violating each rule, in the order of the rules file
+pub fn print_report(files: &[ScannedFile], rules: &[Rule]) {
+    for rule in rules {
+        let violations: Vec<&str> = files.iter()
+            .filter(|f| rule.matches(f).unwrap_or_else(|e| {
+                warn!("Couldn't evaluate rule {} for {}: {}", rule.name, f.name, e);
+                false
+            }))
+            .map(|f| f.name.as_str())
+            .collect();
+        println!("{} {}: {} ({} exes)", rule.severity.name(), rule.name, rule.message, violations.len());
+        for name in violations {
+            println!("\t<= {}", name);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::elf::ElfInfo;
+    use super::*;
+
+    /// Setuid `/opt/app/bin`, not PIE, needing libssl and libc
+    fn file() -> ScannedFile {
+        ScannedFile {
+            name: "bin".to_string(),
+            path: PathBuf::from("/opt/app/bin"),
+            info: ElfInfo {
+                arch: "x86_64".to_string(),
+                bits: 64,
+                size: 4096,
+                mode: 0o4755,
+                needed: vec!["libssl.so.1.1".to_string(), "libc.so.6".to_string()],
+                ..Default::default()
+            },
+        }
+    }
+
+    fn check(source: &str) -> Result<bool, String> {
+        eval(&parse(source)?, &file()).and_then(truthy)
+    }
+
+    #[test]
+    fn evaluates_fields() {
+        assert_eq!(check("binary.setuid && !binary.pie"), Ok(true));
+        assert_eq!(check(r#""libssl.so.1.1" in binary.needed && binary.path.startsWith("/opt")"#), Ok(true));
+        assert_eq!(check(r#"binary.needed.size() == 2 && binary.size >= 4096 && binary.bits < 65"#), Ok(true));
+        assert_eq!(check(r#""app" in binary.path && binary.name.matches("^b.n$")"#), Ok(true));
+        assert_eq!(check("binary.soname == null && binary.mode == 2541"), Ok(true));
+        assert_eq!(check(r#"binary.arch in ['aarch64', "riscv64"]"#), Ok(false));
+    }
+
+    #[test]
+    fn precedence() {
+        // `&&` binds tighter than `||`
+        assert_eq!(check("true || false && false"), Ok(true));
+        assert_eq!(check("(true || false) && false"), Ok(false));
+        // `!` tighter than `&&`, comparisons tighter than `!`
+        assert_eq!(check("!false && false"), Ok(false));
+        assert_eq!(check("!1 == 2"), Ok(true));
+        // methods tighter than comparisons
+        assert_eq!(check("binary.needed.size() > 1"), Ok(true));
+        assert_eq!(check("!binary.pie == true"), Ok(true));
+    }
+
+    #[test]
+    fn short_circuits() {
+        assert_eq!(check(r#"false && 1 < "a""#), Ok(false));
+        assert_eq!(check(r#"true || binary.size.startsWith("a")"#), Ok(true));
+        assert_eq!(check(r#"true && 1 < "a""#), Err("can't order a int and a string".to_string()));
+        assert_eq!(check("false || 1"), Err("expected a bool, got int".to_string()));
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(check("binary.size"), Err("expected a bool, got int".to_string()));
+        assert_eq!(check("!binary.needed"), Err("expected a bool, got list".to_string()));
+        assert_eq!(check("1 in 2"), Err("can't look for a int in a int".to_string()));
+        assert_eq!(check("[1] < [2]"), Err("can't order a list and a list".to_string()));
+        assert_eq!(check(r#"binary.size.startsWith("4")"#), Err("no method startsWith of a int taking 1 arguments".to_string()));
+        assert_eq!(check(r#"binary.name.matches("(")"#).map_err(|e| e.starts_with("invalid regex")), Err(true));
+        // comparing different types is allowed, they're just never equal
+        assert_eq!(check(r#"1 == "1""#), Ok(false));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |source| parse(source).err();
+        assert_eq!(error("binary.nope"), Some("unknown field binary.nope".to_string()));
+        assert_eq!(error("binary"), Some("expected '.'".to_string()));
+        assert_eq!(error("bianry.pie"), Some("unknown name 'bianry'".to_string()));
+        assert_eq!(error(r#"binary.name == "bin"#), Some("unterminated string".to_string()));
+        assert_eq!(error("binary.pie binary.nx"), Some(r#"unexpected Ident("binary") after the expression"#.to_string()));
+        assert_eq!(error("(binary.pie"), Some("expected ')'".to_string()));
+        assert_eq!(error("binary.pie &"), Some("unexpected '&'".to_string()));
+        assert_eq!(error("binary.pie &&"), Some("unexpected end of expression".to_string()));
+    }
+
+    #[test]
+    fn fields_read() {
+        for (source, expected) in [
+            ("binary.embedded.size() > 0", true),
+            ("!binary.pie && (binary.setuid || \"OpenSSL\" in binary.embedded)", true),
+            ("binary.name.startsWith(binary.embedded)", true),
+            ("[1, [binary.embedded]] == []", true),
+            ("binary.imports.contains(\"embedded\")", false),
+            ("\"binary.embedded\" in binary.needed", false),
+        ] {
+            let rule = Rule { name: String::new(), severity: Severity::Notice, message: String::new(), when: parse(source).unwrap() };
+            assert_eq!(rule.reads("embedded"), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn loads_rules_files() {
+        let dir = std::env::temp_dir().join(format!("rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rules.json");
+        fs::write(&path, r#"[{"name": "suid", "severity": "error", "when": "binary.setuid", "message": "setuid binary"}]"#).unwrap();
+        let rules = load(&path).unwrap();
+        assert_eq!((rules[0].name.as_str(), rules[0].severity, rules[0].message.as_str()), ("suid", Severity::Error, "setuid binary"));
+        assert_eq!(rules[0].matches(&file()), Ok(true));
+        fs::write(&path, r#"[{"name": "typo", "severity": "notice", "when": "binary.setiud", "message": ""}]"#).unwrap();
+        assert_eq!(load(&path).err().map(|e| e.to_string()), Some("rule typo: unknown field binary.setiud".to_string()));
+        fs::write(&path, r#"[{"name": "typo", "severity": "fatal", "when": "true", "message": ""}]"#).unwrap();
+        assert_eq!(load(&path).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
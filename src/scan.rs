@@ -0,0 +1,721 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn, debug};
+use crate::archive;
+use crate::cache::{Cache, Parsed};
+use crate::checkpoint::{self, Checkpoint};
+use crate::escape;
+use crate::progress;
+use crate::fd_budget;
+use crate::ignore::PathFilter;
+use crate::elf::{ElfInfo, HandleError, parse_elf_data};
+use crate::macho::parse_macho_data;
+use crate::pe::parse_pe_data;
+use crate::root;
+use crate::signatures;
+use crate::throttle::{self, Throttle};
+use crate::wasm::{self, parse_wasm_data};
+
+/// Binary found during the scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    /// How the file is shown in reports, its escaped file name unless `--paths` says otherwise
+    pub name: String,
+    #[serde(with = "crate::escape::serde_path")]
+    pub path: PathBuf,
+    pub info: ElfInfo,
+}
+
+/// Which binaries besides ELF files the scan picks up
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Windows executables and DLLs
+    pub pe: bool,
+    /// Search binaries for statically linked libraries, which means reading all of their data
+    pub embedded: bool,
+    /// Stop at the first failure that isn't [expected](Failure::is_expected)
+    pub fail_fast: bool,
+    /// Native libraries inside zip, jar and wheel archives
+    pub archives: bool,
+    /// File the progress of a directory scan is saved to periodically
+    pub checkpoint: Option<PathBuf>,
+    /// Continue from `checkpoint` rather than starting over
+    pub resume: bool,
+    /// How fast files may be read
+    pub throttle: throttle::Limits,
+    /// Try to read FIFOs, sockets and device nodes too, which may block
+    pub include_special: bool,
+    pub walk: Walk,
+    /// Number of threads parsing files, one per CPU if 0
+    pub jobs: usize,
+    /// Host directory of the scanned root filesystem, which absolute symlinks point into;
+    /// the host's own root if `None`
+    pub root: Option<PathBuf>,
+    /// Results of earlier runs to reuse for unchanged files
+    pub cache: Option<Arc<Cache>>,
+    /// Counters the scan keeps up to date for `--progress` and `--stats`
+    pub progress: Option<Arc<progress::Progress>>,
+}
+
+impl ScanOptions {
+    /// Number of threads a directory scan uses
+    pub fn threads(&self) -> usize {
+        match self.jobs {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            jobs => jobs,
+        }
+    }
+}
+
+/// Kind of `metadata`'s file if it's neither a regular file nor a directory
+pub fn special_kind(metadata: &fs::Metadata) -> Option<&'static str> {
+    let file_type = metadata.file_type();
+    if file_type.is_file() || file_type.is_dir() {
+        None
+    }
+    else {
+        Some(unix_kind(&file_type).unwrap_or("special file"))
+    }
+}
+
+#[cfg(unix)]
+fn unix_kind(file_type: &fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+        Some("fifo")
+    }
+    else if file_type.is_socket() {
+        Some("socket")
+    }
+    else if file_type.is_block_device() {
+        Some("block device")
+    }
+    else if file_type.is_char_device() {
+        Some("character device")
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_kind(_: &fs::FileType) -> Option<&'static str> {
+    None
+}
+
+/// Permission bits of a file on the host
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+/// Other hosts only tell read-only files apart
+#[cfg(not(unix))]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() { 0o444 } else { 0o644 }
+}
+
+/// Identifies a file on the host whichever path it's reached through
+#[cfg(unix)]
+pub type FileId = (u64, u64);
+#[cfg(not(unix))]
+pub type FileId = PathBuf;
+
+/// Device and inode of the file at `path`, the same for its hardlinks
+#[cfg(unix)]
+pub fn file_id(_path: &Path, metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Canonical path of the file at `path`, so hardlinks count as different files
+#[cfg(not(unix))]
+pub fn file_id(path: &Path, _metadata: &fs::Metadata) -> Option<FileId> {
+    fs::canonicalize(path).ok()
+}
+
+/// Filesystem of a file on the host
+#[cfg(unix)]
+fn device(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Starts of ELF, PE, WebAssembly and Mach-O binaries
+const BINARY_MAGICS: &[&[u8]] = &[
+    b"\x7fELF", b"MZ", b"\0asm",
+    &[0xfe, 0xed, 0xfa, 0xce], &[0xfe, 0xed, 0xfa, 0xcf], &[0xce, 0xfa, 0xed, 0xfe], &[0xcf, 0xfa, 0xed, 0xfe],
+    &[0xca, 0xfe, 0xba, 0xbe],
+];
+
+/// Bytes of a file needed to tell whether it may be a binary
+pub const MAGIC_LEN: usize = 4;
+
+/// Whether a file starting with `head`, its first [`MAGIC_LEN`] bytes, may be a binary of some
+/// format; others aren't read further
+pub fn may_be_binary(head: &[u8]) -> bool {
+    BINARY_MAGICS.iter().any(|m| head.starts_with(m))
+}
+
+/// Parses a single binary of any format enabled by `options`. Only the first bytes of files
+/// that can't be binaries are read.
+pub fn parse_file(path: &Path, options: &ScanOptions) -> Result<ElfInfo, HandleError> {
+    let data = fd_budget::read_if(path, MAGIC_LEN, may_be_binary)
+        .map_err(HandleError::IoError)?
+        .ok_or(HandleError::NotElf)?;
+    let mut info = parse_data(&data, options)?;
+    info.mode = mode(&fs::metadata(path).map_err(HandleError::IoError)?);
+    Ok(info)
+}
+
+/// Parses a binary already read into memory
+pub fn parse_data(data: &[u8], options: &ScanOptions) -> Result<ElfInfo, HandleError> {
+    let mut info = if data.starts_with(wasm::MAGIC) {
+        parse_wasm_data(data)?
+    }
+    else {
+        match object::FileKind::parse(data) {
+            Ok(object::FileKind::Pe32 | object::FileKind::Pe64) if options.pe => parse_pe_data(data)?,
+            Ok(object::FileKind::MachO32 | object::FileKind::MachO64
+                | object::FileKind::MachOFat32 | object::FileKind::MachOFat64) => parse_macho_data(data)?,
+            _ => parse_elf_data(data)?,
+        }
+    };
+    if options.embedded {
+        info.embedded = signatures::embedded(data, &info);
+    }
+    Ok(info)
+}
+
+/// File the scan couldn't handle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Failure {
+    #[serde(with = "crate::escape::serde_path")]
+    pub path: PathBuf,
+    /// One of [`HandleError::kind`]
+    pub kind: String,
+    pub message: String,
+}
+
+impl Failure {
+    /// Whether the file simply isn't a dynamically linked binary, rather than one that couldn't
+    /// be analyzed
+    pub fn is_expected(&self) -> bool {
+        NOT_DYNAMIC.contains(&self.kind.as_str()) || self.kind == "special"
+    }
+}
+
+/// Files found by a scan, both those handled and those that couldn't be
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub files: Vec<ScannedFile>,
+    pub failures: Vec<Failure>,
+}
+
+/// Failure kinds of files that were read but aren't dynamically linked binaries
+pub const NOT_DYNAMIC: &[&str] = &["static", "unsupported", "no-dynamic", "not-elf"];
+
+/// Counts of a scan's results, as included in machine output
+#[derive(Debug, Serialize)]
+pub struct Summary<'a> {
+    pub analyzed: usize,
+    /// Files that aren't dynamically linked binaries, or are special files
+    pub skipped: usize,
+    /// Files that couldn't be analyzed, not counting the skipped ones
+    pub failed: usize,
+    /// Number of failures of every kind
+    pub by_kind: BTreeMap<&'a str, usize>,
+    pub failures: &'a [Failure],
+    /// Failures telling that files aren't dynamically linked binaries, `static`, `unsupported`,
+    /// `no-dynamic` and `not-elf`, by kind, so that every file read is accounted for
+    pub not_dynamic: BTreeMap<&'a str, Vec<&'a Failure>>,
+}
+
+impl Scan {
+    pub fn summary(&self) -> Summary<'_> {
+        let mut by_kind = BTreeMap::new();
+        let mut not_dynamic: BTreeMap<&str, Vec<&Failure>> = BTreeMap::new();
+        for failure in &self.failures {
+            *by_kind.entry(failure.kind.as_str()).or_default() += 1;
+            if NOT_DYNAMIC.contains(&failure.kind.as_str()) {
+                not_dynamic.entry(failure.kind.as_str()).or_default().push(failure);
+            }
+        }
+        let skipped = self.failures.iter().filter(|f| f.is_expected()).count();
+        Summary {
+            analyzed: self.files.len(),
+            skipped,
+            failed: self.failures.len() - skipped,
+            by_kind,
+            failures: &self.failures,
+            not_dynamic,
+        }
+    }
+}
+
+impl Scan {
+    /// Records a failure, returns whether the scan should stop because of it
+    pub fn fail(&mut self, path: PathBuf, e: HandleError, options: &ScanOptions) -> bool {
+        self.record(Failure { path, kind: e.kind().to_string(), message: e.to_string() }, options)
+    }
+
+    fn record(&mut self, failure: Failure, options: &ScanOptions) -> bool {
+        let stop = options.fail_fast && !failure.is_expected();
+        self.failures.push(failure);
+        stop
+    }
+
+    /// Adds the results of another scan, leaving out the files this one already has
+    pub fn merge(&mut self, other: Scan) {
+        let seen: HashSet<PathBuf> = self.files.iter().map(|f| f.path.clone())
+            .chain(self.failures.iter().map(|f| f.path.clone()))
+            .collect();
+        self.files.extend(other.files.into_iter().filter(|f| !seen.contains(&f.path)));
+        self.failures.extend(other.failures.into_iter().filter(|f| !seen.contains(&f.path)));
+    }
+}
+
+/// Parses the native libraries in the archive at `path`, returns whether the scan should stop
+pub fn scan_archive(path: PathBuf, name: &str, options: &ScanOptions, scan: &mut Scan) -> bool {
+    let data = match fd_budget::read(&path) {
+        Ok(data) => data,
+        Err(e) => return scan.fail(path, HandleError::IoError(e), options),
+    };
+    let members = match archive::members(&data) {
+        Ok(members) => members,
+        Err(e) => return scan.fail(path, e, options),
+    };
+    let mut archive_path = path.into_os_string();
+    archive_path.push(archive::SEPARATOR);
+    let archive_path = PathBuf::from(archive_path);
+    for member in members.iter().filter(|m| archive::is_native_library(&m.name)) {
+        let path = archive_path.join(&member.name);
+        let name = format!("{}{}/{}", name, archive::SEPARATOR, member.name);
+        debug!("Handling archive member {}", name);
+        match member.contents().and_then(|data| parse_data(&data, options)) {
+            Ok(info) => scan.files.push(ScannedFile { name, path, info }),
+            Err(e) => {
+                debug!("Couldn't handle {}: {}", name, e);
+                if scan.fail(path, e, options) {
+                    return true;
+                }
+            },
+        }
+    }
+    false
+}
+
+/// How a scan walks the directory tree
+#[derive(Debug, Clone, Default)]
+pub struct Walk {
+    /// Descend into subdirectories
+    pub recursive: bool,
+    /// Deepest level to descend to, files directly in the scanned directory being at depth 1;
+    /// unlimited if `None`
+    pub max_depth: Option<usize>,
+    /// Descend into symlinked directories too
+    pub follow_symlinks: bool,
+    /// Stay on the filesystem of the scanned directory
+    pub one_file_system: bool,
+    /// Paths left out of the walk
+    pub filter: PathFilter,
+}
+
+/// `path` relative to the scanned directory `dir`, as matched by [`PathFilter`]
+fn relative<'a>(path: &'a Path, dir: &Path) -> &'a [u8] {
+    path.strip_prefix(dir).unwrap_or(path).as_os_str().as_encoded_bytes()
+}
+
+/// Entries of `dir` other than subdirectories, and the subdirectories
+fn list(dir: &Path) -> io::Result<(Vec<fs::DirEntry>, Vec<fs::DirEntry>)> {
+    let mut files = vec![];
+    let mut dirs = vec![];
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = match dir_entry {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Couldn't get next path: {:?}", e);
+                continue;
+            },
+        };
+        if dir_entry.path().is_dir() {
+            dirs.push(dir_entry);
+        }
+        else {
+            files.push(dir_entry);
+        }
+    }
+    Ok((files, dirs))
+}
+
+/// Files a scan of `dir` looks at, with their escaped names: relative paths when walking
+/// recursively, with files reached through several links listed only once
+pub fn entries(dir: &Path, walk: &Walk) -> Vec<(PathBuf, String)> {
+    let (entries, failures) = walk_dir(dir, walk, None);
+    for failure in failures {
+        warn!("Couldn't list {}: {}", failure.path.display(), failure.message);
+    }
+    entries
+}
+
+/// [`entries`], counting files as they're found in `progress`
+fn walk_dir(dir: &Path, walk: &Walk, progress: Option<&progress::Progress>) -> (Vec<(PathBuf, String)>, Vec<Failure>) {
+    let unlisted = |path: PathBuf, e: io::Error| {
+        let e = HandleError::IoError(e);
+        Failure { path, kind: e.kind().to_string(), message: e.to_string() }
+    };
+    let (files, dirs) = match list(dir) {
+        Ok(listed) => listed,
+        Err(e) => return (vec![], vec![unlisted(dir.to_path_buf(), e)]),
+    };
+    if let Some(progress) = progress {
+        progress.listed(&escape::path(dir), files.len());
+    }
+    if !walk.recursive {
+        let entries = files.iter()
+            .filter(|e| walk.filter.admits_file(relative(&e.path(), dir)))
+            .map(|e| (e.path(), escape::os_str(&e.file_name())))
+            .collect();
+        return (entries, vec![]);
+    }
+    let root_dev = fs::metadata(dir).ok().and_then(|m| device(&m));
+    // the directories walked, guarding against symlink loops
+    let mut walked: HashSet<FileId> = fs::metadata(dir).ok().and_then(|m| file_id(dir, &m)).into_iter().collect();
+    // every file with whether its best path so far is a symlink, its depth and the path
+    let mut unique: HashMap<FileId, (bool, usize, PathBuf)> = HashMap::new();
+    let mut entries = vec![];
+    // directories that couldn't be listed, counted by the scan like files it couldn't read
+    let mut failures = vec![];
+    let mut pending = vec![(files, dirs, 1)];
+    while let Some((files, dirs, depth)) = pending.pop() {
+        for file in files {
+            let path = file.path();
+            if !walk.filter.admits_file(relative(&path, dir)) {
+                debug!("Leaving out {}", path.display());
+                continue;
+            }
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                // dangling links and the like are reported by the scan
+                Err(_) => {
+                    entries.push(path);
+                    continue;
+                },
+            };
+            let Some(id) = file_id(&path, &metadata) else {
+                entries.push(path);
+                continue;
+            };
+            let link = file.file_type().is_ok_and(|t| t.is_symlink());
+            let candidate = (link, depth, path);
+            match unique.entry(id) {
+                // the file itself rather than a symlink to it, then the shallowest, then the
+                // first path in order
+                Entry::Occupied(mut best) => {
+                    let (kept, dropped) = if candidate < *best.get() {
+                        (candidate.2.clone(), best.insert(candidate).2)
+                    }
+                    else {
+                        (best.get().2.clone(), candidate.2)
+                    };
+                    debug!("{} is the same file as {}", dropped.display(), kept.display());
+                },
+                Entry::Vacant(best) => { best.insert(candidate); },
+            }
+        }
+        if walk.max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        for sub in dirs {
+            let path = sub.path();
+            if !walk.follow_symlinks && sub.file_type().is_ok_and(|t| t.is_symlink()) {
+                continue;
+            }
+            if walk.filter.excludes_dir(relative(&path, dir)) {
+                debug!("Leaving out {}", path.display());
+                continue;
+            }
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    warn!("Couldn't read {}: {}", path.display(), e);
+                    continue;
+                },
+            };
+            if walk.one_file_system && device(&metadata) != root_dev {
+                debug!("Not descending into {} on another filesystem", path.display());
+                continue;
+            }
+            if file_id(&path, &metadata).is_some_and(|id| !walked.insert(id)) {
+                continue;
+            }
+            match list(&path) {
+                Ok((files, dirs)) => {
+                    if let Some(progress) = progress {
+                        progress.listed(&escape::path(&path), files.len());
+                    }
+                    pending.push((files, dirs, depth + 1));
+                },
+                Err(e) => failures.push(unlisted(path, e)),
+            }
+        }
+    }
+    entries.extend(unique.into_values().map(|(_, _, path)| path));
+    let entries = entries.into_iter()
+        .map(|path| {
+            let name = escape::path(path.strip_prefix(dir).unwrap_or(&path));
+            (path, name)
+        })
+        .collect();
+    (entries, failures)
+}
+
+/// Parses the file at `path`, or the libraries in it if it's an archive, into `scan`; returns
+/// whether the scan should stop
+fn scan_entry(path: PathBuf, name: String, options: &ScanOptions, throttle: &Mutex<Throttle>, scan: &mut Scan) -> bool {
+    let real = match &options.root {
+        Some(root) => root::canonicalize(root, &path).unwrap_or_else(|| path.clone()),
+        None => path.clone(),
+    };
+    if let Some(progress) = &options.progress {
+        progress.start(&name);
+    }
+    let metadata = fs::metadata(&real);
+    if let Some(kind) = metadata.as_ref().ok().and_then(special_kind) {
+        if !options.include_special {
+            debug!("Skipping {} {}", kind, name);
+            return scan.fail(path, HandleError::Special(kind), options);
+        }
+    }
+    let cache = options.cache.as_ref().zip(metadata.as_ref().ok()).filter(|_| !(options.archives && archive::is_archive(&path)));
+    match cache.and_then(|(cache, metadata)| cache.get(&real, metadata)) {
+        Some(Parsed::Info(info)) => {
+            scan.files.push(ScannedFile { name, path, info: *info });
+            return false;
+        },
+        Some(Parsed::Failure { kind, message }) => return scan.record(Failure { path, kind, message }, options),
+        None => {},
+    }
+    let size = metadata.as_ref().map_or(0, |m| m.len());
+    let account = |bytes: u64| {
+        throttle.lock().unwrap().file(bytes);
+        if let Some(progress) = &options.progress {
+            progress.read(bytes);
+        }
+    };
+    if options.archives && archive::is_archive(&path) {
+        account(size);
+        return scan_archive(path, &name, options, scan);
+    }
+    debug!("Handling file {}", name);
+    let parsed = parse_file(&real, options);
+    // files that can't be binaries are only read up to their magic
+    account(match parsed {
+        Err(HandleError::NotElf) => size.min(MAGIC_LEN as u64),
+        _ => size,
+    });
+    match parsed {
+        Ok(info) => {
+            if let Some((cache, metadata)) = cache {
+                cache.insert(&real, metadata, Parsed::Info(Box::new(info.clone())));
+            }
+            scan.files.push(ScannedFile { name, path, info });
+            false
+        },
+        Err(e) => {
+            debug!("Couldn't handle {}: {}", name, e);
+            if let Some((cache, metadata)) = cache {
+                cache.insert(&real, metadata, Parsed::Failure { kind: e.kind().to_string(), message: e.to_string() });
+            }
+            scan.fail(path, e, options)
+        },
+    }
+}
+
+/// Parses the single file at `path` the way a directory scan parses its entries
+pub fn scan_file(path: &Path, options: &ScanOptions) -> Scan {
+    let mut scan = Scan::default();
+    let name = path.file_name().map_or_else(|| escape::path(path), escape::os_str);
+    scan_entry(path.to_path_buf(), name, options, &Mutex::new(Throttle::new(options.throttle)), &mut scan);
+    if let Some(progress) = &options.progress {
+        progress.discovered(1);
+        progress.processed();
+    }
+    scan
+}
+
+/// Results so far of a directory scan shared by its threads
+struct Progress {
+    scan: Scan,
+    /// Entries handled, by escaped path
+    processed: BTreeSet<String>,
+    /// When the checkpoint was last saved
+    saved: Instant,
+}
+
+/// Parses every file in `dir` on [`ScanOptions::threads`] threads, collecting those that can't
+/// be handled as failures
+pub fn scan_dir(dir: &Path, options: &ScanOptions) -> Scan {
+    let (entries, failures) = walk_dir(dir, &options.walk, options.progress.as_deref());
+    let mut scan = scan_listed(entries, options);
+    scan.failures.extend(failures);
+    scan.failures.sort_by(|a, b| a.path.cmp(&b.path));
+    scan
+}
+
+/// Paths listed one per line, or separated by NULs as `find -print0` writes them if there are
+/// any, leaving out empty entries
+pub fn file_list(data: &[u8]) -> Vec<PathBuf> {
+    let separator = if data.contains(&0) { 0 } else { b'\n' };
+    data.split(|&b| b == separator)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PathBuf::from(escape::os_string(entry.to_vec())))
+        .collect()
+}
+
+/// Parses the files at `paths` like [`scan_dir`] parses those it finds, named by their escaped
+/// paths. Directories and paths listed twice are skipped.
+pub fn scan_paths(paths: &[PathBuf], options: &ScanOptions) -> Scan {
+    let mut seen = HashSet::new();
+    let entries = paths.iter()
+        .filter(|path| {
+            if path.is_dir() {
+                debug!("Skipping directory {}", path.display());
+                return false;
+            }
+            seen.insert(*path)
+        })
+        .map(|path| (path.clone(), escape::path(path)))
+        .collect();
+    scan_listed(entries, options)
+}
+
+/// Parses the listed files with their names on [`ScanOptions::threads`] threads, keeping a
+/// checkpoint if asked to
+fn scan_listed(listed: Vec<(PathBuf, String)>, options: &ScanOptions) -> Scan {
+    let mut progress = Progress { scan: Scan::default(), processed: BTreeSet::new(), saved: Instant::now() };
+    if let (Some(path), true) = (&options.checkpoint, options.resume) {
+        match Checkpoint::load(path) {
+            Ok(checkpoint) => {
+                info!("Resuming after {} entries", checkpoint.processed.len());
+                progress.processed = checkpoint.processed;
+                progress.scan.files = checkpoint.files;
+                progress.scan.failures = checkpoint.failures;
+            },
+            Err(e) => warn!("Couldn't load checkpoint {}, starting over: {}", path.display(), e),
+        }
+    }
+    let entries: Vec<(PathBuf, String)> = listed.into_iter()
+        .filter(|(path, _)| !progress.processed.contains(&escape::path(path)))
+        .collect();
+    if let Some(progress) = &options.progress {
+        progress.discovered(entries.len());
+    }
+    let progress = Mutex::new(progress);
+    let throttle = Mutex::new(Throttle::new(options.throttle));
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let worker = || {
+        while !stopped.load(Ordering::Relaxed) {
+            let (path, name) = match entries.get(next.fetch_add(1, Ordering::Relaxed)) {
+                Some(entry) => entry.clone(),
+                None => break,
+            };
+            let key = escape::path(&path);
+            let mut scan = Scan::default();
+            let stop = scan_entry(path, name, options, &throttle, &mut scan);
+            if let Some(progress) = &options.progress {
+                progress.processed();
+            }
+            let mut progress = progress.lock().unwrap();
+            progress.scan.files.append(&mut scan.files);
+            progress.scan.failures.append(&mut scan.failures);
+            progress.processed.insert(key);
+            if stop {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            if let Some(checkpoint) = &options.checkpoint {
+                if progress.saved.elapsed() >= checkpoint::INTERVAL {
+                    if let Err(e) = checkpoint::save(checkpoint, &progress.processed, &progress.scan) {
+                        warn!("Couldn't save checkpoint {}: {}", checkpoint.display(), e);
+                    }
+                    progress.saved = Instant::now();
+                }
+            }
+        }
+    };
+    thread::scope(|s| {
+        for _ in 0..options.threads().min(entries.len()).max(1) {
+            s.spawn(worker);
+        }
+    });
+    let Progress { mut scan, processed, .. } = progress.into_inner().unwrap();
+    if let Some(checkpoint) = &options.checkpoint {
+        // a scan stopped by --fail-fast can be resumed after the failing file
+        let result = if stopped.into_inner() {
+            checkpoint::save(checkpoint, &processed, &scan)
+        }
+        else {
+            fs::remove_file(checkpoint)
+        };
+        match result {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                warn!("Couldn't update checkpoint {}: {}", checkpoint.display(), e);
+            },
+            _ => {},
+        }
+    }
+    // directory iteration order differs between filesystems and runs, and threads finish files
+    // in any order
+    scan.files.sort_by(|a, b| a.path.cmp(&b.path));
+    scan.failures.sort_by(|a, b| a.path.cmp(&b.path));
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlisted_directories_are_failures() {
+        let dir = std::env::temp_dir().join(format!("linux-hs-elf-{}-unlisted", std::process::id()));
+        let locked = dir.join("sub/locked");
+        fs::create_dir_all(&locked).unwrap();
+        fs::write(dir.join("sub/notes"), b"text").unwrap();
+        let walk = Walk { recursive: true, ..Default::default() };
+
+        let (entries, failures) = walk_dir(&dir.join("missing"), &walk, None);
+        assert!(entries.is_empty());
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].path.as_path(), failures[0].kind.as_str()), (dir.join("missing").as_path(), "io"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+            // root lists it all the same
+            if fs::read_dir(&locked).is_err() {
+                let scan = scan_dir(&dir, &ScanOptions { walk, ..Default::default() });
+                let failures: Vec<_> = scan.failures.iter().map(|f| (f.path.as_path(), f.kind.as_str())).collect();
+                assert_eq!(failures, [(locked.as_path(), "permission-denied"), (dir.join("sub/notes").as_path(), "not-elf")]);
+                assert_eq!(scan.summary().failed, 1);
+            }
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
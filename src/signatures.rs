@@ -0,0 +1,111 @@
+//! Libraries linked statically into a binary, recognized by the version banners and identifiers
+//! they carry in their read-only data.
+//!
+//! A copy is only reported if the file doesn't also need the library dynamically, since
+//! programs using a shared library often contain its version string from the headers, and if
+//! the file isn't that library itself.
+
+use std::sync::OnceLock;
+use regex::bytes::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use crate::elf::ElfInfo;
+
+/// Library recognized by `pattern`, which has the version as its first group unless `version`
+/// is given
+struct Signature {
+    library: &'static str,
+    /// Prefixes of the names the library is linked by
+    sonames: &'static [&'static str],
+    pattern: &'static str,
+    /// Pattern with the version as its first group, searched once `pattern` matched
+    version: Option<&'static str>,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        library: "OpenSSL",
+        sonames: &["libssl.", "libcrypto."],
+        pattern: r"OpenSSL (\d+\.\d+\.\d+[a-z]?)(?:-\w+)? +\d{1,2} [A-Z][a-z]{2} \d{4}",
+        version: None,
+    },
+    Signature {
+        library: "zlib",
+        sonames: &["libz."],
+        pattern: r"(?:deflate|inflate) (\d+\.\d+(?:\.\d+)*) Copyright \d{4}-\d{4}",
+        version: None,
+    },
+    Signature {
+        library: "curl",
+        sonames: &["libcurl.", "libcurl-"],
+        pattern: r"libcurl/(\d+\.\d+\.\d+)",
+        version: None,
+    },
+    // the database header magic; the release number isn't kept as a banner, but the source id
+    // starts with the check-in date
+    Signature {
+        library: "SQLite",
+        sonames: &["libsqlite3."],
+        pattern: r"SQLite format 3\x00",
+        version: Some(r"(\d{4}-\d{2}-\d{2}) \d{2}:\d{2}:\d{2} [0-9a-f]{40,64}"),
+    },
+];
+
+/// Compiled patterns of every signature: all of them in one set, so that a binary is searched
+/// once, and each `pattern` and `version` on its own to pull the version out of those that matched
+struct Patterns {
+    set: RegexSet,
+    each: Vec<(Regex, Option<Regex>)>,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    let compile = |pattern| Regex::new(pattern).expect("invalid signature pattern");
+    PATTERNS.get_or_init(|| Patterns {
+        set: RegexSet::new(SIGNATURES.iter().map(|s| s.pattern)).expect("invalid signature pattern"),
+        each: SIGNATURES.iter()
+            .map(|s| (compile(s.pattern), s.version.map(compile)))
+            .collect(),
+    })
+}
+
+/// Copy of a library found inside a binary
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Embedded {
+    pub library: String,
+    /// Version from the banner, the source date for SQLite
+    pub version: Option<String>,
+}
+
+impl std::fmt::Display for Embedded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {}", self.library, version),
+            None => f.write_str(&self.library),
+        }
+    }
+}
+
+/// Libraries statically linked into the file `info` was parsed from
+pub fn embedded(data: &[u8], info: &ElfInfo) -> Vec<Embedded> {
+    let links = |sonames: &[&str]| {
+        info.needed.iter().chain(&info.soname)
+            .any(|lib| sonames.iter().any(|s| lib.starts_with(s)))
+    };
+    let patterns = patterns();
+    let matched = patterns.set.matches(data);
+    SIGNATURES.iter().zip(&patterns.each).enumerate()
+        .filter(|&(i, (signature, _))| matched.matched(i) && !links(signature.sonames))
+        .filter_map(|(_, (signature, (pattern, version)))| {
+            let found = pattern.captures(data)?;
+            let found = match version {
+                Some(version) => version.captures(data),
+                None => Some(found),
+            };
+            Some(Embedded {
+                library: signature.library.to_string(),
+                version: found.and_then(|c| c.get(1))
+                    .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned()),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,397 @@
+//! Read-only SQLite reader with just enough of the file format to list the rows of a table, for
+//! package databases kept in SQLite like RPM's `rpmdb.sqlite`.
+//!
+//! Walks the table B-tree from its root page, following overflow pages of large records. Indexes,
+//! WAL files and freelists are ignored, so uncheckpointed writes aren't seen.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const INTERIOR_TABLE_PAGE: u8 = 0x05;
+const LEAF_TABLE_PAGE: u8 = 0x0d;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn bytes(data: &[u8], offset: usize, len: usize) -> io::Result<&[u8]> {
+    // lengths come from the file, so the end may not even fit
+    offset.checked_add(len).and_then(|end| data.get(offset..end)).ok_or_else(|| invalid("Record out of bounds"))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> io::Result<u16> {
+    Ok(u16::from_be_bytes(bytes(data, offset, 2)?.try_into().unwrap()))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> io::Result<u32> {
+    Ok(u32::from_be_bytes(bytes(data, offset, 4)?.try_into().unwrap()))
+}
+
+/// Variable-length integer at `offset` and its length
+fn varint(data: &[u8], offset: usize) -> io::Result<(u64, usize)> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *data.get(offset + i).ok_or_else(|| invalid("Truncated varint"))?;
+        if i == 8 {
+            return Ok(((value << 8) | u64::from(byte), 9));
+        }
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    unreachable!()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    /// Text or blob
+    Bytes(Vec<u8>),
+}
+
+/// Columns of a record
+fn record(payload: &[u8]) -> io::Result<Vec<Value>> {
+    let (header_len, mut offset) = varint(payload, 0)?;
+    let mut body = header_len as usize;
+    let mut values = vec![];
+    while offset < header_len as usize {
+        let (serial, len) = varint(payload, offset)?;
+        offset += len;
+        let int = |size: usize| -> io::Result<i64> {
+            let raw = bytes(payload, body, size)?;
+            // sign-extended big-endian
+            let first = if raw[0] & 0x80 != 0 { -1i64 } else { 0 };
+            Ok(raw.iter().fold(first, |v, &b| (v << 8) | i64::from(b)))
+        };
+        let (value, size) = match serial {
+            0 => (Value::Null, 0),
+            1..=4 => (Value::Int(int(serial as usize)?), serial as usize),
+            5 => (Value::Int(int(6)?), 6),
+            6 => (Value::Int(int(8)?), 8),
+            7 => (Value::Float(f64::from_bits(int(8)? as u64)), 8),
+            8 => (Value::Int(0), 0),
+            9 => (Value::Int(1), 0),
+            10 | 11 => return Err(invalid("Reserved serial type")),
+            n => {
+                let size = (n as usize - 12) / 2;
+                (Value::Bytes(bytes(payload, body, size)?.to_vec()), size)
+            },
+        };
+        values.push(value);
+        body += size;
+    }
+    Ok(values)
+}
+
+pub struct Database {
+    data: Vec<u8>,
+    page_size: usize,
+    /// Page size without the bytes reserved at the end of every page
+    usable: usize,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if !data.starts_with(MAGIC) || data.len() < 100 {
+            return Err(invalid("Not an SQLite database"));
+        }
+        let page_size = match u16_at(&data, 16)? {
+            1 => 65536,
+            n => usize::from(n),
+        };
+        let usable = page_size.checked_sub(usize::from(data[20])).filter(|&u| u >= 480)
+            .ok_or_else(|| invalid("Invalid page size"))?;
+        Ok(Database { data, page_size, usable })
+    }
+
+    fn page(&self, number: u32) -> io::Result<&[u8]> {
+        let start = (number as usize).checked_sub(1).ok_or_else(|| invalid("Invalid page number"))? * self.page_size;
+        bytes(&self.data, start, self.page_size)
+    }
+
+    /// Payload of `total` bytes starting at `offset` of a leaf page, with its overflow pages
+    fn payload(&self, page: &[u8], offset: usize, total: usize) -> io::Result<Vec<u8>> {
+        let usable = self.usable;
+        let max_local = usable - 35;
+        if total <= max_local {
+            return Ok(bytes(page, offset, total)?.to_vec());
+        }
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = match min_local + (total - min_local) % (usable - 4) {
+            k if k <= max_local => k,
+            _ => min_local,
+        };
+        let mut payload = bytes(page, offset, local)?.to_vec();
+        let mut next = u32_at(page, offset + local)?;
+        while payload.len() < total {
+            if next == 0 {
+                return Err(invalid("Truncated overflow chain"));
+            }
+            let overflow = self.page(next)?;
+            next = u32_at(overflow, 0)?;
+            let len = (usable - 4).min(total - payload.len());
+            payload.extend_from_slice(bytes(overflow, 4, len)?);
+        }
+        Ok(payload)
+    }
+
+    /// Rows of the table B-tree rooted at `root`, in rowid order
+    fn tree(&self, root: u32) -> io::Result<Vec<Vec<Value>>> {
+        let mut rows = vec![];
+        let mut pending = vec![root];
+        let page_count = self.data.len() / self.page_size;
+        let mut visited = 0;
+        while let Some(number) = pending.pop() {
+            visited += 1;
+            if visited > page_count {
+                return Err(invalid("B-tree loop"));
+            }
+            let page = self.page(number)?;
+            let header = if number == 1 { 100 } else { 0 };
+            let cells = usize::from(u16_at(page, header + 3)?);
+            match *page.get(header).ok_or_else(|| invalid("Empty page"))? {
+                INTERIOR_TABLE_PAGE => {
+                    // leftmost child popped first
+                    pending.push(u32_at(page, header + 8)?);
+                    for i in (0..cells).rev() {
+                        let cell = usize::from(u16_at(page, header + 12 + 2 * i)?);
+                        pending.push(u32_at(page, cell)?);
+                    }
+                },
+                LEAF_TABLE_PAGE => {
+                    for i in 0..cells {
+                        let cell = usize::from(u16_at(page, header + 8 + 2 * i)?);
+                        let (total, len) = varint(page, cell)?;
+                        let (_rowid, rowid_len) = varint(page, cell + len)?;
+                        rows.push(record(&self.payload(page, cell + len + rowid_len, total as usize)?)?);
+                    }
+                },
+                _ => return Err(invalid("Not a table B-tree page")),
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Rows of `table`. Columns aliasing the rowid, like `INTEGER PRIMARY KEY`, are `Null`.
+    pub fn rows(&self, table: &str) -> io::Result<Vec<Vec<Value>>> {
+        // type, name, tbl_name, rootpage, sql
+        let root = self.tree(1)?.into_iter()
+            .find(|r| r.first() == Some(&Value::Bytes(b"table".to_vec())) && r.get(1) == Some(&Value::Bytes(table.as_bytes().to_vec())))
+            .and_then(|r| match r.get(3) {
+                Some(Value::Int(page)) => u32::try_from(*page).ok(),
+                _ => None,
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No table {}", table)))?;
+        self.tree(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    const PAGE: usize = 512;
+
+    fn varint_bytes(mut v: u64) -> Vec<u8> {
+        let mut out = vec![v as u8 & 0x7f];
+        v >>= 7;
+        while v > 0 {
+            out.insert(0, v as u8 | 0x80);
+            v >>= 7;
+        }
+        out
+    }
+
+    fn record_bytes(values: &[Value]) -> Vec<u8> {
+        let (mut header, mut body) = (vec![], vec![]);
+        for value in values {
+            let serial = match value {
+                Value::Null => 0,
+                Value::Int(0) => 8,
+                Value::Int(1) => 9,
+                Value::Int(i) => {
+                    body.extend_from_slice(&i.to_be_bytes());
+                    6
+                },
+                Value::Float(f) => {
+                    body.extend_from_slice(&f.to_bits().to_be_bytes());
+                    7
+                },
+                Value::Bytes(b) => {
+                    body.extend_from_slice(b);
+                    12 + 2 * b.len() as u64
+                },
+            };
+            header.extend(varint_bytes(serial));
+        }
+        let mut record = varint_bytes(header.len() as u64 + 1);
+        record.extend(header);
+        record.extend(body);
+        record
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Bytes(s.as_bytes().to_vec())
+    }
+
+    /// Page with a B-tree header at `at` and `cells` packed at its end
+    fn page(kind: u8, at: usize, right: Option<u32>, cells: &[Vec<u8>]) -> Vec<u8> {
+        let mut page = vec![0; PAGE];
+        page[at] = kind;
+        page[at + 3..at + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+        let pointers = at + if let Some(right) = right {
+            page[at + 8..at + 12].copy_from_slice(&right.to_be_bytes());
+            12
+        }
+        else {
+            8
+        };
+        let mut end = PAGE;
+        for (i, cell) in cells.iter().enumerate() {
+            end -= cell.len();
+            page[end..end + cell.len()].copy_from_slice(cell);
+            page[pointers + 2 * i..pointers + 2 * i + 2].copy_from_slice(&(end as u16).to_be_bytes());
+        }
+        page[at + 5..at + 7].copy_from_slice(&(end as u16).to_be_bytes());
+        page
+    }
+
+    fn leaf_cell(rowid: u64, payload: &[u8]) -> Vec<u8> {
+        let mut cell = varint_bytes(payload.len() as u64);
+        cell.extend(varint_bytes(rowid));
+        cell.extend_from_slice(payload);
+        cell
+    }
+
+    /// A record too large for its page: the part kept on the page, then overflow pages from
+    /// `first` on
+    fn overflowing(rowid: u64, payload: &[u8], first: u32) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let usable = PAGE;
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = match min_local + (payload.len() - min_local) % (usable - 4) {
+            k if k <= usable - 35 => k,
+            _ => min_local,
+        };
+        let mut cell = varint_bytes(payload.len() as u64);
+        cell.extend(varint_bytes(rowid));
+        cell.extend_from_slice(&payload[..local]);
+        cell.extend_from_slice(&first.to_be_bytes());
+        let chunks: Vec<&[u8]> = payload[local..].chunks(usable - 4).collect();
+        let pages = chunks.iter().enumerate()
+            .map(|(i, chunk)| {
+                let next = if i + 1 < chunks.len() { first + i as u32 + 1 } else { 0 };
+                let mut page = next.to_be_bytes().to_vec();
+                page.extend_from_slice(chunk);
+                page.resize(PAGE, 0);
+                page
+            })
+            .collect();
+        (cell, pages)
+    }
+
+    fn big() -> Vec<u8> {
+        (0..1000).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// `Packages` rooted at page 2, an interior page over the leaves 3 and 4, the second with a
+    /// row overflowing into pages 5 and 6
+    fn database() -> Vec<u8> {
+        let schema = record_bytes(&[text("table"), text("Packages"), text("Packages"), Value::Int(2), text("CREATE TABLE Packages (id INTEGER PRIMARY KEY, blob BLOB, size)")]);
+        let mut first = page(LEAF_TABLE_PAGE, 100, None, &[leaf_cell(1, &schema)]);
+        first[..16].copy_from_slice(MAGIC);
+        first[16..18].copy_from_slice(&(PAGE as u16).to_be_bytes());
+        // what SQLite itself needs to open the file too: format versions, payload fractions,
+        // page count, schema format and UTF-8
+        first[18..24].copy_from_slice(&[1, 1, 0, 64, 32, 32]);
+        first[28..32].copy_from_slice(&6u32.to_be_bytes());
+        first[44..48].copy_from_slice(&4u32.to_be_bytes());
+        first[56..60].copy_from_slice(&1u32.to_be_bytes());
+        let mut interior_cell = 3u32.to_be_bytes().to_vec();
+        interior_cell.extend(varint_bytes(2));
+        let interior = page(INTERIOR_TABLE_PAGE, 0, Some(4), &[interior_cell]);
+        let rows = page(LEAF_TABLE_PAGE, 0, None, &[
+            leaf_cell(1, &record_bytes(&[Value::Null, text("bash"), Value::Int(1_234_567)])),
+            leaf_cell(2, &record_bytes(&[Value::Null, text("coreutils"), Value::Int(0)])),
+        ]);
+        let (cell, overflow) = overflowing(3, &record_bytes(&[Value::Null, Value::Bytes(big()), Value::Float(-1.5)]), 5);
+        let last = page(LEAF_TABLE_PAGE, 0, None, &[cell]);
+        assert_eq!(overflow.len(), 2);
+        [first, interior, rows, last].into_iter().chain(overflow).flatten().collect()
+    }
+
+    fn open(name: &str, data: &[u8]) -> io::Result<Database> {
+        let path: PathBuf = std::env::temp_dir().join(format!("linux-hs-elf-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let db = Database::open(&path);
+        fs::remove_file(&path).unwrap();
+        db
+    }
+
+    fn error<T>(result: io::Result<T>) -> String {
+        result.err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn reads_rows() {
+        let db = open("packages.sqlite", &database()).unwrap();
+        assert_eq!(db.rows("Packages").unwrap(), [
+            vec![Value::Null, text("bash"), Value::Int(1_234_567)],
+            vec![Value::Null, text("coreutils"), Value::Int(0)],
+            vec![Value::Null, Value::Bytes(big()), Value::Float(-1.5)],
+        ]);
+        assert_eq!(db.rows("Other").map_err(|e| e.kind()).err(), Some(io::ErrorKind::NotFound));
+        assert_eq!(record(&record_bytes(&[Value::Int(-2), Value::Int(1)])).unwrap(), [Value::Int(-2), Value::Int(1)]);
+        // small integers take as many bytes as they need, sign-extended
+        assert_eq!(record(&[3, 1, 2, 0xff, 0x12, 0x34]).unwrap(), [Value::Int(-1), Value::Int(0x1234)]);
+    }
+
+    #[test]
+    fn truncated() {
+        let data = database();
+        assert_eq!(error(open("empty.sqlite", &[])), "Not an SQLite database");
+        assert_eq!(error(open("header.sqlite", &data[..99])), "Not an SQLite database");
+        // the last leaf, then the last overflow page missing
+        let db = open("leaf.sqlite", &data[..3 * PAGE + 100]).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Record out of bounds");
+        let db = open("overflow.sqlite", &data[..5 * PAGE]).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Record out of bounds");
+        assert_eq!(error(record(&[3, 6, 1, 0, 0])), "Record out of bounds");
+        assert_eq!(error(record(&[0x81])), "Truncated varint");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let data = database();
+        let patched = |at: usize, bytes: &[u8]| {
+            let mut data = data.clone();
+            data[at..at + bytes.len()].copy_from_slice(bytes);
+            data
+        };
+        // the rightmost child past the end of the file, or page 0
+        let db = open("child.sqlite", &patched(PAGE + 8, &99u32.to_be_bytes())).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Record out of bounds");
+        let db = open("zero.sqlite", &patched(PAGE + 8, &0u32.to_be_bytes())).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Invalid page number");
+        // a child pointing back at the interior page
+        let db = open("loop.sqlite", &patched(PAGE + 8, &2u32.to_be_bytes())).unwrap();
+        assert_eq!(error(db.rows("Packages")), "B-tree loop");
+        // a cell pointer past the end of the page, and an overflow chain ending early
+        let db = open("cell.sqlite", &patched(2 * PAGE + 8, &0xfff0u16.to_be_bytes())).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Truncated varint");
+        let db = open("chain.sqlite", &patched(4 * PAGE, &0u32.to_be_bytes())).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Truncated overflow chain");
+        // a page that isn't part of a table B-tree
+        let db = open("kind.sqlite", &patched(2 * PAGE, &[0x0a])).unwrap();
+        assert_eq!(error(db.rows("Packages")), "Not a table B-tree page");
+        // a blob claiming far more bytes than there are
+        assert_eq!(error(record(&[10, 0x81, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80])), "Record out of bounds");
+        assert_eq!(error(open("reserved.sqlite", &patched(20, &[100]))), "Invalid page size");
+    }
+}
@@ -0,0 +1,296 @@
+//! Just enough of the tar format to read container images and their layers as streams: ustar
+//! headers with GNU long names and PAX path overrides.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+const BLOCK: usize = 512;
+
+pub const REGULAR: u8 = b'0';
+pub const HARD_LINK: u8 = b'1';
+pub const SYMLINK: u8 = b'2';
+pub const DIRECTORY: u8 = b'5';
+const GNU_LONG_NAME: u8 = b'L';
+const GNU_LONG_LINK: u8 = b'K';
+const PAX: u8 = b'x';
+const PAX_GLOBAL: u8 = b'g';
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Octal number of a header field, or a base-256 one for large GNU sizes
+fn number(field: &[u8]) -> io::Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Ok(field[1..].iter().fold(0, |n, &b| n << 8 | u64::from(b)));
+    }
+    let mut digits = field.iter()
+        .skip_while(|&&b| b == b' ')
+        .take_while(|&&b| b.is_ascii_digit());
+    digits.try_fold(0u64, |n, &b| {
+        n.checked_mul(8).map(|n| n + u64::from(b - b'0')).ok_or_else(|| invalid("Tar header number overflows"))
+    })
+}
+
+/// Field up to its first NUL
+fn string(field: &[u8]) -> &[u8] {
+    field.split(|&b| b == 0).next().unwrap_or_default()
+}
+
+/// Stream a tar archive is read from, skipping over member data by seeking when it can
+pub trait Input: Read {
+    /// Skips `count` bytes, returning how many there were
+    fn skip(&mut self, count: u64) -> io::Result<u64> {
+        io::copy(&mut (&mut *self).take(count), &mut io::sink())
+    }
+}
+
+impl Input for File {
+    fn skip(&mut self, count: u64) -> io::Result<u64> {
+        let left = self.metadata()?.len().saturating_sub(self.stream_position()?);
+        let count = count.min(left);
+        self.seek(SeekFrom::Current(count as i64))?;
+        Ok(count)
+    }
+}
+
+impl Input for Box<dyn Read> {}
+
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub name: Vec<u8>,
+    /// One of the type constants, `\0` being taken as a regular file
+    pub kind: u8,
+    pub mode: u32,
+    pub size: u64,
+    /// Target of a symlink or hard link
+    pub link: Vec<u8>,
+    /// Offset of the data in the archive
+    pub offset: u64,
+}
+
+/// Members of a tar stream; the data of the current one is read through [`Read`]
+pub struct Reader<R> {
+    inner: R,
+    /// Bytes read from `inner` so far
+    position: u64,
+    /// Data of the current member left to read, then its padding
+    left: u64,
+    padding: u64,
+}
+
+impl<R: Input> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Reader { inner, position: 0, left: 0, padding: 0 }
+    }
+
+    fn skip(&mut self, count: u64) -> io::Result<()> {
+        let skipped = self.inner.skip(count)?;
+        self.position += skipped;
+        if skipped < count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated tar archive"));
+        }
+        Ok(())
+    }
+
+    /// Next header block, `None` at the end of the archive
+    fn block(&mut self) -> io::Result<Option<[u8; BLOCK]>> {
+        let mut block = [0; BLOCK];
+        let mut filled = 0;
+        while filled < BLOCK {
+            match self.inner.read(&mut block[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated tar header")),
+                n => filled += n,
+            }
+        }
+        self.position += BLOCK as u64;
+        // archives end with two zero blocks
+        Ok(if block.iter().all(|&b| b == 0) { None } else { Some(block) })
+    }
+
+    /// Data of the current member
+    fn data(&mut self) -> io::Result<Vec<u8>> {
+        let mut data = vec![];
+        self.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Header of the next member, skipping whatever is left of the current one
+    pub fn next(&mut self) -> io::Result<Option<Header>> {
+        let (mut long_name, mut long_link) = (None, None);
+        loop {
+            self.skip(self.left + self.padding)?;
+            self.left = 0;
+            self.padding = 0;
+            let block = match self.block()? {
+                Some(block) => block,
+                None => return Ok(None),
+            };
+            let size = number(&block[124..136])?;
+            self.left = size;
+            self.padding = (BLOCK as u64 - size % BLOCK as u64) % BLOCK as u64;
+            let kind = match block[156] {
+                0 => REGULAR,
+                kind => kind,
+            };
+            match kind {
+                GNU_LONG_NAME => long_name = Some(string(&self.data()?).to_vec()),
+                GNU_LONG_LINK => long_link = Some(string(&self.data()?).to_vec()),
+                PAX => {
+                    // `<length> <key>=<value>\n` records
+                    let records = self.data()?;
+                    let mut rest = records.as_slice();
+                    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+                        let length = std::str::from_utf8(&rest[..space]).ok()
+                            .and_then(|l| l.parse::<usize>().ok())
+                            .filter(|&l| l >= space + 2 && l <= rest.len() && rest[l - 1] == b'\n')
+                            .ok_or_else(|| invalid("Invalid PAX record"))?;
+                        let record = &rest[space + 1..length - 1];
+                        if let Some(value) = record.strip_prefix(b"path=") {
+                            long_name = Some(value.to_vec());
+                        }
+                        else if let Some(value) = record.strip_prefix(b"linkpath=") {
+                            long_link = Some(value.to_vec());
+                        }
+                        rest = &rest[length..];
+                    }
+                },
+                PAX_GLOBAL => {},
+                _ => {
+                    let mut name = string(&block[0..100]).to_vec();
+                    let prefix = string(&block[345..500]);
+                    // GNU archives keep other fields there, with `ustar  ` as their magic
+                    if &block[257..263] == b"ustar\0" && !prefix.is_empty() {
+                        name = [prefix, b"/", &name].concat();
+                    }
+                    return Ok(Some(Header {
+                        name: long_name.unwrap_or(name),
+                        kind,
+                        mode: number(&block[100..108])? as u32,
+                        size,
+                        link: long_link.unwrap_or_else(|| string(&block[157..257]).to_vec()),
+                        offset: self.position,
+                    }));
+                },
+            }
+        }
+    }
+}
+
+impl<R: Input> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let wanted = buf.len().min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        if wanted == 0 {
+            return Ok(0);
+        }
+        let read = self.inner.read(&mut buf[..wanted])?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated tar member"));
+        }
+        self.left -= read as u64;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    /// Header block and padded data of a member
+    fn member(name: &[u8], kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut block = vec![0; BLOCK];
+        block[..name.len()].copy_from_slice(name);
+        block[100..107].copy_from_slice(b"0000644");
+        block[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        block[156] = kind;
+        block[257..263].copy_from_slice(b"ustar\0");
+        block.extend_from_slice(data);
+        block.resize(block.len().div_ceil(BLOCK) * BLOCK, 0);
+        block
+    }
+
+    fn reader(members: &[Vec<u8>]) -> Reader<Box<dyn Read>> {
+        let mut data = members.concat();
+        data.resize(data.len() + 2 * BLOCK, 0);
+        Reader::new(Box::new(Cursor::new(data)))
+    }
+
+    #[test]
+    fn reads_members() {
+        let long = vec![b'n'; 150];
+        let mut prefixed = member(b"ld.so", REGULAR, b"");
+        prefixed[345..348].copy_from_slice(b"lib");
+        let mut reader = reader(&[
+            member(b"hello", REGULAR, b"hello world"),
+            member(b"././@LongLink", GNU_LONG_NAME, &[long.as_slice(), b"\0"].concat()),
+            member(b"short", REGULAR, &[1; 600]),
+            member(b"PaxHeaders/link", PAX, b"16 path=pax/lib\n19 linkpath=target\n11 mtime=0\n"),
+            member(b"link", SYMLINK, b""),
+            member(b"old", 0, b""),
+            prefixed,
+        ]);
+
+        let hello = reader.next().unwrap().unwrap();
+        assert_eq!((hello.name.as_slice(), hello.kind, hello.mode, hello.size, hello.offset), (&b"hello"[..], REGULAR, 0o644, 11, 512));
+        let mut start = [0; 5];
+        reader.read_exact(&mut start).unwrap();
+        assert_eq!(&start, b"hello");
+
+        // the rest of the member is skipped
+        let named = reader.next().unwrap().unwrap();
+        assert_eq!((named.name, named.size, named.offset), (long, 600, 5 * 512));
+        let link = reader.next().unwrap().unwrap();
+        assert_eq!((link.name.as_slice(), link.kind, link.link.as_slice()), (&b"pax/lib"[..], SYMLINK, &b"target"[..]));
+        // no type flag is a regular file
+        let old = reader.next().unwrap().unwrap();
+        assert_eq!((old.name.as_slice(), old.kind), (&b"old"[..], REGULAR));
+        assert_eq!(reader.next().unwrap().unwrap().name, b"lib/ld.so");
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number(b"  0000755 \0").unwrap(), 0o755);
+        assert_eq!(number(b"\0\0\0\0").unwrap(), 0);
+        assert_eq!(number(&[0x80, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]).unwrap(), 1 << 56);
+        assert!(number(b"7777777777777777777777").is_err());
+    }
+
+    #[test]
+    fn damaged_pax_records() {
+        for records in [
+            &b"2 x\n"[..],
+            b"3 x\n",
+            b"0 x\n",
+            b"x path=a\n",
+            b"-1 path=a\n",
+            b"99 path=a\n",
+            b"10 path=ab",
+            b"12 path=abc\n12 path=ab",
+            b"11 path=abcd\n",
+        ] {
+            let mut reader = reader(&[member(b"PaxHeaders/a", PAX, records), member(b"a", REGULAR, b"")]);
+            let error = reader.next().unwrap_err();
+            assert_eq!((error.kind(), error.to_string()), (io::ErrorKind::InvalidData, "Invalid PAX record".to_string()), "{:?}", records);
+        }
+        // a record right at the end of the extended header
+        let mut reader = reader(&[member(b"PaxHeaders/a", PAX, b"11 path=ab\n"), member(b"a", REGULAR, b"")]);
+        assert_eq!(reader.next().unwrap().unwrap().name, b"ab");
+    }
+
+    #[test]
+    fn truncated() {
+        let archive = member(b"hello", REGULAR, &[1; 1000]);
+        let cut_at = |len: usize| Reader::new(Box::new(Cursor::new(archive[..len].to_vec())) as Box<dyn Read>);
+        assert_eq!(cut_at(100).next().unwrap_err().to_string(), "Truncated tar header");
+        let mut reader = cut_at(700);
+        reader.next().unwrap();
+        assert_eq!(reader.data().unwrap_err().to_string(), "Truncated tar member");
+        let mut reader = cut_at(700);
+        reader.next().unwrap();
+        assert_eq!(reader.next().unwrap_err().to_string(), "Truncated tar archive");
+    }
+}
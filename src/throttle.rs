@@ -0,0 +1,54 @@
+//! Caps on how fast a scan reads, so that scans on production hosts stay below what would
+//! disturb the workloads sharing the disk.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Maximum rates, unlimited if `None`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub files_per_sec: Option<f64>,
+    pub bytes_per_sec: Option<f64>,
+}
+
+/// Parses a rate given on the command line, which must be positive
+pub fn rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        Ok(_) => Err("must be a positive number".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl Limits {
+    /// Shortest time reading `files` totalling `bytes` may take
+    pub fn minimum(&self, files: u64, bytes: u64) -> Duration {
+        let by_files = self.files_per_sec.map_or(0.0, |rate| files as f64 / rate);
+        let by_bytes = self.bytes_per_sec.map_or(0.0, |rate| bytes as f64 / rate);
+        Duration::from_secs_f64(by_files.max(by_bytes))
+    }
+}
+
+/// Keeps a scan within [`Limits`] by sleeping after files that got it ahead of them
+pub struct Throttle {
+    limits: Limits,
+    started: Instant,
+    files: u64,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(limits: Limits) -> Self {
+        Throttle { limits, started: Instant::now(), files: 0, bytes: 0 }
+    }
+
+    /// Accounts for a file of `bytes` just read, waiting until the rates are back within limits
+    pub fn file(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+        let due = self.limits.minimum(self.files, self.bytes);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+}
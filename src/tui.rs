@@ -0,0 +1,691 @@
+//! Interactive terminal explorer of the scan results, for scans too large to read as a listing:
+//! the libraries and the executables side by side, searched as you type and sorted by name,
+//! number of dependants or dependencies, or size.
+//!
+//! Drilling down from a library narrows the executables to the ones needing it, and from an
+//! executable narrows the libraries to the ones it loads, directly or through other libraries.
+//! Every drill-down can be left again, back to where it started. The terminal is driven with
+//! termios and ANSI escape sequences, and restored however the explorer ends; that's only done
+//! on Unix hosts, which have termios.
+
+// the explorer itself is built and tested everywhere
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use crate::dry_run::human_bytes;
+use crate::escape;
+use crate::graph::{DepGraph, NodeKind};
+
+const LIBRARIES: usize = 0;
+const EXECUTABLES: usize = 1;
+
+/// Library or executable in one of the panes
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    /// Name shown, with the architecture if the scan has several
+    label: String,
+    /// File on disk, `None` for missing libraries
+    path: Option<String>,
+    arch: String,
+    size: u64,
+    missing: bool,
+    /// Entries of the other pane this one is linked to directly: the libraries an executable
+    /// needs, the executables needing a library
+    direct: Vec<usize>,
+    /// The same through other libraries too
+    transitive: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Dependants of libraries, dependencies of executables
+    Count,
+    Name,
+    Size,
+}
+
+impl SortKey {
+    fn next(self) -> SortKey {
+        match self {
+            SortKey::Count => SortKey::Name,
+            SortKey::Name => SortKey::Size,
+            SortKey::Size => SortKey::Count,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Count => "count",
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+        }
+    }
+}
+
+/// What the panes show, one per drill-down
+#[derive(Debug, Clone, Default)]
+struct View {
+    focus: usize,
+    /// Entry of the other pane each pane is narrowed to the links of
+    scopes: [Option<usize>; 2],
+    /// Incremental search of each pane
+    queries: [String; 2],
+    /// Position of the selected entry among the ones shown
+    selected: [usize; 2],
+    /// Position of the first entry on screen
+    offsets: [usize; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Interrupt,
+}
+
+/// Keys in what a read from the terminal returned
+pub fn keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut rest = input;
+    while let Some(&byte) = rest.first() {
+        let (key, length) = match rest {
+            [0x1b, b'[' | b'O', b'A', ..] => (Some(Key::Up), 3),
+            [0x1b, b'[' | b'O', b'B', ..] => (Some(Key::Down), 3),
+            [0x1b, b'[' | b'O', b'C', ..] => (Some(Key::Right), 3),
+            [0x1b, b'[' | b'O', b'D', ..] => (Some(Key::Left), 3),
+            [0x1b, b'[' | b'O', b'H', ..] => (Some(Key::Home), 3),
+            [0x1b, b'[' | b'O', b'F', ..] => (Some(Key::End), 3),
+            [0x1b, b'[', b'1' | b'7', b'~', ..] => (Some(Key::Home), 4),
+            [0x1b, b'[', b'4' | b'8', b'~', ..] => (Some(Key::End), 4),
+            [0x1b, b'[', b'5', b'~', ..] => (Some(Key::PageUp), 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Some(Key::PageDown), 4),
+            // other sequences are skipped up to their final byte
+            [0x1b, b'[', tail @ ..] => (None, 2 + tail.iter().position(|b| (0x40..0x7f).contains(b)).map_or(tail.len(), |i| i + 1)),
+            [0x1b, ..] => (Some(Key::Escape), 1),
+            [b'\r' | b'\n', ..] => (Some(Key::Enter), 1),
+            [b'\t', ..] => (Some(Key::Tab), 1),
+            [0x7f | 0x08, ..] => (Some(Key::Backspace), 1),
+            [0x03, ..] => (Some(Key::Interrupt), 1),
+            _ if byte < 0x20 => (None, 1),
+            _ => {
+                // a whole UTF-8 character, dropped if it's invalid
+                let length = match byte {
+                    0xf0.. => 4,
+                    0xe0.. => 3,
+                    0xc0.. => 2,
+                    _ => 1,
+                }.min(rest.len());
+                (std::str::from_utf8(&rest[..length]).ok().and_then(|c| c.chars().next()).map(Key::Char), length)
+            },
+        };
+        keys.extend(key);
+        rest = &rest[length..];
+    }
+    keys
+}
+
+/// State of the explorer, independent of the terminal it's drawn on
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    panes: [Vec<Entry>; 2],
+    /// The first is the view without drill-downs
+    views: Vec<View>,
+    sort: SortKey,
+    reverse: bool,
+    /// Whether the links followed are transitive or direct ones
+    transitive: bool,
+    /// Whether keys are typed into the search of the focused pane
+    searching: bool,
+}
+
+impl Explorer {
+    pub fn new(graph: &DepGraph) -> Self {
+        let arches: BTreeSet<&str> = graph.nodes.iter().filter_map(|n| n.arch.as_deref()).collect();
+        let mut index = HashMap::new();
+        let mut panes: [Vec<Entry>; 2] = [vec![], vec![]];
+        for (id, node) in graph.nodes.iter().enumerate() {
+            let pane = match node.kind {
+                NodeKind::Library => LIBRARIES,
+                NodeKind::Scanned => EXECUTABLES,
+            };
+            let arch = node.arch.clone().unwrap_or_default();
+            let label = match node.kind {
+                NodeKind::Library if arches.len() > 1 => format!("{} [{}]", node.name, arch),
+                _ => node.name.clone(),
+            };
+            index.insert(id, panes[pane].len());
+            panes[pane].push(Entry {
+                name: node.name.clone(),
+                label,
+                path: node.path.as_deref().map(escape::path),
+                arch,
+                size: node.size.unwrap_or(0),
+                missing: node.missing,
+                direct: vec![],
+                transitive: vec![],
+            });
+        }
+        for id in (0..graph.nodes.len()).filter(|&id| graph.nodes[id].kind == NodeKind::Scanned) {
+            let exe = index[&id];
+            // every library reachable from the executable, each once however many paths lead there
+            let mut seen = BTreeSet::new();
+            let mut queue: VecDeque<usize> = graph.edges[id].iter().copied().collect();
+            while let Some(lib) = queue.pop_front() {
+                if graph.nodes[lib].kind == NodeKind::Library && seen.insert(lib) {
+                    queue.extend(graph.edges[lib].iter().copied());
+                }
+            }
+            for &lib in &seen {
+                panes[LIBRARIES][index[&lib]].transitive.push(exe);
+                panes[EXECUTABLES][exe].transitive.push(index[&lib]);
+            }
+            for &lib in graph.edges[id].iter().filter(|&&l| graph.nodes[l].kind == NodeKind::Library) {
+                panes[LIBRARIES][index[&lib]].direct.push(exe);
+                panes[EXECUTABLES][exe].direct.push(index[&lib]);
+            }
+        }
+        Explorer {
+            panes,
+            views: vec![View::default()],
+            sort: SortKey::Count,
+            reverse: false,
+            transitive: true,
+            searching: false,
+        }
+    }
+
+    fn view(&self) -> &View {
+        self.views.last().unwrap()
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        self.views.last_mut().unwrap()
+    }
+
+    fn links(&self, pane: usize, entry: usize) -> &[usize] {
+        let entry = &self.panes[pane][entry];
+        if self.transitive { &entry.transitive } else { &entry.direct }
+    }
+
+    /// Entries the pane is narrowed to by drill-downs, before searching
+    fn scope(&self, pane: usize) -> Vec<usize> {
+        match self.view().scopes[pane] {
+            Some(origin) => self.links(1 - pane, origin).to_vec(),
+            None => (0..self.panes[pane].len()).collect(),
+        }
+    }
+
+    /// Entries the pane shows, in order
+    fn shown(&self, pane: usize) -> Vec<usize> {
+        let query = self.view().queries[pane].to_lowercase();
+        let entries = &self.panes[pane];
+        let mut shown: Vec<usize> = self.scope(pane).into_iter()
+            .filter(|&e| query.is_empty() || entries[e].label.to_lowercase().contains(&query))
+            .collect();
+        let by_name = |a: &usize, b: &usize| entries[*a].label.cmp(&entries[*b].label);
+        // counts and sizes largest first, names in alphabetical order
+        match self.sort {
+            SortKey::Count => shown.sort_by(|a, b| self.links(pane, *b).len().cmp(&self.links(pane, *a).len()).then_with(|| by_name(a, b))),
+            SortKey::Name => shown.sort_by(by_name),
+            SortKey::Size => shown.sort_by(|a, b| entries[*b].size.cmp(&entries[*a].size).then_with(|| by_name(a, b))),
+        }
+        if self.reverse {
+            shown.reverse();
+        }
+        shown
+    }
+
+    /// Handles `key`, returning whether the explorer keeps running
+    pub fn key(&mut self, key: Key, page: usize) -> bool {
+        let focus = self.view().focus;
+        if self.searching {
+            match key {
+                Key::Char(c) => self.view_mut().queries[focus].push(c),
+                Key::Backspace => {
+                    self.view_mut().queries[focus].pop();
+                },
+                Key::Enter => self.searching = false,
+                Key::Escape => {
+                    self.searching = false;
+                    self.view_mut().queries[focus].clear();
+                },
+                Key::Interrupt => return false,
+                _ => return self.navigate(key, page),
+            }
+            self.view_mut().selected[focus] = 0;
+            return true;
+        }
+        match key {
+            Key::Char('q') | Key::Interrupt => return false,
+            Key::Char('/') => self.searching = true,
+            Key::Char('s') => self.sort = self.sort.next(),
+            Key::Char('r') => self.reverse = !self.reverse,
+            Key::Char('t') => self.transitive = !self.transitive,
+            Key::Tab => self.view_mut().focus = 1 - focus,
+            Key::Left | Key::Char('h') => self.view_mut().focus = LIBRARIES,
+            Key::Right | Key::Char('l') => self.view_mut().focus = EXECUTABLES,
+            Key::Enter => {
+                // narrow the other pane to the links of the selected entry
+                if let Some(&origin) = self.shown(focus).get(self.view().selected[focus]) {
+                    let mut view = self.view().clone();
+                    let other = 1 - focus;
+                    view.focus = other;
+                    view.scopes[other] = Some(origin);
+                    view.queries[other].clear();
+                    view.selected[other] = 0;
+                    view.offsets[other] = 0;
+                    self.views.push(view);
+                }
+            },
+            Key::Escape | Key::Backspace => {
+                if !self.view().queries[focus].is_empty() {
+                    self.view_mut().queries[focus].clear();
+                    self.view_mut().selected[focus] = 0;
+                }
+                else if self.views.len() > 1 {
+                    self.views.pop();
+                }
+            },
+            _ => return self.navigate(key, page),
+        }
+        true
+    }
+
+    /// Moves the selection of the focused pane, `page` entries at a time for page keys
+    fn navigate(&mut self, key: Key, page: usize) -> bool {
+        let focus = self.view().focus;
+        let last = self.shown(focus).len().saturating_sub(1);
+        let selected = &mut self.view_mut().selected[focus];
+        *selected = match key {
+            Key::Up | Key::Char('k') => selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => *selected + 1,
+            Key::PageUp => selected.saturating_sub(page.max(1)),
+            Key::PageDown => *selected + page.max(1),
+            Key::Home | Key::Char('g') => 0,
+            Key::End | Key::Char('G') => last,
+            _ => *selected,
+        }.min(last);
+        true
+    }
+
+    /// Title of the pane, with what it's narrowed to and how many entries it shows
+    fn title(&self, pane: usize, shown: usize) -> String {
+        let view = self.view();
+        let links = if self.transitive { "" } else { "directly " };
+        let mut title = match (pane, view.scopes[pane]) {
+            (LIBRARIES, Some(exe)) => format!("libraries {}loaded by {}", links, self.panes[EXECUTABLES][exe].label),
+            (LIBRARIES, None) => "libraries".to_string(),
+            (_, Some(lib)) => format!("executables {}needing {}", links, self.panes[LIBRARIES][lib].label),
+            (_, None) => "executables".to_string(),
+        };
+        title += &format!(" ({}/{})", shown, self.scope(pane).len());
+        if !view.queries[pane].is_empty() {
+            title += &format!(" /{}", view.queries[pane]);
+        }
+        title
+    }
+
+    /// What's known of the selected entry of the focused pane
+    fn details(&self) -> String {
+        let view = self.view();
+        let pane = view.focus;
+        let entry = match self.shown(pane).get(view.selected[pane]) {
+            Some(&entry) => &self.panes[pane][entry],
+            None => return String::new(),
+        };
+        let (direct, transitive) = (entry.direct.len(), entry.transitive.len());
+        let links = if pane == LIBRARIES {
+            format!("needed by {} exes, {} directly", transitive, direct)
+        }
+        else {
+            format!("loads {} libs, {} directly", transitive, direct)
+        };
+        match &entry.path {
+            Some(path) => format!("{} [{}] {}, {}", path, entry.arch, human_bytes(entry.size), links),
+            None if entry.missing => format!("{} [{}] not found, {}", entry.name, entry.arch, links),
+            None => format!("{} [{}], {}", entry.name, entry.arch, links),
+        }
+    }
+
+    /// Draws the explorer on a terminal of `rows` by `columns`, keeping the selections on screen
+    pub fn render(&mut self, rows: usize, columns: usize) -> String {
+        let mut frame = String::from("\x1b[H");
+        if rows < 6 || columns < 20 {
+            frame += "\x1b[2J\x1b[HTerminal too small";
+            return frame;
+        }
+        let mut lines: Vec<String> = Vec::with_capacity(rows);
+        let missing = self.panes[LIBRARIES].iter().filter(|e| e.missing).count();
+        lines.push(bold(&fit(&format!(
+            "{} executables, {} libraries, {} not found | sort: {}{} | {} links",
+            self.panes[EXECUTABLES].len(), self.panes[LIBRARIES].len(), missing, self.sort.name(),
+            if self.reverse { " reversed" } else { "" }, if self.transitive { "transitive" } else { "direct" },
+        ), columns)));
+        let height = rows - 4;
+        let widths = [(columns - 1) / 2, columns - 1 - (columns - 1) / 2];
+        let mut panes = vec![];
+        for (pane, &width) in widths.iter().enumerate() {
+            let shown = self.shown(pane);
+            let view = self.view_mut();
+            view.selected[pane] = view.selected[pane].min(shown.len().saturating_sub(1));
+            let (selected, offset) = (view.selected[pane], &mut view.offsets[pane]);
+            if selected < *offset {
+                *offset = selected;
+            }
+            else if selected >= *offset + height {
+                *offset = selected + 1 - height;
+            }
+            let offset = *offset;
+            let focused = self.view().focus == pane;
+            let mut column = vec![];
+            let title = fit(&self.title(pane, shown.len()), width);
+            column.push(if focused { format!("\x1b[1;4m{}\x1b[0m", title) } else { bold(&title) });
+            for (row, &entry) in shown.iter().enumerate().skip(offset).take(height) {
+                let line = fit(&self.row(pane, entry), width);
+                column.push(match (row == selected, focused) {
+                    (true, true) => format!("\x1b[7m{}\x1b[0m", line),
+                    (true, false) => bold(&line),
+                    _ => line,
+                });
+            }
+            column.resize(height + 1, " ".repeat(width));
+            panes.push(column);
+        }
+        for (left, right) in panes[LIBRARIES].iter().zip(&panes[EXECUTABLES]) {
+            lines.push(format!("{}\u{2502}{}", left, right));
+        }
+        lines.push(fit(&self.details(), columns));
+        lines.push(if self.searching {
+            fit(&format!("search: {}_  (enter keeps it, esc clears it)", self.view().queries[self.view().focus]), columns)
+        }
+        else {
+            let back = if self.views.len() > 1 { "  esc back" } else { "" };
+            fit(&format!("tab pane  enter drill down{}  / search  s sort  r reverse  t direct/transitive  q quit", back), columns)
+        });
+        for (row, line) in lines.iter().enumerate() {
+            frame += &format!("\x1b[{};1H{}", row + 1, line);
+        }
+        frame
+    }
+
+    /// Line of an entry: its number of links, and its name marked if it's missing
+    fn row(&self, pane: usize, entry: usize) -> String {
+        let marker = if self.panes[pane][entry].missing { "! " } else { "" };
+        format!("{:>5} {}{}", self.links(pane, entry).len(), marker, self.panes[pane][entry].label)
+    }
+}
+
+fn bold(line: &str) -> String {
+    format!("\x1b[1m{}\x1b[0m", line)
+}
+
+/// `line` cut or padded to `width` characters
+fn fit(line: &str, width: usize) -> String {
+    let mut fitted: String = line.chars().filter(|c| !c.is_control()).take(width).collect();
+    let length = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width - length));
+    fitted
+}
+
+#[cfg(unix)]
+pub use self::terminal::run;
+
+/// The explorer isn't available elsewhere, having no termios to put the terminal in raw mode with
+#[cfg(not(unix))]
+pub fn run(_graph: &DepGraph) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "the explorer is only available on Unix"))
+}
+
+#[cfg(unix)]
+mod terminal {
+    use std::io::{self, Read, Write};
+    use std::mem::MaybeUninit;
+    use libc::{cfmakeraw, tcgetattr, tcsetattr, termios, TCSAFLUSH, VMIN, VTIME};
+    use crate::graph::DepGraph;
+    use crate::progress::window_size;
+    use super::{keys, Explorer};
+
+    /// Terminal in raw mode on the alternate screen, restored when dropped
+    struct Terminal {
+        saved: termios,
+    }
+
+    impl Terminal {
+        fn enter() -> io::Result<Terminal> {
+            let mut saved = MaybeUninit::<termios>::uninit();
+            // SAFETY: the pointers are valid for the calls, which only read and write a `termios`,
+            // and `saved` is only read once tcgetattr filled it in
+            let saved = unsafe {
+                if tcgetattr(0, saved.as_mut_ptr()) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let saved = saved.assume_init();
+                let mut raw = saved;
+                cfmakeraw(&mut raw);
+                // reads give up after a fifth of a second, so that resizes are noticed
+                raw.c_cc[VMIN] = 0;
+                raw.c_cc[VTIME] = 2;
+                if tcsetattr(0, TCSAFLUSH, &raw) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                saved
+            };
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x1b[?1049h\x1b[?25l\x1b[2J")?;
+            stdout.flush()?;
+            Ok(Terminal { saved })
+        }
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x1b[0m\x1b[?25h\x1b[?1049l");
+            let _ = stdout.flush();
+            // SAFETY: the pointer is valid for the call, which only reads the `termios`
+            unsafe {
+                tcsetattr(0, TCSAFLUSH, &self.saved);
+            }
+        }
+    }
+
+    /// Explores `graph` on the terminal of stdin and stdout until the user quits
+    pub fn run(graph: &DepGraph) -> io::Result<()> {
+        let mut explorer = Explorer::new(graph);
+        let _terminal = Terminal::enter()?;
+        let mut stdout = io::stdout().lock();
+        let mut stdin = io::stdin().lock();
+        let mut input = [0; 64];
+        let mut drawn = None;
+        let mut changed = true;
+        loop {
+            let size = window_size(1).unwrap_or((24, 80));
+            if changed || drawn != Some(size) {
+                // what a larger terminal showed would stay around the smaller frame
+                if drawn.is_some_and(|drawn| drawn != size) {
+                    stdout.write_all(b"\x1b[2J")?;
+                }
+                stdout.write_all(explorer.render(size.0, size.1).as_bytes())?;
+                stdout.flush()?;
+                drawn = Some(size);
+                changed = false;
+            }
+            let read = stdin.read(&mut input)?;
+            for key in keys(&input[..read]) {
+                if !explorer.key(key, size.0.saturating_sub(4)) {
+                    return Ok(());
+                }
+                changed = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Node;
+
+    /// `app` needs `libfoo`, which needs `libc`; `tool` needs `libc` and the missing `libgone`
+    fn explorer() -> Explorer {
+        let node = |name: &str, kind, size| Node {
+            name: name.to_string(),
+            kind,
+            path: None,
+            arch: Some("x86_64".to_string()),
+            size: Some(size),
+            soname: None,
+            missing: size == 0,
+            depth: 0,
+        };
+        let graph = DepGraph {
+            nodes: vec![
+                node("app", NodeKind::Scanned, 100),
+                node("tool", NodeKind::Scanned, 300),
+                node("libfoo", NodeKind::Library, 5000),
+                node("libc", NodeKind::Library, 2000),
+                node("libgone", NodeKind::Library, 0),
+            ],
+            edges: vec![vec![2], vec![3, 4], vec![3], vec![], vec![]],
+        };
+        Explorer::new(&graph)
+    }
+
+    fn shown(explorer: &Explorer, pane: usize) -> Vec<&str> {
+        explorer.shown(pane).into_iter().map(|e| explorer.panes[pane][e].label.as_str()).collect()
+    }
+
+    fn press(explorer: &mut Explorer, keys: &[Key]) {
+        for &key in keys {
+            assert!(explorer.key(key, 10));
+        }
+    }
+
+    #[test]
+    fn keys_of_input() {
+        assert_eq!(
+            keys(b"\x1b[Aq\x1b[5~\x1b[2;5Hx\xc3\xa9\r\x7f\x1b"),
+            vec![Key::Up, Key::Char('q'), Key::PageUp, Key::Char('x'), Key::Char('é'), Key::Enter, Key::Backspace, Key::Escape],
+        );
+        assert_eq!(keys(b"\x1bOB\x1b[4~\t\x03\x01\xff"), vec![Key::Down, Key::End, Key::Tab, Key::Interrupt]);
+    }
+
+    #[test]
+    fn switches_panes() {
+        let mut explorer = explorer();
+        assert_eq!(explorer.view().focus, LIBRARIES);
+        press(&mut explorer, &[Key::Tab]);
+        assert_eq!(explorer.view().focus, EXECUTABLES);
+        press(&mut explorer, &[Key::Tab]);
+        assert_eq!(explorer.view().focus, LIBRARIES);
+        press(&mut explorer, &[Key::Char('l')]);
+        assert_eq!(explorer.view().focus, EXECUTABLES);
+        press(&mut explorer, &[Key::Left]);
+        assert_eq!(explorer.view().focus, LIBRARIES);
+        assert!(!explorer.key(Key::Char('q'), 10));
+        assert!(!explorer.key(Key::Interrupt, 10));
+    }
+
+    #[test]
+    fn sorts() {
+        let mut explorer = explorer();
+        // by dependants, then by name
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libfoo", "libgone"]);
+        assert_eq!(shown(&explorer, EXECUTABLES), ["app", "tool"]);
+        press(&mut explorer, &[Key::Char('s')]);
+        assert_eq!(explorer.sort, SortKey::Name);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libfoo", "libgone"]);
+        press(&mut explorer, &[Key::Char('s')]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libfoo", "libc", "libgone"]);
+        assert_eq!(shown(&explorer, EXECUTABLES), ["tool", "app"]);
+        press(&mut explorer, &[Key::Char('r')]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libgone", "libc", "libfoo"]);
+        press(&mut explorer, &[Key::Char('r'), Key::Char('s')]);
+        assert_eq!(explorer.sort, SortKey::Count);
+    }
+
+    #[test]
+    fn searches() {
+        let mut explorer = explorer();
+        press(&mut explorer, &[Key::Down, Key::Char('/'), Key::Char('F'), Key::Char('o')]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libfoo"]);
+        assert_eq!(explorer.view().selected[LIBRARIES], 0);
+        // typed into the search rather than quitting
+        press(&mut explorer, &[Key::Char('q')]);
+        assert_eq!(shown(&explorer, LIBRARIES), Vec::<&str>::new());
+        press(&mut explorer, &[Key::Backspace, Key::Enter]);
+        assert!(!explorer.searching);
+        assert_eq!(explorer.view().queries[LIBRARIES], "Fo");
+        assert_eq!(shown(&explorer, EXECUTABLES), ["app", "tool"]);
+        press(&mut explorer, &[Key::Escape]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libfoo", "libgone"]);
+        press(&mut explorer, &[Key::Char('/'), Key::Char('g'), Key::Escape]);
+        assert!(!explorer.searching);
+        assert_eq!(shown(&explorer, LIBRARIES).len(), 3);
+    }
+
+    #[test]
+    fn drills_down_and_back() {
+        let mut explorer = explorer();
+        // libc is needed by both executables, directly by tool only
+        press(&mut explorer, &[Key::Enter]);
+        assert_eq!(explorer.views.len(), 2);
+        assert_eq!(explorer.view().focus, EXECUTABLES);
+        assert_eq!(shown(&explorer, EXECUTABLES), ["app", "tool"]);
+        assert_eq!(explorer.title(EXECUTABLES, 2), "executables needing libc (2/2)");
+        press(&mut explorer, &[Key::Char('t')]);
+        assert_eq!(shown(&explorer, EXECUTABLES), ["tool"]);
+        assert_eq!(explorer.title(EXECUTABLES, 1), "executables directly needing libc (1/1)");
+        // and from tool to the libraries it loads
+        press(&mut explorer, &[Key::Enter]);
+        assert_eq!(explorer.views.len(), 3);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libgone"]);
+        press(&mut explorer, &[Key::Escape, Key::Escape]);
+        assert_eq!(explorer.views.len(), 1);
+        assert_eq!(explorer.view().focus, LIBRARIES);
+        // sorted by direct dependencies now
+        assert_eq!(shown(&explorer, EXECUTABLES), ["tool", "app"]);
+        // nothing left to go back to
+        press(&mut explorer, &[Key::Escape]);
+        assert_eq!(explorer.views.len(), 1);
+    }
+
+    #[test]
+    fn toggles_transitive_links() {
+        let mut explorer = explorer();
+        press(&mut explorer, &[Key::Tab, Key::Enter]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libfoo"]);
+        press(&mut explorer, &[Key::Char('t')]);
+        assert!(!explorer.transitive);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libfoo"]);
+        assert_eq!(explorer.row(LIBRARIES, 0), "    1 libfoo");
+        press(&mut explorer, &[Key::Char('t')]);
+        assert_eq!(shown(&explorer, LIBRARIES), ["libc", "libfoo"]);
+    }
+
+    #[test]
+    fn keeps_the_selection_in_range() {
+        let mut explorer = explorer();
+        press(&mut explorer, &[Key::Down, Key::Down, Key::Down, Key::Down]);
+        assert_eq!(explorer.view().selected[LIBRARIES], 2);
+        press(&mut explorer, &[Key::Home]);
+        assert_eq!(explorer.view().selected[LIBRARIES], 0);
+        press(&mut explorer, &[Key::PageDown]);
+        assert_eq!(explorer.view().selected[LIBRARIES], 2);
+        press(&mut explorer, &[Key::Char('k')]);
+        assert_eq!(explorer.view().selected[LIBRARIES], 1);
+        assert!(explorer.details().starts_with("libfoo [x86_64], needed by 1 exes"), "{}", explorer.details());
+    }
+}
@@ -0,0 +1,189 @@
+//! Imports of WebAssembly modules, read straight from the binary format's import section.
+
+use crate::elf::{BinaryFormat, ElfInfo, HandleError};
+use crate::escape;
+
+pub const MAGIC: &[u8] = b"\0asm";
+
+const IMPORT_SECTION: u8 = 2;
+
+/// Cursor over the module bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, HandleError> {
+        let b = *self.data.get(self.pos).ok_or(HandleError::Malformed("Truncated wasm module"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    /// Unsigned LEB128 number
+    fn uleb(&mut self) -> Result<u64, HandleError> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            result |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(HandleError::Malformed("Invalid LEB128 number in wasm module"))
+    }
+
+    fn bytes(&mut self, len: u64) -> Result<&'a [u8], HandleError> {
+        let end = usize::try_from(len).ok()
+            .and_then(|len| self.pos.checked_add(len))
+            .filter(|end| *end <= self.data.len())
+            .ok_or(HandleError::Malformed("Truncated wasm module"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn name(&mut self) -> Result<String, HandleError> {
+        let len = self.uleb()?;
+        Ok(escape::bytes(self.bytes(len)?))
+    }
+
+    /// Skips table and memory size limits
+    fn skip_limits(&mut self) -> Result<(), HandleError> {
+        let flags = self.byte()?;
+        self.uleb()?;
+        if flags & 1 != 0 {
+            self.uleb()?;
+        }
+        Ok(())
+    }
+
+    /// Skips the description of an import after its names
+    fn skip_import_desc(&mut self) -> Result<(), HandleError> {
+        match self.byte()? {
+            // function: type index
+            0 => self.uleb().map(drop),
+            // table: element type and limits
+            1 => {
+                self.byte()?;
+                self.skip_limits()
+            },
+            2 => self.skip_limits(),
+            // global: value type and mutability
+            3 => self.bytes(2).map(drop),
+            // tag: attribute and type index
+            4 => {
+                self.byte()?;
+                self.uleb().map(drop)
+            },
+            _ => Err(HandleError::Malformed("Unknown wasm import kind")),
+        }
+    }
+}
+
+/// Parses the imports of a wasm module already read into memory. Module names become the
+/// needed libraries and imported names the imports.
+pub fn parse_wasm_data(bin_data: &[u8]) -> Result<ElfInfo, HandleError> {
+    if !bin_data.starts_with(MAGIC) || bin_data.len() < 8 {
+        return Err(HandleError::NotElf);
+    }
+    let mut info = ElfInfo {
+        format: BinaryFormat::Wasm,
+        arch: "wasm32".to_string(),
+        bits: 32,
+        size: bin_data.len() as u64,
+        ..ElfInfo::default()
+    };
+    let mut reader = Reader { data: bin_data, pos: 8 };
+    while reader.pos < bin_data.len() {
+        let id = reader.byte()?;
+        let len = reader.uleb()?;
+        let content = reader.bytes(len)?;
+        if id != IMPORT_SECTION {
+            continue;
+        }
+        let mut section = Reader { data: content, pos: 0 };
+        for _ in 0..section.uleb()? {
+            let module = section.name()?;
+            let name = section.name()?;
+            section.skip_import_desc()?;
+            if !info.needed.contains(&module) {
+                info.needed.push(module);
+            }
+            info.imports.push(name);
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(id: u8, content: &[u8]) -> Vec<u8> {
+        assert!(content.len() < 0x80);
+        [&[id, content.len() as u8][..], content].concat()
+    }
+
+    fn import(module: &str, name: &str, desc: &[u8]) -> Vec<u8> {
+        [&[module.len() as u8][..], module.as_bytes(), &[name.len() as u8], name.as_bytes(), desc].concat()
+    }
+
+    /// Module importing one thing of every kind, between a type section and a custom one
+    fn module() -> Vec<u8> {
+        let imports = [
+            import("env", "memory", &[2, 1, 1, 2]),
+            import("env", "abort", &[0, 0]),
+            import("wasi_snapshot_preview1", "fd_write", &[0, 0]),
+            import("env", "table", &[1, 0x70, 0, 1]),
+            import("env", "stack", &[3, 0x7f, 1]),
+            import("env", "trap", &[4, 0, 0]),
+        ];
+        let mut content = vec![imports.len() as u8];
+        imports.iter().for_each(|i| content.extend_from_slice(i));
+        [
+            &b"\0asm\x01\0\0\0"[..],
+            &section(1, &[1, 0x60, 0, 0]),
+            &section(IMPORT_SECTION, &content),
+            &section(0, b"\x04name\x01\x02\x03"),
+        ].concat()
+    }
+
+    fn error<T>(result: Result<T, HandleError>) -> String {
+        result.err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn reads_imports() {
+        let data = module();
+        let info = parse_wasm_data(&data).unwrap();
+        assert_eq!(info.format, BinaryFormat::Wasm);
+        assert_eq!((info.arch.as_str(), info.bits, info.size), ("wasm32", 32, data.len() as u64));
+        assert_eq!(info.needed, ["env", "wasi_snapshot_preview1"]);
+        assert_eq!(info.imports, ["memory", "abort", "fd_write", "table", "stack", "trap"]);
+        // a module without sections imports nothing
+        assert!(parse_wasm_data(b"\0asm\x01\0\0\0").unwrap().imports.is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let data = module();
+        let imports = 8 + 6;
+        assert_eq!(error(parse_wasm_data(&data[..imports + 20])), "malformed ELF: Truncated wasm module");
+        // a section longer than the module, or more imports than the section holds
+        let mut long = data.clone();
+        long[imports + 1] = 0x7f;
+        assert_eq!(error(parse_wasm_data(&long)), "malformed ELF: Truncated wasm module");
+        let mut count = data.clone();
+        count[imports + 2] = 100;
+        assert_eq!(error(parse_wasm_data(&count)), "malformed ELF: Truncated wasm module");
+        // a kind of import that doesn't exist, and a number that doesn't end
+        let mut kind = data.clone();
+        kind[imports + 3 + 4 + 7] = 9;
+        assert_eq!(error(parse_wasm_data(&kind)), "malformed ELF: Unknown wasm import kind");
+        let endless = [&b"\0asm\x01\0\0\0\x02"[..], &[0x80; 10]].concat();
+        assert_eq!(error(parse_wasm_data(&endless)), "malformed ELF: Invalid LEB128 number in wasm module");
+        assert_eq!(parse_wasm_data(b"\0asm").err().map(|e| e.kind()), Some("not-elf"));
+        assert_eq!(parse_wasm_data(b"\x7fELF\x02\x01\x01\0").err().map(|e| e.kind()), Some("not-elf"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Minimal HTTP client posting JSON notifications. Only plain `http://` URLs are supported, HTTPS
+//! endpoints need a local relay.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host, port and path of an `http://` URL, the host of an IPv6 address without its brackets
+fn parse_url(url: &str) -> io::Result<(&str, u16, &str)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", msg, url));
+    let rest = url.strip_prefix("http://").ok_or_else(|| invalid("only http:// URLs are supported"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, port) = bracketed.split_once(']').ok_or_else(|| invalid("unclosed IPv6 address"))?;
+            match port {
+                "" => (host, None),
+                _ => (host, Some(port.strip_prefix(':').ok_or_else(|| invalid("invalid port"))?)),
+            }
+        },
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid("invalid port"))?,
+        None => 80,
+    };
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    Ok((host, port, path))
+}
+
+/// Posts `body` as JSON to `url`, failing unless the server answers with a 2xx status
+pub fn post(url: &str, body: &str) -> io::Result<()> {
+    let (host, port, path) = parse_url(url)?;
+    let addr = (host, port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("can't resolve {}", host)))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut authority = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+    if port != 80 {
+        authority += &format!(":{}", port);
+    }
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path, authority, body.len(), body
+    )?;
+    let mut response = vec![];
+    stream.take(1024).read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(io::Error::other(format!("webhook answered {}", response.lines().next().unwrap_or_default())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn urls() {
+        assert_eq!(parse_url("http://example.com").unwrap(), ("example.com", 80, "/"));
+        assert_eq!(parse_url("http://10.0.0.1:8080/hooks/scan?x=1").unwrap(), ("10.0.0.1", 8080, "/hooks/scan?x=1"));
+        assert_eq!(parse_url("http://[::1]:8080/hook").unwrap(), ("::1", 8080, "/hook"));
+        assert_eq!(parse_url("http://[fe80::1]").unwrap(), ("fe80::1", 80, "/"));
+        for url in ["https://example.com", "http://:80/", "http://host:x/", "http://[::1/", "http://[::1]8080/", "http:///"] {
+            assert!(parse_url(url).is_err(), "{}", url);
+        }
+    }
+
+    /// Request line, headers and body of the one request posted to `listener`, answered with
+    /// `status`
+    fn receive(listener: TcpListener, status: &'static str) -> thread::JoinHandle<(String, Vec<String>, String)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut lines = reader.by_ref().lines().map(Result::unwrap);
+            let request = lines.next().unwrap();
+            let headers: Vec<String> = lines.take_while(|l| !l.is_empty()).collect();
+            let length = headers.iter().find_map(|h| h.strip_prefix("Content-Length: ")).unwrap().parse().unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(&stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            (request, headers, String::from_utf8(body).unwrap())
+        })
+    }
+
+    #[test]
+    fn posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = receive(listener, "204 No Content");
+        post(&format!("http://127.0.0.1:{}/hook?x=1", port), r#"{"added":["libfoo.so.1"]}"#).unwrap();
+        let (request, headers, body) = received.join().unwrap();
+        assert_eq!(request, "POST /hook?x=1 HTTP/1.1");
+        assert_eq!(headers, [
+            format!("Host: 127.0.0.1:{}", port),
+            "Content-Type: application/json".to_string(),
+            "Content-Length: 25".to_string(),
+            "Connection: close".to_string(),
+        ]);
+        assert_eq!(body, r#"{"added":["libfoo.so.1"]}"#);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = receive(listener, "500 Internal Server Error");
+        let e = post(&format!("http://127.0.0.1:{}", port), "{}").unwrap_err();
+        assert_eq!(e.to_string(), "webhook answered HTTP/1.1 500 Internal Server Error");
+        assert_eq!(received.join().unwrap().0, "POST / HTTP/1.1");
+
+        // hosts without IPv6 can't take this part
+        if let Ok(listener) = TcpListener::bind("[::1]:0") {
+            let port = listener.local_addr().unwrap().port();
+            let received = receive(listener, "200 OK");
+            post(&format!("http://[::1]:{}/", port), "{}").unwrap();
+            assert_eq!(received.join().unwrap().1[0], format!("Host: [::1]:{}", port));
+        }
+    }
+}
@@ -0,0 +1,15 @@
+#!/bin/sh
+# Rebuilds the big-endian 32-bit samples with LLVM's assembler and the lld shipped with rustup;
+# libc.so.6 is a stub only linked against
+set -e
+cd "$(dirname "$0")"
+lld="$(rustc --print sysroot)/lib/rustlib/$(rustc -vV | sed -n 's/^host: //p')/bin/rust-lld"
+llvm-mc -triple=powerpc-linux-gnu -filetype=obj -o lib.o lib.s
+llvm-mc -triple=powerpc-linux-gnu -filetype=obj -o start.o start.s
+"$lld" -flavor gnu -shared -soname libbar.so.2 -o libbar.so.2 lib.o
+"$lld" -flavor gnu -shared -soname libc.so.6 -o libc.so.6 lib.o
+"$lld" -flavor gnu -pie -z notext -dynamic-linker /lib/ld.so.1 --no-as-needed \
+    -o app32 start.o libbar.so.2 libc.so.6
+llvm-objcopy --strip-all app32
+llvm-objcopy --strip-all libbar.so.2
+rm lib.o start.o libc.so.6
@@ -0,0 +1,3 @@
+.globl bar
+.text
+bar: blr
@@ -0,0 +1,4 @@
+.globl _start
+.text
+_start: bl bar@plt
+ blr
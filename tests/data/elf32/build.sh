@@ -0,0 +1,12 @@
+#!/bin/sh
+# Rebuilds the 32-bit samples with GNU binutils; libc.so.6 is a stub only linked against
+set -e
+cd "$(dirname "$0")"
+as --32 -o lib.o lib.s
+as --32 -o start.o start.s
+ld -m elf_i386 -z noseparate-code -shared -soname libbar.so.2 -o libbar.so.2 lib.o
+ld -m elf_i386 -z noseparate-code -shared -soname libc.so.6 -o libc.so.6 lib.o
+ld -m elf_i386 -z noseparate-code -z notext -pie -dynamic-linker /lib/ld-linux.so.2 --no-as-needed \
+    -o app32 start.o libbar.so.2 libc.so.6
+strip app32 libbar.so.2
+rm lib.o start.o libc.so.6
@@ -0,0 +1,20 @@
+#!/bin/sh
+# Rebuilds the x86-64 samples of every hardening level with GNU binutils; libc.so.6 is a stub only
+# linked against
+set -e
+cd "$(dirname "$0")"
+for source in lib start partial legacy; do
+    as --64 -o $source.o $source.s
+done
+ld="ld -m elf_x86_64 -dynamic-linker /lib64/ld-linux-x86-64.so.2 --no-as-needed"
+$ld -shared -soname libbar.so.1 -z relro -z now -z noexecstack -o libbar.so.1 lib.o
+$ld -shared -soname libc.so.6 -o libc.so.6 lib.o
+# PIE, full RELRO, NX and a stack protector, with a fixed build-id
+$ld -pie -z relro -z now -z noexecstack --build-id=0x8f3c2a1be4d5607988aabbccddeeff0011223344 \
+    -o hardened start.o libbar.so.1 libc.so.6
+# RELRO without immediate binding, and a text relocation
+$ld -pie -z relro -z lazy -z noexecstack -z notext -o partial partial.o libbar.so.1
+# none of it
+$ld -z norelro -z execstack -o legacy legacy.o libbar.so.1
+strip hardened partial legacy libbar.so.1
+rm lib.o start.o partial.o legacy.o libc.so.6
@@ -0,0 +1,8 @@
+.globl bar
+.type bar, @function
+.text
+bar: ret
+.globl __stack_chk_fail
+.type __stack_chk_fail, @function
+__stack_chk_fail: ret
+.section .note.GNU-stack,"",@progbits
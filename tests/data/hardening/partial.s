@@ -0,0 +1,7 @@
+.globl _start
+.text
+_start: call bar@PLT
+ ret
+# an absolute address in the code, relocated at load time
+ .quad bar
+.section .note.GNU-stack,"",@progbits
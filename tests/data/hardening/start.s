@@ -0,0 +1,6 @@
+.globl _start
+.text
+_start: call bar@PLT
+ call __stack_chk_fail@PLT
+ ret
+.section .note.GNU-stack,"",@progbits
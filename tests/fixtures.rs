@@ -0,0 +1,629 @@
+//! Runs the binary over fixtures written by its own `gen-fixtures` subcommand.
+
+use std::fs;
+#[cfg(unix)]
+use std::io::Write;
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+
+fn bin() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_linux-hs-elf"));
+    command.env("RUST_LOG", "none");
+    command
+}
+
+/// Standard output of a successful run
+fn stdout(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Fresh directory unique to the test
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("linux-hs-elf-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Fresh directory with the standard fixtures
+fn fixtures(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    stdout(bin().arg("gen-fixtures").arg(&dir));
+    dir
+}
+
+#[test]
+fn reads_every_class_and_endianness() {
+    let dir = fixtures("classes");
+    let out = stdout(bin().arg("-e").arg(&dir));
+    // each one is of another target, so they need different libfoo.so.1 builds
+    for (name, target) in [("elf32-le", "i386 (32-bit)"), ("elf64-be", "powerpc64 (64-bit)"), ("elf32-be", "powerpc (32-bit)")] {
+        assert!(out.contains(&format!("libfoo.so.1 [{}] (1 exes)\n\t<= {}\n", target, name)), "{}", out);
+    }
+    assert!(out.contains("libfoo.so.1 [x86_64 (64-bit)] (2 exes)\n\t<= elf64-le\n\t<= no-section-headers\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn deps_of_big_endian_file() {
+    let dir = fixtures("deps");
+    let out = stdout(bin().arg("deps").arg(dir.join("elf32-be")));
+    assert!(out.contains("\t=> libfoo.so.1\n\t=> libc.so.6\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn malformed_files_are_parse_failures() {
+    let dir = fixtures("malformed");
+    fs::write(dir.join("script.sh"), "#!/bin/sh\n").unwrap();
+    let out = stdout(bin().arg("-e").arg(&dir));
+    assert!(out.contains("\tparse: 3\n"), "{}", out);
+    // files that aren't binaries are skipped, not failures
+    assert!(out.contains("skipped: 1, failed: 3\n"), "{}", out);
+
+    let strict = bin().arg("-e").arg(&dir).arg("--strict").output().unwrap();
+    assert_eq!(strict.status.code(), Some(2));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stripped_executables_are_read_from_program_headers() {
+    let dir = temp_dir("stripped");
+    stdout(bin().arg("gen-fixtures").arg(&dir).args([
+        "--name", "stripped", "--no-section-headers", "--base", "0x400000", "--needed", "libz.so.1,libc.so.6",
+    ]));
+    let out = stdout(bin().arg("deps").arg(dir.join("stripped")));
+    assert!(out.contains("\t=> libz.so.1\n\t=> libc.so.6\n"), "{}", out);
+    // section headers cut off with e_shoff still pointing at them
+    stdout(bin().arg("gen-fixtures").arg(&dir).args([
+        "--name", "cut", "--cut-section-headers", "--needed", "libz.so.1,libm.so.6",
+    ]));
+    let out = stdout(bin().arg("deps").arg(dir.join("cut")));
+    assert!(out.contains("\t=> libz.so.1\n\t=> libm.so.6\n"), "{}", out);
+    let out = stdout(bin().arg("-e").arg(&dir));
+    assert!(out.contains("libz.so.1 (2 exes)\n\t<= cut\n\t<= stripped\n"), "{}", out);
+    assert!(out.contains("\tanalyzed: 2, skipped: 0, failed: 0\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ignore_rules_leave_paths_out() {
+    let dir = fixtures("ignore");
+    fs::write(dir.with_extension("ignore"), "malformed-*\n# only the x86 ones\nelf*-be\n").unwrap();
+    let out = stdout(bin().arg("-e").arg(&dir).arg("--ignore-file").arg(dir.with_extension("ignore")));
+    assert!(!out.contains("parse:") && !out.contains("elf64-be"), "{}", out);
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--include", "elf64-*", "--exclude", "*-be"]));
+    assert!(out.contains("libc.so.6 (1 exes)\n\t<= elf64-le\n"), "{}", out);
+    assert!(out.contains("\tanalyzed: 1, skipped: 0, failed: 0\n"), "{}", out);
+    fs::remove_file(dir.with_extension("ignore")).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// the list is of raw path bytes, which only Unix paths are
+#[cfg(unix)]
+#[test]
+fn files_listed_on_stdin_are_scanned() {
+    let dir = fixtures("files-from");
+    let mut child = bin().args(["--files-from", "-"]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let list = [dir.join("elf32-le"), dir.clone(), dir.join("elf64-be"), dir.join("elf32-le")];
+    for path in &list {
+        let stdin = child.stdin.as_mut().unwrap();
+        stdin.write_all(path.as_os_str().as_bytes()).unwrap();
+        stdin.write_all(b"\0").unwrap();
+    }
+    let output = child.wait_with_output().unwrap();
+    let out = String::from_utf8(output.stdout).unwrap();
+    // the directory is skipped and the file listed twice scanned once
+    assert!(out.contains("\tanalyzed: 2, skipped: 0, failed: 0\n"), "{}", out);
+    assert!(out.contains("libfoo.so.1 [i386 (32-bit)] (1 exes)\n\t<= elf32-le\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn explorer_needs_a_terminal() {
+    let dir = fixtures("tui");
+    let output = bin().arg(&dir).arg("--tui").stdin(Stdio::null()).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--tui needs stdin and stdout to be a terminal"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn custom_fixture() {
+    let dir = temp_dir("custom");
+    stdout(bin().arg("gen-fixtures").arg(&dir).args([
+        "--name", "app", "--bits", "32", "--big-endian", "--machine", "powerpc",
+        "--needed", "libz.so.1,libc.so.6",
+    ]));
+    let out = stdout(bin().arg("deps").arg(dir.join("app")));
+    assert!(out.contains("\t=> libz.so.1\n\t=> libc.so.6\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Linked by GNU ld, see `tests/data/elf32/build.sh`
+fn elf32_samples() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/elf32")
+}
+
+/// Linked for powerpc by lld, see `tests/data/elf32-ppc/build.sh`
+fn elf32_be_samples() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/elf32-ppc")
+}
+
+#[test]
+fn deps_of_linked_elf32_executable() {
+    let out = stdout(bin().arg("deps").arg(elf32_samples().join("app32")));
+    assert!(out.contains("\t=> libbar.so.2\n\t=> libc.so.6\n"), "{}", out);
+}
+
+#[test]
+fn linked_elf32_interpreter_and_soname() {
+    let out = stdout(bin().arg("-e").arg(elf32_samples()).args(["--group-by", "interp"]));
+    assert!(out.contains("glibc i386 /lib/ld-linux.so.2 (1 exes)\n\tlibbar.so.2 (1 exes)\n\t\t<= app32\n"), "{}", out);
+
+    let out = stdout(bin().arg("who-needs").arg("libbar.so.2").arg("-e").arg(elf32_samples()));
+    assert_eq!(out, "libbar.so.2 (1 exes)\n\t<= app32\n\n");
+}
+
+#[test]
+fn linked_big_endian_elf32_executable() {
+    let out = stdout(bin().arg("deps").arg(elf32_be_samples().join("app32")));
+    assert!(out.contains("\t=> libbar.so.2\n\t=> libc.so.6\n"), "{}", out);
+
+    let out = stdout(bin().arg("-e").arg(elf32_be_samples()).args(["--group-by", "interp"]));
+    assert!(out.contains("/lib/ld.so.1 (1 exes)\n\tlibbar.so.2 (1 exes)\n\t\t<= app32\n"), "{}", out);
+    let out = stdout(bin().arg("-e").arg(elf32_be_samples()).args(["--format", "json"]));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(document["executables"][0]["name"], "app32");
+    assert_eq!(document["executables"][0]["arch"], "powerpc");
+}
+
+/// Linked with every hardening level, see `tests/data/hardening/build.sh`
+fn hardening_samples() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/hardening")
+}
+
+#[test]
+fn checksec_reports_the_linked_hardening() {
+    let out = stdout(bin().arg("-e").arg(hardening_samples()).arg("--checksec"));
+    assert!(out.contains("\tpie: 2, full relro: 2, partial relro: 1, nx: 3, canary: 2, textrel: 1\n"), "{}", out);
+    assert!(out.contains("\tyes\tfull\tyes\tyes\tno\thardened\n"), "{}", out);
+    assert!(out.contains("\tyes\tpartial\tyes\tno\tyes\tpartial\n"), "{}", out);
+    assert!(out.contains("\tno\tnone\tno\tno\tno\tlegacy\n"), "{}", out);
+
+    let out = stdout(bin().arg("-e").arg(hardening_samples()).args(["--format", "json"]));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let hardening = |name: &str| document["executables"].as_array().unwrap().iter()
+        .find(|e| e["name"] == name)
+        .map(|e| e["hardening"].clone());
+    assert_eq!(hardening("hardened"), Some(serde_json::json!({
+        "pie": true, "relro": "full", "nx": true, "canary": true, "bind_now": true, "textrel": false,
+    })));
+    assert_eq!(hardening("legacy"), Some(serde_json::json!({
+        "pie": false, "relro": "none", "nx": false, "canary": false, "bind_now": false, "textrel": false,
+    })));
+}
+
+#[test]
+fn build_ids_are_read_from_the_note() {
+    const BUILD_ID: &str = "8f3c2a1be4d5607988aabbccddeeff0011223344";
+    let hardened = hardening_samples().join("hardened");
+    let out = stdout(bin().arg("deps").arg(&hardened));
+    assert!(out.contains(&format!("\tbuild-id {}\n", BUILD_ID)), "{}", out);
+    let out = stdout(bin().arg("-e").arg(hardening_samples()).args(["--format", "json"]));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let build_id = |name: &str| document["executables"].as_array().unwrap().iter()
+        .find(|e| e["name"] == name)
+        .map(|e| e["build_id"].clone());
+    assert_eq!(build_id("hardened"), Some(BUILD_ID.into()));
+    // linked without one
+    assert_eq!(build_id("partial"), Some(serde_json::Value::Null));
+
+    let dir = temp_dir("build-id");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::copy(&hardened, dir.join("hardened")).unwrap();
+    fs::copy(&hardened, dir.join("sub/copy")).unwrap();
+    fs::copy(hardening_samples().join("partial"), dir.join("partial")).unwrap();
+    let out = stdout(bin().arg("-r").arg("-e").arg(&dir).arg("--find-duplicates"));
+    let paths = format!("\t= {}\n\t= {}\n", dir.join("hardened").display(), dir.join("sub/copy").display());
+    assert!(out.contains(&format!("{} (2 paths)\n{}", BUILD_ID, paths)), "{}", out);
+    let rules = dir.join("rules.json");
+    fs::write(&rules, format!(r#"[{{"name": "known-build", "severity": "warning", "when": "binary.build_id == '{}'", "message": "known build"}}]"#, BUILD_ID)).unwrap();
+    let out = stdout(bin().arg("-r").arg("-e").arg(&dir).arg("--rules").arg(&rules));
+    assert!(out.contains("warning known-build: known build (2 exes)\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn elf32_fixtures_keep_their_machine() {
+    let dir = fixtures("elf32-machine");
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--format", "json"]));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let arch = |name: &str| document["executables"].as_array().unwrap().iter()
+        .find(|e| e["name"] == name)
+        .map(|e| e["arch"].clone());
+    assert_eq!(arch("elf32-le"), Some("i386".into()));
+    assert_eq!(arch("elf32-be"), Some("powerpc".into()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn check_exits_with_3_on_missing_libraries() {
+    let dir = temp_dir("check");
+    stdout(bin().arg("gen-fixtures").arg(&dir).args(["--name", "app", "--needed", "libnowhere.so.7"]));
+    let output = bin().arg("-e").arg(&dir).arg("--check").output().unwrap();
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(3), "{}", out);
+    assert!(out.contains("app\n\t! needs libnowhere.so.7, not found for x86_64 (64-bit)\n"), "{}", out);
+
+    let excluded = bin().arg("-e").arg(&dir).args(["--check", "--exclude-libs", "libnowhere.so.*"]).output().unwrap();
+    assert_eq!(excluded.status.code(), Some(0));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn who_needs_list_prints_each_executable_once() {
+    let dir = fixtures("who-needs-list");
+    let out = stdout(bin().args(["who-needs", "lib*.so.*", "--list", "-e"]).arg(&dir));
+    assert_eq!(out, "elf32-be\nelf32-le\nelf64-be\nelf64-le\nlibfoo.so.1\nno-section-headers\n");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directories_and_files_merge_into_one_report() {
+    let dir = fixtures("inputs");
+    let out = stdout(bin().arg(dir.join("elf64-le")).arg(&dir).arg(elf32_samples().join("app32")));
+    assert!(out.contains("libfoo.so.1 [x86_64 (64-bit)] (2 exes)\n\t<= elf64-le\n\t<= no-section-headers\n"), "{}", out);
+    assert!(out.contains("libbar.so.2 [i386 (32-bit)] (1 exes)\n\t<= app32\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sbom_hashes_files_and_links_dependencies() {
+    let dir = fixtures("sbom");
+    let out = stdout(bin().args(["--sbom", "cyclonedx", "-e"]).arg(dir.join("elf64-le")));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let components = document["components"].as_array().unwrap();
+    assert_eq!(components[0]["name"], "elf64-le");
+    // SHA-256 is 64 hex digits
+    assert_eq!(components[0]["hashes"][0]["content"].as_str().unwrap().len(), 64);
+    let libfoo = components.iter().position(|c| c["name"] == "libfoo").unwrap();
+    assert_eq!(components[libfoo]["version"], "1");
+    assert_eq!(document["dependencies"][0]["dependsOn"][0], format!("component-{}", libfoo));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn packages_own_libraries_of_the_sysroot() {
+    let root = temp_dir("packages");
+    stdout(bin().arg("gen-fixtures").arg(root.join("usr/lib")));
+    fs::create_dir_all(root.join("lib/apk/db")).unwrap();
+    fs::write(root.join("lib/apk/db/installed"), "P:musl\nV:1\n\nP:foo\nF:usr/lib\nR:libfoo.so.1\n").unwrap();
+    let out = stdout(bin().arg("--sysroot").arg(&root).args(["/usr/lib", "--packages"]));
+    assert!(out.contains("packages required (1 packages, apk)\n\nfoo (2 exes)\n\tlibfoo.so.1\n\t<= elf64-le\n\t<= no-section-headers\n"), "{}", out);
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn diff_of_saved_scan_and_directory() {
+    let dir = fixtures("diff");
+    let saved = dir.with_extension("json");
+    fs::write(&saved, stdout(bin().arg(&dir).args(["--format", "json"]))).unwrap();
+    fs::remove_file(dir.join("libfoo.so.1")).unwrap();
+    fs::copy(dir.join("elf64-be"), dir.join("copy")).unwrap();
+    let out = stdout(bin().arg("diff").arg(&saved).arg(&dir));
+    assert!(out.contains("added executables (1 exes)\n\t+ copy\n"), "{}", out);
+    assert!(out.contains("removed executables (1 exes)\n\t- libfoo.so.1\n"), "{}", out);
+    assert!(out.contains("\tlibfoo.so.1 (5 -> 6 exes)\n"), "{}", out);
+    let status = bin().arg("diff").arg(&saved).arg(&saved).arg("--exit-code").status().unwrap();
+    assert_eq!(status.code(), Some(0));
+    fs::remove_file(saved).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn cached_results_match_a_fresh_scan() {
+    let dir = fixtures("cache");
+    let cache = dir.with_extension("cache");
+    let fresh = stdout(bin().arg(&dir).arg("--cache").arg(&cache));
+    assert!(cache.exists());
+    assert_eq!(stdout(bin().arg(&dir).arg("--cache").arg(&cache)), fresh);
+    assert_eq!(stdout(bin().arg(&dir).arg("--cache").arg(&cache).arg("--no-cache")), fresh);
+    fs::remove_file(cache).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Uncompressed tarball of regular files
+fn tarball(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut tar = vec![];
+    for (name, data) in members {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000755");
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        // checksummed as if the checksum field were spaces
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(data);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+    tar.resize(tar.len() + 1024, 0);
+    tar
+}
+
+#[test]
+fn image_layers_are_applied_with_whiteouts() {
+    let dir = fixtures("image");
+    let read = |name: &str| fs::read(dir.join(name)).unwrap();
+    let base = tarball(&[
+        ("usr/bin/elf64-le", &read("elf64-le")),
+        ("usr/bin/elf64-be", &read("elf64-be")),
+        ("usr/lib/libfoo.so.1", &read("libfoo.so.1")),
+    ]);
+    let top = tarball(&[("usr/bin/.wh.elf64-be", b"")]);
+    let manifest = br#"[{"Config": "config.json", "RepoTags": ["test:latest"], "Layers": ["base/layer.tar", "top/layer.tar"]}]"#;
+    let image = dir.with_extension("tar");
+    fs::write(&image, tarball(&[("base/layer.tar", &base), ("top/layer.tar", &top), ("manifest.json", manifest)])).unwrap();
+    let out = stdout(bin().arg("image").arg(&image).arg("/usr/bin"));
+    assert!(out.contains("\t<= elf64-le\n"), "{}", out);
+    assert!(!out.contains("elf64-be"), "{}", out);
+    fs::remove_file(image).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn platform_picks_the_image_of_an_index() {
+    let dir = fixtures("platform");
+    let read = |name: &str| fs::read(dir.join(name)).unwrap();
+    let amd64 = tarball(&[("usr/bin/elf64-le", &read("elf64-le"))]);
+    let arm64 = tarball(&[("usr/bin/elf64-be", &read("elf64-be"))]);
+    let index = br#"{"manifests": [
+        {"digest": "sha256:a1", "platform": {"architecture": "arm64", "os": "linux", "variant": "v8"}},
+        {"digest": "sha256:a2", "platform": {"architecture": "amd64", "os": "linux"}},
+        {"digest": "sha256:a3", "platform": {"architecture": "unknown", "os": "unknown"}}
+    ]}"#;
+    let image = dir.with_extension("tar");
+    fs::write(&image, tarball(&[
+        ("index.json", index),
+        ("blobs/sha256/a1", br#"{"layers": [{"digest": "sha256:b1"}]}"#),
+        ("blobs/sha256/a2", br#"{"layers": [{"digest": "sha256:b2"}]}"#),
+        ("blobs/sha256/b1", &arm64),
+        ("blobs/sha256/b2", &amd64),
+    ])).unwrap();
+    // linux/amd64 whatever the host
+    let out = stdout(bin().arg("image").arg(&image));
+    assert!(out.contains("\t<= elf64-le\n") && !out.contains("elf64-be"), "{}", out);
+    let out = stdout(bin().arg("image").arg(&image).args(["--platform", "linux/arm64"]));
+    assert!(out.contains("\t<= elf64-be\n") && !out.contains("elf64-le"), "{}", out);
+    let output = bin().arg("image").arg(&image).args(["--platform", "linux/s390x"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No image for linux/s390x in the index, it has linux/arm64/v8, linux/amd64"));
+    fs::remove_file(image).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn output_is_the_same_whatever_the_directory_order() {
+    let dir = fixtures("deterministic");
+    let formats = ["text", "json", "csv", "graph-json"];
+    // the scan time is the one thing that may change between runs
+    let run = || formats.map(|format| {
+        stdout(bin().arg("-e").arg(&dir).args(["--format", format]).env("SOURCE_DATE_EPOCH", "1700000000"))
+    });
+    let first = run();
+    assert!(first[1].contains("\"time\": 1700000000,"), "{}", first[1]);
+    assert_eq!(run(), first);
+
+    // the same files written the other way round, so they are listed in another order
+    let mut files: Vec<(PathBuf, Vec<u8>)> = fs::read_dir(&dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .map(|path| {
+            let data = fs::read(&path).unwrap();
+            (path, data)
+        })
+        .collect();
+    files.sort();
+    fs::remove_dir_all(&dir).unwrap();
+    fs::create_dir(&dir).unwrap();
+    for (path, data) in files.iter().rev() {
+        fs::write(path, data).unwrap();
+    }
+    for (format, (out, expected)) in formats.iter().zip(run().iter().zip(&first)) {
+        assert!(out == expected, "{} output changed:\n{}\n{}", format, expected, out);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn output_is_the_same_whatever_the_number_of_jobs() {
+    let dir = temp_dir("jobs");
+    for sub in ["a", "b", "c"] {
+        stdout(bin().arg("gen-fixtures").arg(dir.join(sub)));
+    }
+    let run = |jobs: &str| {
+        let scan = |format: &str| {
+            let args = ["--paths", "relative", "--format", format, "--jobs", jobs];
+            stdout(bin().arg("-r").arg("-e").arg(&dir).args(args).env("SOURCE_DATE_EPOCH", "1700000000"))
+        };
+        let mut json: serde_json::Value = serde_json::from_str(&scan("json")).unwrap();
+        // the one difference is the command line in the metadata
+        json["metadata"].as_object_mut().unwrap().remove("arguments").unwrap();
+        (scan("text"), json, scan("csv"))
+    };
+    let single = run("1");
+    assert!(single.0.contains("\tanalyzed: 18, skipped: 0, failed: 9\n"), "{}", single.0);
+    assert!(single.0.contains("libfoo.so.1 [i386 (32-bit)] (3 exes)\n\t<= a/elf32-le\n\t<= b/elf32-le\n\t<= c/elf32-le\n"), "{}", single.0);
+    for _ in 0..3 {
+        assert!(run("4") == single, "output of 4 jobs differs from 1 job");
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// other hosts don't allow names that aren't valid Unicode
+#[cfg(unix)]
+#[test]
+fn names_that_are_not_utf8_are_escaped() {
+    let dir = fixtures("non-utf8");
+    // invalid UTF-8 and a literal `%`, which is escaped too so the name can be recovered
+    let path = dir.join(OsStr::from_bytes(b"app-\xff%"));
+    fs::rename(dir.join("elf64-le"), &path).unwrap();
+    let escaped = format!("{}/app-%FF%25", dir.display());
+
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--include", "app-*"]));
+    assert!(out.contains("libfoo.so.1 (1 exes)\n\t<= app-%FF%25\n"), "{}", out);
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--include", "app-*", "--format", "json"]));
+    let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(document["executables"][0]["name"], "app-%FF%25");
+    assert_eq!(document["executables"][0]["path"], escaped.as_str());
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--include", "app-*", "--format", "csv"]));
+    assert!(out.contains(&format!("app-%FF%25,{},elf,libfoo.so.1,", escaped)), "{}", out);
+    let out = stdout(bin().arg("deps").arg(&path));
+    assert!(out.starts_with(&format!("{} (elf)\n", escaped)), "{}", out);
+
+    let mut child = bin().args(["--files-from", "-"]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(path.as_os_str().as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert!(out.contains("\tanalyzed: 1, skipped: 0, failed: 0\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Root filesystem of custom fixtures needing the given libraries: executables in `/usr/bin`,
+/// and libraries, whose names start with `lib`, in `/usr/lib` with their name as soname
+fn rootfs(test: &str, files: &[(&str, &[&str])]) -> PathBuf {
+    let root = temp_dir(test);
+    for (name, needed) in files {
+        let mut command = bin();
+        command.arg("gen-fixtures").args(["--name", name]);
+        match name.starts_with("lib") {
+            true => command.arg(root.join("usr/lib")).args(["--soname", name]),
+            false => command.arg(root.join("usr/bin")),
+        };
+        if !needed.is_empty() {
+            command.arg("--needed").arg(needed.join(","));
+        }
+        stdout(&mut command);
+    }
+    root
+}
+
+#[test]
+fn centrality_ranks_libraries_by_dependents() {
+    // app2 reaches libbase only through libssl and libcrypto, app3 through libcrypto
+    let root = rootfs("centrality", &[
+        ("app1", &["libssl.so.3", "libbase.so.1"]),
+        ("app2", &["libssl.so.3"]),
+        ("app3", &["libcrypto.so.3"]),
+        ("libssl.so.3", &["libcrypto.so.3"]),
+        ("libcrypto.so.3", &["libbase.so.1"]),
+        ("libbase.so.1", &[]),
+    ]);
+    let out = stdout(bin().arg("--sysroot").arg(&root).args(["-e", "/usr/bin", "--centrality"]));
+    assert!(out.contains(concat!(
+        "most critical libraries\n",
+        "\trank dependents direct betweenness  library\n",
+        "\t   1          5      2         0.0  libbase.so.1\n",
+        "\t   2          4      2         3.0  libcrypto.so.3\n",
+        "\t   3          2      2         3.0  libssl.so.3\n\n",
+    )), "{}", out);
+    let out = stdout(bin().arg("--sysroot").arg(&root).args(["-e", "/usr/bin", "--centrality", "--top", "1"]));
+    let ranking = out.split_once("most critical libraries\n").unwrap().1;
+    assert!(ranking.contains("  libbase.so.1\n\n") && !ranking.contains("libcrypto.so.3"), "{}", out);
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn clusters_group_libraries_with_their_executables() {
+    // libbase has four direct dependents, so it is a hub and does not join the two stacks
+    let root = rootfs("clusters", &[
+        ("curl", &["libssl.so.3", "libbase.so.1"]),
+        ("openssl", &["libssl.so.3", "libcrypto.so.3", "libbase.so.1"]),
+        ("viewer", &["libgtk.so.4", "libbase.so.1"]),
+        ("editor", &["libgtk.so.4", "libbase.so.1"]),
+        ("libssl.so.3", &["libcrypto.so.3"]),
+        ("libcrypto.so.3", &["libbase.so.1"]),
+        ("libgtk.so.4", &["libgdk.so.4"]),
+        ("libgdk.so.4", &["libbase.so.1"]),
+        ("libbase.so.1", &[]),
+    ]);
+    let out = stdout(bin().arg("--sysroot").arg(&root).args(["-e", "/usr/bin", "--clusters"]));
+    assert!(out.contains(concat!(
+        "clusters (2 clusters)\n\n",
+        "libcrypto.so.3 (2 libs, 2 exes)\n",
+        "\tlibssl.so.3\n",
+        "\tlibcrypto.so.3\n",
+        "\t<= curl\n",
+        "\t<= openssl\n\n",
+        "libgtk.so.4 (2 libs, 2 exes)\n",
+        "\tlibgtk.so.4\n",
+        "\tlibgdk.so.4\n",
+        "\t<= editor\n",
+        "\t<= viewer\n\n",
+    )), "{}", out);
+    let clusters = out.split_once("clusters (2 clusters)\n").unwrap().1;
+    assert!(!clusters.split("scan summary").next().unwrap().contains("libbase"), "{}", out);
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn listing_is_sorted_and_filtered() {
+    let dir = temp_dir("sort");
+    for (name, needed) in [("a", "liba.so.1,libc.so.6"), ("b", "libz.so.1,libc.so.6"), ("c", "libz.so.1,libc.so.6")] {
+        stdout(bin().arg("gen-fixtures").arg(&dir).args(["--name", name, "--needed", needed]));
+    }
+    let listed = |args: &[&str]| -> Vec<String> {
+        stdout(bin().arg("-e").arg(&dir).args(args)).lines()
+            .filter(|line| line.ends_with(" exes)"))
+            .map(|line| line.split(' ').next().unwrap().to_string())
+            .collect()
+    };
+    for (args, expected) in [
+        (&[][..], &["libc.so.6", "libz.so.1", "liba.so.1"][..]),
+        (&["--order", "asc"], &["liba.so.1", "libz.so.1", "libc.so.6"]),
+        (&["--sort", "name"], &["liba.so.1", "libc.so.6", "libz.so.1"]),
+        (&["--sort", "name", "--order", "desc"], &["libz.so.1", "libc.so.6", "liba.so.1"]),
+        (&["--min-count", "2"], &["libc.so.6", "libz.so.1"]),
+        (&["--filter", "libz*,liba.*"], &["libz.so.1", "liba.so.1"]),
+        (&["--filter", "lib?.so.*", "--min-count", "3", "--sort", "name"], &["libc.so.6"]),
+        (&["--filter", "libssl*"], &[]),
+    ] {
+        assert_eq!(listed(args), expected, "{:?}", args);
+    }
+    let out = stdout(bin().arg("-e").arg(&dir).args(["--sort", "name"]));
+    assert!(out.contains("libc.so.6 (3 exes)\n\t<= a\n\t<= b\n\t<= c\n"), "{}", out);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn embedded_copies_are_searched_for_on_demand() {
+    let dir = temp_dir("embedded");
+    stdout(bin().arg("gen-fixtures").arg(&dir).args(["--name", "app", "--needed", "libc.so.6"]));
+    let mut data = fs::read(dir.join("app")).unwrap();
+    data.extend_from_slice(b"\0libcurl/8.4.0\0");
+    fs::write(dir.join("app"), data).unwrap();
+
+    let cache = dir.with_extension("cache");
+    stdout(bin().arg(&dir).arg("--cache").arg(&cache));
+    // the cache holds results without signatures, so it isn't used
+    let out = stdout(bin().arg(&dir).arg("--cache").arg(&cache).arg("--embedded"));
+    assert!(out.contains("curl 8.4.0 [static] (1 exes)\n\t<= app\n"), "{}", out);
+    let out = stdout(bin().arg("deps").arg(dir.join("app")));
+    assert!(out.contains("\t=> curl 8.4.0 (static)\n"), "{}", out);
+
+    let rules = dir.with_extension("rules.json");
+    fs::write(&rules, r#"[{"name": "static-curl", "severity": "warning", "when": "\"curl 8.4.0\" in binary.embedded", "message": "bundles curl"}]"#).unwrap();
+    let out = stdout(bin().arg(&dir).arg("--rules").arg(&rules));
+    assert!(out.contains("warning static-curl: bundles curl (1 exes)\n\t<= app\n"), "{}", out);
+    fs::remove_file(rules).unwrap();
+    fs::remove_file(cache).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}